@@ -0,0 +1,86 @@
+/* C bindings for libvapor. */
+
+#ifndef VAPOR_H
+#define VAPOR_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Result of every vapor_* call. Failures follow the error classes the CLI exits with. */
+typedef enum VaporStatus {
+    VAPOR_OK = 0,
+    /* A required pointer was null. */
+    VAPOR_NULL_ARGUMENT = 1,
+    /*
+     * A string argument was not valid UTF-8, a JSON argument could not be parsed, or the
+     * arguments don't make sense, like a pattern matching nothing.
+     */
+    VAPOR_INVALID_ARGUMENT = 2,
+    /* Vapor.toml is missing or broken, or the game is unknown. */
+    VAPOR_CONFIG = 3,
+    VAPOR_IO = 4,
+    /* No mod, collection or recorded version by that name. */
+    VAPOR_MISSING_MOD = 5,
+    /* Files are owned by another mod, or mods are declared to conflict. */
+    VAPOR_CONFLICT = 6,
+    /* The archive could not be read, extracted or trusted. */
+    VAPOR_ARCHIVE = 7,
+    /* mods.toml or the journal could not be read or written. */
+    VAPOR_REGISTRY = 8,
+    VAPOR_OTHER = 9,
+    /* A mod, collection or instance by that name exists already. */
+    VAPOR_ALREADY_EXISTS = 10,
+    /* Missing dependencies, or mods that depend on the one being taken away. */
+    VAPOR_DEPENDENCY = 11,
+    /* Refused to change a mod in the state it's in, like a pinned one or a downgrade. */
+    VAPOR_REFUSED = 12,
+    VAPOR_NETWORK = 13,
+    /* Needed the network while offline. */
+    VAPOR_OFFLINE = 14,
+    /* No Nexus API key, or one without the access needed. */
+    VAPOR_AUTH = 15,
+    /* A hook failed. */
+    VAPOR_HOOK = 16,
+    VAPOR_CANCELLED = 17,
+    /* A check found problems. */
+    VAPOR_PROBLEMS = 18,
+} VaporStatus;
+
+/*
+ * Message of the last failed call on this thread, or NULL if it succeeded.
+ * Owned by the library, valid until the next vapor_* call on this thread.
+ */
+const char *vapor_last_error(void);
+
+/* Free a string returned through an out pointer. */
+void vapor_string_free(char *s);
+
+/*
+ * Install the zip archive at path as name. game may be NULL for Cyberpunk 2077.
+ * dependencies and game_versions are nullable JSON arrays of strings.
+ * out receives the operation report: {"name": ..., "operation": "added" | "updated" |
+ * "downgraded", "version": ..., "old_version": ..., "files": ..., "bytes": ...,
+ * "duration_ms": ..., "conflicts_resolved": ..., "warnings": [...]}. old_version is only
+ * there for updates and downgrades.
+ */
+VaporStatus vapor_add_mod(const char *game, const char *path, const char *name,
+                          const char *version, const char *dependencies,
+                          const char *game_versions, char **out);
+
+/* out receives a JSON object of registry entries keyed by mod name. */
+VaporStatus vapor_list_mods(const char *game, char **out);
+
+/*
+ * Enable or disable name. Disabling a mod enabled mods depend on fails with
+ * VAPOR_DEPENDENCY. out receives {"name": ..., "enabled": ...}.
+ */
+VaporStatus vapor_move_mod(const char *game, const char *name, bool enable, char **out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VAPOR_H */
@@ -0,0 +1,289 @@
+//! C bindings for `libvapor`.
+//!
+//! Every function returns a [`VaporStatus`]. Results are JSON strings written to an `out`
+//! pointer, which must be freed with [`vapor_string_free`]. On failure, [`vapor_last_error`]
+//! describes what went wrong. See `include/vapor.h` for the C declarations.
+
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{CStr, CString, c_char},
+    path::Path,
+    ptr,
+};
+
+use libvapor::{
+    exit::ErrorClass,
+    init::CyberToml,
+    mod_manager::{
+        handler::{AddOptions, Impact, ModHandler, Move},
+        name::{self, ModName},
+    },
+    resolver::Request,
+};
+use serde_json::json;
+
+/// Result of every `vapor_*` call.
+///
+/// Failures follow the [`ErrorClass`] of the error, the same classes the CLI exits with.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VaporStatus {
+    Ok = 0,
+    /// A required pointer was null.
+    NullArgument = 1,
+    /// A string argument was not valid UTF-8, a JSON argument could not be parsed, or the
+    /// arguments don't make sense, like a pattern matching nothing.
+    InvalidArgument = 2,
+    /// `Vapor.toml` is missing or broken, or the game is unknown.
+    Config = 3,
+    Io = 4,
+    /// No mod, collection or recorded version by that name.
+    MissingMod = 5,
+    /// Files are owned by another mod, or mods are declared to conflict.
+    Conflict = 6,
+    /// The archive could not be read, extracted or trusted.
+    Archive = 7,
+    /// `mods.toml` or the journal could not be read or written.
+    Registry = 8,
+    Other = 9,
+    /// A mod, collection or instance by that name exists already.
+    AlreadyExists = 10,
+    /// Missing dependencies, or mods that depend on the one being taken away.
+    Dependency = 11,
+    /// Refused to change a mod in the state it's in, like a pinned one or a downgrade.
+    Refused = 12,
+    Network = 13,
+    /// Needed the network while offline.
+    Offline = 14,
+    /// No Nexus API key, or one without the access needed.
+    Auth = 15,
+    /// A hook failed.
+    Hook = 16,
+    Cancelled = 17,
+    /// A check found problems.
+    Problems = 18,
+}
+
+impl From<ErrorClass> for VaporStatus {
+    fn from(class: ErrorClass) -> Self {
+        match class {
+            ErrorClass::Other => Self::Other,
+            ErrorClass::Usage => Self::InvalidArgument,
+            ErrorClass::Problems => Self::Problems,
+            ErrorClass::Conflict => Self::Conflict,
+            ErrorClass::MissingMod => Self::MissingMod,
+            ErrorClass::AlreadyExists => Self::AlreadyExists,
+            ErrorClass::Dependency => Self::Dependency,
+            ErrorClass::Refused => Self::Refused,
+            ErrorClass::Archive => Self::Archive,
+            ErrorClass::Io => Self::Io,
+            ErrorClass::Registry => Self::Registry,
+            ErrorClass::Config => Self::Config,
+            ErrorClass::Network => Self::Network,
+            ErrorClass::Offline => Self::Offline,
+            ErrorClass::Auth => Self::Auth,
+            ErrorClass::Hook => Self::Hook,
+            ErrorClass::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+struct FfiError {
+    status: VaporStatus,
+    message: String,
+}
+
+impl<E: Error + 'static> From<E> for FfiError {
+    fn from(err: E) -> Self {
+        Self {
+            status: ErrorClass::of(&err).into(),
+            message: err.to_string(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Message of the last failed call on this thread, or null if it succeeded.
+///
+/// The string is owned by the library and valid until the next `vapor_*` call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn vapor_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|err| err.as_ref().map_or(ptr::null(), |err| err.as_ptr()))
+}
+
+/// Free a string returned through an `out` pointer.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that hasn't been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn vapor_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Run `f`, storing its JSON result in `out` or its error in [`LAST_ERROR`].
+fn run(out: *mut *mut c_char, f: impl FnOnce() -> Result<String, FfiError>) -> VaporStatus {
+    let result = f();
+    LAST_ERROR.with_borrow_mut(|last| *last = None);
+
+    match result {
+        Ok(json) => {
+            if !out.is_null() {
+                let json = CString::new(json).expect("JSON never contains NUL");
+                unsafe { *out = json.into_raw() };
+            }
+            VaporStatus::Ok
+        }
+        Err(err) => {
+            LAST_ERROR.with_borrow_mut(|last| {
+                *last = CString::new(err.message.replace('\0', "")).ok();
+            });
+            err.status
+        }
+    }
+}
+
+/// Borrow `s` as a `&str`, or `None` if null.
+unsafe fn arg<'a>(s: *const c_char, name: &str) -> Result<Option<&'a str>, FfiError> {
+    if s.is_null() {
+        return Ok(None);
+    }
+
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map(Some)
+        .map_err(|_| FfiError {
+            status: VaporStatus::InvalidArgument,
+            message: format!("`{name}` is not valid UTF-8"),
+        })
+}
+
+/// Like [`arg`], but null is an error.
+unsafe fn required<'a>(s: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    unsafe { arg(s, name) }?.ok_or_else(|| FfiError {
+        status: VaporStatus::NullArgument,
+        message: format!("`{name}` must not be null"),
+    })
+}
+
+/// Parse a nullable JSON array of strings.
+unsafe fn string_list(s: *const c_char, name: &str) -> Result<Vec<String>, FfiError> {
+    match unsafe { arg(s, name) }? {
+        Some(json) => serde_json::from_str(json).map_err(|err| FfiError {
+            status: VaporStatus::InvalidArgument,
+            message: format!("`{name}` is not a JSON array of strings: {err}"),
+        }),
+        None => Ok(vec![]),
+    }
+}
+
+/// Handler for `game`, or `Cyberpunk 2077` if null, set up the same way as the CLI: locked and
+/// with any interrupted move finished first if the call `mutates` the game.
+unsafe fn handler(game: *const c_char, mutates: bool) -> Result<ModHandler, FfiError> {
+    let game = unsafe { arg(game, "game") }?;
+    let config = CyberToml::load()?;
+    name::set_folding(config.main.name_folding);
+    let handler = ModHandler::from_config(&config, &config.game(game)?)?;
+    if !mutates {
+        return Ok(handler);
+    }
+    let handler = handler.lock()?;
+    handler.fix_pending_move()?;
+    Ok(handler)
+}
+
+/// Install the zip archive at `path` as `name`.
+///
+/// `dependencies` and `game_versions` are nullable JSON arrays of strings. On success, `out`
+/// receives the `OperationReport`, like `{"name": ..., "operation": "added" | "updated" |
+/// "downgraded", "version": ..., "old_version": ..., "files": ..., "bytes": ...,
+/// "duration_ms": ..., "conflicts_resolved": ..., "warnings": [...]}`.
+///
+/// # Safety
+///
+/// String arguments must be null or valid NUL terminated strings, and `out` must be null or
+/// point to writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn vapor_add_mod(
+    game: *const c_char,
+    path: *const c_char,
+    name: *const c_char,
+    version: *const c_char,
+    dependencies: *const c_char,
+    game_versions: *const c_char,
+    out: *mut *mut c_char,
+) -> VaporStatus {
+    run(out, || unsafe {
+        let handler = handler(game, true)?;
+        let path = required(path, "path")?;
+        let name = required(name, "name")?;
+        let version = required(version, "version")?;
+        let dependencies = string_list(dependencies, "dependencies")?;
+        let game_versions = string_list(game_versions, "game_versions")?;
+
+        let options = AddOptions {
+            dependencies: dependencies.into_iter().map(ModName::from).collect(),
+            game_versions,
+            ..Default::default()
+        };
+
+        let report = handler.add_mod(Path::new(path), name, version, &options)?;
+        Ok(serde_json::to_string(&report).expect("Reports are always valid JSON"))
+    })
+}
+
+/// List every mod in the registry.
+///
+/// On success, `out` receives a JSON object of registry entries keyed by mod name.
+///
+/// # Safety
+///
+/// `game` must be null or a valid NUL terminated string, and `out` must be null or point to
+/// writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn vapor_list_mods(
+    game: *const c_char,
+    out: *mut *mut c_char,
+) -> VaporStatus {
+    run(out, || {
+        let registry = unsafe { handler(game, false) }?.load_toml()?;
+        Ok(serde_json::to_string(&registry.mods).expect("Could not serialize"))
+    })
+}
+
+/// Enable or disable `name`.
+///
+/// Disabling a mod enabled mods depend on fails with [`VaporStatus::Dependency`], as the CLI does
+/// without `--cascade` or `--force`. On success, `out` receives `{"name": ..., "enabled": ...}`.
+///
+/// # Safety
+///
+/// String arguments must be null or valid NUL terminated strings, and `out` must be null or
+/// point to writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn vapor_move_mod(
+    game: *const c_char,
+    name: *const c_char,
+    enable: bool,
+    out: *mut *mut c_char,
+) -> VaporStatus {
+    run(out, || unsafe {
+        let handler = handler(game, true)?;
+        let name = required(name, "name")?;
+        let which = if enable { Move::Enable } else { Move::Disable };
+
+        if which == Move::Disable {
+            let planned = handler.plan(&Request::Disable(name.to_string()))?.disable;
+            handler.dependents_to_disable(&[name], planned, Impact::Refuse)?;
+        }
+        handler.move_mod(name, which)?;
+
+        Ok(json!({ "name": name, "enabled": enable }).to_string())
+    })
+}
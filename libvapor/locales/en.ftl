@@ -0,0 +1,55 @@
+# English messages, and the fallback for keys missing from other catalogs.
+
+## vapor status
+
+status-name = Name
+status-enabled = Enabled
+status-version = Version
+status-author = Author
+status-installed = Installed
+status-description = Description
+status-homepage = Homepage
+status-pinned = Pinned
+status-tags = Tags
+status-note = Note
+status-files = Files
+status-missing-files = Missing files
+status-modified-files = Modified files
+status-empty-files = Empty files
+status-incompatible = Not marked compatible with game version `{ $version }`
+status-conflicts = Conflicts with
+status-missing-dependencies = Missing dependencies
+status-dependencies = Dependencies
+status-provided-by = provided by `{ $provider }`
+status-optional-dependencies = Optional dependencies
+status-not-enabled = not enabled
+
+## Prompts
+
+confirm-needed = Confirmation needed: { $message }
+confirm-pass-yes = Pass `--yes` to confirm
+aborted = Aborted
+confirm-enable-dependencies = This also enables dependencies: { $mods }. Continue?
+confirm-remove-all = Remove every mod?
+confirm-reset = Remove every mod and everything Vapor keeps? This can't be undone
+confirm-overwrite = Overwrite `{ $path }`?
+confirm-undo = { $count ->
+    [one] Undo the last operation?
+   *[other] Undo the last { $count } operations?
+}
+confirm-collection-dependencies = This also enables dependencies of collection `{ $name }`: { $mods }. Continue?
+confirm-install-modlist = { $count ->
+    [one] Install { $count } mod from `{ $name }`?
+   *[other] Install { $count } mods from `{ $name }`?
+}
+confirm-restore-saves = Replace the saves in `{ $dir }` with `{ $backup }`?
+confirm-suspicious = Add it anyway?
+
+## Errors
+
+error-missing-mod = Missing mod: `{ $name }`
+error-did-you-mean = did you mean `{ $name }`?
+error-already-exists = A mod named `{ $name }` already exists
+help-already-exists = Pick a different name, or remove the existing mod first
+error-pinned = `{ $name }` is pinned
+help-pinned = Pass `--force` to change it anyway, or run `vapor unpin { $name }`
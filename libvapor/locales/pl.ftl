@@ -0,0 +1,57 @@
+# Polish messages.
+
+## vapor status
+
+status-name = Nazwa
+status-enabled = Włączony
+status-version = Wersja
+status-author = Autor
+status-installed = Zainstalowano
+status-description = Opis
+status-homepage = Strona
+status-pinned = Przypięty
+status-tags = Tagi
+status-note = Notatka
+status-files = Pliki
+status-missing-files = Brakujące pliki
+status-modified-files = Zmienione pliki
+status-empty-files = Puste pliki
+status-incompatible = Nie oznaczono jako zgodny z wersją gry `{ $version }`
+status-conflicts = Konflikty z
+status-missing-dependencies = Brakujące zależności
+status-dependencies = Zależności
+status-provided-by = dostarczane przez `{ $provider }`
+status-optional-dependencies = Opcjonalne zależności
+status-not-enabled = niewłączony
+
+## Prompts
+
+confirm-needed = Wymagane potwierdzenie: { $message }
+confirm-pass-yes = Dodaj `--yes`, aby potwierdzić
+aborted = Przerwano
+confirm-enable-dependencies = Zostaną też włączone zależności: { $mods }. Kontynuować?
+confirm-remove-all = Usunąć wszystkie mody?
+confirm-reset = Usunąć wszystkie mody i wszystko, co przechowuje Vapor? Tego nie można cofnąć
+confirm-overwrite = Nadpisać `{ $path }`?
+confirm-undo = { $count ->
+    [one] Cofnąć ostatnią operację?
+    [few] Cofnąć ostatnie { $count } operacje?
+   *[many] Cofnąć ostatnich { $count } operacji?
+}
+confirm-collection-dependencies = Zostaną też włączone zależności kolekcji `{ $name }`: { $mods }. Kontynuować?
+confirm-install-modlist = { $count ->
+    [one] Zainstalować { $count } mod z `{ $name }`?
+    [few] Zainstalować { $count } mody z `{ $name }`?
+   *[many] Zainstalować { $count } modów z `{ $name }`?
+}
+confirm-restore-saves = Zastąpić zapisy w `{ $dir }` kopią `{ $backup }`?
+confirm-suspicious = Dodać mimo to?
+
+## Errors
+
+error-missing-mod = Brak moda: `{ $name }`
+error-did-you-mean = czy chodziło o `{ $name }`?
+error-already-exists = Mod o nazwie `{ $name }` już istnieje
+help-already-exists = Wybierz inną nazwę albo najpierw usuń istniejący mod
+error-pinned = `{ $name }` jest przypięty
+help-pinned = Dodaj `--force`, aby mimo to go zmienić, albo uruchom `vapor unpin { $name }`
@@ -0,0 +1,55 @@
+# Brazilian Portuguese messages.
+
+## vapor status
+
+status-name = Nome
+status-enabled = Ativado
+status-version = Versão
+status-author = Autor
+status-installed = Instalado
+status-description = Descrição
+status-homepage = Página
+status-pinned = Fixado
+status-tags = Tags
+status-note = Nota
+status-files = Arquivos
+status-missing-files = Arquivos ausentes
+status-modified-files = Arquivos modificados
+status-empty-files = Arquivos vazios
+status-incompatible = Não marcado como compatível com a versão do jogo `{ $version }`
+status-conflicts = Conflita com
+status-missing-dependencies = Dependências ausentes
+status-dependencies = Dependências
+status-provided-by = fornecido por `{ $provider }`
+status-optional-dependencies = Dependências opcionais
+status-not-enabled = não ativado
+
+## Prompts
+
+confirm-needed = Confirmação necessária: { $message }
+confirm-pass-yes = Passe `--yes` para confirmar
+aborted = Cancelado
+confirm-enable-dependencies = Isso também ativa as dependências: { $mods }. Continuar?
+confirm-remove-all = Remover todos os mods?
+confirm-reset = Remover todos os mods e tudo o que o Vapor guarda? Isso não pode ser desfeito
+confirm-overwrite = Sobrescrever `{ $path }`?
+confirm-undo = { $count ->
+    [one] Desfazer a última operação?
+   *[other] Desfazer as últimas { $count } operações?
+}
+confirm-collection-dependencies = Isso também ativa as dependências da coleção `{ $name }`: { $mods }. Continuar?
+confirm-install-modlist = { $count ->
+    [one] Instalar { $count } mod de `{ $name }`?
+   *[other] Instalar { $count } mods de `{ $name }`?
+}
+confirm-restore-saves = Substituir os saves em `{ $dir }` por `{ $backup }`?
+confirm-suspicious = Adicionar mesmo assim?
+
+## Errors
+
+error-missing-mod = Mod não encontrado: `{ $name }`
+error-did-you-mean = você quis dizer `{ $name }`?
+error-already-exists = Já existe um mod chamado `{ $name }`
+help-already-exists = Escolha outro nome ou remova o mod existente primeiro
+error-pinned = `{ $name }` está fixado
+help-pinned = Passe `--force` para alterá-lo mesmo assim, ou execute `vapor unpin { $name }`
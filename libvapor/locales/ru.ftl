@@ -0,0 +1,58 @@
+# Russian messages.
+
+## vapor status
+
+status-name = Имя
+status-enabled = Включён
+status-version = Версия
+status-author = Автор
+status-installed = Установлен
+status-description = Описание
+status-homepage = Домашняя страница
+status-pinned = Закреплён
+status-tags = Теги
+status-note = Заметка
+status-files = Файлы
+status-missing-files = Отсутствующие файлы
+status-modified-files = Изменённые файлы
+status-empty-files = Пустые файлы
+status-incompatible = Не отмечен как совместимый с версией игры `{ $version }`
+status-conflicts = Конфликтует с
+status-missing-dependencies = Отсутствующие зависимости
+status-dependencies = Зависимости
+status-provided-by = предоставляется `{ $provider }`
+status-optional-dependencies = Необязательные зависимости
+status-not-enabled = не включён
+
+## Prompts
+
+confirm-needed = Требуется подтверждение: { $message }
+confirm-pass-yes = Передайте `--yes` для подтверждения
+aborted = Отменено
+confirm-enable-dependencies = Также будут включены зависимости: { $mods }. Продолжить?
+confirm-remove-all = Удалить все моды?
+confirm-reset = Удалить все моды и всё, что хранит Vapor? Это нельзя отменить
+confirm-overwrite = Перезаписать `{ $path }`?
+confirm-undo = { $count ->
+    [1] Отменить последнюю операцию?
+    [one] Отменить последнюю { $count } операцию?
+    [few] Отменить последние { $count } операции?
+   *[many] Отменить последние { $count } операций?
+}
+confirm-collection-dependencies = Также будут включены зависимости коллекции `{ $name }`: { $mods }. Продолжить?
+confirm-install-modlist = { $count ->
+    [one] Установить { $count } мод из `{ $name }`?
+    [few] Установить { $count } мода из `{ $name }`?
+   *[many] Установить { $count } модов из `{ $name }`?
+}
+confirm-restore-saves = Заменить сохранения в `{ $dir }` копией `{ $backup }`?
+confirm-suspicious = Всё равно добавить?
+
+## Errors
+
+error-missing-mod = Мод не найден: `{ $name }`
+error-did-you-mean = возможно, вы имели в виду `{ $name }`?
+error-already-exists = Мод с именем `{ $name }` уже существует
+help-already-exists = Выберите другое имя или сначала удалите существующий мод
+error-pinned = `{ $name }` закреплён
+help-pinned = Передайте `--force`, чтобы всё равно изменить его, или выполните `vapor unpin { $name }`
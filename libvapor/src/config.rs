@@ -0,0 +1,421 @@
+//! Typed access to the settings in `Vapor.toml`, behind `vapor config`.
+
+use std::path::{Path, PathBuf};
+
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize, de::value::StrDeserializer};
+use thiserror::Error;
+
+use crate::hooks::HooksToml;
+use crate::init::{ColorChoice, ConflictPolicy, CyberToml, DeployMode, Init, InitError};
+use crate::mod_manager::name::NameFolding;
+
+/// Keys understood by [`Config::get`] and [`Config::set`].
+pub const KEYS: &[&str] = &[
+    "path",
+    "deploy_mode",
+    "download_dir",
+    "nexus_api_key",
+    "color",
+    "conflict_policy",
+    "auto_clear_cache",
+    "name_folding",
+    "staging_dir",
+    "trash",
+    "minimal_diff",
+    "saves_dir",
+    "backup_saves",
+    "dedupe",
+    "modlist",
+    "network.enabled",
+    "network.proxy",
+    "network.ca_bundle",
+];
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum ConfigError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Init(#[from] InitError),
+    #[error("Unknown config key `{0}`")]
+    #[diagnostic(help("Known keys are {}", KEYS.join(", ")))]
+    UnknownKey(String),
+    #[error("Invalid value `{value}` for `{key}`")]
+    InvalidValue {
+        key: String,
+        value: String,
+        #[help]
+        expected: String,
+    },
+}
+
+/// `Vapor.toml`, loaded for reading and changing settings.
+pub struct Config {
+    path: PathBuf,
+    toml: CyberToml,
+}
+
+impl Config {
+    /// Load the config file.
+    pub fn load() -> Result<Self, ConfigError> {
+        Ok(Self {
+            path: Init::get_config()?,
+            toml: CyberToml::load()?,
+        })
+    }
+
+    /// Where the config file lives.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path to the `Cyberpunk 2077` directory.
+    pub fn game_path(&self) -> &str {
+        &self.toml.main.path
+    }
+
+    pub fn set_game_path(&mut self, path: impl Into<String>) {
+        self.toml.main.path = path.into();
+    }
+
+    pub fn deploy_mode(&self) -> DeployMode {
+        self.toml.main.deploy_mode
+    }
+
+    pub fn set_deploy_mode(&mut self, mode: DeployMode) {
+        self.toml.main.deploy_mode = mode;
+    }
+
+    /// Configured download directory, if not the default.
+    pub fn download_dir(&self) -> Option<&str> {
+        self.toml.main.download_dir.as_deref()
+    }
+
+    pub fn set_download_dir(&mut self, dir: Option<String>) {
+        self.toml.main.download_dir = dir;
+    }
+
+    pub fn staging_dir(&self) -> Option<&str> {
+        self.toml.main.staging_dir.as_deref()
+    }
+
+    pub fn set_staging_dir(&mut self, dir: Option<String>) {
+        self.toml.main.staging_dir = dir;
+    }
+
+    /// Where to find the Nexus API key.
+    pub fn nexus_api_key(&self) -> Option<&str> {
+        self.toml.main.nexus_api_key.as_deref()
+    }
+
+    pub fn set_nexus_api_key(&mut self, reference: Option<String>) {
+        self.toml.main.nexus_api_key = reference;
+    }
+
+    pub fn color(&self) -> ColorChoice {
+        self.toml.main.color
+    }
+
+    pub fn set_color(&mut self, color: ColorChoice) {
+        self.toml.main.color = color;
+    }
+
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        self.toml.main.conflict_policy
+    }
+
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.toml.main.conflict_policy = policy;
+    }
+
+    /// Hooks run for every mod, and the commands allowed to run.
+    pub fn hooks(&self) -> &HooksToml {
+        &self.toml.hooks
+    }
+
+    pub fn hooks_mut(&mut self) -> &mut HooksToml {
+        &mut self.toml.hooks
+    }
+
+    pub fn auto_clear_cache(&self) -> bool {
+        self.toml.main.auto_clear_cache
+    }
+
+    pub fn set_auto_clear_cache(&mut self, auto_clear_cache: bool) {
+        self.toml.main.auto_clear_cache = auto_clear_cache;
+    }
+
+    pub fn trash(&self) -> bool {
+        self.toml.main.trash
+    }
+
+    pub fn set_trash(&mut self, trash: bool) {
+        self.toml.main.trash = trash;
+    }
+
+    pub fn minimal_diff(&self) -> bool {
+        self.toml.main.minimal_diff
+    }
+
+    pub fn set_minimal_diff(&mut self, minimal_diff: bool) {
+        self.toml.main.minimal_diff = minimal_diff;
+    }
+
+    /// Directory the game keeps its saves in, if set rather than found.
+    pub fn saves_dir(&self) -> Option<&str> {
+        self.toml.main.saves_dir.as_deref()
+    }
+
+    pub fn set_saves_dir(&mut self, dir: Option<String>) {
+        self.toml.main.saves_dir = dir;
+    }
+
+    pub fn backup_saves(&self) -> bool {
+        self.toml.main.backup_saves
+    }
+
+    pub fn set_backup_saves(&mut self, backup_saves: bool) {
+        self.toml.main.backup_saves = backup_saves;
+    }
+
+    pub fn dedupe(&self) -> bool {
+        self.toml.main.dedupe
+    }
+
+    pub fn set_dedupe(&mut self, dedupe: bool) {
+        self.toml.main.dedupe = dedupe;
+    }
+
+    pub fn modlist(&self) -> bool {
+        self.toml.main.modlist
+    }
+
+    pub fn set_modlist(&mut self, modlist: bool) {
+        self.toml.main.modlist = modlist;
+    }
+
+    pub fn network_enabled(&self) -> bool {
+        self.toml.network.enabled
+    }
+
+    pub fn set_network_enabled(&mut self, enabled: bool) {
+        self.toml.network.enabled = enabled;
+    }
+
+    pub fn network_proxy(&self) -> Option<&str> {
+        self.toml.network.proxy.as_deref()
+    }
+
+    pub fn set_network_proxy(&mut self, proxy: Option<String>) {
+        self.toml.network.proxy = proxy;
+    }
+
+    pub fn network_ca_bundle(&self) -> Option<&str> {
+        self.toml.network.ca_bundle.as_deref()
+    }
+
+    pub fn set_network_ca_bundle(&mut self, ca_bundle: Option<String>) {
+        self.toml.network.ca_bundle = ca_bundle;
+    }
+
+    pub fn name_folding(&self) -> NameFolding {
+        self.toml.main.name_folding
+    }
+
+    pub fn set_name_folding(&mut self, folding: NameFolding) {
+        self.toml.main.name_folding = folding;
+    }
+
+    /// Value of `key` as text, empty when unset.
+    pub fn get(&self, key: &str) -> Result<String, ConfigError> {
+        Ok(match key {
+            "path" => self.game_path().to_string(),
+            "deploy_mode" => name(self.deploy_mode()),
+            "download_dir" => self.download_dir().unwrap_or_default().to_string(),
+            "nexus_api_key" => self.nexus_api_key().unwrap_or_default().to_string(),
+            "color" => name(self.color()),
+            "conflict_policy" => name(self.conflict_policy()),
+            "auto_clear_cache" => self.auto_clear_cache().to_string(),
+            "name_folding" => name(self.name_folding()),
+            "staging_dir" => self.staging_dir().unwrap_or_default().to_string(),
+            "trash" => self.trash().to_string(),
+            "minimal_diff" => self.minimal_diff().to_string(),
+            "saves_dir" => self.saves_dir().unwrap_or_default().to_string(),
+            "backup_saves" => self.backup_saves().to_string(),
+            "dedupe" => self.dedupe().to_string(),
+            "modlist" => self.modlist().to_string(),
+            "network.enabled" => self.network_enabled().to_string(),
+            "network.proxy" => self.network_proxy().unwrap_or_default().to_string(),
+            "network.ca_bundle" => self.network_ca_bundle().unwrap_or_default().to_string(),
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        })
+    }
+
+    /// Set `key` from text, an empty value unsetting optional keys.
+    ///
+    /// Nothing is written until [`Config::save`].
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let optional = || (!value.is_empty()).then(|| value.to_string());
+
+        match key {
+            "path" => {
+                if !Path::new(value).is_dir() {
+                    return Err(ConfigError::InvalidValue {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        expected: "expected an existing directory".to_string(),
+                    });
+                }
+                self.set_game_path(value);
+            }
+            "deploy_mode" => self.set_deploy_mode(parse(key, value, "move, symlink or hardlink")?),
+            "download_dir" => self.set_download_dir(optional()),
+            "nexus_api_key" => self.set_nexus_api_key(optional()),
+            "color" => self.set_color(parse(key, value, "auto, always or never")?),
+            "conflict_policy" => {
+                self.set_conflict_policy(parse(key, value, "error or overwrite")?);
+            }
+            "auto_clear_cache" => self.set_auto_clear_cache(parse_bool(key, value)?),
+            "name_folding" => {
+                self.set_name_folding(parse(key, value, "none, case, whitespace or all")?);
+            }
+            "staging_dir" => self.set_staging_dir(optional()),
+            "trash" => self.set_trash(parse_bool(key, value)?),
+            "minimal_diff" => self.set_minimal_diff(parse_bool(key, value)?),
+            "saves_dir" => self.set_saves_dir(optional()),
+            "backup_saves" => self.set_backup_saves(parse_bool(key, value)?),
+            "dedupe" => self.set_dedupe(parse_bool(key, value)?),
+            "modlist" => self.set_modlist(parse_bool(key, value)?),
+            "network.enabled" => self.set_network_enabled(parse_bool(key, value)?),
+            "network.proxy" => self.set_network_proxy(optional()),
+            "network.ca_bundle" => self.set_network_ca_bundle(optional()),
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Write back to the config file.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        Ok(self.toml.save()?)
+    }
+}
+
+/// Name of a unit enum variant as written in `Vapor.toml`.
+fn name<T: Serialize>(value: T) -> String {
+    toml::Value::try_from(value)
+        .ok()
+        .and_then(|value| value.as_str().map(ToString::to_string))
+        .unwrap_or_default()
+}
+
+/// `value` as `true` or `false`.
+fn parse_bool(key: &str, value: &str) -> Result<bool, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        expected: "expected true or false".to_string(),
+    })
+}
+
+fn parse<'de, T: Deserialize<'de>>(
+    key: &str,
+    value: &'de str,
+    expected: &str,
+) -> Result<T, ConfigError> {
+    T::deserialize(StrDeserializer::<serde::de::value::Error>::new(value)).map_err(|_| {
+        ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            expected: format!("expected {expected}"),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn config() -> Config {
+        Config {
+            path: PathBuf::from("Vapor.toml"),
+            toml: toml::from_str("[main]\npath = \"/game\"\ncreated = \"2024-01-01T00:00:00Z\"")
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn every_key_reads_back_what_was_set() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_string_lossy().into_owned();
+        let mut config = config();
+
+        let values = [
+            ("path", path.as_str()),
+            ("deploy_mode", "symlink"),
+            ("download_dir", "/downloads"),
+            ("nexus_api_key", "keyring:nexus"),
+            ("color", "never"),
+            ("conflict_policy", "overwrite"),
+            ("auto_clear_cache", "true"),
+            ("name_folding", "case"),
+            ("staging_dir", "/staging"),
+            ("trash", "false"),
+            ("minimal_diff", "true"),
+            ("saves_dir", "/saves"),
+            ("backup_saves", "false"),
+            ("dedupe", "true"),
+            ("modlist", "true"),
+            ("network.enabled", "false"),
+            ("network.proxy", "http://proxy:8080"),
+            ("network.ca_bundle", "/ca.pem"),
+        ];
+        assert_eq!(values.map(|(key, _)| key), KEYS);
+
+        for (key, value) in values {
+            config.set(key, value).unwrap();
+            assert_eq!(config.get(key).unwrap(), value, "{key}");
+        }
+        assert_eq!(config.deploy_mode(), DeployMode::Symlink);
+        assert!(!config.network_enabled());
+    }
+
+    #[test]
+    fn empty_values_unset_optional_keys() {
+        let mut config = config();
+        config.set("download_dir", "/downloads").unwrap();
+        config.set("download_dir", "").unwrap();
+        assert_eq!(config.download_dir(), None);
+        assert_eq!(config.get("download_dir").unwrap(), "");
+    }
+
+    #[test]
+    fn refuses_bad_values_and_unknown_keys() {
+        let mut config = config();
+        for (key, value) in [
+            ("deploy_mode", "copy"),
+            ("trash", "yes"),
+            ("name_folding", ""),
+            ("path", "/no/such/game/directory"),
+        ] {
+            assert!(
+                matches!(
+                    config.set(key, value),
+                    Err(ConfigError::InvalidValue { key: bad, .. }) if bad == key
+                ),
+                "{key} = {value}"
+            );
+        }
+        assert_eq!(config.get("path").unwrap(), "/game");
+
+        assert!(matches!(
+            config.set("colour", "never"),
+            Err(ConfigError::UnknownKey(key)) if key == "colour"
+        ));
+        assert!(matches!(
+            config.get("colour"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+}
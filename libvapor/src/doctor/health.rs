@@ -0,0 +1,297 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+use crate::{
+    init::{CyberToml, DEFAULT_GAME, Init},
+    mod_manager::{handler::ModHandler, load_order::MODLIST, registry::ModRegistry},
+    platform, proton, steam,
+};
+
+/// A problem found by [`check`].
+#[derive(Error, Diagnostic, Debug)]
+pub enum HealthIssue {
+    #[error("Missing config at `{0}`")]
+    #[diagnostic(code(doctor::config), help("Run `vapor init` to create it"))]
+    MissingConfig(PathBuf),
+    #[error("Could not parse `{path}`: {reason}")]
+    #[diagnostic(
+        code(doctor::config),
+        help("Fix the config, or delete it and run `vapor init`")
+    )]
+    BrokenConfig {
+        path: PathBuf,
+        reason: String,
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("here")]
+        span: Option<SourceSpan>,
+    },
+    #[error("Unknown game `{0}`")]
+    #[diagnostic(code(doctor::game), help("Add it with `vapor init --game {0}`"))]
+    UnknownGame(String),
+    #[error("Game directory `{0}` does not exist")]
+    #[diagnostic(
+        code(doctor::game),
+        help("Point `path` in `Vapor.toml` at your `Cyberpunk 2077` directory")
+    )]
+    MissingGame(PathBuf),
+    #[error("Missing registry at `{0}`")]
+    #[diagnostic(code(doctor::registry), help("Create an empty `mods.toml` there"))]
+    MissingRegistry(PathBuf),
+    #[error("Could not parse `{path}`: {reason}")]
+    #[diagnostic(code(doctor::registry), help("Fix the entry in `mods.toml` by hand"))]
+    BrokenRegistry {
+        path: PathBuf,
+        reason: String,
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("here")]
+        span: Option<SourceSpan>,
+    },
+    #[error("Missing `{0}`")]
+    #[diagnostic(
+        code(doctor::disabled),
+        help("Create the directory, disabled mods live there")
+    )]
+    MissingDisabledDir(PathBuf),
+    #[error("`{mod_name}` is missing `{path}`")]
+    #[diagnostic(
+        code(doctor::files),
+        help("Reinstall the mod with `vapor add` to restore its files")
+    )]
+    MissingFile { mod_name: String, path: PathBuf },
+    #[error("`{mod_name}` is split between enabled and disabled mods ({count} stray files)")]
+    #[diagnostic(
+        code(doctor::transaction),
+        help("An enable or disable was interrupted, run `vapor fix-moves` to finish it")
+    )]
+    PartialMove { mod_name: String, count: usize },
+    #[error("`{mod_name}` has a modified `{path}`")]
+    #[diagnostic(
+        code(doctor::hash),
+        help(
+            "Something else overwrote this file, reinstall the mod with `vapor add` to restore it, or keep the change with `vapor edit`"
+        )
+    )]
+    ModifiedFile { mod_name: String, path: PathBuf },
+    #[error("Paths only differ by case: {}", .0.join(", "))]
+    #[diagnostic(
+        code(doctor::case),
+        help("The game only loads one of these trees, remove the mods that use the wrong casing")
+    )]
+    CaseCollision(Vec<String>),
+    #[error("`{MODLIST}` lists archives that aren't installed: {}", .0.join(", "))]
+    #[diagnostic(
+        code(doctor::modlist),
+        help(
+            "The game skips these without a word, rewrite the file with `vapor modlist --write` or delete it"
+        )
+    )]
+    StaleModlist(Vec<String>),
+    #[error("No Proton prefix for the game")]
+    #[diagnostic(
+        code(doctor::proton),
+        help(
+            "Launch the game through Steam once to create it, or set `prefix` under `[launch]` in `Vapor.toml`"
+        )
+    )]
+    MissingPrefix,
+    #[error("The game runs with Proton {0}, older than Proton {min}", min = proton::MIN_VERSION)]
+    #[diagnostic(
+        code(doctor::proton),
+        help("Pick a newer Proton under the game's Properties > Compatibility in Steam")
+    )]
+    OldProton(String),
+    #[error(
+        "The game's prefix was last run with Proton {prefix}, but `[launch]` uses {configured}"
+    )]
+    #[diagnostic(
+        code(doctor::proton),
+        help("Point `proton` under `[launch]` at the Proton Steam runs the game with")
+    )]
+    ProtonMismatch { prefix: String, configured: String },
+    #[error("Proton won't load {}", .dlls.join(", "))]
+    #[diagnostic(code(doctor::proton))]
+    MissingOverride {
+        dlls: Vec<String>,
+        #[help]
+        help: String,
+    },
+}
+
+/// Problems with the game's Proton prefix that stop frameworks from loading.
+fn check_proton(config: &CyberToml, game_path: &Path) -> Vec<HealthIssue> {
+    // Only Steam installs have a prefix Vapor knows where to find.
+    let from_steam = steam::library_of(&steam::resolve(game_path)).is_some();
+    if cfg!(windows) || !(from_steam || config.launch.is_some()) {
+        return vec![];
+    }
+    let Some(prefix) = proton::find_prefix(config) else {
+        return vec![HealthIssue::MissingPrefix];
+    };
+
+    let mut issues = vec![];
+    let prefix_version = proton::prefix_version(&prefix);
+    if let Some(version) = &prefix_version
+        && proton::major_version(version).is_some_and(|major| major < proton::MIN_VERSION)
+    {
+        issues.push(HealthIssue::OldProton(version.clone()));
+    }
+
+    if let (Some(prefix), Some(launch)) = (prefix_version, &config.launch)
+        && let Some(configured) = proton::proton_version(&steam::resolve(Path::new(&launch.proton)))
+        && prefix != configured
+    {
+        issues.push(HealthIssue::ProtonMismatch { prefix, configured });
+    }
+
+    let missing = proton::missing_overrides(game_path, &prefix);
+    if !missing.is_empty() {
+        issues.push(HealthIssue::MissingOverride {
+            help: format!(
+                "Set the game's launch options in Steam to `WINEDLLOVERRIDES=\"{}\" %command%`, or add the overrides with `protontricks {} winecfg`",
+                proton::dll_overrides(&missing),
+                crate::launch::STEAM_APP_ID,
+            ),
+            dlls: missing.into_iter().map(|dll| format!("{dll}.dll")).collect(),
+        });
+    }
+    issues
+}
+
+/// Check the whole install of the game `id` for problems.
+pub fn check(id: Option<&str>) -> Vec<HealthIssue> {
+    let mut issues = vec![];
+
+    let config_path = match Init::get_config() {
+        Ok(path) => path,
+        Err(_) => {
+            issues.push(HealthIssue::MissingConfig(
+                platform::config_home()
+                    .unwrap_or_default()
+                    .join("Vapor.toml"),
+            ));
+            return issues;
+        }
+    };
+
+    let contents = fs::read_to_string(&config_path).unwrap_or_default();
+    let config = match CyberToml::from_str(&contents) {
+        Ok(config) => config,
+        Err(err) => {
+            issues.push(HealthIssue::BrokenConfig {
+                reason: err.message().to_string(),
+                src: NamedSource::new(config_path.to_string_lossy(), contents),
+                span: err.span().map(Into::into),
+                path: config_path,
+            });
+            return issues;
+        }
+    };
+
+    let Ok(game) = config.game(id) else {
+        issues.push(HealthIssue::UnknownGame(id.unwrap_or_default().to_string()));
+        return issues;
+    };
+
+    let game_path = PathBuf::from(&game.path);
+    if !game_path.is_dir() {
+        issues.push(HealthIssue::MissingGame(game_path));
+        return issues;
+    }
+
+    if id.is_none_or(|id| id == DEFAULT_GAME) {
+        issues.extend(check_proton(&config, &game_path));
+    }
+
+    let handler = ModHandler::for_game(&game);
+    if !handler.deploy_mode.links() && !handler.disabled.is_dir() {
+        issues.push(HealthIssue::MissingDisabledDir(handler.disabled.clone()));
+    }
+
+    let registry_path = handler.toml.clone();
+    let Ok(contents) = fs::read_to_string(&registry_path) else {
+        issues.push(HealthIssue::MissingRegistry(registry_path));
+        return issues;
+    };
+
+    let registry: ModRegistry = match toml::from_str(&contents) {
+        Ok(registry) => registry,
+        Err(err) => {
+            issues.push(HealthIssue::BrokenRegistry {
+                reason: err.message().to_string(),
+                src: NamedSource::new(registry_path.to_string_lossy(), contents),
+                span: err.span().map(Into::into),
+                path: registry_path,
+            });
+            return issues;
+        }
+    };
+
+    for (mod_name, entry) in &registry.mods {
+        let (home, other) = handler.file_roots(mod_name, entry.installed);
+
+        let mut stray = 0;
+        for file in entry.deployed_files() {
+            if home.join(file).exists() {
+                continue;
+            }
+
+            if other
+                .as_ref()
+                .is_some_and(|other| other.join(file).exists())
+            {
+                stray += 1;
+            } else {
+                issues.push(HealthIssue::MissingFile {
+                    mod_name: mod_name.to_string(),
+                    path: home.join(file),
+                });
+            }
+        }
+
+        if stray > 0 {
+            issues.push(HealthIssue::PartialMove {
+                mod_name: mod_name.to_string(),
+                count: stray,
+            });
+        }
+
+        issues.extend(
+            handler
+                .modified_files(mod_name, entry, true)
+                .into_iter()
+                .map(|path| HealthIssue::ModifiedFile {
+                    mod_name: mod_name.to_string(),
+                    path,
+                }),
+        );
+    }
+
+    let stale = handler.stale_modlist_entries();
+    if !stale.is_empty() {
+        issues.push(HealthIssue::StaleModlist(stale));
+    }
+
+    let mut folded: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for file in registry.mods.values().flat_map(|entry| &entry.files) {
+        let paths = folded.entry(file.to_lowercase()).or_default();
+        if !paths.contains(&file.as_str()) {
+            paths.push(file);
+        }
+    }
+    for paths in folded.into_values().filter(|paths| paths.len() > 1) {
+        issues.push(HealthIssue::CaseCollision(
+            paths.into_iter().map(ToString::to_string).collect(),
+        ));
+    }
+
+    issues
+}
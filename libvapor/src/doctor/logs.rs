@@ -0,0 +1,153 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use inline_colorization::*;
+
+use crate::{mod_manager::registry::ModRegistry, render};
+
+/// Directory CET keeps its own log and every lua mod in.
+const CET_DIR: &str = "bin/x64/plugins/cyber_engine_tweaks";
+
+/// An error line found in a log.
+pub struct LogFinding {
+    /// Log the error came from.
+    pub log: PathBuf,
+    /// Line number in the log.
+    pub line: usize,
+    /// The offending log line.
+    pub message: String,
+    /// Game file the error points to, relative to the game root.
+    pub file: Option<String>,
+    /// Mod owning [`LogFinding::file`].
+    pub owner: Option<String>,
+}
+
+/// Scan redscript and CET logs under `root` for errors.
+pub fn scan(root: &Path, registry: &ModRegistry) -> Vec<LogFinding> {
+    let mut findings = vec![];
+
+    let redscript = root.join("r6").join("logs").join("redscript_rCURRENT.log");
+    for (line, message) in error_lines(&redscript, "[ERROR") {
+        let file = script_path(&message);
+        findings.push(LogFinding {
+            owner: file
+                .as_deref()
+                .and_then(|file| registry.owner_of(file))
+                .map(ToString::to_string),
+            log: redscript.clone(),
+            line,
+            message,
+            file,
+        });
+    }
+
+    let cet = root.join(CET_DIR);
+    let cet_log = cet.join("cyber_engine_tweaks.log");
+    for (line, message) in error_lines(&cet_log, "[error]") {
+        findings.push(LogFinding {
+            log: cet_log.clone(),
+            line,
+            message,
+            file: None,
+            owner: None,
+        });
+    }
+
+    // Every CET mod logs into `mods/<name>/<name>.log`, so errors there belong to whoever owns
+    // that directory.
+    if let Ok(dirs) = fs::read_dir(cet.join("mods")) {
+        for dir in dirs.flatten() {
+            let name = dir.file_name().to_string_lossy().to_string();
+            let log = dir.path().join(format!("{name}.log"));
+            let mod_dir = format!("{CET_DIR}/mods/{name}/");
+            let owner = registry
+                .mods
+                .iter()
+                .find(|(_, entry)| entry.files.iter().any(|f| f.starts_with(&mod_dir)))
+                .map(|(owner, _)| owner.to_string());
+
+            for (line, message) in error_lines(&log, "[error]") {
+                findings.push(LogFinding {
+                    log: log.clone(),
+                    line,
+                    message,
+                    file: Some(mod_dir.clone()),
+                    owner: owner.clone(),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+/// Render findings grouped by owning mod.
+#[allow(unused_must_use)]
+pub fn report(findings: &[LogFinding]) -> String {
+    let mut out = String::new();
+    let mut by_owner: BTreeMap<Option<&str>, Vec<&LogFinding>> = BTreeMap::new();
+
+    for finding in findings {
+        by_owner
+            .entry(finding.owner.as_deref())
+            .or_default()
+            .push(finding);
+    }
+
+    for (owner, findings) in by_owner {
+        match owner {
+            Some(owner) => writeln!(
+                &mut out,
+                "{style_bold}*{style_reset} {style_bold}{color_red}{owner}{style_reset} is likely broken ({} errors)",
+                findings.len()
+            ),
+            None => writeln!(
+                &mut out,
+                "{style_bold}*{style_reset} {style_bold}{color_yellow}Unattributed errors{style_reset} ({})",
+                findings.len()
+            ),
+        };
+
+        for finding in findings {
+            writeln!(
+                &mut out,
+                "    > {}:{}: {}",
+                finding.log.display(),
+                finding.line,
+                finding.message.trim()
+            );
+        }
+    }
+
+    render::finish(out)
+}
+
+/// Lines in `log` containing `marker`, with their 1-based line numbers.
+fn error_lines(log: &Path, marker: &str) -> Vec<(usize, String)> {
+    let Ok(contents) = fs::read(log) else {
+        return vec![];
+    };
+
+    String::from_utf8_lossy(&contents)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains(marker))
+        .map(|(i, line)| (i + 1, line.to_string()))
+        .collect()
+}
+
+/// Pull an `r6/scripts/...` path out of a redscript error line.
+///
+/// The log uses Windows paths, so separators are normalized and the `:line:col` suffix dropped.
+fn script_path(line: &str) -> Option<String> {
+    let line = line.replace('\\', "/");
+    let start = line.find("r6/scripts/")?;
+    let rest = &line[start..];
+    let end = rest.find(".reds")? + ".reds".len();
+
+    Some(rest[..end].to_string())
+}
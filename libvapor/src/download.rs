@@ -0,0 +1,315 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tracing::info;
+use ureq::{
+    Agent, Proxy,
+    tls::{PemItem, RootCerts, TlsConfig},
+};
+
+use crate::platform;
+
+const USER_AGENT: &str = concat!("vapor/", env!("CARGO_PKG_VERSION"));
+
+/// Set by [`set_offline`], checked before every request.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Settings from [`configure`], and the client built from them on the first request.
+static CLIENT: Mutex<Client> = Mutex::new(Client {
+    network: None,
+    agent: None,
+});
+
+struct Client {
+    network: Option<NetworkToml>,
+    agent: Option<Agent>,
+}
+
+/// `[network]` in `Vapor.toml`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct NetworkToml {
+    /// Allow Vapor to go online at all, for Nexus, GitHub, downloads and update checks.
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    /// Proxy to send requests through, like `http://proxy.example.com:8080`, instead of the one
+    /// in `HTTPS_PROXY` and friends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file of certificates to trust instead of the built in ones, instead of the one in
+    /// `SSL_CERT_FILE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+}
+
+impl Default for NetworkToml {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            proxy: None,
+            ca_bundle: None,
+        }
+    }
+}
+
+impl NetworkToml {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Refuse every request from now on, for `--offline` and `enabled = false` under `[network]`.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Check if requests are refused, see [`set_offline`].
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Send requests through the proxy and trust the certificates `network` names from now on.
+///
+/// Without a call to this, or for settings left unset, requests go through the proxy in
+/// `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` unless `NO_PROXY` matches, and trust the
+/// certificates in `SSL_CERT_FILE` if set. Mistakes in either are reported by the first request.
+pub fn configure(network: &NetworkToml) {
+    let mut client = CLIENT.lock().unwrap_or_else(|err| err.into_inner());
+    client.network = Some(network.clone());
+    client.agent = None;
+}
+
+/// The client to send requests with, failing if there's no network or it's set up wrong.
+fn agent(url: &str) -> Result<Agent, DownloadError> {
+    if is_offline() {
+        info!(%url, "not fetching, offline");
+        return Err(DownloadError::Offline(url.to_string()));
+    }
+
+    let mut client = CLIENT.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(agent) = &client.agent {
+        return Ok(agent.clone());
+    }
+    let agent = build_agent(&client.network.clone().unwrap_or_default())?;
+    client.agent = Some(agent.clone());
+    Ok(agent)
+}
+
+fn build_agent(network: &NetworkToml) -> Result<Agent, DownloadError> {
+    let proxy = match &network.proxy {
+        Some(proxy) => {
+            Some(Proxy::new(proxy).map_err(|_| DownloadError::InvalidProxy(proxy.clone()))?)
+        }
+        None => Proxy::try_from_env(),
+    };
+    if let Some(proxy) = &proxy {
+        info!(host = proxy.host(), port = proxy.port(), "using proxy");
+    }
+
+    let ca_bundle = match &network.ca_bundle {
+        Some(path) => Some(PathBuf::from(shellexpand::tilde(path).as_ref())),
+        None => env::var_os("SSL_CERT_FILE").map(PathBuf::from),
+    };
+    let mut tls = TlsConfig::builder();
+    if let Some(path) = ca_bundle {
+        info!(path = %path.display(), "trusting certificates from CA bundle");
+        tls = tls.root_certs(RootCerts::new_with_certs(&certificates(&path)?));
+    }
+
+    Ok(Agent::config_builder()
+        .proxy(proxy)
+        .tls_config(tls.build())
+        .build()
+        .into())
+}
+
+/// Certificates in the PEM file at `path`.
+fn certificates(path: &Path) -> Result<Vec<ureq::tls::Certificate<'static>>, DownloadError> {
+    let pem = fs::read(path).map_err(|source| DownloadError::CaBundle {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let certs = ureq::tls::parse_pem(&pem)
+        .filter_map(|item| match item {
+            Ok(PemItem::Certificate(cert)) => Some(cert),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if certs.is_empty() {
+        return Err(DownloadError::NoCertificates(path.to_path_buf()));
+    }
+    Ok(certs)
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum DownloadError {
+    #[error("io error: `{0}`")]
+    Io(#[from] std::io::Error),
+    #[error("http error: `{0}`")]
+    #[diagnostic(help("Check your internet connection and try again"))]
+    Http(#[from] ureq::Error),
+    #[error("Download cancelled")]
+    Cancelled,
+    #[error("Not fetching `{0}` while offline")]
+    #[diagnostic(help(
+        "Drop `--offline`, or set `enabled = true` under `[network]` in `Vapor.toml`"
+    ))]
+    Offline(String),
+    #[error("Invalid proxy `{0}`")]
+    #[diagnostic(help(
+        "Set `proxy` under `[network]` in `Vapor.toml`, or `HTTPS_PROXY`, to a URL like `http://proxy.example.com:8080`"
+    ))]
+    InvalidProxy(String),
+    #[error("Could not read CA bundle `{path}`")]
+    #[diagnostic(help(
+        "Point `ca_bundle` under `[network]` in `Vapor.toml`, or `SSL_CERT_FILE`, at a PEM file"
+    ))]
+    CaBundle {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("No certificates in CA bundle `{0}`")]
+    #[diagnostic(help("The file should hold `-----BEGIN CERTIFICATE-----` blocks"))]
+    NoCertificates(PathBuf),
+}
+
+/// Fetch `url` and deserialize the body as JSON.
+pub fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, DownloadError> {
+    get_json_with(url, &[])
+}
+
+/// Like [`get_json`], sending extra `headers` such as an API key.
+pub fn get_json_with<T: DeserializeOwned>(
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<T, DownloadError> {
+    let mut request = agent(url)?
+        .get(url)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    Ok(request.call()?.body_mut().read_json()?)
+}
+
+/// POST `body` as JSON to `url`, deserializing the response as JSON.
+pub fn post_json_with<T: DeserializeOwned>(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &impl Serialize,
+) -> Result<T, DownloadError> {
+    let mut request = agent(url)?
+        .post(url)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/json");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    Ok(request.send_json(body)?.body_mut().read_json()?)
+}
+
+/// Download `url` into `dir/file_name`.
+///
+/// The body is streamed into a `.part` file which is only renamed once complete, so an
+/// interrupted download never leaves a truncated archive behind.
+pub fn download_to(url: &str, dir: &Path, file_name: &str) -> Result<PathBuf, DownloadError> {
+    download_until(url, dir, file_name, || false)
+}
+
+/// Like [`download_to`], but gives up once `cancelled` returns `true`, checked between chunks.
+pub fn download_until(
+    url: &str,
+    dir: &Path,
+    file_name: &str,
+    cancelled: impl Fn() -> bool,
+) -> Result<PathBuf, DownloadError> {
+    download_with_progress(url, dir, file_name, |_, _| {}, cancelled)
+}
+
+/// Like [`download_until`], calling `progress` with the bytes downloaded so far and the total, if
+/// the server said, after every chunk.
+pub fn download_with_progress(
+    url: &str,
+    dir: &Path,
+    file_name: &str,
+    mut progress: impl FnMut(u64, Option<u64>),
+    cancelled: impl Fn() -> bool,
+) -> Result<PathBuf, DownloadError> {
+    let agent = agent(url)?;
+    fs::create_dir_all(dir)?;
+
+    let dest = dir.join(file_name);
+    let part = dir.join(format!("{file_name}.part"));
+
+    info!(%url, dest = %dest.display(), "downloading");
+    let mut response = agent.get(url).header("User-Agent", USER_AGENT).call()?;
+    let mut file = File::create(&part)?;
+    let total = response.body().content_length();
+    let mut body = response.body_mut().as_reader();
+    let mut buf = vec![0; 64 * 1024];
+    let mut downloaded = 0;
+
+    loop {
+        if cancelled() {
+            drop(file);
+            fs::remove_file(&part)?;
+            return Err(DownloadError::Cancelled);
+        }
+
+        match body.read(&mut buf)? {
+            0 => break,
+            n => {
+                file.write_all(&buf[..n])?;
+                downloaded += n as u64;
+                progress(downloaded, total);
+            }
+        }
+    }
+
+    fs::rename(&part, &dest)?;
+
+    Ok(dest)
+}
+
+/// Stream `reader`, like stdin, into `dir/file_name`, through a `.part` file like a download.
+pub fn save_to(mut reader: impl Read, dir: &Path, file_name: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let dest = dir.join(file_name);
+    let part = dir.join(format!("{file_name}.part"));
+
+    info!(dest = %dest.display(), "saving stream");
+    let mut file = File::create(&part)?;
+    if let Err(err) = std::io::copy(&mut reader, &mut file) {
+        drop(file);
+        let _ = fs::remove_file(&part);
+        return Err(err);
+    }
+    fs::rename(&part, &dest)?;
+
+    Ok(dest)
+}
+
+/// Directory downloaded archives are kept in.
+pub fn cache_dir() -> Result<PathBuf, std::io::Error> {
+    platform::cache_dir("downloads")
+}
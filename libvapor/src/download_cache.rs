@@ -0,0 +1,231 @@
+//! Keeping the download directory and archive listing cache from growing forever.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tracing::{debug, info};
+
+use crate::mod_manager::mod_file_formats;
+
+/// Disk used by the caches.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub archives: usize,
+    pub archive_bytes: u64,
+    /// Downloads that never finished.
+    pub partial: usize,
+    pub partial_bytes: u64,
+    pub listings: usize,
+    pub listing_bytes: u64,
+}
+
+/// What `vapor cache gc` keeps.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    /// Newest archives to keep of each mod.
+    pub keep: usize,
+    /// Most bytes of archives to keep, deleting the oldest first.
+    pub max_size: Option<u64>,
+}
+
+struct Archive {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+fn archives(dir: &Path) -> io::Result<Vec<Archive>> {
+    let mut archives = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            archives.push(Archive {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            });
+        }
+    }
+    Ok(archives)
+}
+
+fn is_partial(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "part")
+}
+
+/// How much is in the download directory `downloads` and the listing cache.
+pub fn stats(downloads: &Path) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+
+    for archive in archives(downloads)? {
+        if is_partial(&archive.path) {
+            stats.partial += 1;
+            stats.partial_bytes += archive.size;
+        } else {
+            stats.archives += 1;
+            stats.archive_bytes += archive.size;
+        }
+    }
+
+    if let Some(dir) = mod_file_formats::listing_dir() {
+        for listing in archives(&dir)? {
+            stats.listings += 1;
+            stats.listing_bytes += listing.size;
+        }
+    }
+
+    Ok(stats)
+}
+
+/// The mod a downloaded archive is of, which is its name up to the version.
+///
+/// Nexus names downloads like `Mod Name-1234-2-1-1700000000.zip`, and GitHub releases usually
+/// look like `mod-v1.2.zip`, so this cuts at the first `-` or `_` followed by a digit or `v` and a
+/// digit.
+pub fn family(file_name: &str) -> &str {
+    let stem = file_name
+        .split_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+    let bytes = stem.as_bytes();
+
+    (0..bytes.len())
+        .find(|&i| {
+            matches!(bytes[i], b'-' | b'_')
+                && match bytes.get(i + 1) {
+                    Some(b'v' | b'V') => bytes.get(i + 2).is_some_and(u8::is_ascii_digit),
+                    Some(byte) => byte.is_ascii_digit(),
+                    None => false,
+                }
+        })
+        .map_or(stem, |i| &stem[..i])
+}
+
+/// Delete archives from `downloads` that `retention` doesn't keep, along with unfinished downloads
+/// and the cached listings of what was deleted.
+///
+/// Archives in `referenced`, which installed mods came from, are never deleted. Returns the
+/// deleted archives and their sizes, without deleting anything if `dry_run`.
+pub fn gc(
+    downloads: &Path,
+    retention: Retention,
+    referenced: &[PathBuf],
+    dry_run: bool,
+) -> io::Result<Vec<(PathBuf, u64)>> {
+    let referenced = referenced
+        .iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect::<Vec<_>>();
+
+    let (partial, mut archives): (Vec<_>, Vec<_>) = archives(downloads)?
+        .into_iter()
+        .partition(|archive| is_partial(&archive.path));
+    // Newest first.
+    archives.sort_by_key(|archive| std::cmp::Reverse(archive.modified));
+
+    let mut removed = partial;
+    let mut kept = vec![];
+    let mut seen = BTreeMap::<String, usize>::new();
+    for archive in archives {
+        let name = archive
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let count = seen.entry(family(&name).to_lowercase()).or_default();
+
+        let is_referenced = archive
+            .path
+            .canonicalize()
+            .is_ok_and(|path| referenced.contains(&path));
+        if is_referenced || *count < retention.keep {
+            *count += 1;
+            kept.push(archive);
+        } else {
+            removed.push(archive);
+        }
+    }
+
+    if let Some(max_size) = retention.max_size {
+        let mut total = kept.iter().map(|archive| archive.size).sum::<u64>();
+        // Oldest first.
+        for index in (0..kept.len()).rev() {
+            if total <= max_size {
+                break;
+            }
+            let is_referenced = kept[index]
+                .path
+                .canonicalize()
+                .is_ok_and(|path| referenced.contains(&path));
+            if !is_referenced {
+                total -= kept[index].size;
+                removed.push(kept.remove(index));
+            }
+        }
+    }
+
+    if !dry_run {
+        let listings = mod_file_formats::listing_dir();
+        for archive in &removed {
+            if let Some(dir) = &listings
+                && let Some(key) = mod_file_formats::listing_key(&archive.path)
+            {
+                let listing = dir.join(key);
+                if listing.exists() {
+                    debug!(listing = %listing.display(), "removing cached listing");
+                    fs::remove_file(listing)?;
+                }
+            }
+            info!(archive = %archive.path.display(), "removing cached archive");
+            fs::remove_file(&archive.path)?;
+        }
+    }
+
+    Ok(removed
+        .into_iter()
+        .map(|archive| (archive.path, archive.size))
+        .collect())
+}
+
+/// Parse a size like `500M` or `2GiB`, in powers of 1024.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+
+    let shift = match unit.trim().to_ascii_lowercase().trim_end_matches("ib") {
+        "" | "b" => 0,
+        "k" | "kb" => 10,
+        "m" | "mb" => 20,
+        "g" | "gb" => 30,
+        "t" | "tb" => 40,
+        _ => return None,
+    };
+
+    Some((number * (1u64 << shift) as f64) as u64)
+}
+
+/// Format `bytes` like `1.5 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
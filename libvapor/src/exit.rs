@@ -0,0 +1,431 @@
+//! What kind of error stopped Vapor, as exit codes and diagnostic codes scripts and frontends can
+//! branch on.
+//!
+//! The codes are a contract: a class keeps its code, and new classes get new ones.
+
+use std::{error::Error, fmt, io};
+
+#[cfg(feature = "tokio")]
+use crate::nonblocking::TaskError;
+use crate::{
+    config::ConfigError,
+    download::DownloadError,
+    frameworks::FrameworkError,
+    init::InitError,
+    launch::LaunchError,
+    migrate::MigrateError,
+    mod_manager::{
+        fs::ExtractError, handler::ModError, inspection::InspectionError, journal::JournalError,
+        merge::MergeError, mod_file_formats::FormatError, nested::NestedError, split::SplitError,
+    },
+    nexus::NexusError,
+    resolver::ResolveError,
+    saves::SavesError,
+    secrets::SecretError,
+};
+
+/// A kind of error. The discriminant is the exit code.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
+pub enum ErrorClass {
+    /// Anything not covered below.
+    Other = 1,
+    /// Arguments that don't make sense, like a pattern matching nothing. Clap exits with the same
+    /// code for ones it rejects.
+    Usage = 2,
+    /// A check found problems, like a broken mod in `vapor status` or an issue in `vapor doctor`.
+    Problems = 3,
+    /// Files owned by another mod, or mods declared to conflict.
+    Conflict = 10,
+    /// No mod, collection or recorded version by that name.
+    MissingMod = 11,
+    /// A mod, collection or instance by that name exists already.
+    AlreadyExists = 12,
+    /// Missing dependencies, or mods that depend on the one being taken away.
+    Dependency = 13,
+    /// Refused to change a mod in the state it's in, like a pinned one or a downgrade.
+    Refused = 14,
+    /// An archive that can't be read, extracted or trusted.
+    Archive = 15,
+    Io = 20,
+    /// `mods.toml` or the journal can't be read or written.
+    Registry = 21,
+    /// `Vapor.toml` is missing or broken, or names an unknown game or instance.
+    Config = 22,
+    Network = 30,
+    /// Needed the network while offline.
+    Offline = 31,
+    /// No Nexus API key, or one without the access needed.
+    Auth = 32,
+    /// A hook failed.
+    Hook = 40,
+    /// Cancelled by the user, as with Ctrl-C.
+    Cancelled = 130,
+}
+
+impl ErrorClass {
+    /// Every class, by exit code.
+    pub const ALL: [Self; 17] = [
+        Self::Other,
+        Self::Usage,
+        Self::Problems,
+        Self::Conflict,
+        Self::MissingMod,
+        Self::AlreadyExists,
+        Self::Dependency,
+        Self::Refused,
+        Self::Archive,
+        Self::Io,
+        Self::Registry,
+        Self::Config,
+        Self::Network,
+        Self::Offline,
+        Self::Auth,
+        Self::Hook,
+        Self::Cancelled,
+    ];
+
+    /// The class of `err`, or of the first error it wraps that has one.
+    pub fn of(err: &(dyn Error + 'static)) -> Self {
+        let mut next = Some(err);
+        while let Some(err) = next {
+            if let Some(class) = classify(err) {
+                return class;
+            }
+            next = err.source();
+        }
+        Self::Other
+    }
+
+    /// Process exit code.
+    pub fn exit_code(self) -> u8 {
+        self as u8
+    }
+
+    /// The class with diagnostic code `code`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.code() == code)
+    }
+
+    /// Diagnostic code, for errors that don't have a more specific one.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Other => "vapor::other",
+            Self::Usage => "vapor::usage",
+            Self::Problems => "vapor::problems",
+            Self::Conflict => "vapor::conflict",
+            Self::MissingMod => "vapor::missing_mod",
+            Self::AlreadyExists => "vapor::already_exists",
+            Self::Dependency => "vapor::dependency",
+            Self::Refused => "vapor::refused",
+            Self::Archive => "vapor::archive",
+            Self::Io => "vapor::io",
+            Self::Registry => "vapor::registry",
+            Self::Config => "vapor::config",
+            Self::Network => "vapor::network",
+            Self::Offline => "vapor::offline",
+            Self::Auth => "vapor::auth",
+            Self::Hook => "vapor::hook",
+            Self::Cancelled => "vapor::cancelled",
+        }
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Errors that know their [`ErrorClass`].
+pub trait Classify {
+    fn class(&self) -> ErrorClass;
+}
+
+/// The class of `err` itself, if it's an error Vapor knows.
+fn classify(err: &(dyn Error + 'static)) -> Option<ErrorClass> {
+    macro_rules! known {
+        ($($error:ty),* $(,)?) => {
+            $(
+                if let Some(err) = err.downcast_ref::<$error>() {
+                    return Some(err.class());
+                }
+            )*
+        };
+    }
+    known!(
+        ModError,
+        NexusError,
+        FrameworkError,
+        DownloadError,
+        ConfigError,
+        InitError,
+        LaunchError,
+        MigrateError,
+        ResolveError,
+        JournalError,
+        MergeError,
+        InspectionError,
+        FormatError,
+        NestedError,
+        SplitError,
+        ExtractError,
+        SavesError,
+        SecretError,
+    );
+    #[cfg(feature = "tokio")]
+    known!(TaskError);
+
+    if err.is::<io::Error>() {
+        Some(ErrorClass::Io)
+    } else if err.is::<ureq::Error>() {
+        Some(ErrorClass::Network)
+    } else if err.is::<toml::de::Error>() || err.is::<toml::ser::Error>() {
+        Some(ErrorClass::Registry)
+    } else if err.is::<glob::PatternError>() {
+        Some(ErrorClass::Usage)
+    } else {
+        None
+    }
+}
+
+impl Classify for ModError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::De(_) | Self::Ser(_) => ErrorClass::Registry,
+            Self::Migrate(err) => err.class(),
+            Self::Journal(err) => err.class(),
+            Self::Merge(err) => err.class(),
+            Self::Format(err) => err.class(),
+            Self::Inspection(err) => err.class(),
+            Self::Resolve(err) => err.class(),
+            Self::MissingMod { .. }
+            | Self::MissingCollection(_)
+            | Self::NoOlderVersion { .. }
+            | Self::UnrecordedVersion { .. }
+            | Self::MissingSnapshot(_) => ErrorClass::MissingMod,
+            Self::AlreadyExists(_) | Self::CollectionExists(_) => ErrorClass::AlreadyExists,
+            Self::DoubleOwnedFiles { .. } | Self::Conflicting { .. } => ErrorClass::Conflict,
+            Self::HasDependents { .. } => ErrorClass::Dependency,
+            Self::Pinned(_)
+            | Self::PinnedDependent { .. }
+            | Self::Downgrade { .. }
+            | Self::Disabled(_)
+            | Self::AlreadyFrozen(_)
+            | Self::NotFrozen
+            | Self::SourceInGame { .. } => ErrorClass::Refused,
+            Self::ZipArchive(_)
+            | Self::ExtractionIncomplete { .. }
+            | Self::Corrupt { .. }
+            | Self::Checksum { .. }
+            | Self::PasswordRequired { .. }
+            | Self::WrongPassword { .. }
+            | Self::VariantRequired { .. }
+            | Self::MissingVariant { .. }
+            | Self::ArchiveGone { .. } => ErrorClass::Archive,
+            Self::Pattern(_)
+            | Self::NoMatches { .. }
+            | Self::NothingToAdopt(_)
+            | Self::OutsideGame(_)
+            | Self::NothingToChown { .. }
+            | Self::ChownAcrossStates { .. }
+            | Self::ChownMissing { .. }
+            | Self::MissingFile { .. }
+            | Self::MissingFiles { .. } => ErrorClass::Usage,
+            Self::HookFailed(_) => ErrorClass::Hook,
+        }
+    }
+}
+
+impl Classify for NexusError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Download(err) => err.class(),
+            Self::Mod(err) => err.class(),
+            Self::Io(_) => ErrorClass::Io,
+            Self::Manifest(_) => ErrorClass::Usage,
+            Self::MissingKey | Self::NotPremium(_) => ErrorClass::Auth,
+            Self::Cycle(_) => ErrorClass::Dependency,
+            Self::Search(_) => ErrorClass::Network,
+            Self::ChecksumMismatch { .. } => ErrorClass::Archive,
+            Self::UnsupportedSource(_) | Self::NoFiles(_) => ErrorClass::Other,
+        }
+    }
+}
+
+impl Classify for FrameworkError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Download(err) => err.class(),
+            Self::Mod(err) => err.class(),
+            Self::Unknown(_) => ErrorClass::Usage,
+            Self::MissingAsset(_) => ErrorClass::Other,
+        }
+    }
+}
+
+impl Classify for DownloadError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::Http(_) => ErrorClass::Network,
+            Self::Cancelled => ErrorClass::Cancelled,
+            Self::Offline(_) => ErrorClass::Offline,
+            Self::InvalidProxy(_) | Self::CaBundle { .. } | Self::NoCertificates(_) => {
+                ErrorClass::Config
+            }
+        }
+    }
+}
+
+impl Classify for ConfigError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Init(err) => err.class(),
+            Self::UnknownKey(_) | Self::InvalidValue { .. } => ErrorClass::Usage,
+        }
+    }
+}
+
+impl Classify for InitError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::InstanceExists(_) => ErrorClass::AlreadyExists,
+            Self::MissingConfig(_)
+            | Self::De(_)
+            | Self::UnknownGame(_)
+            | Self::UnknownInstance(_)
+            | Self::NotADirectory(_)
+            | Self::NotInLibrary(_)
+            | Self::Unmanaged(_) => ErrorClass::Config,
+        }
+    }
+}
+
+impl Classify for LaunchError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::MissingDependencies(_) => ErrorClass::Dependency,
+            Self::MissingProton => ErrorClass::Config,
+        }
+    }
+}
+
+impl Classify for MigrateError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::De(_) | Self::Ser(_) | Self::TooNew { .. } | Self::NameCollision { .. } => {
+                ErrorClass::Registry
+            }
+        }
+    }
+}
+
+impl Classify for ResolveError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::MissingMod(_) => ErrorClass::MissingMod,
+            Self::Unsatisfiable { .. } => ErrorClass::Dependency,
+        }
+    }
+}
+
+impl Classify for JournalError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::Json(_) => ErrorClass::Registry,
+            Self::NothingToUndo => ErrorClass::MissingMod,
+        }
+    }
+}
+
+impl Classify for MergeError {
+    fn class(&self) -> ErrorClass {
+        ErrorClass::Other
+    }
+}
+
+impl Classify for InspectionError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Format(err) => err.class(),
+            Self::Suspicious { .. } | Self::Unreadable { .. } => ErrorClass::Archive,
+        }
+    }
+}
+
+impl Classify for FormatError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::UnsupportedFormat { .. } => ErrorClass::Archive,
+        }
+    }
+}
+
+impl Classify for NestedError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::NoMatch { .. } | Self::Ambiguous { .. } => ErrorClass::Usage,
+            Self::Unreadable { .. } | Self::Checksum { .. } | Self::TooDeep { .. } => {
+                ErrorClass::Archive
+            }
+        }
+    }
+}
+
+impl Classify for SplitError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::MissingParts { .. } | Self::Incomplete { .. } | Self::Unsupported { .. } => {
+                ErrorClass::Archive
+            }
+        }
+    }
+}
+
+impl Classify for ExtractError {
+    fn class(&self) -> ErrorClass {
+        ErrorClass::Archive
+    }
+}
+
+impl Classify for SavesError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::NotFound => ErrorClass::Config,
+            Self::NoBackups | Self::UnknownBackup(_) => ErrorClass::MissingMod,
+        }
+    }
+}
+
+impl Classify for SecretError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Io(_) => ErrorClass::Io,
+            Self::De(_) | Self::Ser(_) | Self::MissingVar(_) | Self::InvalidReference(_) => {
+                ErrorClass::Config
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Classify for TaskError {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Self::Download(err) => err.class(),
+            Self::Mod(err) => err.class(),
+            Self::Cancelled => ErrorClass::Cancelled,
+            Self::Join(_) => ErrorClass::Other,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use inline_colorization::*;
+use miette::Diagnostic;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    download::{self, DownloadError},
+    mod_manager::{
+        handler::{AddOptions, ModError, ModHandler, OperationReport},
+        registry::ModRegistry,
+    },
+    render,
+};
+
+/// A core modding framework with a known upstream.
+pub struct Framework {
+    /// Name used as the registry key.
+    pub name: &'static str,
+    /// GitHub `owner/repo`.
+    pub repo: &'static str,
+    /// Release asset names start with this (case insensitive) and end in `.zip`.
+    asset_prefix: &'static str,
+    /// Frameworks that must be installed first.
+    pub dependencies: &'static [&'static str],
+}
+
+/// Every known framework, in install order.
+pub const FRAMEWORKS: &[Framework] = &[
+    Framework {
+        name: "Cyber Engine Tweaks",
+        repo: "maximegmd/CyberEngineTweaks",
+        asset_prefix: "cet_",
+        dependencies: &[],
+    },
+    Framework {
+        name: "RED4ext",
+        repo: "wopss/RED4ext",
+        asset_prefix: "red4ext_",
+        dependencies: &[],
+    },
+    Framework {
+        name: "redscript",
+        repo: "jac3km4/redscript",
+        asset_prefix: "redscript-",
+        dependencies: &[],
+    },
+    Framework {
+        name: "ArchiveXL",
+        repo: "psiberx/cp2077-archive-xl",
+        asset_prefix: "archivexl",
+        dependencies: &["RED4ext"],
+    },
+    Framework {
+        name: "TweakXL",
+        repo: "psiberx/cp2077-tweak-xl",
+        asset_prefix: "tweakxl",
+        dependencies: &["RED4ext"],
+    },
+    Framework {
+        name: "Codeware",
+        repo: "psiberx/cp2077-codeware",
+        asset_prefix: "codeware",
+        dependencies: &["RED4ext"],
+    },
+];
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum FrameworkError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Download(#[from] DownloadError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Mod(#[from] ModError),
+    #[error("Unknown framework: `{0}`")]
+    #[diagnostic(help("See `vapor frameworks status` for known frameworks"))]
+    Unknown(String),
+    #[error("No release asset found for `{0}`")]
+    #[diagnostic(help("The upstream release layout may have changed, install it manually"))]
+    MissingAsset(String),
+}
+
+#[derive(Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    /// Release tag without a leading `v`.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+}
+
+impl Framework {
+    /// Look up a framework by name, ignoring case.
+    pub fn find(name: &str) -> Option<&'static Framework> {
+        FRAMEWORKS
+            .iter()
+            .find(|framework| framework.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Fetch the latest upstream release.
+    pub fn latest_release(&self) -> Result<Release, FrameworkError> {
+        Ok(download::get_json(&format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            self.repo
+        ))?)
+    }
+
+    /// Download `release` into `downloads` and install it through `handler`.
+    pub fn install(
+        &self,
+        handler: &ModHandler,
+        release: &Release,
+        downloads: &Path,
+    ) -> Result<OperationReport, FrameworkError> {
+        let Some(asset) = release.assets.iter().find(|asset| {
+            let name = asset.name.to_ascii_lowercase();
+            name.starts_with(self.asset_prefix) && name.ends_with(".zip")
+        }) else {
+            return Err(FrameworkError::MissingAsset(self.name.to_string()));
+        };
+
+        let archive = download::download_to(&asset.browser_download_url, downloads, &asset.name)?;
+
+        let options = AddOptions {
+            dependencies: self.dependencies.iter().map(|&name| name.into()).collect(),
+            url: Some(format!("https://github.com/{}", self.repo)),
+            ..Default::default()
+        };
+
+        Ok(handler.add_mod(&archive, self.name, release.version(), &options)?)
+    }
+}
+
+/// Resolve `names` and their dependencies into install order.
+///
+/// An empty list resolves to every framework.
+pub fn resolve(names: &[String]) -> Result<Vec<&'static Framework>, FrameworkError> {
+    if names.is_empty() {
+        return Ok(FRAMEWORKS.iter().collect());
+    }
+
+    let mut wanted = vec![];
+    let mut stack = names.to_vec();
+
+    while let Some(name) = stack.pop() {
+        let Some(framework) = Framework::find(&name) else {
+            return Err(FrameworkError::Unknown(name));
+        };
+
+        if !wanted.contains(&framework.name) {
+            wanted.push(framework.name);
+            stack.extend(framework.dependencies.iter().map(ToString::to_string));
+        }
+    }
+
+    Ok(FRAMEWORKS
+        .iter()
+        .filter(|framework| wanted.contains(&framework.name))
+        .collect())
+}
+
+/// Frameworks already present in `registry`.
+pub fn installed(registry: &ModRegistry) -> Vec<&'static Framework> {
+    FRAMEWORKS
+        .iter()
+        .filter(|framework| registry.contains(framework.name))
+        .collect()
+}
+
+#[allow(unused_must_use)]
+pub fn status(registry: &ModRegistry) -> String {
+    let mut out = String::new();
+
+    for framework in FRAMEWORKS {
+        match registry.get(framework.name) {
+            Some(entry) => writeln!(
+                &mut out,
+                "{style_bold}*{style_reset} {style_bold}{}{style_reset}: {color_cyan}{}{style_reset}{}",
+                framework.name,
+                entry.version,
+                if entry.installed {
+                    String::new()
+                } else {
+                    format!(" {color_red}(disabled){style_reset}")
+                }
+            ),
+            None => writeln!(
+                &mut out,
+                "{style_bold}*{style_reset} {style_bold}{}{style_reset}: {color_red}not installed{style_reset}",
+                framework.name
+            ),
+        };
+    }
+
+    render::finish(out)
+}
@@ -0,0 +1,96 @@
+use std::{fs, path::Path};
+
+use crate::launch::STEAM_APP_ID;
+
+/// Detect the installed game version.
+///
+/// Reads the version resource of `bin/x64/Cyberpunk2077.exe`, falling back to the Steam build
+/// ID from the app manifest if the executable can't be parsed.
+pub fn detect_version(root: &Path) -> Option<String> {
+    exe_version(&root.join("bin").join("x64").join("Cyberpunk2077.exe"))
+        .or_else(|| steam_build(root))
+}
+
+/// Check whether `game_version` is one of `versions`.
+///
+/// Versions match by leading components, so `2.1` covers `2.1` and `2.1.3` but not `2.12`.
+pub fn supports(versions: &[String], game_version: &str) -> bool {
+    versions.iter().any(|version| {
+        let mut wanted = version.split('.');
+        let mut have = game_version.split('.');
+        wanted.all(|part| have.next() == Some(part))
+    })
+}
+
+fn exe_version(exe: &Path) -> Option<String> {
+    let bytes = fs::read(exe).ok()?;
+    string_file_info(&bytes, "ProductVersion").or_else(|| fixed_file_info(&bytes))
+}
+
+/// Read a value out of the `StringFileInfo` table, stored as UTF-16LE.
+fn string_file_info(bytes: &[u8], key: &str) -> Option<String> {
+    let needle = key
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+
+    let mut pos = bytes
+        .windows(needle.len())
+        .position(|window| window == needle)?
+        + needle.len();
+
+    // Values are 32-bit aligned after the key.
+    pos = pos.next_multiple_of(4);
+
+    let value = bytes[pos..]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect::<Vec<_>>();
+
+    let value = String::from_utf16(&value).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Read the product version out of `VS_FIXEDFILEINFO`.
+fn fixed_file_info(bytes: &[u8]) -> Option<String> {
+    const SIGNATURE: [u8; 4] = 0xFEEF04BDu32.to_le_bytes();
+
+    let start = bytes
+        .windows(SIGNATURE.len())
+        .position(|window| window == SIGNATURE)?;
+    let read = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            bytes
+                .get(start + offset..start + offset + 4)?
+                .try_into()
+                .ok()?,
+        ))
+    };
+
+    let ms = read(16)?;
+    let ls = read(20)?;
+
+    Some(format!(
+        "{}.{}.{}.{}",
+        ms >> 16,
+        ms & 0xFFFF,
+        ls >> 16,
+        ls & 0xFFFF
+    ))
+}
+
+/// Steam build ID from `steamapps/appmanifest_1091500.acf`.
+fn steam_build(root: &Path) -> Option<String> {
+    let manifest = root
+        .parent()?
+        .parent()?
+        .join(format!("appmanifest_{STEAM_APP_ID}.acf"));
+
+    fs::read_to_string(manifest).ok()?.lines().find_map(|line| {
+        let mut parts = line.split('"').filter(|part| !part.trim().is_empty());
+        (parts.next()? == "buildid").then(|| parts.next().map(ToString::to_string))?
+    })
+}
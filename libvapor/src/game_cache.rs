@@ -0,0 +1,75 @@
+//! Caches the game builds from script and tweak mods, which go stale when those mods change.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use glob::Pattern;
+
+/// A cache the game or its frameworks rebuild on the next start.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameCache {
+    /// Compiled redscript.
+    Scripts,
+    /// TweakDB with modded records merged in.
+    Tweaks,
+}
+
+impl GameCache {
+    pub const ALL: [Self; 2] = [Self::Scripts, Self::Tweaks];
+
+    /// Globs of the cache files, relative to the game directory.
+    ///
+    /// Only the modded copies, never the game's own `final.redscripts` or `tweakdb.bin`.
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Self::Scripts => &[
+                "r6/cache/modded/final.redscripts*",
+                "r6/cache/final.redscripts.modded",
+            ],
+            Self::Tweaks => &[
+                "r6/cache/modded/tweakdb*.bin",
+                "r6/cache/tweakdb*.bin.modded",
+            ],
+        }
+    }
+
+    /// Directory of the mod files this cache is built from.
+    fn source(self) -> &'static str {
+        match self {
+            Self::Scripts => "r6/scripts/",
+            Self::Tweaks => "r6/tweaks/",
+        }
+    }
+
+    /// Caches built from any of `files`, relative to the game directory.
+    pub fn touched_by<'a>(files: impl IntoIterator<Item = &'a String>) -> Vec<Self> {
+        let files = files.into_iter().collect::<Vec<_>>();
+        Self::ALL
+            .into_iter()
+            .filter(|cache| files.iter().any(|file| file.starts_with(cache.source())))
+            .collect()
+    }
+
+    /// Cache files currently in the game directory at `root`.
+    pub fn files(self, root: &Path) -> Vec<PathBuf> {
+        let root = Pattern::escape(&root.to_string_lossy());
+        self.patterns()
+            .iter()
+            .filter_map(|pattern| glob::glob(&format!("{root}/{pattern}")).ok())
+            .flatten()
+            .flatten()
+            .filter(|path| path.is_file())
+            .collect()
+    }
+}
+
+impl fmt::Display for GameCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Scripts => "script",
+            Self::Tweaks => "tweak",
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Commands run around installing and enabling mods and launching the game, like a patcher,
+//! clearing `r6/cache` or a desktop notification.
+//!
+//! Hooks come from a mod's entry in `mods.toml` and from `[hooks]` in `Vapor.toml`, but a command
+//! only ever runs once the user has put it on the allowlist in `Vapor.toml`.
+
+use std::{
+    fmt, io,
+    path::Path,
+    process::{Command, Output},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// When a hook runs.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// Before a mod's files are extracted.
+    PreInstall,
+    /// After a mod is added or updated.
+    PostInstall,
+    /// Before a disabled mod is enabled.
+    PreEnable,
+    /// After a mod is enabled.
+    PostEnable,
+    /// Before the game is launched with `vapor run`.
+    PreRun,
+}
+
+impl Event {
+    pub const ALL: [Self; 5] = [
+        Self::PreInstall,
+        Self::PostInstall,
+        Self::PreEnable,
+        Self::PostEnable,
+        Self::PreRun,
+    ];
+
+    /// Whether a failure stops the operation, rather than being reported after it.
+    pub fn is_pre(self) -> bool {
+        matches!(self, Self::PreInstall | Self::PreEnable | Self::PreRun)
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PreInstall => "pre_install",
+            Self::PostInstall => "post_install",
+            Self::PreEnable => "pre_enable",
+            Self::PostEnable => "post_enable",
+            Self::PreRun => "pre_run",
+        })
+    }
+}
+
+/// Commands to run for each [`Event`], in order.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_install: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_install: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_enable: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_enable: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_run: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        Event::ALL.iter().all(|event| self.get(*event).is_empty())
+    }
+
+    pub fn get(&self, event: Event) -> &[String] {
+        match event {
+            Event::PreInstall => &self.pre_install,
+            Event::PostInstall => &self.post_install,
+            Event::PreEnable => &self.pre_enable,
+            Event::PostEnable => &self.post_enable,
+            Event::PreRun => &self.pre_run,
+        }
+    }
+
+    pub fn get_mut(&mut self, event: Event) -> &mut Vec<String> {
+        match event {
+            Event::PreInstall => &mut self.pre_install,
+            Event::PostInstall => &mut self.post_install,
+            Event::PreEnable => &mut self.pre_enable,
+            Event::PostEnable => &mut self.post_enable,
+            Event::PreRun => &mut self.pre_run,
+        }
+    }
+}
+
+/// `[hooks]` in `Vapor.toml`: hooks run for every mod, and the commands allowed to run at all.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct HooksToml {
+    #[serde(flatten)]
+    pub hooks: Hooks,
+    /// Exact commands the user agreed to run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+}
+
+impl HooksToml {
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty() && self.allow.is_empty()
+    }
+
+    pub fn is_allowed(&self, command: &str) -> bool {
+        self.allow.iter().any(|allowed| allowed == command)
+    }
+
+    /// Add `command` to the allowlist, returning whether it wasn't already on it.
+    pub fn allow(&mut self, command: &str) -> bool {
+        if self.is_allowed(command) {
+            return false;
+        }
+        self.allow.push(command.to_string());
+        true
+    }
+}
+
+/// Run `command` through the shell in `dir`, logging its output.
+///
+/// The mod and its version are passed on as `VAPOR_MOD` and `VAPOR_MOD_VERSION`, both empty for
+/// hooks not about one mod, the event as `VAPOR_EVENT`, and the game directory as `VAPOR_GAME_DIR`.
+pub fn run(
+    command: &str,
+    dir: &Path,
+    mod_name: &str,
+    version: &str,
+    event: Event,
+) -> io::Result<Output> {
+    info!(%command, %mod_name, %version, %event, "running hook");
+
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+
+    let output = cmd
+        .current_dir(dir)
+        .env("VAPOR_MOD", mod_name)
+        .env("VAPOR_MOD_VERSION", version)
+        .env("VAPOR_EVENT", event.to_string())
+        .env("VAPOR_GAME_DIR", dir)
+        .output()?;
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        info!(%command, "stdout: {line}");
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        info!(%command, "stderr: {line}");
+    }
+    info!(%command, status = %output.status, "hook finished");
+
+    Ok(output)
+}
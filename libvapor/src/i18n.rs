@@ -0,0 +1,191 @@
+//! Translations of user-facing output, kept as Fluent catalogs in `locales/` and picked once at
+//! startup with [`init`].
+//!
+//! Messages are formatted with `fluent-bundle`, so catalogs can pick plural forms with selectors
+//! like `{ $count -> [one] ... *[other] ... }`. A message missing from a catalog falls back to
+//! English.
+
+use std::{
+    env, fmt,
+    sync::{LazyLock, OnceLock},
+};
+
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue, concurrent::FluentBundle};
+use unic_langid::LanguageIdentifier;
+
+/// Bundled catalogs, by language tag.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("pl", include_str!("../locales/pl.ftl")),
+    ("pt-BR", include_str!("../locales/pt-BR.ftl")),
+    ("ru", include_str!("../locales/ru.ftl")),
+];
+
+type Bundle = FluentBundle<FluentResource>;
+
+static ENGLISH: LazyLock<Bundle> = LazyLock::new(|| bundle(CATALOGS[0]));
+static SELECTED: OnceLock<Bundle> = OnceLock::new();
+
+/// Translate output into `lang`, like `pl` or `pt_BR.UTF-8`, or else the language of
+/// `LC_ALL`, `LC_MESSAGES` or `LANG`, whichever is set first. Languages without a catalog get
+/// English.
+pub fn init(lang: Option<&str>) {
+    let requested = lang.map(ToString::to_string).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+    });
+
+    let catalog = requested.as_deref().and_then(find).unwrap_or(CATALOGS[0]);
+    let _ = SELECTED.set(bundle(catalog));
+}
+
+/// The catalog for a POSIX locale or language tag, matching the region if there is a catalog for
+/// it and else just the language.
+fn find(locale: &str) -> Option<(&'static str, &'static str)> {
+    let tag = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default();
+
+    CATALOGS
+        .iter()
+        .find(|(lang, _)| lang.eq_ignore_ascii_case(&tag))
+        .or_else(|| {
+            CATALOGS
+                .iter()
+                .find(|(lang, _)| lang.split('-').next() == Some(language))
+        })
+        .copied()
+}
+
+/// The message `key`, or the key itself if no catalog has it.
+pub fn tr(key: &str) -> String {
+    tr_with(key, &[])
+}
+
+/// The message `key` with each `{ $name }` replaced by its value in `args`.
+///
+/// Values that are whole numbers are passed as numbers, so catalogs can pick plural forms by them.
+pub fn tr_with(key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let args = args
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_string();
+            let value = match value.parse::<i64>() {
+                Ok(number) if number.to_string() == value => FluentValue::from(number),
+                _ => FluentValue::from(value),
+            };
+            (*name, value)
+        })
+        .collect::<FluentArgs>();
+
+    SELECTED
+        .get()
+        .and_then(|catalog| format(catalog, key, &args))
+        .or_else(|| format(&ENGLISH, key, &args))
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// The bundle of a catalog, keeping every message that parses.
+fn bundle((lang, catalog): (&str, &str)) -> Bundle {
+    let lang = lang
+        .parse::<LanguageIdentifier>()
+        .expect("Catalogs are named after valid language tags");
+    let resource =
+        FluentResource::try_new(catalog.to_string()).unwrap_or_else(|(resource, _)| resource);
+
+    let mut bundle = Bundle::new_concurrent(vec![lang]);
+    // Terminals would show the Unicode isolation marks around placeables.
+    bundle.set_use_isolating(false);
+    let _ = bundle.add_resource(resource);
+    bundle
+}
+
+fn format(bundle: &Bundle, key: &str, args: &FluentArgs) -> Option<String> {
+    let pattern = bundle.get_message(key)?.value()?;
+    let mut errors = vec![];
+    Some(
+        bundle
+            .format_pattern(pattern, Some(args), &mut errors)
+            .into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_catalog_parses() {
+        for (lang, catalog) in CATALOGS {
+            if let Err((_, errors)) = FluentResource::try_new(catalog.to_string()) {
+                panic!("{lang} doesn't parse: {errors:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn finds_catalogs_by_locale() {
+        let lang = |locale| find(locale).map(|(lang, _)| lang);
+
+        assert_eq!(lang("pt_BR.UTF-8"), Some("pt-BR"));
+        assert_eq!(lang("pt-br"), Some("pt-BR"));
+        assert_eq!(lang("pt_PT"), Some("pt-BR"));
+        assert_eq!(lang("pl_PL.UTF-8@euro"), Some("pl"));
+        assert_eq!(lang("en"), Some("en"));
+        assert_eq!(lang("de_DE"), None);
+        assert_eq!(lang(""), None);
+    }
+
+    #[test]
+    fn every_translation_is_of_an_english_message() {
+        for catalog in &CATALOGS[1..] {
+            let (lang, text) = catalog;
+            let resource = FluentResource::try_new(text.to_string()).unwrap();
+            for entry in resource.entries() {
+                if let fluent_syntax::ast::Entry::Message(message) = entry {
+                    let key = message.id.name;
+                    assert!(ENGLISH.has_message(key), "`{key}` in {lang} isn't in en");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn picks_plural_forms() {
+        let undo = |lang: &str, count: usize| {
+            let catalog = *CATALOGS.iter().find(|(tag, _)| *tag == lang).unwrap();
+            let args = [("count", FluentValue::from(count))]
+                .into_iter()
+                .collect::<FluentArgs>();
+            format(&bundle(catalog), "confirm-undo", &args).unwrap()
+        };
+
+        assert_eq!(undo("en", 1), "Undo the last operation?");
+        assert_eq!(undo("en", 3), "Undo the last 3 operations?");
+        assert_eq!(undo("pl", 1), "Cofnąć ostatnią operację?");
+        assert_eq!(undo("pl", 3), "Cofnąć ostatnie 3 operacje?");
+        assert_eq!(undo("pl", 5), "Cofnąć ostatnich 5 operacji?");
+        assert_eq!(undo("ru", 1), "Отменить последнюю операцию?");
+        assert_eq!(undo("ru", 3), "Отменить последние 3 операции?");
+        assert_eq!(undo("ru", 5), "Отменить последние 5 операций?");
+        assert_eq!(undo("ru", 21), "Отменить последнюю 21 операцию?");
+    }
+
+    #[test]
+    fn fills_in_placeables() {
+        assert_eq!(
+            tr_with("confirm-undo", &[("count", &3)]),
+            "Undo the last 3 operations?"
+        );
+        assert_eq!(
+            tr_with("confirm-overwrite", &[("path", &"1.0")]),
+            "Overwrite `1.0`?"
+        );
+        assert_eq!(tr("no-such-message"), "no-such-message");
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::Write,
     path::{Path, PathBuf},
@@ -11,6 +12,19 @@ use miette::Diagnostic;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::{
+    download::{self, NetworkToml},
+    game,
+    hooks::HooksToml,
+    migrate::SCHEMA_VERSION,
+    mod_manager::{
+        handler::{DISABLED_DIR, VALID_ROOT_DIRS},
+        merge::MERGE_FILES,
+        name::NameFolding,
+    },
+    platform, steam,
+};
+
 #[derive(Error, Diagnostic, Debug)]
 pub enum InitError {
     #[error("io error: `{0}`")]
@@ -18,12 +32,56 @@ pub enum InitError {
     #[error("missing config at `{0}`")]
     #[diagnostic(help("Vapor attempted to find this config file but failed"))]
     MissingConfig(PathBuf),
+    #[error("Deserialization error: `{0}`")]
+    De(#[from] toml::de::Error),
+    #[error("unknown game `{0}`")]
+    #[diagnostic(help("Add it with `vapor init --game {0}`"))]
+    UnknownGame(String),
+    #[error("unknown instance `{0}`")]
+    #[diagnostic(help(
+        "Add it with `vapor instance add {0} <path>`, or see `vapor instance list`"
+    ))]
+    UnknownInstance(String),
+    #[error("instance `{0}` already exists")]
+    InstanceExists(String),
+    #[error("`{0}` is not a directory")]
+    NotADirectory(PathBuf),
+    #[error("`{0}` is not in a Steam library")]
+    #[diagnostic(help("`--sdcard` keeps Vapor's files next to the game's `steamapps` directory"))]
+    NotInLibrary(PathBuf),
+    #[error("`{0}` has no `mods.toml`")]
+    #[diagnostic(help("Run `vapor --root '{}' init` to start managing it", .0.display()))]
+    Unmanaged(PathBuf),
 }
 
+/// Game ID of `Cyberpunk 2077`, used when no game is given.
+pub const DEFAULT_GAME: &str = "cyberpunk2077";
+
+/// Name of the install in [`MainToml`], used when no instance is given.
+pub const DEFAULT_INSTANCE: &str = "default";
+
 /// Main config file.
 #[derive(Serialize, Deserialize)]
 pub struct CyberToml {
     pub main: MainToml,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch: Option<LaunchToml>,
+    /// Game profiles other than the one in [`MainToml`], keyed by game ID.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub games: BTreeMap<String, GameToml>,
+    /// Installs of `Cyberpunk 2077` other than the one in [`MainToml`], keyed by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub instances: BTreeMap<String, InstanceToml>,
+    #[serde(default, skip_serializing_if = "HooksToml::is_empty")]
+    pub hooks: HooksToml,
+    #[serde(default, skip_serializing_if = "NetworkToml::is_default")]
+    pub network: NetworkToml,
+    /// Instance this run manages, see [`CyberToml::select_instance`].
+    #[serde(skip)]
+    active: Option<String>,
+    /// Game directory this run manages instead of the configured one, see [`CyberToml::set_root`].
+    #[serde(skip)]
+    root: Option<String>,
 }
 
 /// Inner contents of [`CyberToml`].
@@ -33,65 +91,473 @@ pub struct MainToml {
     pub path: String,
     /// Time created.
     pub created: DateTime<Utc>,
+    /// Detected game version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+    /// How mods are enabled and disabled.
+    #[serde(default)]
+    pub deploy_mode: DeployMode,
+    /// Directory downloaded archives are kept in, defaults to [`download::cache_dir`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_dir: Option<String>,
+    /// Where to find the Nexus API key, like `env:NEXUS_API_KEY`, rather than the key itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nexus_api_key: Option<String>,
+    #[serde(default)]
+    pub color: ColorChoice,
+    /// What to do when a mod brings files another mod already owns.
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// Clear the script and tweak caches after changes to `r6/scripts` and `r6/tweaks`.
+    #[serde(default)]
+    pub auto_clear_cache: bool,
+    /// Move files of removed mods to the trash rather than keeping them under `.vapor`.
+    #[serde(default)]
+    pub trash: bool,
+    /// Rewrite only the parts of `mods.toml` that changed, keeping its formatting and comments.
+    #[serde(default)]
+    pub minimal_diff: bool,
+    /// Directory the game keeps its saves in, found automatically if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saves_dir: Option<String>,
+    /// Back up saves before removing mods.
+    #[serde(default)]
+    pub backup_saves: bool,
+    /// Keep one copy of identical files across mods in `.vapor/objects`, hardlinked into place.
+    #[serde(default)]
+    pub dedupe: bool,
+    /// Keep `archive/pc/mod/modlist.txt` in the load order of the registry.
+    #[serde(default)]
+    pub modlist: bool,
+    /// What is ignored when matching mod names.
+    #[serde(default)]
+    pub name_folding: NameFolding,
+    /// Directory staged, excluded and removed files are kept in, defaults to `.vapor` in the game
+    /// directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staging_dir: Option<String>,
+    /// Instance used without `--instance`, set by `vapor instance use`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+}
+
+/// Another install of `Cyberpunk 2077`, with its own `mods.toml`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstanceToml {
+    /// Path to the `Cyberpunk 2077` directory.
+    pub path: String,
+    /// Detected game version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+}
+
+/// When to color output.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Only when writing to a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How `add` handles files already owned by another mod.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Refuse to add the mod.
+    #[default]
+    Error,
+    /// Take the files over from the other mod.
+    Overwrite,
+}
+
+/// How enabled mods end up in the game directory.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployMode {
+    /// Files are moved between the game directory and the disabled directory.
+    #[default]
+    Move,
+    /// Files live in `.vapor/mods/<name>/` and are symlinked into the game directory.
+    Symlink,
+    /// Like [`DeployMode::Symlink`], but with hardlinks.
+    Hardlink,
+}
+
+impl DeployMode {
+    /// Whether mods are staged and linked rather than moved.
+    pub const fn links(self) -> bool {
+        matches!(self, Self::Symlink | Self::Hardlink)
+    }
+}
+
+/// A game managed by Vapor.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameToml {
+    /// Path to the game directory.
+    pub path: String,
+    /// Top level directories mods install into.
+    #[serde(default)]
+    pub root_dirs: Vec<String>,
+    /// Config files mods may all edit, merged from each mod's copy, see [`crate::mod_manager::merge`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merge_files: Vec<String>,
+    #[serde(default)]
+    pub deploy: DeployToml,
+}
+
+/// Where mod files go for a [`GameToml`].
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DeployToml {
+    /// Directory under the game path that archives are extracted into.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Directory under the game path that disabled mods are moved into.
+    #[serde(default = "default_disabled_dir")]
+    pub disabled_dir: String,
+    #[serde(default)]
+    pub mode: DeployMode,
+    /// Directory staged, excluded and removed files are kept in, defaults to `.vapor` under the
+    /// game path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staging_dir: Option<String>,
+}
+
+impl Default for DeployToml {
+    fn default() -> Self {
+        Self {
+            target: None,
+            disabled_dir: default_disabled_dir(),
+            mode: DeployMode::default(),
+            staging_dir: None,
+        }
+    }
+}
+
+fn default_disabled_dir() -> String {
+    DISABLED_DIR.to_string()
+}
+
+/// Settings for launching the game without Steam.
+#[derive(Serialize, Deserialize)]
+pub struct LaunchToml {
+    /// Path to the `proton` script of a Proton install.
+    pub proton: String,
+    /// Path to the game's compatdata prefix (`.../compatdata/1091500`).
+    pub prefix: String,
 }
 
 /// Create a new Vapor install.
 pub struct Init {
     pub path: PathBuf,
+    /// See [`MainToml::staging_dir`].
+    pub staging_dir: Option<PathBuf>,
 }
 
 impl Init {
     pub fn new() -> Result<Self, InitError> {
-        let t = Input::new("Enter the path to your `Cyberpunk 2077` directory")
+        Self::prompt("Cyberpunk 2077")
+    }
+
+    /// Prompt for the directory of the game `id`.
+    pub fn new_game(id: &str) -> Result<Self, InitError> {
+        Self::prompt(id)
+    }
+
+    fn prompt(game: &str) -> Result<Self, InitError> {
+        let mut t = Input::new(format!("Enter the path to your `{game}` directory"))
             .description("We will use this as a base directory for storing and managing mods.")
             .prompt("Path: ")
             .validation(|path| {
-                if Path::new(path).exists() {
+                if steam::resolve(Path::new(path)).exists() {
                     Ok(())
                 } else {
                     Err("Path does not exist")
                 }
             });
+        if game == "Cyberpunk 2077"
+            && let Some(found) = steam::find_game()
+        {
+            t = t.default_value(found.to_string_lossy());
+        }
 
         Ok(Self {
-            path: PathBuf::from(t.run()?),
+            path: steam::resolve(Path::new(&t.run()?)),
+            staging_dir: None,
         })
     }
 
+    /// Keep staged files on the same drive as the game but outside its directory, in
+    /// `.vapor` next to its Steam library's `steamapps`, so they travel with an SD card and
+    /// survive Steam reinstalling the game.
+    pub fn sdcard(mut self) -> Result<Self, InitError> {
+        let library = steam::library_of(&self.path)
+            .ok_or_else(|| InitError::NotInLibrary(self.path.clone()))?;
+        self.staging_dir = Some(library.join(".vapor"));
+        Ok(self)
+    }
+
     pub fn setup_cyber(&self) -> Result<(), std::io::Error> {
-        let xdg_dirs = xdg::BaseDirectories::with_prefix("vapor");
-        let config_path = xdg_dirs.place_config_file("Vapor.toml")?;
+        let config_path = platform::place_config_file("Vapor.toml")?;
 
         let mut config_file = File::create_new(config_path)?;
 
+        let mut config = CyberToml::for_root(&self.path);
+        config.main.staging_dir = self
+            .staging_dir
+            .as_ref()
+            .map(|dir| dir.to_string_lossy().to_string());
         write!(
             &mut config_file,
             "{}",
-            toml::to_string_pretty(&CyberToml {
-                main: MainToml {
-                    path: self.path.to_string_lossy().to_string(),
-                    created: Utc::now(),
-                }
-            })
-            .expect("Could not serialize")
+            toml::to_string_pretty(&config).expect("Could not serialize")
         )?;
 
-        File::create_new(self.path.join("mods.toml"))?;
+        write!(
+            File::create_new(self.path.join("mods.toml"))?,
+            "schema_version = {SCHEMA_VERSION}"
+        )?;
 
-        fs::create_dir(self.path.join("Disabled Mods"))?;
+        fs::create_dir(self.path.join(DISABLED_DIR))?;
 
         Ok(())
     }
 
+    /// Add a game profile to an existing config.
+    pub fn setup_game(&self, id: &str, root_dirs: Vec<String>) -> Result<(), InitError> {
+        let mut config = CyberToml::load()?;
+        let game = GameToml {
+            path: self.path.to_string_lossy().to_string(),
+            root_dirs,
+            merge_files: vec![],
+            deploy: DeployToml {
+                staging_dir: self
+                    .staging_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+        };
+
+        write!(
+            File::create_new(self.path.join("mods.toml"))?,
+            "schema_version = {SCHEMA_VERSION}"
+        )?;
+        fs::create_dir_all(self.path.join(&game.deploy.disabled_dir))?;
+
+        config.games.insert(id.to_string(), game);
+        config.save()
+    }
+
     pub fn get_config() -> Result<PathBuf, InitError> {
-        let xdg_dirs = xdg::BaseDirectories::with_prefix("vapor");
+        let path = platform::config_home()?.join("Vapor.toml");
+        if path.exists() {
+            Ok(path)
+        } else {
+            Err(InitError::MissingConfig(path))
+        }
+    }
+}
+
+impl CyberToml {
+    /// Load the config file, managing the instance picked with `vapor instance use`.
+    pub fn load() -> Result<Self, InitError> {
+        let mut config = Self::from_str(&fs::read_to_string(Init::get_config()?)?)?;
+        config.select_instance(None)?;
+        Ok(config)
+    }
+
+    /// A config managing the game at `path` with every setting at its default, for when there is no
+    /// `Vapor.toml` to read.
+    pub fn for_root(path: &Path) -> Self {
+        CyberToml {
+            main: MainToml {
+                path: path.to_string_lossy().to_string(),
+                created: Utc::now(),
+                game_version: game::detect_version(path),
+                deploy_mode: DeployMode::default(),
+                download_dir: None,
+                nexus_api_key: None,
+                color: ColorChoice::default(),
+                conflict_policy: ConflictPolicy::default(),
+                name_folding: NameFolding::default(),
+                auto_clear_cache: false,
+                trash: false,
+                minimal_diff: false,
+                saves_dir: None,
+                backup_saves: false,
+                dedupe: false,
+                modlist: false,
+                staging_dir: None,
+                instance: None,
+            },
+            launch: None,
+            games: BTreeMap::new(),
+            instances: BTreeMap::new(),
+            hooks: HooksToml::default(),
+            network: NetworkToml::default(),
+            active: None,
+            root: None,
+        }
+    }
+
+    /// Manage the game at `path` rather than any configured install, for this run only.
+    ///
+    /// Instances are ignored and the staging directory is the default one in `path`, which must
+    /// already have a `mods.toml`, see [`init_root`]. Nothing about `path` is written back by
+    /// [`CyberToml::save`].
+    pub fn set_root(&mut self, path: &Path) -> Result<(), InitError> {
+        if !path.is_dir() {
+            return Err(InitError::NotADirectory(path.to_path_buf()));
+        }
+        if !path.join("mods.toml").is_file() {
+            return Err(InitError::Unmanaged(path.to_path_buf()));
+        }
+        self.active = None;
+        self.root = Some(path.to_string_lossy().to_string());
+        Ok(())
+    }
+
+    /// Manage the instance `name`, or the one picked with `vapor instance use` if `None`.
+    pub fn select_instance(&mut self, name: Option<&str>) -> Result<(), InitError> {
+        self.active = match name.or(self.main.instance.as_deref()) {
+            None | Some(DEFAULT_INSTANCE) => None,
+            Some(name) if self.instances.contains_key(name) => Some(name.to_string()),
+            Some(name) => return Err(InitError::UnknownInstance(name.to_string())),
+        };
+        Ok(())
+    }
+
+    /// Name of the instance being managed.
+    pub fn instance(&self) -> &str {
+        self.active.as_deref().unwrap_or(DEFAULT_INSTANCE)
+    }
+
+    fn active_instance(&self) -> Option<&InstanceToml> {
+        self.instances.get(self.active.as_deref()?)
+    }
+
+    /// Path to the `Cyberpunk 2077` directory of the instance being managed.
+    pub fn game_path(&self) -> &str {
+        if let Some(root) = &self.root {
+            return root;
+        }
+        self.active_instance()
+            .map_or(&self.main.path, |instance| &instance.path)
+    }
+
+    /// Detected game version of the instance being managed.
+    pub fn game_version(&self) -> Option<&str> {
+        if self.root.is_some() {
+            return None;
+        }
+        match self.active_instance() {
+            Some(instance) => instance.game_version.as_deref(),
+            None => self.main.game_version.as_deref(),
+        }
+    }
+
+    pub fn set_game_version(&mut self, version: Option<String>) {
+        if self.root.is_some() {
+            return;
+        }
+        match self
+            .active
+            .as_ref()
+            .and_then(|name| self.instances.get_mut(name))
+        {
+            Some(instance) => instance.game_version = version,
+            None => self.main.game_version = version,
+        }
+    }
+
+    /// Add the install at `path` as the instance `name`, creating its `mods.toml` if missing.
+    pub fn add_instance(&mut self, name: &str, path: &Path) -> Result<(), InitError> {
+        if name == DEFAULT_INSTANCE || self.instances.contains_key(name) {
+            return Err(InitError::InstanceExists(name.to_string()));
+        }
+        prepare_game_dir(path)?;
+
+        self.instances.insert(
+            name.to_string(),
+            InstanceToml {
+                path: path.to_string_lossy().to_string(),
+                game_version: game::detect_version(path),
+            },
+        );
+        Ok(())
+    }
+
+    /// Get the profile for the game `id`, defaulting to `Cyberpunk 2077`.
+    pub fn game(&self, id: Option<&str>) -> Result<GameToml, InitError> {
+        let id = id.unwrap_or(DEFAULT_GAME);
+
+        let mut game = if let Some(game) = self.games.get(id) {
+            game.clone()
+        } else if id == DEFAULT_GAME {
+            GameToml {
+                path: self.game_path().to_string(),
+                root_dirs: VALID_ROOT_DIRS.iter().map(ToString::to_string).collect(),
+                merge_files: MERGE_FILES.iter().map(ToString::to_string).collect(),
+                deploy: DeployToml {
+                    mode: self.main.deploy_mode,
+                    staging_dir: self.main.staging_dir.clone(),
+                    ..Default::default()
+                },
+            }
+        } else {
+            return Err(InitError::UnknownGame(id.to_string()));
+        };
+
+        if let Some(root) = &self.root {
+            game.path = root.clone();
+            game.deploy.staging_dir = None;
+        }
+        Ok(game)
+    }
+
+    /// Directory downloaded archives are kept in, created if missing.
+    pub fn download_dir(&self) -> Result<PathBuf, std::io::Error> {
+        match &self.main.download_dir {
+            Some(dir) => {
+                let dir = PathBuf::from(shellexpand::tilde(dir).as_ref());
+                fs::create_dir_all(&dir)?;
+                Ok(dir)
+            }
+            None => download::cache_dir(),
+        }
+    }
+
+    /// Write back to the config file.
+    pub fn save(&self) -> Result<(), InitError> {
+        fs::write(
+            Init::get_config()?,
+            toml::to_string_pretty(self).expect("Could not serialize"),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Start managing the game directory at `path` without a config, for `--root`.
+pub fn init_root(path: &Path) -> Result<(), InitError> {
+    prepare_game_dir(path)
+}
+
+/// Give the game directory at `path` an empty `mods.toml` and disabled directory if missing.
+fn prepare_game_dir(path: &Path) -> Result<(), InitError> {
+    if !path.is_dir() {
+        return Err(InitError::NotADirectory(path.to_path_buf()));
+    }
 
-        xdg_dirs
-            .get_config_file("Vapor.toml")
-            .ok_or(InitError::MissingConfig(
-                xdg_dirs.get_config_home().unwrap().join("Vapor.toml"),
-            ))
+    let registry = path.join("mods.toml");
+    if !registry.exists() {
+        fs::write(registry, format!("schema_version = {SCHEMA_VERSION}"))?;
     }
+    fs::create_dir_all(path.join(DISABLED_DIR))?;
+    Ok(())
 }
 
 impl FromStr for CyberToml {
@@ -0,0 +1,574 @@
+//! A queue of long running operations, like installs, run one at a time on a worker thread for
+//! frontends and servers that take requests faster than they can be carried out.
+//!
+//! Every job gets an ID to look up its [`JobStatus`] by or [`JobQueue::cancel`] it with. Jobs are
+//! run in the order they were submitted, so two of them never race on `mods.toml`.
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use serde::Serialize;
+
+/// How many finished jobs are remembered for [`JobQueue::status`] before the oldest are forgotten.
+const KEPT_FINISHED: usize = 100;
+
+/// ID of a job, unique within its [`JobQueue`].
+pub type JobId = u64;
+
+/// Where a job is at.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for the jobs before it.
+    Queued,
+    /// Downloading, with the percentage done if the server said how big the file is.
+    Downloading {
+        percent: Option<u8>,
+    },
+    /// Extracting and deploying files. Past this point, cancelling has no effect.
+    Extracting,
+    /// Running, for jobs that don't say what they're doing.
+    Running,
+    /// Finished, with what the job returned, like the installed version.
+    Done {
+        result: String,
+    },
+    Failed {
+        error: String,
+    },
+    /// Cancelled before it finished.
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Check if the job won't change anymore.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Self::Done { .. } | Self::Failed { .. } | Self::Cancelled
+        )
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Queued => write!(f, "queued"),
+            Self::Downloading {
+                percent: Some(percent),
+            } => write!(f, "downloading {percent}%"),
+            Self::Downloading { percent: None } => write!(f, "downloading"),
+            Self::Extracting => write!(f, "extracting"),
+            Self::Running => write!(f, "running"),
+            Self::Done { .. } => write!(f, "done"),
+            Self::Failed { error } => write!(f, "failed: {error}"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// A job, as reported by [`JobQueue::jobs`].
+#[derive(Serialize, Clone, Debug)]
+pub struct Job {
+    pub id: JobId,
+    /// What the job does, like "add `My Mod`".
+    pub operation: String,
+    pub status: JobStatus,
+}
+
+type Work = Box<dyn FnOnce(&JobContext) -> miette::Result<String> + Send>;
+type Listener = Box<dyn Fn(&Job) + Send + Sync>;
+
+struct Entry {
+    job: Job,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct State {
+    next: JobId,
+    jobs: BTreeMap<JobId, Entry>,
+    queue: VecDeque<(JobId, Work)>,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled whenever a job is submitted or changes status.
+    changed: Condvar,
+    listener: Option<Listener>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn set(&self, id: JobId, status: JobStatus) {
+        let job = {
+            let mut state = self.lock();
+            let Some(entry) = state.jobs.get_mut(&id) else {
+                return;
+            };
+            entry.job.status = status;
+            let job = entry.job.clone();
+
+            if job.status.is_finished() {
+                forget_finished(&mut state);
+            }
+            job
+        };
+
+        if let Some(listener) = &self.listener {
+            listener(&job);
+        }
+        self.changed.notify_all();
+    }
+}
+
+fn forget_finished(state: &mut State) {
+    let finished = state
+        .jobs
+        .values()
+        .filter(|entry| entry.job.status.is_finished())
+        .map(|entry| entry.job.id)
+        .collect::<Vec<_>>();
+
+    for id in finished
+        .iter()
+        .take(finished.len().saturating_sub(KEPT_FINISHED))
+    {
+        state.jobs.remove(id);
+    }
+}
+
+/// What a running job uses to report progress and find out it was cancelled.
+pub struct JobContext {
+    id: JobId,
+    cancelled: Arc<AtomicBool>,
+    shared: Arc<Shared>,
+}
+
+impl JobContext {
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Check if [`JobQueue::cancel`] was called on this job. Jobs should check this between steps
+    /// and give up with an error if it was.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Report `downloaded` bytes out of `total`, if known.
+    pub fn downloading(&self, downloaded: u64, total: Option<u64>) {
+        let percent = total
+            .filter(|&total| total > 0)
+            .map(|total| (downloaded.min(total) * 100 / total) as u8);
+        let status = JobStatus::Downloading { percent };
+
+        // Only report whole percent changes, not every chunk.
+        if self
+            .shared
+            .lock()
+            .jobs
+            .get(&self.id)
+            .map(|entry| &entry.job.status)
+            != Some(&status)
+        {
+            self.shared.set(self.id, status);
+        }
+    }
+
+    /// Report that extraction started.
+    pub fn extracting(&self) {
+        self.shared.set(self.id, JobStatus::Extracting);
+    }
+}
+
+/// Jobs run one at a time, in the order they were submitted.
+///
+/// Dropping the queue cancels the jobs still queued and waits for the running one to finish.
+pub struct JobQueue {
+    shared: Arc<Shared>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::default(),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Call `listener` whenever a job is submitted or changes status, from whichever thread
+    /// changed it.
+    pub fn with_listener(mut self, listener: impl Fn(&Job) + Send + Sync + 'static) -> Self {
+        Arc::get_mut(&mut self.shared)
+            .expect("The queue isn't shared before the first job")
+            .listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Queue `work`, described by `operation`, returning its ID.
+    ///
+    /// What `work` returns is kept as [`JobStatus::Done`], and its error as [`JobStatus::Failed`],
+    /// or [`JobStatus::Cancelled`] if the job was cancelled.
+    pub fn submit(
+        &self,
+        operation: impl Into<String>,
+        work: impl FnOnce(&JobContext) -> miette::Result<String> + Send + 'static,
+    ) -> JobId {
+        let job = {
+            let mut state = self.shared.lock();
+            let id = state.next;
+            state.next += 1;
+
+            let job = Job {
+                id,
+                operation: operation.into(),
+                status: JobStatus::Queued,
+            };
+            state.jobs.insert(
+                id,
+                Entry {
+                    job: job.clone(),
+                    cancelled: Arc::default(),
+                },
+            );
+            state.queue.push_back((id, Box::new(work)));
+            job
+        };
+
+        if let Some(listener) = &self.shared.listener {
+            listener(&job);
+        }
+        self.start_worker();
+        self.shared.changed.notify_all();
+
+        job.id
+    }
+
+    /// Cancel job `id`. A queued job is dropped right away, and a running one gives up at its next
+    /// check of [`JobContext::is_cancelled`].
+    ///
+    /// Returns `false` if there's no such job or it already finished.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let queued = {
+            let mut state = self.shared.lock();
+            let Some(entry) = state.jobs.get(&id) else {
+                return false;
+            };
+            if entry.job.status.is_finished() {
+                return false;
+            }
+            entry.cancelled.store(true, Ordering::Relaxed);
+
+            let before = state.queue.len();
+            state.queue.retain(|(queued, _)| *queued != id);
+            state.queue.len() != before
+        };
+
+        if queued {
+            self.shared.set(id, JobStatus::Cancelled);
+        }
+        true
+    }
+
+    /// Status of job `id`, if it exists and hasn't been forgotten.
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.shared
+            .lock()
+            .jobs
+            .get(&id)
+            .map(|entry| entry.job.status.clone())
+    }
+
+    /// Every job not yet forgotten, oldest first.
+    pub fn jobs(&self) -> Vec<Job> {
+        self.shared
+            .lock()
+            .jobs
+            .values()
+            .map(|entry| entry.job.clone())
+            .collect()
+    }
+
+    /// Block until job `id` finishes, returning how it ended.
+    pub fn wait(&self, id: JobId) -> Option<JobStatus> {
+        let mut state = self.shared.lock();
+        loop {
+            match state.jobs.get(&id).map(|entry| &entry.job.status) {
+                Some(status) if status.is_finished() => return Some(status.clone()),
+                Some(_) => {
+                    state = self
+                        .shared
+                        .changed
+                        .wait(state)
+                        .unwrap_or_else(|err| err.into_inner());
+                }
+                None => return None,
+            }
+        }
+    }
+
+    fn start_worker(&self) {
+        let mut worker = self.worker.lock().unwrap_or_else(|err| err.into_inner());
+        if worker.is_none() {
+            let shared = Arc::clone(&self.shared);
+            *worker = Some(thread::spawn(move || run(&shared)));
+        }
+    }
+}
+
+impl Drop for JobQueue {
+    fn drop(&mut self) {
+        let cancelled = {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            state.queue.drain(..).map(|(id, _)| id).collect::<Vec<_>>()
+        };
+        for id in cancelled {
+            self.shared.set(id, JobStatus::Cancelled);
+        }
+        self.shared.changed.notify_all();
+
+        let worker = self.worker.get_mut().unwrap_or_else(|err| err.into_inner());
+        if let Some(worker) = worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run(shared: &Arc<Shared>) {
+    loop {
+        let (id, work, cancelled) = {
+            let mut state = shared.lock();
+            loop {
+                if state.closed {
+                    return;
+                }
+                if let Some((id, work)) = state.queue.pop_front() {
+                    let cancelled = Arc::clone(&state.jobs[&id].cancelled);
+                    break (id, work, cancelled);
+                }
+                state = shared
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|err| err.into_inner());
+            }
+        };
+
+        shared.set(id, JobStatus::Running);
+        let context = JobContext {
+            id,
+            cancelled: Arc::clone(&cancelled),
+            shared: Arc::clone(shared),
+        };
+
+        tracing::info!(job = id, "running");
+        // A job that panics fails on its own, rather than taking the worker and every job
+        // queued after it down with it.
+        let status = match panic::catch_unwind(AssertUnwindSafe(|| work(&context))) {
+            Ok(Ok(result)) => JobStatus::Done { result },
+            Ok(Err(_)) if cancelled.load(Ordering::Relaxed) => JobStatus::Cancelled,
+            Ok(Err(err)) => JobStatus::Failed {
+                error: err.to_string(),
+            },
+            Err(payload) => JobStatus::Failed {
+                error: format!("the job crashed: {}", panic_message(&*payload)),
+            },
+        };
+        tracing::info!(job = id, %status, "finished");
+        shared.set(id, status);
+    }
+}
+
+/// What a panic was raised with, if it was a message.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown error")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    /// Submit a job to `queue` that runs until something is sent to the returned sender.
+    fn blocker(queue: &JobQueue) -> (JobId, mpsc::Sender<()>) {
+        let (tx, rx) = mpsc::channel();
+        let id = queue.submit("block", move |_| {
+            rx.recv().ok();
+            Ok("unblocked".to_string())
+        });
+        (id, tx)
+    }
+
+    #[test]
+    fn runs_jobs_in_order() {
+        let order = Arc::new(Mutex::new(vec![]));
+        let queue = JobQueue::new();
+        let (first, unblock) = blocker(&queue);
+        let ids = (0..3)
+            .map(|i| {
+                let order = Arc::clone(&order);
+                queue.submit(format!("job {i}"), move |_| {
+                    order.lock().unwrap().push(i);
+                    Ok(i.to_string())
+                })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(queue.status(ids[2]), Some(JobStatus::Queued));
+
+        unblock.send(()).unwrap();
+        assert_eq!(
+            queue.wait(first),
+            Some(JobStatus::Done {
+                result: "unblocked".into()
+            })
+        );
+        for (i, id) in ids.iter().enumerate() {
+            assert_eq!(
+                queue.wait(*id),
+                Some(JobStatus::Done {
+                    result: i.to_string()
+                })
+            );
+        }
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+        assert_eq!(
+            queue
+                .jobs()
+                .iter()
+                .map(|job| job.operation.as_str())
+                .collect::<Vec<_>>(),
+            ["block", "job 0", "job 1", "job 2"]
+        );
+    }
+
+    #[test]
+    fn failed_and_panicking_jobs_leave_the_worker_running() {
+        let queue = JobQueue::new();
+        let failed = queue.submit("fail", |_| Err(miette::miette!("no space left")));
+        let panicked = queue.submit("panic", |_| panic!("oh no"));
+        let after = queue.submit("after", |_| Ok("fine".to_string()));
+
+        assert_eq!(
+            queue.wait(failed),
+            Some(JobStatus::Failed {
+                error: "no space left".into()
+            })
+        );
+        assert_eq!(
+            queue.wait(panicked),
+            Some(JobStatus::Failed {
+                error: "the job crashed: oh no".into()
+            })
+        );
+        assert_eq!(
+            queue.wait(after),
+            Some(JobStatus::Done {
+                result: "fine".into()
+            })
+        );
+    }
+
+    #[test]
+    fn cancels_queued_and_running_jobs() {
+        let queue = JobQueue::new();
+        let (started_tx, started) = mpsc::channel();
+        let running = queue.submit("running", move |context| {
+            started_tx.send(()).unwrap();
+            while !context.is_cancelled() {
+                thread::yield_now();
+            }
+            Err(miette::miette!("cancelled"))
+        });
+        let queued = queue.submit("queued", |_| Ok("ran".to_string()));
+
+        started.recv().unwrap();
+        assert!(queue.cancel(queued));
+        assert_eq!(queue.status(queued), Some(JobStatus::Cancelled));
+        assert!(queue.cancel(running));
+        assert_eq!(queue.wait(running), Some(JobStatus::Cancelled));
+
+        assert!(!queue.cancel(running));
+        assert!(!queue.cancel(JobId::MAX));
+    }
+
+    #[test]
+    fn reports_progress_to_the_listener() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let queue = JobQueue::new().with_listener({
+            let seen = Arc::clone(&seen);
+            move |job| seen.lock().unwrap().push(job.status.clone())
+        });
+        let id = queue.submit("add", |context| {
+            context.downloading(0, None);
+            context.downloading(10, Some(200));
+            // Not a whole percent more, so not reported.
+            context.downloading(11, Some(200));
+            context.downloading(300, Some(200));
+            context.extracting();
+            Ok("1.0".to_string())
+        });
+        queue.wait(id);
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                JobStatus::Queued,
+                JobStatus::Running,
+                JobStatus::Downloading { percent: None },
+                JobStatus::Downloading { percent: Some(5) },
+                JobStatus::Downloading { percent: Some(100) },
+                JobStatus::Extracting,
+                JobStatus::Done {
+                    result: "1.0".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dropping_cancels_what_is_queued() {
+        let queue = JobQueue::new();
+        let seen = Arc::clone(&queue.shared);
+        let (running, unblock) = blocker(&queue);
+        let queued = queue.submit("queued", |_| Ok(String::new()));
+
+        let dropper = thread::spawn(move || drop(queue));
+        while !seen.lock().closed {
+            thread::yield_now();
+        }
+        // The blocker may have been cancelled before it started, dropping its receiver.
+        unblock.send(()).ok();
+        dropper.join().unwrap();
+
+        let state = seen.lock();
+        assert_eq!(state.jobs[&queued].job.status, JobStatus::Cancelled);
+        assert!(state.jobs[&running].job.status.is_finished());
+    }
+}
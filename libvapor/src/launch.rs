@@ -0,0 +1,105 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::{init::CyberToml, mod_manager::registry::ModRegistry, proton, steam};
+
+/// Steam app ID of `Cyberpunk 2077`.
+pub const STEAM_APP_ID: u32 = 1091500;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum LaunchError {
+    #[error("io error: `{0}`")]
+    Io(#[from] std::io::Error),
+    #[error("Mods have missing dependencies: {}", .0.join(", "))]
+    #[diagnostic(help("Run `vapor status` for details, or pass `--force` to launch anyway"))]
+    MissingDependencies(Vec<String>),
+    #[error("No Proton install configured")]
+    #[diagnostic(help(
+        "Add a `[launch]` table with `proton` and `prefix` paths to `Vapor.toml`, or launch through Steam"
+    ))]
+    MissingProton,
+}
+
+/// How to start the game.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Via {
+    /// `steam -applaunch`.
+    Steam,
+    /// Directly through the configured Proton prefix.
+    Proton,
+}
+
+/// Fail if any mod has unsatisfied dependencies.
+pub fn check(registry: &ModRegistry) -> Result<(), LaunchError> {
+    let broken = registry
+        .mods
+        .keys()
+        .filter(|name| !registry.unsatisfied_deps(*name).is_empty())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    if broken.is_empty() {
+        Ok(())
+    } else {
+        Err(LaunchError::MissingDependencies(broken))
+    }
+}
+
+/// Build the command that launches the game.
+///
+/// `modded` passes `-modded` so REDmod mods are deployed on startup.
+pub fn command(config: &CyberToml, via: Via, modded: bool) -> Result<Command, LaunchError> {
+    let mut cmd = match via {
+        Via::Steam => {
+            // Steam on Windows is rarely on `PATH`.
+            let steam = match steam::roots().first() {
+                Some(root) if cfg!(windows) => root.join("steam.exe"),
+                _ => PathBuf::from("steam"),
+            };
+            let mut cmd = Command::new(steam);
+            cmd.arg("-applaunch").arg(STEAM_APP_ID.to_string());
+            cmd
+        }
+        Via::Proton => {
+            let Some(launch) = &config.launch else {
+                return Err(LaunchError::MissingProton);
+            };
+
+            let mut cmd = Command::new(expand(&launch.proton));
+            cmd.arg("run")
+                .arg(
+                    Path::new(config.game_path())
+                        .join("bin")
+                        .join("x64")
+                        .join("Cyberpunk2077.exe"),
+                )
+                .env("STEAM_COMPAT_DATA_PATH", expand(&launch.prefix))
+                .env(
+                    "STEAM_COMPAT_CLIENT_INSTALL_PATH",
+                    expand("~/.local/share/Steam"),
+                );
+
+            // Steam's launch options don't apply here, so load framework DLLs native first.
+            let dlls = proton::deployed_overrides(Path::new(config.game_path()));
+            if !dlls.is_empty() && std::env::var_os("WINEDLLOVERRIDES").is_none() {
+                cmd.env("WINEDLLOVERRIDES", proton::dll_overrides(&dlls));
+            }
+            cmd
+        }
+    };
+
+    if modded {
+        cmd.arg("-modded");
+    }
+
+    Ok(cmd)
+}
+
+fn expand(path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(path).as_ref())
+}
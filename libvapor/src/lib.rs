@@ -1,2 +1,28 @@
+pub mod config;
+pub mod doctor;
+pub mod download;
+pub mod download_cache;
+pub mod exit;
+pub mod frameworks;
+pub mod game;
+pub mod game_cache;
+pub mod hooks;
+pub mod i18n;
 pub mod init;
+pub mod jobs;
+pub mod launch;
+pub mod migrate;
 pub mod mod_manager;
+pub mod nexus;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+pub mod platform;
+pub mod proton;
+pub mod render;
+pub mod resolver;
+pub mod saves;
+pub mod secrets;
+pub mod steam;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod trash;
@@ -0,0 +1,259 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+use toml::{Table, Value};
+use tracing::info;
+
+use crate::mod_manager::name::{self, ModName};
+
+/// Registry layout written by this version of Vapor.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Upgrades from version `i` to `i + 1`, indexed by `i`.
+const MIGRATIONS: &[fn(&mut Table)] = &[v0_to_v1, v1_to_v2];
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum MigrateError {
+    #[error("io error: `{0}`")]
+    Io(#[from] std::io::Error),
+    #[error("Deserialization error: `{0}`")]
+    De(#[from] toml::de::Error),
+    #[error("Serialization error: `{0}`")]
+    Ser(#[from] toml::ser::Error),
+    #[error("Registry schema version {found} is newer than supported version {SCHEMA_VERSION}")]
+    #[diagnostic(help("Update Vapor to manage this registry"))]
+    TooNew { found: u32 },
+    #[error("`{first}` and `{second}` are the same mod name")]
+    #[diagnostic(help(
+        "Run `vapor config set name_folding none`, `vapor rename` one of them, then set `name_folding` back"
+    ))]
+    NameCollision { first: String, second: String },
+}
+
+/// Read the schema version of a raw registry, missing means `0`.
+pub fn schema_version(table: &Table) -> u32 {
+    table
+        .get("schema_version")
+        .and_then(Value::as_integer)
+        .map_or(0, |version| version as u32)
+}
+
+/// Upgrade the registry at `path` to [`SCHEMA_VERSION`] in place.
+///
+/// The original file is copied next to it first. Returns the backup path if anything changed.
+pub fn migrate(path: &Path) -> Result<Option<PathBuf>, MigrateError> {
+    let contents = fs::read_to_string(path)?;
+    let mut table: Table = toml::from_str(&contents)?;
+    let found = schema_version(&table);
+
+    if !upgrade(&mut table)? {
+        return Ok(None);
+    }
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{found}.bak"));
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup)?;
+    info!(from = found, to = SCHEMA_VERSION, backup = %backup.display(), "migrated registry");
+
+    fs::write(path, toml::to_string_pretty(&table)?)?;
+
+    Ok(Some(backup))
+}
+
+/// Upgrade a raw registry to [`SCHEMA_VERSION`] in memory, returning whether anything changed.
+pub fn upgrade(table: &mut Table) -> Result<bool, MigrateError> {
+    check_names(table)?;
+    let found = schema_version(table);
+
+    if found > SCHEMA_VERSION {
+        return Err(MigrateError::TooNew { found });
+    }
+    if found == SCHEMA_VERSION {
+        return Ok(false);
+    }
+
+    for migration in &MIGRATIONS[found as usize..] {
+        migration(table);
+    }
+    table.insert(
+        "schema_version".to_string(),
+        Value::Integer(SCHEMA_VERSION.into()),
+    );
+
+    Ok(true)
+}
+
+/// Unversioned registries could have entries without a `files` list.
+fn v0_to_v1(table: &mut Table) {
+    let Some(Value::Table(mods)) = table.get_mut("mods") else {
+        return;
+    };
+
+    for (_, entry) in mods.iter_mut() {
+        if let Value::Table(entry) = entry {
+            entry.entry("files").or_insert_with(|| Value::Array(vec![]));
+        }
+    }
+}
+
+/// Fail if two mods only differ in what [`name::folding`] ignores, since only one of them could
+/// ever be looked up.
+fn check_names(table: &Table) -> Result<(), MigrateError> {
+    let Some(Value::Table(mods)) = table.get("mods") else {
+        return Ok(());
+    };
+
+    let mut seen = BTreeMap::new();
+    for key in mods.keys() {
+        if let Some(first) = seen.insert(ModName::from(key), key) {
+            return Err(MigrateError::NameCollision {
+                first: first.clone(),
+                second: key.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Names used to be compared exactly, so point dependencies, conflicts and collection members at
+/// the mod as it is spelled in the registry.
+fn v1_to_v2(table: &mut Table) {
+    let Some(Value::Table(mods)) = table.get("mods") else {
+        return;
+    };
+    let names = mods.keys().cloned().collect::<Vec<_>>();
+    let respell = |value: &mut Value| {
+        if let Value::String(reference) = value
+            && let Some(name) = names
+                .iter()
+                .find(|name| name != &reference && name::fold(name) == name::fold(reference))
+        {
+            info!(from = %reference, to = %name, "respelled mod reference");
+            reference.clone_from(name);
+        }
+    };
+
+    if let Some(Value::Table(mods)) = table.get_mut("mods") {
+        for (_, entry) in mods.iter_mut() {
+            let Some(entry) = entry.as_table_mut() else {
+                continue;
+            };
+            for key in ["dependencies", "optional_dependencies", "conflicts"] {
+                if let Some(Value::Array(references)) = entry.get_mut(key) {
+                    references.iter_mut().for_each(respell);
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Table(collections)) = table.get_mut("collections") {
+        for (_, collection) in collections.iter_mut() {
+            let Some(collection) = collection.as_table_mut() else {
+                continue;
+            };
+            if let Some(Value::Array(members)) = collection.get_mut("mods") {
+                members.iter_mut().for_each(respell);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const V0: &str = r#"
+[mods."Cyber Engine Tweaks"]
+version = "1.0"
+
+[mods.Addon]
+version = "1.0"
+files = ["r6/scripts/addon.reds"]
+dependencies = ["cyber engine tweaks"]
+conflicts = ["CYBER ENGINE TWEAKS", "Unknown"]
+
+[collections.Favourites]
+mods = ["addon"]
+"#;
+
+    fn upgraded(registry: &str) -> Table {
+        let mut table = toml::from_str(registry).unwrap();
+        assert!(upgrade(&mut table).unwrap());
+        table
+    }
+
+    #[test]
+    fn upgrades_unversioned_registries() {
+        let table = upgraded(V0);
+        assert_eq!(schema_version(&table), SCHEMA_VERSION);
+        assert_eq!(
+            table["mods"]["Cyber Engine Tweaks"]["files"],
+            Value::Array(vec![])
+        );
+        assert_eq!(
+            table["mods"]["Addon"]["files"][0].as_str(),
+            Some("r6/scripts/addon.reds")
+        );
+    }
+
+    #[test]
+    fn respells_references_like_the_mods_they_point_at() {
+        let table = upgraded(V0);
+        let addon = &table["mods"]["Addon"];
+        assert_eq!(
+            addon["dependencies"][0].as_str(),
+            Some("Cyber Engine Tweaks")
+        );
+        assert_eq!(addon["conflicts"][0].as_str(), Some("Cyber Engine Tweaks"));
+        assert_eq!(addon["conflicts"][1].as_str(), Some("Unknown"));
+        assert_eq!(
+            table["collections"]["Favourites"]["mods"][0].as_str(),
+            Some("Addon")
+        );
+    }
+
+    #[test]
+    fn current_registries_are_left_alone() {
+        let mut table = toml::from_str(&format!("schema_version = {SCHEMA_VERSION}")).unwrap();
+        assert!(!upgrade(&mut table).unwrap());
+    }
+
+    #[test]
+    fn refuses_newer_registries_and_colliding_names() {
+        let mut table =
+            toml::from_str(&format!("schema_version = {}", SCHEMA_VERSION + 1)).unwrap();
+        assert!(matches!(
+            upgrade(&mut table),
+            Err(MigrateError::TooNew { found }) if found == SCHEMA_VERSION + 1
+        ));
+
+        let mut table = toml::from_str("[mods.cet]\n[mods.CET]").unwrap();
+        assert!(matches!(
+            upgrade(&mut table),
+            Err(MigrateError::NameCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn migrating_a_file_keeps_a_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("mods.toml");
+        fs::write(&path, V0).unwrap();
+
+        let backup = migrate(&path).unwrap().unwrap();
+        assert_eq!(backup, dir.path().join("mods.toml.v0.bak"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), V0);
+        let migrated: Table = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(schema_version(&migrated), SCHEMA_VERSION);
+
+        assert!(migrate(&path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use super::registry::ModRegistry;
+
+/// Canonical casing of paths already in the game directory or the registry.
+///
+/// Linux treats `Archive/pc/mod` and `archive/pc/mod` as two trees, while the game only ever
+/// sees one, so incoming paths are folded onto whatever casing is already there.
+#[derive(Default)]
+pub struct CaseMap {
+    /// Lowercased path prefix to its canonical casing.
+    canonical: HashMap<String, String>,
+    /// Every path seen, so a path that exists in several casings is left alone.
+    known: HashSet<String>,
+    /// Whether the filesystem ignores case, so every casing is one path and always folded.
+    insensitive: bool,
+}
+
+impl CaseMap {
+    /// Learn the casing of every prefix of `files` from `root` on disk, then from `registry`.
+    pub fn new(root: &Path, registry: &ModRegistry, files: &[String]) -> Self {
+        let mut map = Self::default();
+        let mut listed = HashSet::new();
+
+        for file in files {
+            let mut prefix = String::new();
+            for component in file.split('/') {
+                if listed.insert(prefix.to_lowercase())
+                    && let Ok(entries) = fs::read_dir(root.join(&prefix))
+                {
+                    for entry in entries.flatten() {
+                        let name = entry.file_name().to_string_lossy().into_owned();
+                        map.learn(&join(&prefix, &name));
+                    }
+                }
+                prefix = map.fold_component(&prefix, component);
+            }
+        }
+
+        for file in registry.mods.values().flat_map(|entry| &entry.files) {
+            let mut prefix = String::new();
+            for component in file.split('/') {
+                prefix = join(&prefix, component);
+                map.learn(&prefix);
+            }
+        }
+
+        map
+    }
+
+    fn learn(&mut self, path: &str) {
+        self.known.insert(path.to_string());
+        self.canonical
+            .entry(path.to_lowercase())
+            .or_insert_with(|| path.to_string());
+    }
+
+    /// Fold every path, even ones known in several casings, for case-insensitive filesystems.
+    pub fn case_insensitive(mut self, insensitive: bool) -> Self {
+        self.insensitive = insensitive;
+        self
+    }
+
+    fn fold_component(&self, prefix: &str, component: &str) -> String {
+        let path = join(prefix, component);
+        if !self.insensitive && self.known.contains(&path) {
+            return path;
+        }
+        self.canonical
+            .get(&path.to_lowercase())
+            .cloned()
+            .unwrap_or(path)
+    }
+
+    /// `path` with every component matching a known path in another case rewritten to it.
+    pub fn fold(&self, path: &str) -> String {
+        path.split('/')
+            .filter(|component| !component.is_empty())
+            .fold(String::new(), |prefix, component| {
+                self.fold_component(&prefix, component)
+            })
+    }
+}
+
+fn join(prefix: &str, component: &str) -> String {
+    if prefix.is_empty() {
+        component.to_string()
+    } else {
+        format!("{prefix}/{component}")
+    }
+}
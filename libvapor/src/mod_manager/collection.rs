@@ -0,0 +1,74 @@
+//! Named groups of mods, like a quest mod and its patches, toggled and shared as a unit.
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{handler::ModError, name::ModName, registry::ModRegistry};
+
+/// A collection in the registry.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Collection {
+    /// Members, in the order they were given.
+    pub mods: Vec<ModName>,
+}
+
+/// A collection as written by `vapor collection export`, with the versions it was made with.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionFile {
+    pub name: String,
+    pub mods: Vec<CollectionMod>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionMod {
+    pub name: String,
+    pub version: String,
+}
+
+impl CollectionFile {
+    /// The collection `name` in `registry`, with the installed version of each member.
+    pub fn new(registry: &ModRegistry, name: &str, collection: &Collection) -> Self {
+        Self {
+            name: name.to_string(),
+            mods: collection
+                .mods
+                .iter()
+                .map(|member| CollectionMod {
+                    name: member.to_string(),
+                    version: registry
+                        .mods
+                        .get(member)
+                        .map(|entry| entry.version.to_string())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ModError> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ModError> {
+        Ok(fs::write(path, toml::to_string_pretty(self)?)?)
+    }
+
+    /// How `registry` differs from what the collection was exported with, one line per member.
+    pub fn differences(&self, registry: &ModRegistry) -> Vec<String> {
+        self.mods
+            .iter()
+            .filter_map(|member| match registry.get(&member.name) {
+                None => Some(format!(
+                    "`{}` {} is not installed",
+                    member.name, member.version
+                )),
+                Some(entry) if entry.version != member.version => Some(format!(
+                    "`{}` is {} here, exported with {}",
+                    member.name, entry.version, member.version
+                )),
+                Some(_) => None,
+            })
+            .collect()
+    }
+}
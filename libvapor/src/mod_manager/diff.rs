@@ -0,0 +1,118 @@
+//! Differences between two registry states, for `vapor diff`.
+
+use std::fmt::Write;
+
+use inline_colorization::*;
+
+use super::{registry::ModRegistry, version::ModVersion};
+use crate::render;
+
+/// What changed from an old registry to a new one.
+#[derive(Debug, Default)]
+pub struct RegistryDiff {
+    /// Mods only in the new registry, with their version.
+    pub added: Vec<(String, ModVersion)>,
+    /// Mods only in the old registry, with their version.
+    pub removed: Vec<(String, ModVersion)>,
+    /// Mods whose version changed, as `(name, old, new)`.
+    pub updated: Vec<(String, ModVersion, ModVersion)>,
+    /// Mods enabled or disabled since, with whether they are enabled now.
+    pub toggled: Vec<(String, bool)>,
+    /// Files in both registries whose deployed copy comes from another mod now, as
+    /// `(file, old owner, new owner)`.
+    pub owners: Vec<(String, String, String)>,
+}
+
+impl RegistryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.updated.is_empty()
+            && self.toggled.is_empty()
+            && self.owners.is_empty()
+    }
+}
+
+/// Compare `old` against `new`.
+pub fn diff(old: &ModRegistry, new: &ModRegistry) -> RegistryDiff {
+    let mut diff = RegistryDiff::default();
+
+    for (name, entry) in &new.mods {
+        let Some(before) = old.get(name) else {
+            diff.added.push((name.to_string(), entry.version.clone()));
+            continue;
+        };
+
+        if before.version != entry.version {
+            diff.updated.push((
+                name.to_string(),
+                before.version.clone(),
+                entry.version.clone(),
+            ));
+        }
+        if before.installed != entry.installed {
+            diff.toggled.push((name.to_string(), entry.installed));
+        }
+    }
+
+    for (name, entry) in &old.mods {
+        if !new.contains(name) {
+            diff.removed.push((name.to_string(), entry.version.clone()));
+        }
+    }
+
+    let old_owners = old.ownership();
+    for (file, owners) in new.ownership() {
+        if let (Some(before), Some(now)) = (
+            old_owners.get(file).and_then(|owners| owners.first()),
+            owners.first(),
+        ) && before != now
+        {
+            diff.owners
+                .push((file.to_string(), before.to_string(), now.to_string()));
+        }
+    }
+
+    diff
+}
+
+/// Render `diff`, one change per line.
+#[allow(unused_must_use)]
+pub fn report(diff: &RegistryDiff) -> String {
+    let mut out = String::new();
+
+    for (name, version) in &diff.added {
+        writeln!(&mut out, "{color_green}+{style_reset} `{name}` {version}");
+    }
+    for (name, version) in &diff.removed {
+        writeln!(&mut out, "{color_red}-{style_reset} `{name}` {version}");
+    }
+    for (name, old, new) in &diff.updated {
+        if old.is_downgrade_to(new) {
+            writeln!(
+                &mut out,
+                "{color_yellow}~{style_reset} `{name}` `{old}` ~> `{new}` (downgrade)"
+            );
+        } else {
+            writeln!(
+                &mut out,
+                "{color_cyan}~{style_reset} `{name}` `{old}` ~> `{new}`"
+            );
+        }
+    }
+    for (name, enabled) in &diff.toggled {
+        if *enabled {
+            writeln!(&mut out, "{color_green}●{style_reset} `{name}` enabled");
+        } else {
+            writeln!(&mut out, "{color_yellow}○{style_reset} `{name}` disabled");
+        }
+    }
+    if !diff.owners.is_empty() {
+        writeln!(&mut out, "{style_bold}Changed owners:{style_reset}");
+        for (file, old, new) in &diff.owners {
+            writeln!(&mut out, "  {file}: `{old}` -> `{new}`");
+        }
+    }
+
+    render::finish(out)
+}
@@ -0,0 +1,114 @@
+//! Machine readable log of every operation, one JSON object per line in `events.jsonl` in the
+//! state directory, for dashboards and for working out when a file changed.
+//!
+//! Unlike the [`super::journal`], it is never rewritten by undoing: an undo is an event of its
+//! own.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::journal::{JournalError, Record};
+use crate::platform;
+
+/// How an operation ended.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    /// Reversed by `vapor undo`.
+    Undone,
+    /// Failed, and rolled back as far as it got.
+    Failed,
+}
+
+/// One operation, as logged.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OperationEvent {
+    pub at: DateTime<Utc>,
+    pub operation: String,
+    /// Mods whose registry entries the operation changed.
+    pub mods: Vec<String>,
+    /// Every file moved, created, linked or unlinked.
+    pub files: Vec<PathBuf>,
+    pub result: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl OperationEvent {
+    /// The event of `record` ending with `result`.
+    pub fn new(record: &Record, mods: Vec<String>, result: Outcome) -> Self {
+        let mut files = record
+            .moves
+            .iter()
+            .chain(&record.linked)
+            .chain(&record.unlinked)
+            .flat_map(|(from, to)| [from.clone(), to.clone()])
+            .chain(record.created.iter().cloned())
+            .collect::<Vec<_>>();
+        files.sort();
+        files.dedup();
+
+        Self {
+            at: Utc::now(),
+            operation: record.operation.clone(),
+            mods,
+            files,
+            result,
+            error: None,
+        }
+    }
+
+    /// An operation that failed with `error`.
+    pub fn failed(mut self, error: String) -> Self {
+        self.result = Outcome::Failed;
+        self.error = Some(error);
+        self
+    }
+}
+
+/// Append-only log of [`OperationEvent`]s.
+pub struct EventLog {
+    pub path: PathBuf,
+}
+
+impl EventLog {
+    pub fn new<T: Into<PathBuf>>(path: T) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The log in the state directory.
+    pub fn open_default() -> Result<Self, JournalError> {
+        Ok(Self::new(platform::place_state_file("events.jsonl")?))
+    }
+
+    pub fn append(&self, event: &OperationEvent) -> Result<(), JournalError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(&mut file, "{}", serde_json::to_string(event)?)?;
+        Ok(())
+    }
+
+    /// Every event, oldest first.
+    pub fn events(&self) -> Result<Vec<OperationEvent>, JournalError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+}
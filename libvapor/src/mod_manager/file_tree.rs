@@ -0,0 +1,86 @@
+//! The files of a mod as a directory tree, for `vapor list <mod> --tree`.
+
+use std::{collections::BTreeMap, fmt::Write, io::Cursor};
+
+use inline_colorization::*;
+use ptree::{TreeBuilder, write_tree};
+use serde::Serialize;
+
+use super::mod_file_formats::FileKind;
+use crate::{download_cache::format_size, render};
+
+/// A file of a mod, and whether it is where it should be.
+#[derive(Serialize, Debug, Clone)]
+pub struct FileInfo {
+    /// Relative to the game directory.
+    pub path: String,
+    pub kind: FileKind,
+    /// Size in bytes, if the file exists.
+    pub size: Option<u64>,
+    pub exists: bool,
+    /// Kept out of the game directory by an exclude pattern.
+    pub excluded: bool,
+}
+
+#[derive(Default)]
+struct Dir<'a> {
+    dirs: BTreeMap<&'a str, Dir<'a>>,
+    files: BTreeMap<&'a str, &'a FileInfo>,
+}
+
+impl<'a> Dir<'a> {
+    fn insert(&mut self, file: &'a FileInfo) {
+        let mut dir = self;
+        let mut parts = file
+            .path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                dir.files.insert(part, file);
+            } else {
+                dir = dir.dirs.entry(part).or_default();
+            }
+        }
+    }
+
+    fn build(&self, builder: &mut TreeBuilder) {
+        for (name, dir) in &self.dirs {
+            builder.begin_child(format!("{style_bold}{name}/{style_reset}"));
+            dir.build(builder);
+            builder.end_child();
+        }
+
+        for (name, file) in &self.files {
+            let mut line = String::new();
+            match (file.excluded, file.exists) {
+                (true, _) => write!(&mut line, "{color_yellow}-{color_reset} {name} (excluded)"),
+                (false, true) => write!(&mut line, "{color_green}✓{color_reset} {name}"),
+                (false, false) => write!(&mut line, "{color_red}✗{color_reset} {name}"),
+            }
+            .expect("writing to a String can't fail");
+            if let Some(size) = file.size {
+                write!(&mut line, " {color_cyan}{}{color_reset}", format_size(size))
+                    .expect("writing to a String can't fail");
+            }
+            builder.add_empty_child(line);
+        }
+    }
+}
+
+/// `files` of `mod_name` rendered as a tree of directories.
+pub fn render(mod_name: &str, files: &[FileInfo]) -> String {
+    let mut root = Dir::default();
+    for file in files {
+        root.insert(file);
+    }
+
+    let mut builder = TreeBuilder::new(format!("{style_bold}{mod_name}{style_reset}"));
+    root.build(&mut builder);
+
+    let mut buffer = Cursor::new(Vec::new());
+    let _ = write_tree(&builder.build(), &mut buffer);
+
+    render::finish(String::from_utf8(buffer.into_inner()).unwrap())
+}
@@ -0,0 +1,26 @@
+//! Keeping mods that hook into the game's code out of the way while the game is patched, for
+//! `vapor freeze` and `vapor thaw`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::registry::ModRegistry;
+
+/// Path prefixes of files that hook into the game's code, which game patches tend to break.
+pub const SCRIPT_PATHS: &[&str] = &["r6/scripts/", "red4ext/", "bin/x64/plugins/"];
+
+/// What [`crate::mod_manager::handler::ModHandler::freeze`] did, so it can be undone.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Freeze {
+    pub created: DateTime<Utc>,
+    /// Mods disabled by the freeze, in the order they were disabled.
+    pub disabled: Vec<String>,
+    /// The registry right before the freeze.
+    pub registry: ModRegistry,
+}
+
+/// Whether `file` hooks into the game's code, see [`SCRIPT_PATHS`].
+pub fn is_script_file(file: &str) -> bool {
+    let file = file.replace('\\', "/").to_lowercase();
+    SCRIPT_PATHS.iter().any(|prefix| file.starts_with(prefix))
+}
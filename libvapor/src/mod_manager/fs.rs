@@ -0,0 +1,1186 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::File,
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use inline_colorization::*;
+use rayon::prelude::*;
+use thiserror::Error;
+use tracing::debug;
+use zip::{ZipArchive, read::ZipFile, result::ZipError};
+
+use super::mod_file_formats::dir_files;
+use crate::{render, trash};
+
+/// A filesystem change made, or that would have been made, by [`Fs`].
+#[derive(Clone, Debug)]
+pub enum Change {
+    /// An archive entry extracted to this path.
+    Extract(PathBuf),
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Link {
+        source: PathBuf,
+        link: PathBuf,
+    },
+    /// A file or directory deleted.
+    Remove(PathBuf),
+    /// A file overwritten.
+    Write(PathBuf),
+    /// A file or directory moved to the trash.
+    Trash(PathBuf),
+}
+
+/// What [`VaporFs::metadata`] finds at a path.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Metadata {
+    pub len: u64,
+    pub is_dir: bool,
+    /// When it was last modified, if the filesystem keeps track.
+    pub modified: Option<SystemTime>,
+}
+
+/// A file [`VaporFs::create`] opened for writing, seekable since zip archives are written to
+/// it.
+pub trait WriteFile: Write + Seek + Send {}
+
+impl<T: Write + Seek + Send> WriteFile for T {}
+
+/// The filesystem operations [`Fs`] is built on.
+///
+/// [`RealFs`] makes changes, [`DryRunFs`] only records them, and [`MemoryFs`] makes them in
+/// memory, for tests that shouldn't touch the disk. Reads go through it too, so each sees the
+/// files it made.
+pub trait VaporFs: Send + Sync {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Move `from` to `to`, a file or a whole directory.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove `path` if it's an empty directory.
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Create `link` pointing at `source`, with `hard` picking a hardlink over a symlink.
+    fn link(&self, source: &Path, link: &Path, hard: bool) -> io::Result<()>;
+
+    /// Move `path` to the trash, returning where it went if it can be restored from there.
+    fn trash(&self, path: &Path) -> io::Result<Option<PathBuf>>;
+
+    /// Extract every entry of the zip archive at `archive` into `dir`, or copy every file when
+    /// it's a directory of loose files, at the path `place` gives each, skipping those it gives
+    /// none, reading it as `options` say.
+    fn extract(
+        &self,
+        archive: &Path,
+        dir: &Path,
+        place: &(dyn Fn(&str) -> Option<String> + Sync),
+        options: ExtractOptions<'_>,
+    ) -> Result<(), ExtractError>;
+
+    /// Contents of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Open the file at `path` to read it a bit at a time, like to hash it.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Create the file at `path`, or empty it, to write it a bit at a time.
+    fn create(&self, path: &Path) -> io::Result<Box<dyn WriteFile>>;
+
+    /// Check if there's anything at `path`, without following a symlink there.
+    fn exists(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Size and kind of what's at `path`, following a symlink there.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Where the symlink at `path` points.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// `path` with every symlink along it followed.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Check if changes are only recorded, see [`VaporFs::changes`].
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Changes recorded instead of made.
+    fn changes(&self) -> Vec<Change> {
+        vec![]
+    }
+}
+
+/// The real filesystem.
+#[derive(Default)]
+pub struct RealFs;
+
+impl VaporFs for RealFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        debug!(from = %from.display(), to = %to.display(), "moving");
+        move_path(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        debug!(path = %path.display(), "removing file");
+        std::fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        debug!(path = %path.display(), "removing directory");
+        std::fs::remove_dir_all(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        debug!(path = %path.display(), bytes = contents.len(), "writing");
+        std::fs::write(path, contents)
+    }
+
+    fn link(&self, source: &Path, link: &Path, hard: bool) -> io::Result<()> {
+        debug!(source = %source.display(), link = %link.display(), hard, "linking");
+        if hard {
+            return std::fs::hard_link(source, link);
+        }
+
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(source, link);
+        #[cfg(windows)]
+        return std::os::windows::fs::symlink_file(source, link);
+    }
+
+    fn trash(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        trash::put(path)
+    }
+
+    fn extract(
+        &self,
+        archive: &Path,
+        dir: &Path,
+        place: &(dyn Fn(&str) -> Option<String> + Sync),
+        options: ExtractOptions<'_>,
+    ) -> Result<(), ExtractError> {
+        if archive.is_dir() {
+            let files = dir_files(archive);
+            debug!(dir = %dir.display(), files = files.len(), "copying");
+            return files
+                .into_par_iter()
+                .filter_map(|file| Some((archive.join(&file), dir.join(place(&file)?))))
+                .try_for_each(|(from, to)| copy_file(&from, &to))
+                .map_err(ExtractError::from);
+        }
+
+        let len = ZipArchive::new(File::open(archive)?)?.len();
+
+        debug!(dir = %dir.display(), entries = len, "extracting");
+        // Each worker reads through its own handle, so entries decompress in parallel.
+        (0..len).into_par_iter().try_for_each_init(
+            || {
+                File::open(archive)
+                    .map_err(ZipError::from)
+                    .and_then(ZipArchive::new)
+            },
+            |zip, i| match zip {
+                Ok(zip) => extract_entry(zip, i, dir, place, options),
+                Err(err) => Err(io::Error::other(err.to_string()).into()),
+            },
+        )
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn create(&self, path: &Path) -> io::Result<Box<dyn WriteFile>> {
+        debug!(path = %path.display(), "creating");
+        Ok(Box::new(File::create(path)?))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.symlink_metadata().is_ok()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.symlink_metadata().is_ok_and(|meta| meta.is_dir())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Metadata {
+            len: metadata.len(),
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::read_link(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+}
+
+/// Records changes instead of making them, reading from the real filesystem.
+///
+/// Reads don't see recorded changes, so a directory is never empty for
+/// [`VaporFs::remove_dir`], since nothing was actually emptied.
+#[derive(Default)]
+pub struct DryRunFs {
+    changes: Mutex<Vec<Change>>,
+}
+
+impl DryRunFs {
+    fn record(&self, change: Change) {
+        self.changes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(change);
+    }
+}
+
+impl VaporFs for DryRunFs {
+    fn create_dir_all(&self, _: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.record(Change::Move {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        });
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.record(Change::Remove(path.to_owned()));
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.record(Change::Remove(path.to_owned()));
+        Ok(())
+    }
+
+    fn remove_dir(&self, _: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::DirectoryNotEmpty.into())
+    }
+
+    fn write(&self, path: &Path, _: &[u8]) -> io::Result<()> {
+        self.record(Change::Write(path.to_owned()));
+        Ok(())
+    }
+
+    fn link(&self, source: &Path, link: &Path, _: bool) -> io::Result<()> {
+        self.record(Change::Link {
+            source: source.to_owned(),
+            link: link.to_owned(),
+        });
+        Ok(())
+    }
+
+    fn trash(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        self.record(Change::Trash(path.to_owned()));
+        Ok(None)
+    }
+
+    fn extract(
+        &self,
+        archive: &Path,
+        dir: &Path,
+        place: &(dyn Fn(&str) -> Option<String> + Sync),
+        options: ExtractOptions<'_>,
+    ) -> Result<(), ExtractError> {
+        if archive.is_dir() {
+            for file in dir_files(archive) {
+                if let Some(path) = place(&file) {
+                    self.record(Change::Extract(dir.join(path)));
+                }
+            }
+            return Ok(());
+        }
+
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let file = open_entry(&mut zip, i, options.password)?;
+            if !file.is_dir()
+                && let Some(name) = file.enclosed_name()
+                && let Some(path) = place(&name.to_string_lossy())
+            {
+                self.record(Change::Extract(dir.join(path)));
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        RealFs.read(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        RealFs.open(path)
+    }
+
+    /// Records a write, and takes what's written without keeping it.
+    fn create(&self, path: &Path) -> io::Result<Box<dyn WriteFile>> {
+        self.record(Change::Write(path.to_owned()));
+        Ok(Box::new(Cursor::new(vec![])))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        RealFs.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        RealFs.is_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        RealFs.metadata(path)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        RealFs.read_link(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        RealFs.canonicalize(path)
+    }
+
+    fn is_dry_run(&self) -> bool {
+        true
+    }
+
+    fn changes(&self) -> Vec<Change> {
+        self.changes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+/// What's at a path in a [`MemoryFs`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Node {
+    Dir,
+    File(Vec<u8>),
+    Symlink(PathBuf),
+}
+
+/// A filesystem kept in memory, starting out empty apart from the root.
+///
+/// Hardlinks are copies, and trashed paths are simply gone. Only symlinks at the end of a path are
+/// followed, and files have no modification time. Archives are still read from the real
+/// filesystem, only what they extract to is kept in memory.
+#[derive(Default)]
+pub struct MemoryFs {
+    nodes: Arc<Mutex<BTreeMap<PathBuf, Node>>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn nodes(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+        self.nodes.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// What's at `path`.
+    pub fn get(&self, path: &Path) -> Option<Node> {
+        self.nodes().get(path).cloned()
+    }
+
+    /// Every path and what's there, in order.
+    pub fn paths(&self) -> Vec<(PathBuf, Node)> {
+        self.nodes()
+            .iter()
+            .map(|(path, node)| (path.clone(), node.clone()))
+            .collect()
+    }
+
+    /// Check the directory `path` should go in exists.
+    fn check_parent(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && parent.parent().is_some() => {
+                match nodes.get(parent) {
+                    Some(Node::Dir) => Ok(()),
+                    Some(_) => Err(io::ErrorKind::NotADirectory.into()),
+                    None => Err(io::ErrorKind::NotFound.into()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `path` and everything under it.
+    fn subtree(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Vec<PathBuf> {
+        nodes
+            .keys()
+            .filter(|key| key.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    /// What `path` is after following the symlinks at it, and where that is.
+    fn resolve(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<(PathBuf, Node)> {
+        let mut path = path.to_path_buf();
+        // As many links as Linux follows before giving up.
+        for _ in 0..40 {
+            match nodes.get(&path) {
+                Some(Node::Symlink(target)) => {
+                    path = path.parent().unwrap_or(&path).join(target);
+                }
+                Some(node) => return Ok((path, node.clone())),
+                None if path.parent().is_none() => return Ok((path, Node::Dir)),
+                None => return Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+        Err(io::Error::other("too many levels of symbolic links"))
+    }
+}
+
+/// A file being written in a [`MemoryFs`], kept there as it's flushed and when it's dropped.
+struct MemoryFile {
+    nodes: Arc<Mutex<BTreeMap<PathBuf, Node>>>,
+    path: PathBuf,
+    contents: Cursor<Vec<u8>>,
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.contents.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.nodes
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(
+                self.path.clone(),
+                Node::File(self.contents.get_ref().clone()),
+            );
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.contents.seek(pos)
+    }
+}
+
+impl Drop for MemoryFile {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl VaporFs for MemoryFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        for dir in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            if dir.as_os_str().is_empty() || dir.parent().is_none() {
+                continue;
+            }
+            match nodes.get(dir) {
+                Some(Node::Dir) => {}
+                Some(_) => return Err(io::ErrorKind::NotADirectory.into()),
+                None => {
+                    nodes.insert(dir.to_path_buf(), Node::Dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        if !nodes.contains_key(from) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        Self::check_parent(&nodes, to)?;
+
+        for path in Self::subtree(&nodes, to) {
+            nodes.remove(&path);
+        }
+        for path in Self::subtree(&nodes, from) {
+            let node = nodes.remove(&path).expect("Listed above");
+            let moved = to.join(path.strip_prefix(from).expect("Under `from`"));
+            nodes.insert(moved, node);
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::Dir) => Err(io::ErrorKind::IsADirectory.into()),
+            Some(_) => {
+                nodes.remove(path);
+                Ok(())
+            }
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::Dir) => {
+                for path in Self::subtree(&nodes, path) {
+                    nodes.remove(&path);
+                }
+                Ok(())
+            }
+            Some(_) => Err(io::ErrorKind::NotADirectory.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        match nodes.get(path) {
+            Some(Node::Dir) if Self::subtree(&nodes, path).len() > 1 => {
+                Err(io::ErrorKind::DirectoryNotEmpty.into())
+            }
+            Some(Node::Dir) => {
+                nodes.remove(path);
+                Ok(())
+            }
+            Some(_) => Err(io::ErrorKind::NotADirectory.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        Self::check_parent(&nodes, path)?;
+        if nodes.get(path) == Some(&Node::Dir) {
+            return Err(io::ErrorKind::IsADirectory.into());
+        }
+        nodes.insert(path.to_path_buf(), Node::File(contents.to_vec()));
+        Ok(())
+    }
+
+    fn link(&self, source: &Path, link: &Path, hard: bool) -> io::Result<()> {
+        let mut nodes = self.nodes();
+        Self::check_parent(&nodes, link)?;
+        if nodes.contains_key(link) {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+
+        let node = if hard {
+            match nodes.get(source) {
+                Some(Node::File(contents)) => Node::File(contents.clone()),
+                Some(_) => return Err(io::ErrorKind::InvalidInput.into()),
+                None => return Err(io::ErrorKind::NotFound.into()),
+            }
+        } else {
+            Node::Symlink(source.to_path_buf())
+        };
+        nodes.insert(link.to_path_buf(), node);
+        Ok(())
+    }
+
+    fn trash(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        let mut nodes = self.nodes();
+        if !nodes.contains_key(path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        for path in Self::subtree(&nodes, path) {
+            nodes.remove(&path);
+        }
+        Ok(None)
+    }
+
+    fn extract(
+        &self,
+        archive: &Path,
+        dir: &Path,
+        place: &(dyn Fn(&str) -> Option<String> + Sync),
+        options: ExtractOptions<'_>,
+    ) -> Result<(), ExtractError> {
+        if archive.is_dir() {
+            for file in dir_files(archive) {
+                let Some(path) = place(&file).map(|path| dir.join(path)) else {
+                    continue;
+                };
+                if let Some(parent) = path.parent() {
+                    self.create_dir_all(parent)?;
+                }
+                self.write(&path, &std::fs::read(archive.join(&file))?)?;
+            }
+            return Ok(());
+        }
+
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let mut entry = open_entry(&mut zip, i, options.password)?;
+            let Some(path) = entry
+                .enclosed_name()
+                .and_then(|name| place(&name.to_string_lossy()))
+                .map(|path| dir.join(path))
+            else {
+                continue;
+            };
+
+            if entry.is_dir() {
+                self.create_dir_all(&path)?;
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                self.create_dir_all(parent)?;
+            }
+            let mut contents = vec![];
+            match entry.read_to_end(&mut contents) {
+                Err(err) if is_checksum_error(&err) && !options.ignore_crc => {
+                    return Err(ExtractError::Checksum {
+                        entry: path.display().to_string(),
+                    });
+                }
+                Err(err) if !is_checksum_error(&err) => return Err(err.into()),
+                _ => {}
+            }
+            self.write(&path, &contents)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match Self::resolve(&self.nodes(), path)? {
+            (_, Node::File(contents)) => Ok(contents),
+            _ => Err(io::ErrorKind::IsADirectory.into()),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(Cursor::new(self.read(path)?)))
+    }
+
+    fn create(&self, path: &Path) -> io::Result<Box<dyn WriteFile>> {
+        self.write(path, &[])?;
+        Ok(Box::new(MemoryFile {
+            nodes: Arc::clone(&self.nodes),
+            path: path.to_path_buf(),
+            contents: Cursor::new(vec![]),
+        }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.parent().is_none() || self.nodes().contains_key(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.parent().is_none() || self.nodes().get(path) == Some(&Node::Dir)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let (_, node) = Self::resolve(&self.nodes(), path)?;
+        Ok(Metadata {
+            len: match &node {
+                Node::File(contents) => contents.len() as u64,
+                _ => 0,
+            },
+            is_dir: node == Node::Dir,
+            modified: None,
+        })
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.nodes().get(path) {
+            Some(Node::Symlink(target)) => Ok(target.clone()),
+            Some(_) => Err(io::ErrorKind::InvalidInput.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Self::resolve(&self.nodes(), path).map(|(path, _)| path)
+    }
+}
+
+/// Filesystem mutations made by [`super::handler::ModHandler`], through a [`VaporFs`].
+///
+/// In a dry run nothing is touched, and every change is collected for [`Fs::changes`] instead.
+pub struct Fs {
+    backend: Box<dyn VaporFs>,
+    /// Move what [`Fs::discard`] is given to the trash rather than deleting it.
+    pub trash: bool,
+    /// Decrypts encrypted archives in [`Fs::extract`].
+    pub password: Option<String>,
+    /// Keep entries whose checksum doesn't match in [`Fs::extract`], see
+    /// [`ExtractOptions::ignore_crc`].
+    pub ignore_crc: bool,
+}
+
+impl Default for Fs {
+    fn default() -> Self {
+        Self::new(RealFs)
+    }
+}
+
+impl Fs {
+    /// Make changes through `backend`.
+    pub fn new(backend: impl VaporFs + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+            trash: false,
+            password: None,
+            ignore_crc: false,
+        }
+    }
+
+    /// Only collect changes, never make them.
+    pub fn dry_run() -> Self {
+        Self::new(DryRunFs::default())
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.backend.is_dry_run()
+    }
+
+    /// Changes collected so far in a dry run.
+    pub fn changes(&self) -> Vec<Change> {
+        self.backend.changes()
+    }
+
+    pub fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.backend.create_dir_all(path)
+    }
+
+    pub fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.backend.rename(from, to)
+    }
+
+    /// Get rid of the file or directory at `path`, into the trash if [`Fs::trash`] is set.
+    ///
+    /// Returns where it was trashed to.
+    pub fn discard(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        if self.trash {
+            return self.backend.trash(path);
+        }
+
+        if !self.backend.exists(path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        if self.backend.is_dir(path) {
+            self.remove_dir_all(path)?;
+        } else {
+            self.remove_file(path)?;
+        }
+        Ok(None)
+    }
+
+    pub fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.backend.remove_file(path)
+    }
+
+    pub fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.backend.remove_dir_all(path)
+    }
+
+    /// Remove `path` if it's an empty directory.
+    ///
+    /// Always fails in a dry run, since nothing was actually emptied.
+    pub fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.backend.remove_dir(path)
+    }
+
+    pub fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.backend.write(path, contents.as_bytes())
+    }
+
+    pub fn read_to_string(&self, path: impl AsRef<Path>) -> io::Result<String> {
+        String::from_utf8(self.backend.read(path.as_ref())?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Check if there's anything at `path`, without following a symlink there.
+    pub fn exists(&self, path: &Path) -> bool {
+        self.backend.exists(path)
+    }
+
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.backend.is_dir(path)
+    }
+
+    /// Check if there's a file at `path`, following a symlink there.
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok_and(|metadata| !metadata.is_dir)
+    }
+
+    pub fn is_symlink(&self, path: &Path) -> bool {
+        self.backend.read_link(path).is_ok()
+    }
+
+    /// Open the file at `path` to read it a bit at a time.
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        self.backend.open(path)
+    }
+
+    /// Create the file at `path`, or empty it, to write it a bit at a time.
+    pub fn create(&self, path: &Path) -> io::Result<Box<dyn WriteFile>> {
+        self.backend.create(path)
+    }
+
+    /// Size and kind of what's at `path`, following a symlink there.
+    pub fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.backend.metadata(path)
+    }
+
+    pub fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        self.backend.read_link(path)
+    }
+
+    pub fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.backend.canonicalize(path)
+    }
+
+    /// Create `link` pointing at `source`, with `hard` picking a hardlink over a symlink.
+    pub fn link(&self, source: &Path, link: &Path, hard: bool) -> io::Result<()> {
+        self.backend.link(source, link, hard)
+    }
+
+    /// Check that [`Fs::password`] opens the archive at `archive`, if anything in it is
+    /// encrypted, failing like [`open_entry`] otherwise.
+    ///
+    /// Only the first encrypted entry is tried, since archives are encrypted with one password.
+    pub fn check_password(&self, archive: &Path) -> Result<(), ZipError> {
+        if archive.is_dir() {
+            return Ok(());
+        }
+
+        let mut zip = ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            if zip.by_index_raw(i)?.encrypted() {
+                open_entry(&mut zip, i, self.password.as_deref())?;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract every entry of the zip archive at `archive` into `dir`, across the rayon pool on
+    /// the real filesystem.
+    ///
+    /// Each entry goes where `place` says, or is skipped if it gives nowhere. Encrypted entries
+    /// are decrypted with [`Fs::password`].
+    pub fn extract(
+        &self,
+        archive: &Path,
+        dir: &Path,
+        place: &(dyn Fn(&str) -> Option<String> + Sync),
+    ) -> Result<(), ExtractError> {
+        let options = ExtractOptions {
+            password: self.password.as_deref(),
+            ignore_crc: self.ignore_crc,
+        };
+        self.backend.extract(archive, dir, place, options)
+    }
+}
+
+/// How [`VaporFs::extract`] reads archives.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ExtractOptions<'a> {
+    /// Decrypts encrypted entries.
+    pub password: Option<&'a str>,
+    /// Keep entries whose checksum doesn't match, as long as they're as long as the archive says.
+    /// Some tools write archives with wrong checksums for files that are fine.
+    pub ignore_crc: bool,
+}
+
+/// Why [`Fs::extract`] failed.
+#[derive(Error, Debug)]
+pub enum ExtractError {
+    #[error(transparent)]
+    Zip(#[from] ZipError),
+    /// An entry didn't match its checksum.
+    #[error("`{entry}` doesn't match its checksum")]
+    Checksum { entry: String },
+    /// An entry was only partly written.
+    #[error("`{entry}` was cut off after {written} of {expected} bytes")]
+    Truncated {
+        entry: String,
+        expected: u64,
+        written: u64,
+        /// The read or write error that stopped it, if any.
+        reason: Option<String>,
+    },
+}
+
+impl From<io::Error> for ExtractError {
+    fn from(err: io::Error) -> Self {
+        Self::Zip(err.into())
+    }
+}
+
+/// Check if `err` is the one the zip crate fails with when an entry doesn't match its checksum.
+pub(crate) fn is_checksum_error(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::InvalidData && err.to_string() == "Invalid checksum"
+}
+
+/// Entry `i` of `zip`, decrypted with `password` if it's encrypted.
+///
+/// Fails with [`ZipError::InvalidPassword`] when `password` is wrong, and with
+/// [`ZipError::PASSWORD_REQUIRED`] when the entry is encrypted and there is none.
+pub fn open_entry<'a, R: Read + Seek>(
+    zip: &'a mut ZipArchive<R>,
+    i: usize,
+    password: Option<&str>,
+) -> Result<ZipFile<'a, R>, ZipError> {
+    match password {
+        Some(password) => zip.by_index_decrypt(i, password.as_bytes()),
+        None => zip.by_index(i),
+    }
+}
+
+/// Stream entry `i` of `zip` into `dir`, skipping entries that would land outside of it or that
+/// `place` skips.
+///
+/// Only one buffer is held per entry, and the written size is checked against the size the
+/// archive claims.
+fn extract_entry<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    i: usize,
+    dir: &Path,
+    place: &(dyn Fn(&str) -> Option<String> + Sync),
+    options: ExtractOptions<'_>,
+) -> Result<(), ExtractError> {
+    let mut entry = open_entry(zip, i, options.password)?;
+    let Some(name) = entry
+        .enclosed_name()
+        .and_then(|name| place(&name.to_string_lossy()))
+    else {
+        return Ok(());
+    };
+    let path = dir.join(&name);
+
+    if entry.is_dir() {
+        std::fs::create_dir_all(&path)?;
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Replaced rather than written over, since the old file may be a hardlink shared with other
+    // mods through the store.
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+    let mut out = File::create(&path)?;
+    let mut buf = vec![0; 64 * 1024];
+    let expected = entry.size();
+    let mut written = 0;
+
+    let truncated = |written, reason: Option<io::Error>| ExtractError::Truncated {
+        entry: name.clone(),
+        expected,
+        written,
+        reason: reason.map(|err| err.to_string()),
+    };
+
+    loop {
+        let n = match entry.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            // Only checked once everything was read.
+            Err(err) if is_checksum_error(&err) && options.ignore_crc => break,
+            Err(err) if is_checksum_error(&err) => {
+                return Err(ExtractError::Checksum {
+                    entry: name.clone(),
+                });
+            }
+            Err(err) => return Err(truncated(written, Some(err))),
+        };
+        if let Err(err) = out.write_all(&buf[..n]) {
+            return Err(truncated(written, Some(err)));
+        }
+        written += n as u64;
+    }
+
+    if written != expected {
+        return Err(truncated(written, None));
+    }
+
+    #[cfg(unix)]
+    if let Some(mode) = entry.unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+    }
+
+    Ok(())
+}
+
+/// Copy the loose file `from` to `to`, like [`extract_entry`] does an archive entry.
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Unlinking `to` would delete the only copy when it is `from` itself.
+    if same_file(from, to) {
+        return Ok(());
+    }
+    // Replaced rather than written over, for the same reason as in `extract_entry`.
+    match std::fs::remove_file(to) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    std::fs::copy(from, to).map(|_| ())
+}
+
+/// Whether `a` and `b` are the same file, through links or different spellings of the path.
+fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Rename `from` to `to`, copying and deleting it when they are on different drives, like an SD
+/// card and internal storage.
+///
+/// Across drives the copy is made next to `to` and renamed into place, so `to` never shows up half
+/// copied, and `from` is only deleted once it has.
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            debug!(from = %from.display(), "copying across devices");
+            let mut partial = to.as_os_str().to_owned();
+            partial.push(".vapor-part");
+            let partial = PathBuf::from(partial);
+            remove_path(&partial)?;
+            copy_all(from, &partial)?;
+            std::fs::rename(&partial, to)?;
+            remove_path(from)
+        }
+        result => result,
+    }
+}
+
+/// Remove the file, link or directory at `path`, if there is one.
+fn remove_path(path: &Path) -> io::Result<()> {
+    let result = match path.symlink_metadata() {
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(err) => Err(err),
+    };
+    match result {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Copy `from` to `to`, recreating directories and symlinks as they are and keeping modification
+/// times.
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    if metadata.is_symlink() {
+        let target = std::fs::read_link(from)?;
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(target, to);
+        #[cfg(windows)]
+        return std::os::windows::fs::symlink_file(target, to);
+    }
+
+    if metadata.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+
+    std::fs::copy(from, to)?;
+    // Kept, so the file doesn't look modified to `vapor status`.
+    if let Ok(modified) = metadata.modified() {
+        File::options()
+            .write(true)
+            .open(to)?
+            .set_modified(modified)?;
+    }
+    Ok(())
+}
+
+#[allow(unused_must_use)]
+pub fn report(changes: &[Change]) -> String {
+    let mut out = String::new();
+
+    for change in changes {
+        match change {
+            Change::Extract(path) => writeln!(
+                &mut out,
+                "{color_green}extract{style_reset} {}",
+                path.display()
+            ),
+            Change::Move { from, to } => writeln!(
+                &mut out,
+                "{color_cyan}move{style_reset}    {} ~> {}",
+                from.display(),
+                to.display()
+            ),
+            Change::Link { source, link } => writeln!(
+                &mut out,
+                "{color_cyan}link{style_reset}    {} ~> {}",
+                link.display(),
+                source.display()
+            ),
+            Change::Remove(path) => writeln!(
+                &mut out,
+                "{color_red}delete{style_reset}  {}",
+                path.display()
+            ),
+            Change::Write(path) => writeln!(
+                &mut out,
+                "{color_yellow}write{style_reset}   {}",
+                path.display()
+            ),
+            Change::Trash(path) => writeln!(
+                &mut out,
+                "{color_red}trash{style_reset}   {}",
+                path.display()
+            ),
+        };
+    }
+
+    render::finish(out)
+}
@@ -1,22 +1,133 @@
 use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
     ffi::OsStr,
-    fs::{self, File, OpenOptions},
-    io::Write,
+    fs::{self, File, TryLockError},
     ops::Not,
     path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use chrono::Utc;
-use miette::{Diagnostic, NamedSource};
+use chrono::{DateTime, Utc};
+use glob::Pattern;
+use miette::Diagnostic;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeMap};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
-use zip::ZipArchive;
+use tracing::{debug, info, warn};
+use zip::{CompressionMethod, ZipWriter, result::ZipError, write::SimpleFileOptions};
+
+use crate::{
+    download_cache::format_size,
+    game_cache::GameCache,
+    hooks::{self, Event, Hooks, HooksToml},
+    i18n,
+    init::{ConflictPolicy, CyberToml, DeployMode, DeployToml, GameToml},
+    migrate::{self, MigrateError},
+    platform,
+    resolver::{self, Plan, Request, ResolveError},
+};
 
 use super::{
-    mod_file_formats::read_files,
-    registry::{ModEntry, ModRegistry},
+    case::CaseMap,
+    collection::{Collection, CollectionFile},
+    events::{EventLog, OperationEvent, Outcome},
+    file_tree::FileInfo,
+    freeze::Freeze,
+    fs::{DryRunFs, ExtractError, Fs, VaporFs},
+    hash,
+    inspection::{self, InspectionError},
+    journal::{HistoryEntry, Journal, JournalError, Record},
+    load_order::{self, Archive, MODLIST},
+    merge::{self, MERGE_FILES, MergeError},
+    metadata,
+    mod_file_formats::{self, FileKind, FormatError, read_files_cached},
+    name::ModName,
+    preview::Preview,
+    registry::{FileCheck, FileHealth, FileStamp, InstallRecord, ModEntry, ModRegistry},
+    store::Store,
+    toml_format, variant,
+    version::ModVersion,
 };
 
-const VALID_ROOT_DIRS: &[&str] = &["r6", "archive", "bin", "red4ext", "engine"];
+/// Top level directories `Cyberpunk 2077` mods install into.
+pub const VALID_ROOT_DIRS: &[&str] = &["r6", "archive", "bin", "red4ext", "engine"];
+
+/// Directory disabled mods are moved into by default.
+pub const DISABLED_DIR: &str = "Disabled Mods";
+
+/// Directory mods are staged in when deploying with links.
+pub const STAGING_DIR: &str = ".vapor/mods";
+
+/// Directory excluded files are kept in.
+pub const EXCLUDED_DIR: &str = ".vapor/excluded";
+
+/// Directory files of removed mods are kept in, so removals can be undone.
+pub const REMOVED_DIR: &str = ".vapor/removed";
+
+/// Directory the content-addressed store is kept in, see [`super::store`].
+pub const OBJECTS_DIR: &str = ".vapor/objects";
+
+/// Directory each mod's copy of a mergeable config file is kept in, see [`super::merge`].
+pub const FRAGMENTS_DIR: &str = ".vapor/fragments";
+
+/// Directory mergeable config files are kept in as they were before any mod, see
+/// [`super::merge`].
+pub const MERGE_BASE_DIR: &str = ".vapor/merge-base";
+
+/// Directory game files mods were installed over are kept in, put back when the mod is removed.
+pub const VANILLA_DIR: &str = ".vapor/vanilla";
+
+/// File [`ModHandler::freeze`] records what it disabled in.
+pub const FROZEN_FILE: &str = ".vapor/frozen.toml";
+
+/// File [`ModHandler::move_mod`] records the move in progress in, see [`ModHandler::fix_moves`].
+pub const MOVING_FILE: &str = ".vapor/moving.toml";
+
+/// File held locked while a process changes the game, see [`ModHandler::lock`].
+pub const LOCK_FILE: &str = ".vapor/lock";
+
+/// Where one of the `.vapor` directories of `game` at `path` is, under
+/// [`DeployToml::staging_dir`] in place of `.vapor` if that is set.
+fn staging_path(path: &Path, deploy: &DeployToml, dir: &str) -> PathBuf {
+    match &deploy.staging_dir {
+        Some(staging) => {
+            let staging = PathBuf::from(shellexpand::tilde(staging).as_ref());
+            game_staging(&staging, path).join(dir.trim_start_matches(".vapor/"))
+        }
+        None => path.join(dir),
+    }
+}
+
+/// Directory of the game at `path` in `staging`, so games and instances sharing one staging
+/// directory, like every game on an SD card, keep their files apart.
+///
+/// Staging directories set up before this kept one game's files directly in `staging`, and are
+/// used as they are while they still have any.
+fn game_staging(staging: &Path, path: &Path) -> PathBuf {
+    let legacy = [STAGING_DIR, REMOVED_DIR, EXCLUDED_DIR, OBJECTS_DIR]
+        .iter()
+        .any(|dir| staging.join(dir.trim_start_matches(".vapor/")).exists());
+    if legacy {
+        return staging.to_path_buf();
+    }
+
+    let name = path
+        .file_name()
+        .map_or_else(|| "game".into(), |name| name.to_string_lossy());
+    let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+    staging.join(format!(
+        "{name}-{}",
+        digest[..4]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    ))
+}
+
+/// Lines of a failed hook's output shown in the error, the rest is only in the log.
+const HOOK_OUTPUT_LINES: usize = 10;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum Move {
@@ -35,14 +146,189 @@ impl Not for Move {
     }
 }
 
+/// What [`ModHandler::add_mod`] records about a mod besides its name and version.
+#[derive(Default, Clone, Debug)]
+pub struct AddOptions {
+    pub dependencies: Vec<ModName>,
+    /// Mods it works best with, never required.
+    pub optional_dependencies: Vec<ModName>,
+    /// Game versions the mod is known to work with.
+    pub game_versions: Vec<String>,
+    /// Other names it satisfies dependencies on.
+    pub provides: Vec<ModName>,
+    /// Mods that can't be enabled alongside it.
+    pub conflicts: Vec<ModName>,
+    /// Update the mod even if it is pinned.
+    pub force: bool,
+    /// Author, description and homepage to use over what the archive says.
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// Which variant to install when the archive bundles several, see [`variant`].
+    pub variant: Option<String>,
+    /// Replace the mod with an older version than the one installed, failing with
+    /// [`ModError::Downgrade`] otherwise.
+    pub allow_downgrade: bool,
+    /// Install the archive even if [`inspection::check_archive`] finds unusual files in it.
+    pub trust: bool,
+}
+
+/// What disabling or removing a mod does with enabled mods depending on it, see
+/// [`ModHandler::dependents_to_disable`].
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Impact {
+    /// Fail with [`ModError::HasDependents`].
+    #[default]
+    Refuse,
+    /// Disable them as well, unless one is pinned.
+    Cascade,
+    /// Leave them enabled with a missing dependency.
+    Force,
+}
+
+/// A [`ModHandler::move_mod`] in progress, so [`ModHandler::fix_moves`] can finish it if Vapor
+/// dies partway.
+#[derive(Serialize, Deserialize)]
+struct PendingMove {
+    name: String,
+    /// Whether the mod is being enabled.
+    enable: bool,
+}
+
+/// A mod [`ModHandler::fix_moves`] put back together.
+pub struct FixedMove {
+    pub name: String,
+    /// Where the mod's files went.
+    pub action: Move,
+    /// Whether this finished the interrupted move, rather than moving its files back.
+    pub completed: bool,
+    /// Files moved or linked.
+    pub files: usize,
+}
+
 pub enum Operation {
     /// Version.
-    Added(String),
+    Added(ModVersion),
     Updated {
-        old: String,
-        new: String,
+        old: ModVersion,
+        new: ModVersion,
+    },
+    /// Replaced with an older version, see [`AddOptions::allow_downgrade`].
+    Downgraded {
+        old: ModVersion,
+        new: ModVersion,
     },
     Move(Move),
+    /// Version it had.
+    Removed(ModVersion),
+    Renamed {
+        /// The name it had.
+        old: String,
+        /// Mods whose dependencies were rewritten.
+        dependents: Vec<String>,
+    },
+    /// Version.
+    Adopted(ModVersion),
+    /// Files handed over, with their previous owner.
+    Chowned(Vec<OwnedFile>),
+    Undone {
+        /// Summary of the undone operation.
+        operation: String,
+        /// Every mod it changed in name order, the report being about the first.
+        mods: Vec<String>,
+    },
+}
+
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            Self::Added(version) => {
+                map.serialize_entry("operation", "added")?;
+                map.serialize_entry("version", version)?;
+            }
+            Self::Updated { old, new } => {
+                map.serialize_entry("operation", "updated")?;
+                map.serialize_entry("version", new)?;
+                map.serialize_entry("old_version", old)?;
+            }
+            Self::Downgraded { old, new } => {
+                map.serialize_entry("operation", "downgraded")?;
+                map.serialize_entry("version", new)?;
+                map.serialize_entry("old_version", old)?;
+            }
+            // Moves report where the mod came from.
+            Self::Move(Move::Enable) => map.serialize_entry("operation", "disabled")?,
+            Self::Move(Move::Disable) => map.serialize_entry("operation", "enabled")?,
+            Self::Removed(version) => {
+                map.serialize_entry("operation", "removed")?;
+                map.serialize_entry("version", version)?;
+            }
+            Self::Renamed { old, dependents } => {
+                map.serialize_entry("operation", "renamed")?;
+                map.serialize_entry("old_name", old)?;
+                map.serialize_entry("dependents", dependents)?;
+            }
+            Self::Adopted(version) => {
+                map.serialize_entry("operation", "adopted")?;
+                map.serialize_entry("version", version)?;
+            }
+            Self::Chowned(files) => {
+                map.serialize_entry("operation", "chowned")?;
+                map.serialize_entry("taken", files)?;
+            }
+            Self::Undone { operation, mods } => {
+                map.serialize_entry("operation", "undone")?;
+                map.serialize_entry("undid", operation)?;
+                map.serialize_entry("mods", mods)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// What a change to a mod, like [`ModHandler::add_mod`] or [`ModHandler::move_mod`], did, for
+/// frontends to show and serialize the same way.
+#[derive(Serialize)]
+pub struct OperationReport {
+    /// The mod, as named in the registry.
+    pub name: String,
+    #[serde(flatten)]
+    pub operation: Operation,
+    /// Files the mod has deployed, or moved into place for a move, or had before a removal.
+    pub files: usize,
+    /// Their total size, nothing for a dry run.
+    pub bytes: u64,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+    /// Files taken over from other mods, see [`ConflictPolicy::Overwrite`].
+    pub conflicts_resolved: usize,
+    /// Each warning raised, also left for [`ModHandler::take_warnings`].
+    pub warnings: Vec<String>,
+}
+
+impl OperationReport {
+    /// One line summary, like `12 files, 3.4 MiB in 0.42s`.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} file{}, {} in {:.2}s",
+            self.files,
+            if self.files == 1 { "" } else { "s" },
+            format_size(self.bytes),
+            self.duration.as_secs_f64()
+        );
+        if self.conflicts_resolved > 0 {
+            summary.push_str(&format!(
+                ", took over {} from other mods",
+                self.conflicts_resolved
+            ));
+        }
+        summary
+    }
+}
+
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
 }
 
 impl Move {
@@ -60,239 +346,3843 @@ pub enum ModError {
     De(#[from] toml::de::Error),
     #[error("Serialization error: `{0}`")]
     Ser(#[from] toml::ser::Error),
-    #[error("Missing mod: `{0}`")]
-    MissingMod(String),
+    #[error("Missing mod: `{name}`")]
+    MissingMod {
+        name: String,
+        #[help]
+        suggestion: Option<String>,
+    },
     #[error("Decompression issue: `{0}`")]
     ZipArchive(#[from] zip::result::ZipError),
     #[error("Files from `{incoming}` already exist in mod directory")]
     #[diagnostic(help("Ensure that mods are not trying to overwrite others."))]
     DoubleOwnedFiles {
         incoming: String,
-        #[source_code]
-        files: NamedSource<String>,
-        raw_splits: Vec<(String, String)>,
-        #[label = "Files(s) listed here are already owned by another mod"]
-        span: std::ops::Range<usize>,
+        /// Game directory the paths of `files` are relative to.
+        root: PathBuf,
+        files: Vec<OwnedFile>,
     },
     #[error("Extraction incomplete")]
     #[diagnostic(code(ModHandler::add_mod))]
     ExtractionIncomplete {
-        #[source_code]
-        files: NamedSource<String>,
-        raw_splits: Vec<PathBuf>,
-        #[label = "Files(s) listed here are could not be found after extraction"]
-        span: std::ops::Range<usize>,
+        /// Files that aren't there after extracting.
+        missing: Vec<PathBuf>,
+        /// Which entry stopped short, and why.
+        #[help]
+        truncated: Option<String>,
+    },
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Migrate(#[from] MigrateError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Journal(#[from] JournalError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Merge(#[from] MergeError),
+    #[error("A mod named `{0}` already exists")]
+    #[diagnostic(help("Pick a different name, or remove the existing mod first"))]
+    AlreadyExists(String),
+    #[error("Invalid pattern: `{0}`")]
+    Pattern(#[from] glob::PatternError),
+    #[error("`{pattern}` does not match any files of `{mod_name}`")]
+    #[diagnostic(help("Patterns match the paths shown by `vapor list <mod>`"))]
+    NoMatches { mod_name: String, pattern: String },
+    #[error("`{0}` does not match any files in the game directory")]
+    #[diagnostic(help("Patterns are relative to the game directory"))]
+    NothingToAdopt(String),
+    #[error("`{0}` reaches outside the game directory")]
+    #[diagnostic(help("Patterns are relative to the game directory"))]
+    OutsideGame(String),
+    #[error("`{pattern}` does not match any files of mods other than `{new_owner}`")]
+    #[diagnostic(help("Patterns match the paths shown by `vapor list <mod>`"))]
+    NothingToChown { new_owner: String, pattern: String },
+    #[error(
+        "`{owner}` is {}, but `{new_owner}` is {}",
+        if *installed { "disabled" } else { "enabled" },
+        if *installed { "enabled" } else { "disabled" }
+    )]
+    #[diagnostic(help("Enable or disable both mods before handing files over"))]
+    ChownAcrossStates {
+        owner: String,
+        new_owner: String,
+        /// Whether `new_owner` is enabled.
+        installed: bool,
+    },
+    #[error("{} matched files aren't where their mod keeps them", missing.len())]
+    #[diagnostic(help(
+        "Missing: {}\nRun `vapor fix-moves`, or reinstall the mods with `vapor add`",
+        missing.iter().map(|path| format!("`{}`", path.display())).collect::<Vec<_>>().join(", ")
+    ))]
+    ChownMissing { missing: Vec<PathBuf> },
+    #[error("`{archive}` bundles {} variants of the mod", available.len())]
+    #[diagnostic(help(
+        "Pick one with `--variant`, like `--variant '{}'`. {}",
+        available[0],
+        variant::listing(available)
+    ))]
+    VariantRequired {
+        archive: String,
+        available: Vec<String>,
     },
+    #[error("`{variant}` is not a variant in `{archive}`")]
+    #[diagnostic(help("{}", variant::listing(available)))]
+    MissingVariant {
+        archive: String,
+        variant: String,
+        available: Vec<String>,
+    },
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Format(#[from] FormatError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Inspection(#[from] InspectionError),
+    #[error("Could not read `{archive}`")]
+    #[diagnostic(
+        code(ModHandler::add_mod),
+        help("The file may be corrupt, re-download it")
+    )]
+    Corrupt {
+        archive: String,
+        #[source]
+        source: ZipError,
+    },
+    #[error("`{entry}` in `{archive}` doesn't match its checksum")]
+    #[diagnostic(
+        code(ModHandler::add_mod),
+        help(
+            "The archive may be corrupt, re-download it. Pass `--ignore-crc` to install it anyway"
+        )
+    )]
+    Checksum { archive: String, entry: String },
+    #[error("`{archive}` is encrypted")]
+    #[diagnostic(help("Pass its password with `--password`"))]
+    PasswordRequired { archive: String },
+    #[error("Wrong password for `{archive}`")]
+    #[diagnostic(help("Check the password on the mod's download page"))]
+    WrongPassword { archive: String },
+    #[error("No operation `{0}` in the history")]
+    #[diagnostic(help("Run `vapor history` to see the operations that can be compared against"))]
+    MissingSnapshot(u64),
+    #[error("Missing collection: `{0}`")]
+    #[diagnostic(help("Run `vapor collection list` to see the collections"))]
+    MissingCollection(String),
+    #[error("A collection named `{0}` already exists")]
+    #[diagnostic(help("Pick a different name, or delete the existing collection first"))]
+    CollectionExists(String),
+    #[error("`{name}` conflicts with enabled {}", quoted(others))]
+    #[diagnostic(help("Disable the conflicting mods first"))]
+    Conflicting { name: String, others: Vec<String> },
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Resolve(ResolveError),
+    #[error(
+        "{} enabled {} on `{name}`: {}",
+        dependents.len(),
+        if dependents.len() == 1 { "mod depends" } else { "mods depend" },
+        dependents.join(", ")
+    )]
+    #[diagnostic(help(
+        "Pass `--cascade` to disable them as well, or `--force` to leave them with a missing dependency"
+    ))]
+    HasDependents {
+        name: String,
+        dependents: Vec<String>,
+    },
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    HookFailed(HookFailure),
+    #[error("`{0}` is pinned")]
+    #[diagnostic(help("Pass `--force` to change it anyway, or run `vapor unpin {0}`"))]
+    Pinned(String),
+    #[error("`{dependent}` depends on `{name}` and is pinned, so `--cascade` can't disable it")]
+    #[diagnostic(help(
+        "Run `vapor unpin {dependent}` first, or pass `--force` instead of `--cascade` to leave it enabled with a missing dependency"
+    ))]
+    PinnedDependent { name: String, dependent: String },
+    #[error("`{new}` is older than the installed `{name}` `{old}`")]
+    #[diagnostic(help("Pass `--allow-downgrade` to install it anyway"))]
+    Downgrade {
+        name: String,
+        old: ModVersion,
+        new: ModVersion,
+    },
+    #[error("`{name}` has no older version recorded")]
+    #[diagnostic(help(
+        "Vapor can only go back to versions it recorded installing, see `vapor info {name} --history`"
+    ))]
+    NoOlderVersion { name: String },
+    #[error("No other version `{version}` of `{name}` recorded")]
+    #[diagnostic(help("{}", versions(available)))]
+    UnrecordedVersion {
+        name: String,
+        version: String,
+        available: Vec<ModVersion>,
+    },
+    #[error("`{archive}`, which `{name}` `{version}` was installed from, is gone")]
+    #[diagnostic(help(
+        "Download it again, then run `vapor add <archive> --name '{name}' --version '{version}' --allow-downgrade`"
+    ))]
+    ArchiveGone {
+        name: String,
+        version: ModVersion,
+        archive: String,
+    },
+    #[error("`{0}` is disabled")]
+    #[diagnostic(help("Run `vapor enable {0}` first"))]
+    Disabled(String),
+    #[error("Mods are already frozen since {}", .0.format("%Y-%m-%d %H:%M UTC"))]
+    #[diagnostic(help("Run `vapor thaw` to enable them again first"))]
+    AlreadyFrozen(DateTime<Utc>),
+    #[error("Mods aren't frozen")]
+    #[diagnostic(help("Run `vapor freeze` before updating the game"))]
+    NotFrozen,
     #[error("Missing file in dry-run: `{mod_name}` does not have `{path}`")]
     #[diagnostic(code(ModHandler::add_mod))]
     MissingFile { mod_name: String, path: String },
+    #[error("`{mod_name}` is missing {} files: {}", files.len(), files.join(", "))]
+    #[diagnostic(
+        code(ModHandler::pack),
+        help("Run `vapor fix-moves`, or reinstall the mod with `vapor add` to restore them")
+    )]
+    MissingFiles {
+        mod_name: String,
+        files: Vec<String>,
+    },
+    #[error("`{}` overlaps `{}`, which Vapor manages", path.display(), managed.display())]
+    #[diagnostic(help("Copy the mod's files somewhere outside the game directory and add that"))]
+    SourceInGame { path: PathBuf, managed: PathBuf },
+}
+
+impl ModError {
+    /// `err` from reading the archive at `path`, telling a missing or wrong password apart from
+    /// other failures.
+    fn from_zip(path: &Path, err: ZipError) -> Self {
+        let archive = archive_name(path);
+        match err {
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                Self::PasswordRequired { archive }
+            }
+            ZipError::InvalidPassword => Self::WrongPassword { archive },
+            ZipError::InvalidArchive(_) | ZipError::Io(_) => Self::Corrupt {
+                archive,
+                source: err,
+            },
+            err => err.into(),
+        }
+    }
+}
+
+/// File name of the archive at `path`, for diagnostics.
+fn archive_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `names` in backticks, separated by commas.
+fn quoted(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Recorded versions for a diagnostic.
+fn versions(available: &[ModVersion]) -> String {
+    if available.is_empty() {
+        return "No other versions are recorded".to_string();
+    }
+    format!(
+        "Recorded versions are: {}",
+        available
+            .iter()
+            .map(|version| format!("`{version}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// A file in the game directory and the mod it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OwnedFile {
+    /// Relative to the game directory.
+    pub path: PathBuf,
+    pub owner: String,
+}
+
+impl OwnedFile {
+    /// Files from [`ModRegistry::crossover_paths`].
+    fn from_crossover(paths: &[(String, String)]) -> Vec<Self> {
+        paths
+            .iter()
+            .map(|(owner, path)| Self {
+                path: PathBuf::from(path),
+                owner: owner.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A hook that exited unsuccessfully, an error before an operation and a warning after it.
+#[derive(Error, Diagnostic, Debug)]
+#[error("`{event}` hook of `{mod_name}` failed: `{command}`")]
+#[diagnostic(code(ModHandler::hooks))]
+pub struct HookFailure {
+    pub mod_name: String,
+    pub event: Event,
+    pub command: String,
+    /// Exit status and the end of its output.
+    #[help]
+    pub output: String,
+}
+
+/// Something [`ModHandler`] worked around, collected by [`ModHandler::take_warnings`].
+#[derive(Error, Diagnostic, Debug)]
+#[diagnostic(severity(Warning))]
+pub enum ModWarning {
+    #[error(
+        "Paths of `{mod_name}` differ in case from existing ones, installed with the existing casing"
+    )]
+    #[diagnostic(
+        code(ModHandler::add_mod),
+        help(
+            "{}",
+            paths
+                .iter()
+                .map(|(archive, installed)| format!("`{}` -> `{}`", archive.display(), installed.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    )]
+    CaseFolded {
+        mod_name: String,
+        /// Every rewritten path, as it is in the archive and as it was installed.
+        paths: Vec<(PathBuf, PathBuf)>,
+    },
+    #[error("Paths of `{mod_name}` aren't valid on Windows, installed under sanitized names")]
+    #[diagnostic(
+        code(ModHandler::add_mod),
+        help(
+            "{}",
+            paths
+                .iter()
+                .map(|(archive, installed)| format!("`{}` -> `{}`", archive.display(), installed.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    )]
+    Sanitized {
+        mod_name: String,
+        /// Every renamed path, as it is in the archive and as it was installed.
+        paths: Vec<(PathBuf, PathBuf)>,
+    },
+    #[error(
+        "Left out {} {} of `{mod_name}` that can't be installed",
+        entries.len(),
+        if entries.len() == 1 { "entry" } else { "entries" }
+    )]
+    #[diagnostic(
+        code(ModHandler::add_mod),
+        help(
+            "They're unreadable, would land outside the mod, or aren't named in UTF-8: {}",
+            entries.iter().map(|entry| format!("`{entry}`")).collect::<Vec<_>>().join(", ")
+        )
+    )]
+    Skipped {
+        mod_name: String,
+        entries: Vec<String>,
+    },
+    #[error("`{mod_name}` took over files from other mods")]
+    #[diagnostic(
+        code(ModHandler::add_mod),
+        help(
+            "{}",
+            files
+                .iter()
+                .map(|file| format!("`{}` from `{}`", file.path.display(), file.owner))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    )]
+    Overwrote {
+        mod_name: String,
+        /// Every file taken over, with its previous owner.
+        files: Vec<OwnedFile>,
+    },
+    #[error("Left `{mod_name}`'s copy of `{file}` out of the merged file")]
+    #[diagnostic(code(ModHandler::merge), help("It couldn't be merged: {reason}"))]
+    MergeSkipped {
+        mod_name: String,
+        file: String,
+        reason: String,
+    },
+    #[error("Skipped `{event}` hook of `{mod_name}` that isn't allowed: `{command}`")]
+    #[diagnostic(
+        code(ModHandler::hooks),
+        help("Run `vapor hook allow '{command}'` to let it run")
+    )]
+    HookNotAllowed {
+        mod_name: String,
+        event: Event,
+        command: String,
+    },
+    // Not `diagnostic(transparent)`, which would lose the warning severity.
+    #[error(transparent)]
+    #[diagnostic(code(ModHandler::hooks), help("{}", .0.output))]
+    HookFailed(HookFailure),
+    #[error("Couldn't move the files of `{mod_name}` to the trash")]
+    #[diagnostic(
+        code(ModHandler::remove_mod),
+        help("They're kept in `{}` instead: {reason}", kept.display())
+    )]
+    TrashFailed {
+        mod_name: String,
+        /// Where the files are.
+        kept: PathBuf,
+        reason: String,
+    },
+    #[error("Collection `{name}` differs from what was exported")]
+    #[diagnostic(code(ModHandler::import_collection))]
+    CollectionDiffers {
+        name: String,
+        /// Every missing member or version mismatch.
+        #[help]
+        differences: String,
+    },
+}
+
+impl ModError {
+    /// [`ModError::HasDependents`] listing `dependents`.
+    pub fn has_dependents(name: String, dependents: &[&str]) -> Self {
+        Self::HasDependents {
+            name,
+            dependents: dependents.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// [`ModError::Conflicting`] listing `others`.
+    pub fn conflicting(name: String, others: &[&str]) -> Self {
+        Self::Conflicting {
+            name,
+            others: others.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// [`ModError::MissingMod`] suggesting the closest name in `registry`.
+    pub fn missing_mod(registry: &ModRegistry, name: String) -> Self {
+        let suggestion = registry
+            .similar_names(&name)
+            .first()
+            .map(|similar| i18n::tr_with("error-did-you-mean", &[("name", similar)]));
+
+        Self::MissingMod { name, suggestion }
+    }
 }
 
 pub struct ModHandler {
+    /// Directory mods are extracted into.
     pub root: PathBuf,
     pub toml: PathBuf,
+    /// Directory disabled mods are moved into.
+    pub disabled: PathBuf,
+    /// Top level directories mods install into, which are never cleaned up.
+    pub root_dirs: Vec<String>,
+    /// Where mutating operations are recorded for [`ModHandler::undo`].
+    pub journal: Option<Journal>,
+    /// Where every operation, undo and failure is logged for other tools to read.
+    pub events: Option<EventLog>,
+    pub deploy_mode: DeployMode,
+    /// Directory each mod is extracted into when [`DeployMode::links`].
+    pub staging: PathBuf,
+    /// Directory excluded files of each mod are kept in.
+    pub excluded: PathBuf,
+    /// Directory files of removed mods are kept in.
+    pub removed: PathBuf,
+    /// Directory of the content-addressed store, used when [`ModHandler::dedupe`] is on.
+    pub objects: PathBuf,
+    /// Directory each mod's fragment of a mergeable file is kept in.
+    pub fragments: PathBuf,
+    /// Directory mergeable files are kept in as they were before any mod.
+    pub merge_base: PathBuf,
+    /// Config files merged from each mod's copy rather than owned by one mod.
+    pub merge_files: Vec<String>,
+    /// Directory game files no mod owned are kept in while a mod's copy replaces them.
+    pub vanilla: PathBuf,
+    /// Where [`ModHandler::freeze`] records what it disabled.
+    pub frozen: PathBuf,
+    /// Where [`ModHandler::move_mod`] records the move in progress.
+    pub moving: PathBuf,
+    /// File locked by [`ModHandler::lock`].
+    pub lock_file: PathBuf,
+    /// The lock on [`ModHandler::lock_file`] while held.
+    lock: Option<File>,
+    /// Every filesystem change goes through here.
+    pub fs: Fs,
+    /// What [`ModHandler::add_mod`] does with files owned by other mods.
+    pub conflict_policy: ConflictPolicy,
+    /// Hooks run for every mod, and the commands allowed to run.
+    pub hooks: HooksToml,
+    /// Clear [`GameCache`]s built from files an operation touched.
+    pub auto_clear_cache: bool,
+    /// Rewrite only what changed in `mods.toml`, see [`toml_format::to_string_minimal`].
+    pub minimal_diff: bool,
+    /// Hardlink installed files to one copy in [`ModHandler::objects`].
+    pub dedupe: bool,
+    /// Rewrite [`MODLIST`] after every operation, see [`ModHandler::write_modlist`].
+    pub modlist: bool,
+    warnings: RefCell<Vec<ModWarning>>,
+    /// Records of the [`ModHandler::transaction`] in progress, journaled together at its end.
+    transaction: RefCell<Option<Vec<Record>>>,
 }
 
 impl ModHandler {
+    /// Handler for a `Cyberpunk 2077` install at `root`.
     pub fn new<T: Into<PathBuf>>(root: T) -> Self {
-        let root = root.into();
+        let root = platform::normalize(&root.into());
         Self {
             root: root.clone(),
             toml: root.join("mods.toml"),
+            disabled: root.join(DISABLED_DIR),
+            root_dirs: VALID_ROOT_DIRS.iter().map(ToString::to_string).collect(),
+            journal: None,
+            events: None,
+            deploy_mode: DeployMode::default(),
+            staging: root.join(STAGING_DIR),
+            excluded: root.join(EXCLUDED_DIR),
+            removed: root.join(REMOVED_DIR),
+            objects: root.join(OBJECTS_DIR),
+            fragments: root.join(FRAGMENTS_DIR),
+            merge_base: root.join(MERGE_BASE_DIR),
+            merge_files: MERGE_FILES.iter().map(ToString::to_string).collect(),
+            vanilla: root.join(VANILLA_DIR),
+            frozen: root.join(FROZEN_FILE),
+            moving: root.join(MOVING_FILE),
+            lock_file: root.join(LOCK_FILE),
+            lock: None,
+            fs: Fs::default(),
+            conflict_policy: ConflictPolicy::default(),
+            hooks: HooksToml::default(),
+            auto_clear_cache: false,
+            minimal_diff: false,
+            dedupe: false,
+            modlist: false,
+            warnings: RefCell::default(),
+            transaction: RefCell::default(),
+        }
+    }
+
+    /// Handler for any game profile.
+    pub fn for_game(game: &GameToml) -> Self {
+        let path = platform::normalize(Path::new(&game.path));
+        Self {
+            root: match &game.deploy.target {
+                Some(target) => path.join(target),
+                None => path.clone(),
+            },
+            toml: path.join("mods.toml"),
+            disabled: path.join(&game.deploy.disabled_dir),
+            root_dirs: game.root_dirs.clone(),
+            journal: None,
+            events: None,
+            deploy_mode: game.deploy.mode,
+            staging: staging_path(&path, &game.deploy, STAGING_DIR),
+            excluded: staging_path(&path, &game.deploy, EXCLUDED_DIR),
+            removed: staging_path(&path, &game.deploy, REMOVED_DIR),
+            objects: staging_path(&path, &game.deploy, OBJECTS_DIR),
+            fragments: staging_path(&path, &game.deploy, FRAGMENTS_DIR),
+            merge_base: staging_path(&path, &game.deploy, MERGE_BASE_DIR),
+            merge_files: game.merge_files.clone(),
+            vanilla: staging_path(&path, &game.deploy, VANILLA_DIR),
+            frozen: staging_path(&path, &game.deploy, FROZEN_FILE),
+            moving: staging_path(&path, &game.deploy, MOVING_FILE),
+            lock_file: staging_path(&path, &game.deploy, LOCK_FILE),
+            lock: None,
+            fs: Fs::default(),
+            conflict_policy: ConflictPolicy::default(),
+            hooks: HooksToml::default(),
+            auto_clear_cache: false,
+            minimal_diff: false,
+            dedupe: false,
+            modlist: false,
+            warnings: RefCell::default(),
+            transaction: RefCell::default(),
         }
     }
 
-    fn term_link(&self, file: &str) -> String {
-        let full_path = self.root.join(file);
-        let path_str = full_path.to_string_lossy();
-        let url = format!("file://{path_str}");
-        format!("\x1b]8;;{url}\x1b\\{file}\x1b]8;;\x1b\\")
+    /// Handler for `game` with the journal, event log, hooks and `[main]` settings from `config`,
+    /// the way every frontend should load one.
+    pub fn from_config(config: &CyberToml, game: &GameToml) -> Result<Self, ModError> {
+        Ok(Self::for_game(game)
+            .with_journal(Journal::open_default()?)
+            .with_events(EventLog::open_default()?)
+            .with_conflict_policy(config.main.conflict_policy)
+            .with_hooks(config.hooks.clone())
+            .with_auto_clear_cache(config.main.auto_clear_cache)
+            .with_trash(config.main.trash)
+            .with_minimal_diff(config.main.minimal_diff)
+            .with_dedupe(config.main.dedupe)
+            .with_modlist(config.main.modlist))
     }
 
-    pub fn add_mod<S: Into<String>>(
-        &self,
-        path: &Path,
-        name: S,
-        version: S,
-        dependencies: &[String],
-    ) -> Result<Operation, ModError> {
-        let name = name.into();
-        let version = version.into();
+    /// Wait for other processes changing this game to finish, then keep them waiting until the
+    /// handler is dropped.
+    ///
+    /// Only commands that change the game need this. A move another process left interrupted is
+    /// safe to finish with [`ModHandler::fix_pending_move`] once it is held.
+    pub fn lock(mut self) -> Result<Self, ModError> {
+        if let Some(parent) = self.lock_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_file)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                warn!(root = %self.root.display(), "waiting for another Vapor process to finish");
+                file.lock()?;
+            }
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+        self.lock = Some(file);
+        Ok(self)
+    }
 
-        let mut toml = self.load_toml()?;
+    /// Record every mutating operation in `journal`.
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
 
-        let mut archive = ZipArchive::new(File::open(path)?).expect("Could not read zip file");
+    /// Log every operation to `events`.
+    pub fn with_events(mut self, events: EventLog) -> Self {
+        self.events = Some(events);
+        self
+    }
 
-        let files = read_files(path);
+    /// Handle files owned by other mods according to `policy`.
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
 
-        let crossed_paths = toml.crossover_paths(&name, files.clone());
-        if !crossed_paths.is_empty() {
-            let text = crossed_paths
-                .iter()
-                .map(|(owned, file)| format!("{owned} | {}", self.term_link(file)))
-                .collect::<Vec<_>>()
-                .join("\n");
-            let span = 0..text.len();
-            return Err(ModError::DoubleOwnedFiles {
-                raw_splits: crossed_paths,
-                incoming: name,
-                files: NamedSource::new("conflicting files", text),
-                span,
-            });
-        }
+    /// Run `hooks` around installs and enables, as far as they are allowed.
+    pub fn with_hooks(mut self, hooks: HooksToml) -> Self {
+        self.hooks = hooks;
+        self
+    }
 
-        archive.extract(self.root.clone())?;
+    /// Clear caches built from script and tweak files whenever those change.
+    pub fn with_auto_clear_cache(mut self, auto_clear_cache: bool) -> Self {
+        self.auto_clear_cache = auto_clear_cache;
+        self
+    }
 
-        let extracted_files = files.iter().map(|f| self.root.join(f)).collect::<Vec<_>>();
+    /// Keep the formatting of `mods.toml`, rewriting only what changed.
+    pub fn with_minimal_diff(mut self, minimal_diff: bool) -> Self {
+        self.minimal_diff = minimal_diff;
+        self
+    }
 
-        let missing: Vec<_> = extracted_files.iter().filter(|p| !p.exists()).collect();
+    /// Keep one copy of identical files in [`ModHandler::objects`], hardlinked into place.
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
 
-        if !missing.is_empty() {
-            let text = missing
-                .iter()
-                .map(|file| self.term_link(file.to_str().unwrap()))
-                .collect::<Vec<_>>()
-                .join("\n");
-            let span = 0..text.len();
-            return Err(ModError::ExtractionIncomplete {
-                raw_splits: missing.into_iter().cloned().collect(),
-                files: NamedSource::new("missing files", text),
-                span,
-            });
-        }
+    /// Keep [`MODLIST`] in the load order of the registry.
+    pub fn with_modlist(mut self, modlist: bool) -> Self {
+        self.modlist = modlist;
+        self
+    }
 
-        let old_version = toml.mods.get(&name).map(|entry| entry.version.clone());
+    /// Move removed files to the trash rather than keeping them in [`ModHandler::removed`].
+    pub fn with_trash(mut self, trash: bool) -> Self {
+        self.fs.trash = trash;
+        self
+    }
 
-        toml.mods.insert(
-            name,
-            ModEntry {
-                version: version.clone(),
-                file: path.to_string_lossy().to_string(),
-                installed: true,
-                installed_at: Some(Utc::now()),
-                dependencies: if dependencies.is_empty() {
-                    None
-                } else {
-                    Some(dependencies.to_vec())
-                },
-                files: read_files(path),
-            },
-        );
+    /// Decrypt encrypted archives with `password`.
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.fs.password = password;
+        self
+    }
 
-        let mut mods = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.toml)?;
+    /// Install entries of archives that don't match their checksum, see
+    /// [`ExtractOptions::ignore_crc`](super::fs::ExtractOptions::ignore_crc).
+    pub fn with_ignore_crc(mut self, ignore_crc: bool) -> Self {
+        self.fs.ignore_crc = ignore_crc;
+        self
+    }
 
-        write!(&mut mods, "{}", toml::to_string_pretty(&toml)?)?;
+    /// Collect changes in [`ModHandler::fs`] instead of making them.
+    pub fn with_dry_run(self) -> Self {
+        self.with_fs(DryRunFs::default())
+    }
 
-        if let Some(old_version) = old_version {
-            if old_version != version {
-                return Ok(Operation::Updated {
-                    old: old_version,
-                    new: version,
-                });
-            }
-        }
+    /// Make filesystem changes through `backend`, like a [`MemoryFs`](super::fs::MemoryFs).
+    pub fn with_fs(mut self, backend: impl VaporFs + 'static) -> Self {
+        let (trash, password, ignore_crc) =
+            (self.fs.trash, self.fs.password.take(), self.fs.ignore_crc);
+        self.fs = Fs::new(backend);
+        self.fs.trash = trash;
+        self.fs.password = password;
+        self.fs.ignore_crc = ignore_crc;
+        self
+    }
 
-        Ok(Operation::Added(version))
+    /// Warnings collected since the last call.
+    pub fn take_warnings(&self) -> Vec<ModWarning> {
+        self.warnings.take()
     }
 
-    pub fn move_mod<S: Into<String>>(
+    pub fn add_mod<S: Into<String>>(
         &self,
+        path: &Path,
         name: S,
-        move_where: Move,
-    ) -> Result<Operation, ModError> {
+        version: S,
+        options: &AddOptions,
+    ) -> Result<OperationReport, ModError> {
         let name = name.into();
-        let mut toml = self.load_toml()?;
+        self.report(name.clone(), || {
+            self.install_archive(path, name, ModVersion::new(version), options)
+        })
+    }
 
-        let Some(entry) = toml.mods.get_mut(&name) else {
-            return Err(ModError::MissingMod(name));
-        };
+    /// Check that the archive at `path` can be opened with the password given to
+    /// [`ModHandler::with_password`], failing with [`ModError::PasswordRequired`] or
+    /// [`ModError::WrongPassword`] otherwise.
+    pub fn check_password(&self, path: &Path) -> Result<(), ModError> {
+        self.fs
+            .check_password(path)
+            .map_err(|err| ModError::from_zip(path, err))
+    }
 
-        let installed = move_where.installed();
+    /// Refuse a directory of loose files that is, holds or lies in a directory Vapor manages, since
+    /// copying it would copy files onto themselves or into the directory being read.
+    fn check_source_dir(&self, path: &Path) -> Result<(), ModError> {
+        let source = self.fs.canonicalize(path)?;
+        for managed in [&self.root, &self.staging, &self.disabled] {
+            let Ok(canonical) = self.fs.canonicalize(managed) else {
+                continue;
+            };
+            if source.starts_with(&canonical) || canonical.starts_with(&source) {
+                return Err(ModError::SourceInGame {
+                    path: path.to_path_buf(),
+                    managed: managed.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
 
-        if entry.installed == installed {
-            return Err(ModError::MissingMod(name));
+    /// Which variant of the archive at `path` to install: the one in `options`, or else the one
+    /// `old` was installed with while the archive still has it.
+    fn choose_variant(
+        &self,
+        path: &Path,
+        files: &[String],
+        old: Option<&ModEntry>,
+        options: &AddOptions,
+    ) -> Result<Option<String>, ModError> {
+        let available = variant::variants(files, &self.root_dirs);
+        let archive = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+
+        if let Some(wanted) = &options.variant {
+            let wanted = wanted.trim_end_matches(['/', '\\']);
+            return match available.iter().find(|v| v.eq_ignore_ascii_case(wanted)) {
+                Some(found) => Ok(Some(found.clone())),
+                None => Err(ModError::MissingVariant {
+                    archive,
+                    variant: wanted.to_string(),
+                    available,
+                }),
+            };
+        }
+        if available.is_empty() {
+            return Ok(None);
+        }
+        if let Some(stored) = old.and_then(|entry| entry.variant.as_ref())
+            && available.contains(stored)
+        {
+            return Ok(Some(stored.clone()));
         }
+        Err(ModError::VariantRequired { archive, available })
+    }
 
-        let old_root = match move_where {
-            Move::Enable => self.root.join("Disabled Mods"),
-            Move::Disable => self.root.clone(),
-        };
+    fn install_archive(
+        &self,
+        path: &Path,
+        name: String,
+        version: ModVersion,
+        options: &AddOptions,
+    ) -> Result<Operation, ModError> {
+        info!(%name, %version, archive = %path.display(), "adding mod");
 
-        let new_root = match move_where {
-            Move::Enable => self.root.clone(),
-            Move::Disable => self.root.join("Disabled Mods"),
-        };
+        let mut toml = self.load_toml()?;
+        let name = toml.canonical_name(name);
+        if toml.get(&name).is_some_and(|entry| entry.pinned) && !options.force {
+            return Err(ModError::Pinned(name));
+        }
+        if let Some(old) = toml.get(&name).map(|entry| &entry.version)
+            && old.is_downgrade_to(&version)
+            && !options.allow_downgrade
+        {
+            return Err(ModError::Downgrade {
+                name,
+                old: old.clone(),
+                new: version,
+            });
+        }
 
-        for file in &entry.files {
-            let from = old_root.join(file);
-            if !from.exists() {
-                return Err(ModError::MissingFile {
-                    mod_name: name,
-                    path: file.to_owned(),
-                });
-            }
+        if path.is_dir() {
+            self.check_source_dir(path)?;
+        }
+        mod_file_formats::check_format(path)?;
+        self.check_password(path)?;
+        if !options.trust {
+            inspection::check_archive(path, &self.root_dirs)?;
+        }
 
-            let to = new_root.join(file);
+        let archive_files = read_files_cached(path);
+        let chosen = self.choose_variant(path, &archive_files, toml.get(&name), options)?;
+        let variants = variant::variants(&archive_files, &self.root_dirs);
+        let placed = archive_files
+            .iter()
+            .filter_map(|file| variant::place(file, chosen.as_deref(), &variants))
+            .collect::<Vec<_>>();
+        let sanitized = mod_file_formats::sanitize_unique(&placed);
+        let names = placed
+            .iter()
+            .cloned()
+            .zip(sanitized.iter().cloned())
+            .collect::<BTreeMap<_, _>>();
+        let place = |file: &str| {
+            variant::place(file, chosen.as_deref(), &variants)
+                .and_then(|file| names.get(&file).cloned())
+        };
+        let renamed = placed
+            .iter()
+            .zip(&sanitized)
+            .filter(|(file, sanitized)| file != sanitized)
+            .map(|(file, sanitized)| (PathBuf::from(file), PathBuf::from(sanitized)))
+            .collect::<Vec<_>>();
+        let mut seen = BTreeSet::new();
+        let archive_files = sanitized
+            .into_iter()
+            .filter(|file| seen.insert(file.clone()))
+            .collect::<Vec<_>>();
+        // Folded onto the casing already in the game directory, so `Archive/` and `archive/` don't
+        // end up as two trees on case sensitive filesystems, or as two owners of one file on case
+        // insensitive ones.
+        let case_map = CaseMap::new(&self.root, &toml, &archive_files)
+            .case_insensitive(platform::is_case_insensitive(&self.root));
+        let files = archive_files
+            .iter()
+            .map(|file| case_map.fold(file))
+            .collect::<Vec<_>>();
 
-            if let Some(parent) = to.parent() {
-                fs::create_dir_all(parent)?;
-            }
+        let folded = archive_files
+            .iter()
+            .zip(&files)
+            .filter(|(file, folded)| file != folded)
+            .map(|(file, folded)| (PathBuf::from(file), PathBuf::from(folded)))
+            .collect::<Vec<_>>();
 
-            fs::rename(&from, &to)?;
+        // Config files every mod may edit are kept as fragments and merged, never owned.
+        let (merged, files): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .partition(|file| merge::is_mergeable(file, &self.merge_files));
 
-            if let Some(parent) = from.parent() {
-                Self::clean_upwards(parent, &old_root);
-            }
+        let crossed_paths = toml.crossover_paths(&name, files.clone());
+        if !crossed_paths.is_empty() && self.conflict_policy == ConflictPolicy::Error {
+            info!(%name, count = crossed_paths.len(), "files already owned by other mods");
+            return Err(ModError::DoubleOwnedFiles {
+                incoming: name,
+                root: self.root.clone(),
+                files: OwnedFile::from_crossover(&crossed_paths),
+            });
         }
 
-        entry.installed = installed;
-        entry.installed_at = if installed { Some(Utc::now()) } else { None };
+        let conflicting = toml.declared_conflicts(&name, &options.conflicts);
+        if !conflicting.is_empty() {
+            return Err(ModError::conflicting(name, &conflicting));
+        }
 
-        let mut mods = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.toml)?;
+        // Game files no mod owns, set aside so removing the mod can put them back.
+        let owned = (toml.mods.values())
+            .flat_map(|entry| entry.files.iter().chain(&entry.shadowed))
+            .collect::<BTreeSet<_>>();
+        let vanilla = files
+            .iter()
+            .filter(|file| {
+                !owned.contains(file)
+                    && self.fs.exists(&self.root.join(file))
+                    && !self.fs.exists(&self.vanilla.join(file))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
 
-        write!(&mut mods, "{}", toml::to_string_pretty(&toml)?)?;
+        let hooks = toml
+            .get(&name)
+            .map(|entry| entry.hooks.clone())
+            .unwrap_or_default();
+        self.run_hooks(Event::PreInstall, &name, &version, &hooks)?;
 
-        Ok(Operation::Move(!move_where))
-    }
+        for (owner, file) in &crossed_paths {
+            if let Some(entry) = toml.get_mut(owner) {
+                entry.files.retain(|owned| owned != file);
+                entry.hashes.remove(file);
+                entry.stamps.remove(file);
+                entry.shadowed.push(file.clone());
+            }
+        }
 
-    pub fn load_toml(&self) -> Result<ModRegistry, ModError> {
-        let toml_string = fs::read_to_string(&self.toml)?;
+        let previous = self.fs.read_to_string(&self.toml)?;
 
-        Ok(toml::from_str(&toml_string)?)
-    }
+        let links = self.deploy_mode.links();
+        let deploy_root = if links {
+            self.staging.join(&name)
+        } else {
+            self.root.clone()
+        };
+        // A linked update is extracted beside the installed version and only swapped in once it's
+        // whole, so a failed one leaves the installed version linked as it was.
+        let replaced = links.then(|| toml.get(&name)).flatten();
+        let stamp = Utc::now().timestamp();
+        let extract_root = if replaced.is_some() {
+            self.removed.join(format!("{name}-{stamp}.new"))
+        } else {
+            deploy_root.clone()
+        };
 
-    fn clean_upwards(mut path: &Path, stop: &Path) {
-        while path.starts_with(stop) && path != stop {
-            if let Some(name) = path.file_name() {
-                if VALID_ROOT_DIRS.contains(&name.to_str().unwrap()) {
-                    break;
+        let mut created = if replaced.is_some() {
+            vec![]
+        } else {
+            files
+                .iter()
+                .map(|f| deploy_root.join(f))
+                .filter(|p| !self.fs.exists(p))
+                .collect::<Vec<_>>()
+        };
+
+        for file in &merged {
+            let (target, fragment) = (self.root.join(file), self.fragment(&name, file));
+            let base = self.merge_base.join(file);
+            if !self.fs.exists(&target) {
+                created.push(target);
+            } else if !self.fs.exists(&base) && !toml.mods.values().any(|e| e.merged.contains(file))
+            {
+                // What the game shipped, merged under every mod's copy.
+                if let Some(parent) = base.parent() {
+                    self.fs.create_dir_all(parent)?;
                 }
+                self.fs.write(&base, &self.fs.read_to_string(&target)?)?;
             }
-
-            match fs::remove_dir(path) {
-                Ok(()) => {}
-                Err(_) => break,
+            if !self.fs.exists(&fragment) {
+                created.push(fragment);
             }
+        }
 
-            path = path.parent().unwrap();
+        let mut moves = vec![];
+        for file in &vanilla {
+            let (from, to) = (self.root.join(file), self.vanilla.join(file));
+            self.move_file(&from, &to, &self.root)?;
+            moves.push((from, to));
         }
+
+        // Don't leave a half extracted mod behind, or the game's own files set aside for it.
+        let roll_back = || -> Result<(), ModError> {
+            if replaced.is_some() && self.fs.exists(&extract_root) {
+                self.fs.remove_dir_all(&extract_root)?;
+            }
+            for file in &created {
+                if self.fs.remove_file(file).is_ok()
+                    && let Some(parent) = file.parent()
+                {
+                    self.clean_upwards(parent, &deploy_root);
+                }
+            }
+            for (from, to) in moves.iter().rev() {
+                self.move_file(to, from, &self.vanilla)?;
+            }
+            Ok(())
+        };
+
+        if let Err(err) = self.fs.extract(path, &extract_root, &|file| {
+            place(file).map(|file| case_map.fold(&file))
+        }) {
+            roll_back()?;
+
+            let message = err.to_string();
+            let (entry, reason) = match err {
+                ExtractError::Zip(err) => return Err(ModError::from_zip(path, err)),
+                ExtractError::Checksum { entry } => {
+                    return Err(ModError::Checksum {
+                        archive: archive_name(path),
+                        entry,
+                    });
+                }
+                ExtractError::Truncated { entry, reason, .. } => (entry, reason),
+            };
+
+            info!(%name, %message, "extraction stopped short");
+            return Err(ModError::ExtractionIncomplete {
+                missing: vec![extract_root.join(&entry)],
+                truncated: Some(match reason {
+                    Some(reason) => format!("{message}: {reason}"),
+                    None => format!("{message}, the archive may be corrupt"),
+                }),
+            });
+        }
+
+        let extracted_files = files
+            .iter()
+            .map(|f| extract_root.join(f))
+            .collect::<Vec<_>>();
+
+        let missing: Vec<_> = extracted_files
+            .iter()
+            .filter(|p| !self.fs.exists(p))
+            .collect();
+
+        if !missing.is_empty() && !self.fs.is_dry_run() {
+            info!(%name, count = missing.len(), "files missing after extraction");
+            let missing = missing.into_iter().cloned().collect();
+            roll_back()?;
+            return Err(ModError::ExtractionIncomplete {
+                missing,
+                truncated: None,
+            });
+        }
+
+        for file in &merged {
+            self.move_file(
+                &extract_root.join(file),
+                &self.fragment(&name, file),
+                &extract_root,
+            )?;
+        }
+
+        // The old version's links and staging directory are recorded, so `vapor undo` puts them
+        // back and parks the new version where it was extracted.
+        let mut unlinked = vec![];
+        if let Some(old) = replaced {
+            for file in old.deployed_files().filter(|_| old.installed) {
+                let link = self.root.join(file);
+                if self.fs.exists(&link) {
+                    self.unlink(&link)?;
+                    unlinked.push((deploy_root.join(file), link));
+                }
+            }
+            if self.fs.exists(&deploy_root) {
+                let kept = self.removed.join(format!("{name}-{stamp}"));
+                self.move_file(&deploy_root, &kept, &self.staging)?;
+                let mut old_moves = vec![(deploy_root.clone(), kept.clone())];
+                self.trash_kept(&name, &kept, &mut old_moves);
+                moves.extend(old_moves);
+            }
+            self.move_file(&extract_root, &deploy_root, &self.removed)?;
+            moves.push((extract_root.clone(), deploy_root.clone()));
+        }
+
+        // Files only the replaced version had, kept in `removed` so they don't linger and
+        // `vapor undo` brings them back. Linked mods had their whole staging directory replaced.
+        if !links && let Some(old) = toml.get(&name) {
+            let kept = self.removed.join(format!("{name}-{stamp}"));
+            let gone = (old.files.iter())
+                .filter(|file| !files.contains(file) && !merged.contains(file))
+                .cloned()
+                .collect::<Vec<_>>();
+            for file in &gone {
+                let home = if old.is_excluded(file) {
+                    self.excluded.join(&name)
+                } else {
+                    self.root.clone()
+                };
+                let from = home.join(file);
+                if self.fs.exists(&from) {
+                    let to = kept.join(file);
+                    self.move_file(&from, &to, &home)?;
+                    moves.push((from, to));
+                }
+            }
+            moves.extend(self.restore_vanilla(&gone)?);
+        }
+
+        // Spelled like the mods they point at, so the registry reads the same under any folding.
+        let respell = |names: &[ModName]| {
+            names
+                .iter()
+                .map(|name| ModName::from(toml.canonical_name(name.to_string())))
+                .collect::<Vec<_>>()
+        };
+        let dependencies = respell(&options.dependencies);
+        let optional_dependencies = respell(&options.optional_dependencies);
+        let conflicts = respell(&options.conflicts);
+
+        // Start from the old entry so metadata set by other commands survives updates.
+        let old = toml.get(&name).cloned();
+        let old_version = old.as_ref().map(|entry| entry.version.clone());
+        let mut entry = old.clone().unwrap_or_default();
+        let old_merged = std::mem::take(&mut entry.merged);
+        entry.version.clone_from(&version);
+        entry.file = path.to_string_lossy().to_string();
+        entry.installed = true;
+        entry.installed_at = Some(Utc::now());
+        entry.variant = chosen;
+        entry.record_install(old.as_ref());
+        entry.dependencies = if options.dependencies.is_empty() {
+            None
+        } else {
+            Some(dependencies)
+        };
+        entry.game_versions = if options.game_versions.is_empty() {
+            None
+        } else {
+            Some(options.game_versions.clone())
+        };
+        entry.optional_dependencies = optional_dependencies;
+        entry.provides.clone_from(&options.provides);
+        entry.conflicts = conflicts;
+        // What the archive says about itself, keeping what was known if it says nothing.
+        let metadata = metadata::detect(path);
+        entry.author = options.author.clone().or(metadata.author).or(entry.author);
+        entry.description = (options.description.clone())
+            .or(metadata.description)
+            .or(entry.description);
+        entry.url = options.url.clone().or(metadata.url).or(entry.url);
+        entry.files.clone_from(&files);
+        entry.merged.clone_from(&merged);
+        entry.shadowed.retain(|file| !files.contains(file));
+        entry.hashes = if self.fs.is_dry_run() {
+            BTreeMap::new()
+        } else {
+            hash::hash_files(&deploy_root, &entry.files)
+        };
+        entry.edited.clear();
+        if self.dedupe {
+            let store = self.store();
+            // Configs get written in place by the game and by users, which would change every
+            // mod sharing them.
+            for (file, hash) in &entry.hashes {
+                if mod_file_formats::is_config(file) {
+                    continue;
+                }
+                store.insert(&deploy_root.join(file), hash)?;
+            }
+        }
+        // Taken after deduping, since linking to the store changes the modification time.
+        entry.stamps = FileStamp::of_files(&deploy_root, &entry.files);
+
+        let mut linked = vec![];
+        for file in &files {
+            if entry.is_excluded(file) {
+                if !links {
+                    let (from, to) = (self.root.join(file), self.excluded.join(&name).join(file));
+                    self.move_file(&from, &to, &self.root)?;
+                    moves.push((from, to));
+                }
+            } else if links {
+                let (source, link) = (deploy_root.join(file), self.root.join(file));
+                // Links of the old version come back on undo, rather than being deleted.
+                if !self.fs.exists(&link) && !unlinked.iter().any(|(_, old)| *old == link) {
+                    created.push(link.clone());
+                }
+                self.link(&source, &link)?;
+                linked.push((source, link));
+            }
+        }
+
+        toml.mods.insert(name.clone().into(), entry);
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation: format!("add `{name}` {version}"),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created,
+            linked,
+            unlinked,
+        })?;
+        self.remerge(&toml, old_merged.iter().chain(&merged))?;
+
+        if !crossed_paths.is_empty() {
+            info!(%name, count = crossed_paths.len(), "took over files from other mods");
+            self.warnings.borrow_mut().push(ModWarning::Overwrote {
+                mod_name: name.clone(),
+                files: OwnedFile::from_crossover(&crossed_paths),
+            });
+        }
+
+        let skipped = mod_file_formats::skipped_files(path);
+        if !skipped.is_empty() {
+            info!(%name, count = skipped.len(), "skipped entries");
+            self.warnings.borrow_mut().push(ModWarning::Skipped {
+                mod_name: name.clone(),
+                entries: skipped,
+            });
+        }
+
+        if !renamed.is_empty() {
+            info!(%name, count = renamed.len(), "sanitized paths");
+            self.warnings.borrow_mut().push(ModWarning::Sanitized {
+                mod_name: name.clone(),
+                paths: renamed,
+            });
+        }
+
+        if !folded.is_empty() {
+            info!(%name, count = folded.len(), "folded case-variant paths");
+            self.warnings.borrow_mut().push(ModWarning::CaseFolded {
+                mod_name: name.clone(),
+                paths: folded,
+            });
+        }
+
+        self.clear_stale_cache(&files)?;
+        self.run_hooks(Event::PostInstall, &name, &version, &hooks)?;
+
+        if let Some(old_version) = old_version
+            && old_version != version
+        {
+            if old_version.is_downgrade_to(&version) {
+                return Ok(Operation::Downgraded {
+                    old: old_version,
+                    new: version,
+                });
+            }
+            return Ok(Operation::Updated {
+                old: old_version,
+                new: version,
+            });
+        }
+
+        Ok(Operation::Added(version))
+    }
+
+    /// The recorded install of `name` to go back to, and the archive it came from, found where
+    /// it was installed from or in `downloads`. Without a `version`, the newest version older
+    /// than the installed one.
+    pub fn downgrade_source(
+        &self,
+        name: &str,
+        version: Option<&str>,
+        downloads: &Path,
+    ) -> Result<(InstallRecord, PathBuf), ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name.to_string());
+        let Some(entry) = toml.get(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+
+        // Newest first, the installed version left out.
+        let mut others = entry
+            .history
+            .iter()
+            .rev()
+            .filter(|record| record.version.cmp_version(&entry.version).is_ne())
+            .collect::<Vec<_>>();
+        others.dedup_by(|a, b| a.version == b.version);
+
+        let record = match version {
+            Some(version) => {
+                let wanted = ModVersion::from(version);
+                others
+                    .iter()
+                    .find(|record| record.version == wanted)
+                    .or_else(|| {
+                        others
+                            .iter()
+                            .find(|record| record.version.cmp_version(&wanted).is_eq())
+                    })
+                    .ok_or_else(|| ModError::UnrecordedVersion {
+                        name: name.clone(),
+                        version: version.to_string(),
+                        available: others.iter().map(|record| record.version.clone()).collect(),
+                    })?
+            }
+            None => others
+                .iter()
+                .find(|record| entry.version.is_downgrade_to(&record.version))
+                .ok_or_else(|| ModError::NoOlderVersion { name: name.clone() })?,
+        };
+
+        let source = Path::new(&record.source);
+        let cached = source.file_name().map(|file| downloads.join(file));
+        let archive = [Some(source.to_path_buf()), cached]
+            .into_iter()
+            .flatten()
+            .find(|path| !record.source.is_empty() && path.exists())
+            .ok_or_else(|| ModError::ArchiveGone {
+                name: name.clone(),
+                version: record.version.clone(),
+                archive: archive_name(source),
+            })?;
+        Ok(((*record).clone(), archive))
+    }
+
+    /// Install the version in `record` of `name` again from `archive`, with the variant it had,
+    /// see [`ModHandler::downgrade_source`].
+    pub fn downgrade_mod(
+        &self,
+        name: &str,
+        archive: &Path,
+        record: &InstallRecord,
+        force: bool,
+    ) -> Result<OperationReport, ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name.to_string());
+        let Some(current) = toml.get(&name).cloned() else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+        if !current.installed {
+            return Err(ModError::Disabled(name));
+        }
+        let version = record.version.clone();
+        info!(%name, from = %current.version, to = %version, "downgrading mod");
+
+        let options = AddOptions {
+            dependencies: current.dependencies.clone().unwrap_or_default(),
+            optional_dependencies: current.optional_dependencies.clone(),
+            game_versions: current.game_versions.clone().unwrap_or_default(),
+            provides: current.provides.clone(),
+            conflicts: current.conflicts.clone(),
+            force,
+            // Histories from before variants were recorded only have the installed one.
+            variant: record.variant.clone().or(current.variant.clone()),
+            allow_downgrade: true,
+            // It was let in when it was first installed.
+            trust: true,
+            ..Default::default()
+        };
+        self.report(name.clone(), || {
+            self.install_archive(archive, name.clone(), version, &options)
+        })
+    }
+
+    /// Remove `name` from the registry, moving its files into [`ModHandler::removed`] so the
+    /// removal can be undone.
+    pub fn remove_mod<S: Into<String>>(&self, name: S) -> Result<OperationReport, ModError> {
+        let name = name.into();
+        self.report(name.clone(), || self.remove_files(name))
+    }
+
+    fn remove_files(&self, name: String) -> Result<Operation, ModError> {
+        info!(%name, "removing mod");
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+        let name = toml.canonical_name(name);
+
+        let Some(entry) = toml.remove(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+        for collection in toml.collections.values_mut() {
+            collection.mods.retain(|member| *member != name);
+        }
+
+        let kept = self
+            .removed
+            .join(format!("{name}-{}", Utc::now().timestamp()));
+        let mut moves = vec![];
+        let mut unlinked = vec![];
+
+        // Where each file is, including excluded ones.
+        let sources = if self.deploy_mode.links() {
+            let staged = self.staging.join(&name);
+            if entry.installed {
+                for file in entry.deployed_files() {
+                    let link = self.root.join(file);
+                    self.unlink(&link)?;
+                    unlinked.push((staged.join(file), link));
+                }
+            }
+            entry
+                .files
+                .iter()
+                .map(|file| (staged.clone(), file))
+                .collect::<Vec<_>>()
+        } else {
+            let (home, _) = self.file_roots(&name, entry.installed);
+            let excluded = self.excluded.join(&name);
+            entry
+                .files
+                .iter()
+                .map(|file| {
+                    if entry.is_excluded(file) {
+                        (excluded.clone(), file)
+                    } else {
+                        (home.clone(), file)
+                    }
+                })
+                .collect()
+        };
+
+        for (home, file) in sources {
+            let from = home.join(file);
+            if self.fs.exists(&from) {
+                let to = kept.join(file);
+                self.move_file(&from, &to, &home)?;
+                moves.push((from, to));
+            }
+        }
+        let fragments = self.fragments.join(&name);
+        if self.fs.exists(&fragments) {
+            let to = kept.join(FRAGMENTS_DIR);
+            self.move_file(&fragments, &to, &self.fragments)?;
+            moves.push((fragments, to));
+        }
+        let restored = self.restore_vanilla(&entry.files)?;
+
+        self.trash_kept(&name, &kept, &mut moves);
+
+        moves.extend(restored);
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation: format!("remove `{name}`"),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created: vec![],
+            linked: vec![],
+            unlinked,
+        })?;
+        self.remerge(&toml, &entry.merged)?;
+        self.clear_stale_cache(&entry.files)?;
+
+        Ok(Operation::Removed(entry.version))
+    }
+
+    /// With [`Fs::trash`], move `kept`, where files of `name` were moved into by `moves`, to the
+    /// trash as one directory, pointing `moves` at where they went so it's still undoable until
+    /// the trash is emptied.
+    ///
+    /// Everything has moved by then, so a trash that fails only warns and leaves the files in
+    /// `kept`.
+    fn trash_kept(&self, name: &str, kept: &Path, moves: &mut [(PathBuf, PathBuf)]) {
+        if !self.fs.trash || !self.fs.exists(kept) {
+            return;
+        }
+        match self.fs.discard(kept) {
+            Ok(Some(trashed)) => {
+                for (_, to) in moves {
+                    if let Ok(file) = to.strip_prefix(kept) {
+                        *to = trashed.join(file);
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(%name, %err, "couldn't move removed files to the trash");
+                self.warnings.borrow_mut().push(ModWarning::TrashFailed {
+                    mod_name: name.to_string(),
+                    kept: kept.to_path_buf(),
+                    reason: err.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Put back the game files set aside when a mod installed `files` over them, where nothing
+    /// else took their place since. Returns the moves made.
+    fn restore_vanilla(&self, files: &[String]) -> Result<Vec<(PathBuf, PathBuf)>, ModError> {
+        let mut moves = vec![];
+        for file in files {
+            let (from, to) = (self.vanilla.join(file), self.root.join(file));
+            if self.fs.exists(&from) && !self.fs.exists(&to) {
+                self.move_file(&from, &to, &self.vanilla)?;
+                moves.push((from, to));
+            }
+        }
+        Ok(moves)
+    }
+
+    /// Remove every mod with [`ModHandler::remove_mod`], returning a report for each.
+    ///
+    /// Each removal is its own operation, so `vapor undo` brings mods back one at a time.
+    pub fn purge(&self) -> Result<Vec<OperationReport>, ModError> {
+        self.load_toml()?
+            .mods
+            .into_keys()
+            .map(|name| self.remove_mod(name.to_string()))
+            .collect()
+    }
+
+    /// [`ModHandler::purge`], then delete everything Vapor keeps for this game and empty the
+    /// registry and its history in the journal. Other games and instances are left alone.
+    ///
+    /// Unlike a purge, this can't be undone. Returns the deleted directories and files.
+    pub fn reset(&self) -> Result<Vec<PathBuf>, ModError> {
+        let toml = self.load_toml()?;
+        self.purge()?;
+        info!(root = %self.root.display(), "resetting");
+
+        let mut deleted = vec![];
+        for dir in [
+            &self.staging,
+            &self.excluded,
+            &self.removed,
+            &self.objects,
+            &self.fragments,
+            &self.merge_base,
+            &self.vanilla,
+        ] {
+            if self.fs.exists(dir) {
+                self.fs.remove_dir_all(dir)?;
+                deleted.push(dir.clone());
+            }
+        }
+        for file in [&self.frozen, &self.moving] {
+            if self.fs.exists(file) {
+                self.fs.remove_file(file)?;
+                deleted.push(file.clone());
+            }
+        }
+        if let Some(journal) = &self.journal
+            && journal.path.exists()
+        {
+            self.fs
+                .write(&journal.path, &journal.without(&self.toml)?)?;
+        }
+
+        self.save_toml(&ModRegistry {
+            schema_version: toml.schema_version,
+            mods: BTreeMap::new(),
+            collections: BTreeMap::new(),
+            extra: toml::Table::new(),
+        })?;
+        Ok(deleted)
     }
 
-    fn root_dir_common_filter(path: &Path) -> bool {
-        if let Some(first) = path.components().next()
-            && let Component::Normal(name) = first
+    /// Disable every enabled mod that hooks into the game's code, along with mods depending on
+    /// them, so a game patch can't break on them. Returns the disabled mods.
+    ///
+    /// Pinned mods are left alone, and so are the mods they depend on, so none of them loses a
+    /// dependency.
+    ///
+    /// What was disabled is recorded in [`ModHandler::frozen`] for [`ModHandler::thaw`].
+    pub fn freeze(&self) -> Result<Vec<String>, ModError> {
+        if let Some(freeze) = self.frozen()? {
+            return Err(ModError::AlreadyFrozen(freeze.created));
+        }
+
+        let registry = self.load_toml()?;
+        let mut disabled = vec![];
+        let pinned = |name: &str| registry.get(name).is_some_and(|entry| entry.pinned);
+        for (name, _) in registry
+            .mods
+            .iter()
+            .filter(|(name, entry)| entry.installed && entry.affects_scripts() && !pinned(name))
         {
-            return VALID_ROOT_DIRS
+            let planned = self.plan(&Request::Disable(name.to_string()))?.disable;
+            if let Some(dependent) = planned.iter().find(|planned| pinned(planned)) {
+                info!(%name, %dependent, "not freezing a dependency of a pinned mod");
+                continue;
+            }
+            for name in planned {
+                if !disabled.contains(&name) {
+                    disabled.push(name);
+                }
+            }
+        }
+
+        info!(mods = disabled.len(), "freezing");
+        for name in &disabled {
+            self.move_mod(name, Move::Disable)?;
+        }
+
+        let freeze = Freeze {
+            created: Utc::now(),
+            disabled: disabled.clone(),
+            registry,
+        };
+        if let Some(parent) = self.frozen.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+        self.fs
+            .write(&self.frozen, &toml::to_string_pretty(&freeze)?)?;
+
+        Ok(disabled)
+    }
+
+    /// The last [`ModHandler::freeze`], if it hasn't been thawed.
+    pub fn frozen(&self) -> Result<Option<Freeze>, ModError> {
+        match self.fs.read_to_string(&self.frozen) {
+            Ok(contents) => Ok(Some(toml::from_str(&contents)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Enable the mods the last [`ModHandler::freeze`] disabled again, returning them.
+    ///
+    /// Mods removed or enabled since are skipped.
+    pub fn thaw(&self) -> Result<Vec<String>, ModError> {
+        let Some(freeze) = self.frozen()? else {
+            return Err(ModError::NotFrozen);
+        };
+
+        let registry = self.load_toml()?;
+        let mut enabled = vec![];
+        // Dependents were disabled first, so enable in reverse to bring dependencies back first.
+        for name in freeze.disabled.iter().rev() {
+            if registry.get(name).is_some_and(|entry| !entry.installed) {
+                self.move_mod(name, Move::Enable)?;
+                enabled.push(name.clone());
+            }
+        }
+
+        info!(mods = enabled.len(), "thawing");
+        self.fs.remove_file(&self.frozen)?;
+        Ok(enabled)
+    }
+
+    /// Zip up the files of `name` as they are now, edits and excluded files included, into `out`
+    /// with the layout they have in the game directory. Returns the files packed.
+    ///
+    /// Nothing is written in a dry run.
+    pub fn pack(&self, name: &str, out: &Path) -> Result<Vec<String>, ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name.to_string());
+        let Some(entry) = toml.get(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+
+        let (home, _) = self.file_roots(&name, entry.installed);
+        let excluded = if self.deploy_mode.links() {
+            self.staging.join(&name)
+        } else {
+            self.excluded.join(&name)
+        };
+        let sources = entry
+            .files
+            .iter()
+            .map(|file| {
+                let root = if entry.is_excluded(file) {
+                    &excluded
+                } else {
+                    &home
+                };
+                (file, root.join(file))
+            })
+            .chain((entry.merged.iter()).map(|file| (file, self.fragment(&name, file))))
+            .collect::<Vec<_>>();
+
+        let missing = sources
+            .iter()
+            .filter(|(_, source)| !self.fs.is_file(source))
+            .map(|(file, _)| file.to_string())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(ModError::MissingFiles {
+                mod_name: name,
+                files: missing,
+            });
+        }
+
+        if !self.fs.is_dry_run() {
+            info!(%name, out = %out.display(), files = sources.len(), "packing mod");
+            // Written beside it first, so a failed pack doesn't leave a broken archive behind.
+            let partial = out.with_extension("zip.part");
+            let mut zip = ZipWriter::new(self.fs.create(&partial)?);
+            let options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            for (file, source) in &sources {
+                let large = self.fs.metadata(source)?.len >= u64::from(u32::MAX);
+                zip.start_file(file.as_str(), options.large_file(large))?;
+                std::io::copy(&mut self.fs.open(source)?, &mut zip)?;
+            }
+            zip.finish()?.flush()?;
+            self.fs.rename(&partial, out)?;
+        }
+
+        Ok(sources.into_iter().map(|(file, _)| file.clone()).collect())
+    }
+
+    /// Where `name`'s fragment of the mergeable `file` is kept.
+    fn fragment(&self, name: &str, file: &str) -> PathBuf {
+        self.fragments.join(name).join(file)
+    }
+
+    /// Rebuild each of `files` in the game directory from the fragments of enabled mods, oldest
+    /// first, on top of what the game shipped. A file no mod has a fragment of anymore goes back
+    /// to how the game shipped it, or is deleted if it didn't.
+    fn remerge<'a>(
+        &self,
+        toml: &ModRegistry,
+        files: impl IntoIterator<Item = &'a String>,
+    ) -> Result<(), ModError> {
+        for file in files.into_iter().collect::<BTreeSet<_>>() {
+            let mut owners = (toml.mods.iter())
+                .filter(|(_, entry)| entry.installed && entry.merged.contains(file))
+                .collect::<Vec<_>>();
+            owners.sort_by_key(|(_, entry)| entry.installed_at);
+
+            let base = self.fs.read_to_string(self.merge_base.join(file)).ok();
+            let mut fragments = base.iter().map(String::as_str).collect::<Vec<_>>();
+            let texts = owners
+                .iter()
+                .filter_map(|(name, _)| {
+                    Some((
+                        name,
+                        self.fs
+                            .read_to_string(self.fragment(name.as_ref(), file))
+                            .ok()?,
+                    ))
+                })
+                .collect::<Vec<_>>();
+            for (name, text) in &texts {
+                match merge::merge(file, &[text]) {
+                    Ok(_) => fragments.push(text),
+                    Err(err) => {
+                        info!(%name, %file, %err, "leaving fragment out of merge");
+                        self.warnings.borrow_mut().push(ModWarning::MergeSkipped {
+                            mod_name: name.to_string(),
+                            file: file.clone(),
+                            reason: err.to_string(),
+                        });
+                    }
+                }
+            }
+
+            let target = self.root.join(file);
+            if fragments.is_empty() {
+                if self.fs.exists(&target) {
+                    self.fs.remove_file(&target)?;
+                    if let Some(parent) = target.parent() {
+                        self.clean_upwards(parent, &self.root);
+                    }
+                }
+                continue;
+            }
+
+            let merged = merge::merge(file, &fragments)?;
+            if self
+                .fs
+                .read_to_string(&target)
+                .is_ok_and(|current| current == merged)
+            {
+                continue;
+            }
+            info!(%file, fragments = fragments.len(), "merging");
+            if let Some(parent) = target.parent() {
+                self.fs.create_dir_all(parent)?;
+            }
+            // A link would write the merge through into a staged file.
+            if self.fs.is_symlink(&target) {
+                self.fs.remove_file(&target)?;
+            }
+            self.fs.write(&target, &merged)?;
+        }
+        Ok(())
+    }
+
+    /// The content-addressed store in [`ModHandler::objects`].
+    pub fn store(&self) -> Store<'_> {
+        Store {
+            dir: &self.objects,
+            fs: &self.fs,
+        }
+    }
+
+    /// Delete objects in [`ModHandler::store`] no mod in the registry has a file of, returning
+    /// them with their sizes.
+    ///
+    /// Installed files are hardlinks, so this never loses a file, even one of a mod that was
+    /// removed but can still be brought back with `vapor undo`.
+    pub fn gc(&self) -> Result<Vec<(PathBuf, u64)>, ModError> {
+        let toml = self.load_toml()?;
+        let referenced = toml
+            .mods
+            .values()
+            .flat_map(|entry| entry.hashes.values())
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>();
+
+        Ok(self.store().gc(&referenced)?)
+    }
+
+    /// Plan `request` against the registry, see [`resolver::plan`].
+    pub fn plan(&self, request: &Request) -> Result<Plan, ModError> {
+        let toml = self.load_toml()?;
+
+        resolver::plan(&toml, request).map_err(|err| match err {
+            ResolveError::MissingMod(name) => ModError::missing_mod(&toml, name),
+            err => ModError::Resolve(err),
+        })
+    }
+
+    /// Dependents in `planned`, a plan's [`Plan::disable`], to disable along with `names` as
+    /// `impact` says.
+    ///
+    /// Fails with [`ModError::HasDependents`] if `impact` doesn't say what to do with them, and
+    /// with [`ModError::PinnedDependent`] rather than cascade to a pinned one.
+    pub fn dependents_to_disable<S: AsRef<str>>(
+        &self,
+        names: &[S],
+        planned: Vec<String>,
+        impact: Impact,
+    ) -> Result<Vec<String>, ModError> {
+        let dependents = planned
+            .into_iter()
+            .filter(|planned| !names.iter().any(|name| name.as_ref() == planned))
+            .collect::<Vec<_>>();
+        let name = || {
+            names
                 .iter()
-                .any(|&valid| OsStr::new(valid) == name);
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+                .join("`, `")
+        };
+
+        match impact {
+            _ if dependents.is_empty() => Ok(dependents),
+            Impact::Cascade => {
+                let toml = self.load_toml()?;
+                match dependents
+                    .iter()
+                    .find(|dependent| toml.get(dependent).is_some_and(|entry| entry.pinned))
+                {
+                    Some(pinned) => Err(ModError::PinnedDependent {
+                        name: name(),
+                        dependent: pinned.clone(),
+                    }),
+                    None => Ok(dependents),
+                }
+            }
+            Impact::Force => Ok(vec![]),
+            Impact::Refuse => {
+                let dependents = dependents.iter().map(String::as_str).collect::<Vec<_>>();
+                Err(ModError::has_dependents(name(), &dependents))
+            }
+        }
+    }
+
+    pub fn move_mod<S: Into<String>>(
+        &self,
+        name: S,
+        move_where: Move,
+    ) -> Result<OperationReport, ModError> {
+        let name = name.into();
+        self.report(name.clone(), || self.move_files(name, move_where))
+    }
+
+    fn move_files(&self, name: String, move_where: Move) -> Result<Operation, ModError> {
+        info!(%name, installed = move_where.installed(), "moving mod");
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+        let name = toml.canonical_name(name);
+
+        let Some(entry) = toml.get_mut(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+
+        let installed = move_where.installed();
+
+        if entry.installed == installed {
+            return Err(ModError::MissingMod {
+                name,
+                suggestion: None,
+            });
+        }
+
+        if installed {
+            let conflicting = toml.enabled_conflicts(&name);
+            if !conflicting.is_empty() {
+                return Err(ModError::conflicting(name, &conflicting));
+            }
+        }
+        let Some(entry) = toml.get_mut(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+        let hooks = entry.hooks.clone();
+        let version = entry.version.clone();
+        let files = entry.deployed_files().cloned().collect::<Vec<_>>();
+
+        if installed {
+            self.run_hooks(Event::PreEnable, &name, &version, &hooks)?;
+        }
+
+        let old_root = match move_where {
+            Move::Enable => self.disabled.clone(),
+            Move::Disable => self.root.clone(),
+        };
+
+        let new_root = match move_where {
+            Move::Enable => self.root.clone(),
+            Move::Disable => self.disabled.clone(),
+        };
+
+        // Check every file is there first, so a missing one doesn't leave the mod split.
+        for file in &files {
+            let source = if self.deploy_mode.links() {
+                self.staging.join(&name).join(file)
+            } else {
+                old_root.join(file)
+            };
+            if !self.fs.exists(&source) {
+                return Err(ModError::MissingFile {
+                    mod_name: name,
+                    path: file.to_owned(),
+                });
+            }
         }
 
-        false
+        self.start_move(&name, move_where)?;
+        let mut record = Record {
+            operation: format!(
+                "{} `{name}`",
+                match move_where {
+                    Move::Enable => "enable",
+                    Move::Disable => "disable",
+                }
+            ),
+            registry: self.toml.clone(),
+            previous,
+            moves: vec![],
+            created: vec![],
+            linked: vec![],
+            unlinked: vec![],
+        };
+
+        let moved = files.iter().try_for_each(|file| {
+            if self.deploy_mode.links() {
+                let source = self.staging.join(&name).join(file);
+                let link = self.root.join(file);
+                match move_where {
+                    Move::Enable => {
+                        self.link(&source, &link)?;
+                        record.linked.push((source, link));
+                    }
+                    Move::Disable => {
+                        self.unlink(&link)?;
+                        record.unlinked.push((source, link));
+                    }
+                }
+                return Ok(());
+            }
+
+            let (from, to) = (old_root.join(file), new_root.join(file));
+            self.move_file(&from, &to, &old_root)?;
+            record.moves.push((from, to));
+            Ok::<_, std::io::Error>(())
+        });
+        if let Err(err) = moved {
+            // Put back what did move, so the mod is whole where the registry says it is.
+            self.reverse(&record)?;
+            self.finish_move()?;
+            return Err(err.into());
+        }
+
+        entry.installed = installed;
+        entry.installed_at = if installed { Some(Utc::now()) } else { None };
+
+        let merged = entry.merged.clone();
+        self.save_toml(&toml)?;
+        self.record(record)?;
+        self.finish_move()?;
+        self.remerge(&toml, &merged)?;
+
+        self.clear_stale_cache(&files)?;
+        if installed {
+            self.run_hooks(Event::PostEnable, &name, &version, &hooks)?;
+        }
+
+        Ok(Operation::Move(!move_where))
+    }
+
+    /// Record that `name` is being moved, see [`ModHandler::fix_moves`].
+    fn start_move(&self, name: &str, move_where: Move) -> Result<(), ModError> {
+        if self.fs.is_dry_run() {
+            return Ok(());
+        }
+        if let Some(parent) = self.moving.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+        let pending = PendingMove {
+            name: name.to_string(),
+            enable: move_where.installed(),
+        };
+        self.fs
+            .write(&self.moving, &toml::to_string_pretty(&pending)?)?;
+        Ok(())
+    }
+
+    /// Clear the record of [`ModHandler::start_move`].
+    fn finish_move(&self) -> Result<(), ModError> {
+        if !self.fs.is_dry_run() && self.fs.exists(&self.moving) {
+            self.fs.remove_file(&self.moving)?;
+        }
+        Ok(())
+    }
+
+    /// Finish the move [`ModHandler::move_mod`] recorded as in progress, if Vapor died partway
+    /// through it.
+    ///
+    /// Only reads one file when there is nothing to do, so it's cheap enough to run on every load.
+    pub fn fix_pending_move(&self) -> Result<Option<FixedMove>, ModError> {
+        let pending: PendingMove = match self.fs.read_to_string(&self.moving) {
+            Ok(contents) => toml::from_str(&contents)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let fixed = if self.load_toml()?.contains(&pending.name) {
+            let action = if pending.enable {
+                Move::Enable
+            } else {
+                Move::Disable
+            };
+            Some(self.gather(&pending.name, action, true)?)
+        } else {
+            None
+        };
+        self.fs.remove_file(&self.moving)?;
+        Ok(fixed)
+    }
+
+    /// Put back together mods left split between the game and disabled mods by an interrupted
+    /// [`ModHandler::move_mod`].
+    ///
+    /// A move recorded as in progress is finished. Other split mods, like ones from before moves
+    /// were recorded, go wherever most of their files already are. Split links can only be found
+    /// from the record.
+    pub fn fix_moves(&self) -> Result<Vec<FixedMove>, ModError> {
+        let mut fixed = self.fix_pending_move()?.into_iter().collect::<Vec<_>>();
+
+        for (name, entry) in self.load_toml()?.mods {
+            let (home, Some(other)) = self.file_roots(&name, entry.installed) else {
+                continue;
+            };
+            let count = |root: &Path| {
+                entry
+                    .deployed_files()
+                    .filter(|file| self.fs.exists(&root.join(file)))
+                    .count()
+            };
+            let (at_home, stray) = (count(&home), count(&other));
+            if stray == 0 {
+                continue;
+            }
+
+            let completed = stray > at_home;
+            let installed = entry.installed != completed;
+            let action = if installed {
+                Move::Enable
+            } else {
+                Move::Disable
+            };
+            fixed.push(self.gather(&name, action, completed)?);
+        }
+
+        Ok(fixed)
+    }
+
+    /// Move every file of `name` that isn't where `action` puts it there, and mark it as moved.
+    fn gather(&self, name: &str, action: Move, completed: bool) -> Result<FixedMove, ModError> {
+        info!(%name, installed = action.installed(), "fixing split mod");
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+        let Some(entry) = toml.get_mut(name) else {
+            return Err(ModError::missing_mod(&toml, name.to_string()));
+        };
+
+        let (old_root, new_root) = match action {
+            Move::Enable => (&self.disabled, &self.root),
+            Move::Disable => (&self.root, &self.disabled),
+        };
+        let mut moves = vec![];
+        let mut linked = vec![];
+        let mut unlinked = vec![];
+        for file in entry.deployed_files() {
+            if self.deploy_mode.links() {
+                let source = self.staging.join(name).join(file);
+                let link = self.root.join(file);
+                let deployed = self.fs.exists(&link);
+                match action {
+                    Move::Enable if !deployed && self.fs.exists(&source) => {
+                        self.link(&source, &link)?;
+                        linked.push((source, link));
+                    }
+                    Move::Disable if deployed => {
+                        self.unlink(&link)?;
+                        unlinked.push((source, link));
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let (from, to) = (old_root.join(file), new_root.join(file));
+            if self.fs.exists(&from) && !self.fs.exists(&to) {
+                self.move_file(&from, &to, old_root)?;
+                moves.push((from, to));
+            }
+        }
+
+        entry.installed = action.installed();
+        entry.installed_at = match action {
+            Move::Enable => entry.installed_at.or(Some(Utc::now())),
+            Move::Disable => None,
+        };
+        self.save_toml(&toml)?;
+
+        let files = moves.len() + linked.len() + unlinked.len();
+        self.record(Record {
+            operation: format!(
+                "fix {} `{name}`",
+                match action {
+                    Move::Enable => "enabling",
+                    Move::Disable => "disabling",
+                }
+            ),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created: vec![],
+            linked,
+            unlinked,
+        })?;
+
+        Ok(FixedMove {
+            name: name.to_string(),
+            action,
+            completed,
+            files,
+        })
+    }
+
+    /// What [`ModHandler::move_mod`] would do to the files of `name`, without doing it.
+    ///
+    /// Unlike a move, a missing file is reported rather than stopping it.
+    pub fn preview_move(&self, name: &str, move_where: Move) -> Result<Preview, ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name.to_string());
+        let Some(entry) = toml.get(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+
+        let (old_root, new_root) = match move_where {
+            Move::Enable => (&self.disabled, &self.root),
+            Move::Disable => (&self.root, &self.disabled),
+        };
+
+        let mut preview = Preview {
+            name: name.clone(),
+            action: move_where,
+            moves: vec![],
+            clobbers: vec![],
+            missing: vec![],
+        };
+        for file in entry.deployed_files() {
+            let (from, to) = if self.deploy_mode.links() {
+                let source = self.staging.join(&name).join(file);
+                let link = self.root.join(file);
+                if move_where == Move::Enable
+                    && self.fs.exists(&link)
+                    && self.fs.read_link(&link).ok().as_ref() != Some(&source)
+                {
+                    preview.clobbers.push(link.clone());
+                }
+                (source, link)
+            } else {
+                let (from, to) = (old_root.join(file), new_root.join(file));
+                if self.fs.exists(&to) {
+                    preview.clobbers.push(to.clone());
+                }
+                (from, to)
+            };
+
+            if self.fs.exists(&from) {
+                preview.moves.push((from, to));
+            } else {
+                preview.missing.push(from);
+            }
+        }
+
+        Ok(preview)
+    }
+
+    /// Load the registry, migrating it to the current schema first.
+    pub fn load_toml(&self) -> Result<ModRegistry, ModError> {
+        let mut table = toml::from_str(&self.fs.read_to_string(&self.toml)?)?;
+        // Upgrades are kept, along with a backup, unless this is a dry run.
+        if migrate::upgrade(&mut table)? && !self.fs.is_dry_run() {
+            migrate::migrate(&self.toml)?;
+        }
+
+        Ok(table.try_into()?)
+    }
+
+    /// Read a registry other than this one, like a copy of `mods.toml` from elsewhere.
+    pub fn read_registry(path: &Path) -> Result<ModRegistry, ModError> {
+        Self::parse_registry(&fs::read_to_string(path)?)
+    }
+
+    /// The registry as it was right before the journaled operation `id`.
+    pub fn registry_before(&self, id: u64) -> Result<ModRegistry, ModError> {
+        let entry = self
+            .history()?
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .ok_or(ModError::MissingSnapshot(id))?;
+
+        Self::parse_registry(&entry.record.previous)
+    }
+
+    /// Parse a registry, upgrading it to the current schema in memory.
+    fn parse_registry(contents: &str) -> Result<ModRegistry, ModError> {
+        let mut table = toml::from_str(contents)?;
+        migrate::upgrade(&mut table)?;
+        Ok(table.try_into()?)
+    }
+
+    /// Rename the registry entry `old` to `new`, updating dependencies that point at it.
+    ///
+    /// The report lists the mods whose dependencies were rewritten.
+    pub fn rename_mod<S: Into<String>>(&self, old: S, new: S) -> Result<OperationReport, ModError> {
+        let (old, new) = (old.into(), new.into());
+        self.report(new.clone(), || self.rename_files(old, new))
+    }
+
+    fn rename_files(&self, old: String, new: String) -> Result<Operation, ModError> {
+        info!(%old, %new, "renaming mod");
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+        let old = toml.canonical_name(old);
+
+        // Respelling a mod, like `cet` to `CET`, is fine.
+        if toml.contains(&new) && !ModName::from(&old).matches(&new) {
+            return Err(ModError::AlreadyExists(new));
+        }
+
+        let Some(entry) = toml.remove(&old) else {
+            return Err(ModError::missing_mod(&toml, old));
+        };
+
+        let mut moves = vec![];
+        for dir in [&self.staging, &self.excluded, &self.fragments] {
+            let (from, to) = (dir.join(&old), dir.join(&new));
+            if self.fs.exists(&from) {
+                self.fs.rename(&from, &to)?;
+                moves.push((from, to));
+            }
+        }
+
+        // Links point into the old staging directory, so point them at the new one.
+        let mut unlinked = vec![];
+        if self.deploy_mode.links() && entry.installed {
+            for file in entry.deployed_files() {
+                let link = self.root.join(file);
+                self.link(&self.staging.join(&new).join(file), &link)?;
+                unlinked.push((self.staging.join(&old).join(file), link));
+            }
+        }
+
+        toml.mods.insert(new.clone().into(), entry);
+
+        let mut dependents = vec![];
+        for (name, entry) in &mut toml.mods {
+            for dep in entry.dependencies.iter_mut().flatten() {
+                if *dep == old {
+                    *dep = new.clone().into();
+                    dependents.push(name.to_string());
+                }
+            }
+        }
+        for member in toml
+            .collections
+            .values_mut()
+            .flat_map(|collection| &mut collection.mods)
+        {
+            if *member == old {
+                *member = new.clone().into();
+            }
+        }
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation: format!("rename `{old}` to `{new}`"),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created: vec![],
+            linked: vec![],
+            unlinked,
+        })?;
+
+        Ok(Operation::Renamed { old, dependents })
+    }
+
+    /// Tag `name` with `tag`, returning `false` if it already was.
+    pub fn add_tag<S: Into<String>>(&self, name: S, tag: &str) -> Result<bool, ModError> {
+        let name = name.into();
+        self.edit_entry(&name, format!("tag `{name}` with `{tag}`"), |entry| {
+            if entry.has_tag(tag) {
+                return false;
+            }
+            entry.tags.push(tag.to_string());
+            true
+        })
+    }
+
+    /// Remove `tag` from `name`, returning `false` if it wasn't tagged.
+    pub fn remove_tag<S: Into<String>>(&self, name: S, tag: &str) -> Result<bool, ModError> {
+        let name = name.into();
+        self.edit_entry(&name, format!("untag `{tag}` from `{name}`"), |entry| {
+            let before = entry.tags.len();
+            entry.tags.retain(|t| !t.eq_ignore_ascii_case(tag));
+            entry.tags.len() != before
+        })
+    }
+
+    /// Set the note on `name`, an empty note clears it.
+    pub fn set_note<S: Into<String>>(&self, name: S, note: &str) -> Result<(), ModError> {
+        let name = name.into();
+        self.edit_entry(&name, format!("note `{name}`"), |entry| {
+            entry.note = note.to_string();
+        })
+    }
+
+    /// Set the author, description and homepage of `name`, leaving those that are `None` as they
+    /// are and clearing those that are empty.
+    pub fn describe<S: Into<String>>(
+        &self,
+        name: S,
+        author: Option<&str>,
+        description: Option<&str>,
+        url: Option<&str>,
+    ) -> Result<(), ModError> {
+        let name = name.into();
+        let set = |field: &mut Option<String>, value: Option<&str>| {
+            if let Some(value) = value {
+                *field = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            }
+        };
+        self.edit_entry(&name, format!("describe `{name}`"), |entry| {
+            set(&mut entry.author, author);
+            set(&mut entry.description, description);
+            set(&mut entry.url, url);
+        })
+    }
+
+    /// Pin or unpin `name`, returning `false` if it already was.
+    ///
+    /// Pinned mods aren't updated, and aren't disabled along with their dependencies or by
+    /// [`ModHandler::freeze`], unless forced.
+    pub fn pin<S: Into<String>>(&self, name: S, pinned: bool) -> Result<bool, ModError> {
+        let name = name.into();
+        let operation = if pinned { "pin" } else { "unpin" };
+        self.edit_entry(&name, format!("{operation} `{name}`"), |entry| {
+            let changed = entry.pinned != pinned;
+            entry.pinned = pinned;
+            changed
+        })
+    }
+
+    /// Run `command` for `name` on `event`, returning `false` if it already does.
+    ///
+    /// The command still has to be allowed in `Vapor.toml` before it runs.
+    pub fn add_hook<S: Into<String>>(
+        &self,
+        name: S,
+        event: Event,
+        command: &str,
+    ) -> Result<bool, ModError> {
+        let name = name.into();
+        self.edit_entry(&name, format!("add `{event}` hook to `{name}`"), |entry| {
+            let commands = entry.hooks.get_mut(event);
+            if commands.iter().any(|c| c == command) {
+                return false;
+            }
+            commands.push(command.to_string());
+            true
+        })
+    }
+
+    /// Stop running `command` for `name` on `event`, returning `false` if it didn't.
+    pub fn remove_hook<S: Into<String>>(
+        &self,
+        name: S,
+        event: Event,
+        command: &str,
+    ) -> Result<bool, ModError> {
+        let name = name.into();
+        self.edit_entry(
+            &name,
+            format!("remove `{event}` hook from `{name}`"),
+            |entry| {
+                let commands = entry.hooks.get_mut(event);
+                let before = commands.len();
+                commands.retain(|c| c != command);
+                commands.len() != before
+            },
+        )
+    }
+
+    /// Group `mods` into a new collection `name`.
+    pub fn create_collection<S: Into<String>>(
+        &self,
+        name: S,
+        mods: &[String],
+    ) -> Result<(), ModError> {
+        let name = name.into();
+        self.edit_registry(format!("create collection `{name}`"), |toml| {
+            if toml.collections.contains_key(&name) {
+                return Err(ModError::CollectionExists(name.clone()));
+            }
+            if let Some(missing) = mods.iter().find(|member| !toml.contains(member)) {
+                return Err(ModError::missing_mod(toml, missing.clone()));
+            }
+
+            let mut members = vec![];
+            for member in mods {
+                let member = ModName::from(toml.canonical_name(member.clone()));
+                if !members.contains(&member) {
+                    members.push(member);
+                }
+            }
+            toml.collections
+                .insert(name.clone(), Collection { mods: members });
+            Ok(())
+        })
+    }
+
+    /// Delete the collection `name`, leaving its mods alone.
+    pub fn delete_collection<S: Into<String>>(&self, name: S) -> Result<(), ModError> {
+        let name = name.into();
+        self.edit_registry(format!("delete collection `{name}`"), |toml| {
+            toml.collections
+                .remove(&name)
+                .map(|_| ())
+                .ok_or_else(|| ModError::MissingCollection(name.clone()))
+        })
+    }
+
+    /// The collection `name`.
+    pub fn collection(&self, name: &str) -> Result<Collection, ModError> {
+        self.load_toml()?
+            .collections
+            .remove(name)
+            .ok_or_else(|| ModError::MissingCollection(name.to_string()))
+    }
+
+    /// Write the collection `name` to `path` for [`ModHandler::import_collection`].
+    pub fn export_collection(&self, name: &str, path: &Path) -> Result<(), ModError> {
+        let toml = self.load_toml()?;
+        let Some(collection) = toml.collections.get(name) else {
+            return Err(ModError::MissingCollection(name.to_string()));
+        };
+
+        CollectionFile::new(&toml, name, collection).write(path)
+    }
+
+    /// Create a collection from a file written by [`ModHandler::export_collection`], returning its
+    /// name.
+    ///
+    /// Members that aren't installed are left out, and they and version mismatches are reported as
+    /// a warning.
+    pub fn import_collection(&self, path: &Path) -> Result<String, ModError> {
+        let file = CollectionFile::read(path)?;
+        let toml = self.load_toml()?;
+
+        let differences = file.differences(&toml);
+        let members = file
+            .mods
+            .iter()
+            .filter(|member| toml.contains(&member.name))
+            .map(|member| member.name.clone())
+            .collect::<Vec<_>>();
+
+        self.create_collection(file.name.clone(), &members)?;
+
+        if !differences.is_empty() {
+            self.warnings
+                .borrow_mut()
+                .push(ModWarning::CollectionDiffers {
+                    name: file.name.clone(),
+                    differences: differences.join("\n"),
+                });
+        }
+
+        Ok(file.name)
+    }
+
+    /// Change the registry entry of `name` without touching any files.
+    fn edit_entry<T>(
+        &self,
+        name: &str,
+        operation: String,
+        edit: impl FnOnce(&mut ModEntry) -> T,
+    ) -> Result<T, ModError> {
+        self.edit_registry(operation, |toml| match toml.get_mut(name) {
+            Some(entry) => Ok(edit(entry)),
+            None => Err(ModError::missing_mod(toml, name.to_string())),
+        })
+    }
+
+    /// Change the registry without touching any files.
+    fn edit_registry<T>(
+        &self,
+        operation: String,
+        edit: impl FnOnce(&mut ModRegistry) -> Result<T, ModError>,
+    ) -> Result<T, ModError> {
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+
+        let ret = edit(&mut toml)?;
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation,
+            registry: self.toml.clone(),
+            previous,
+            moves: vec![],
+            created: vec![],
+            linked: vec![],
+            unlinked: vec![],
+        })?;
+
+        Ok(ret)
+    }
+
+    /// Keep files of `name` matching `pattern` out of the game directory.
+    ///
+    /// Returns the newly excluded files.
+    pub fn exclude_mod<S: Into<String>>(
+        &self,
+        name: S,
+        pattern: &str,
+    ) -> Result<Vec<String>, ModError> {
+        let name = name.into();
+        let pattern = Pattern::new(pattern)?;
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+        let name = toml.canonical_name(name);
+
+        let Some(entry) = toml.get_mut(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+
+        let matched = entry
+            .deployed_files()
+            .filter(|file| pattern.matches(file))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if matched.is_empty() {
+            return Err(ModError::NoMatches {
+                mod_name: name,
+                pattern: pattern.to_string(),
+            });
+        }
+
+        let mut moves = vec![];
+        let mut unlinked = vec![];
+        let (home, _) = self.file_roots(&name, entry.installed);
+
+        for file in &matched {
+            if self.deploy_mode.links() {
+                if entry.installed {
+                    let link = self.root.join(file);
+                    self.unlink(&link)?;
+                    unlinked.push((self.staging.join(&name).join(file), link));
+                }
+            } else {
+                let (from, to) = (home.join(file), self.excluded.join(&name).join(file));
+                if self.fs.exists(&from) {
+                    self.move_file(&from, &to, &home)?;
+                    moves.push((from, to));
+                }
+            }
+        }
+
+        entry
+            .excludes
+            .get_or_insert_default()
+            .push(pattern.to_string());
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation: format!("exclude `{pattern}` from `{name}`"),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created: vec![],
+            linked: vec![],
+            unlinked,
+        })?;
+
+        Ok(matched)
+    }
+
+    /// Claim files already in the game directory matching `patterns` as a new mod `name`,
+    /// hashing them without extracting anything.
+    ///
+    /// Directories match every file under them. With [`DeployMode::links`], the files are moved
+    /// into staging and linked back.
+    pub fn adopt_mod<S: Into<String>>(
+        &self,
+        name: S,
+        version: S,
+        patterns: &[String],
+    ) -> Result<OperationReport, ModError> {
+        let name = name.into();
+        self.report(name.clone(), || {
+            self.adopt_files(name, ModVersion::new(version), patterns)
+        })
+    }
+
+    fn adopt_files(
+        &self,
+        name: String,
+        version: ModVersion,
+        patterns: &[String],
+    ) -> Result<Operation, ModError> {
+        info!(%name, %version, "adopting mod");
+
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+
+        if toml.contains(&name) {
+            return Err(ModError::AlreadyExists(name));
+        }
+
+        let mut files = BTreeSet::new();
+        for pattern in patterns {
+            let matched = self.match_files(pattern)?;
+            if matched.is_empty() {
+                return Err(ModError::NothingToAdopt(pattern.clone()));
+            }
+            files.extend(matched);
+        }
+        let files = files.into_iter().collect::<Vec<_>>();
+
+        let crossed_paths = toml.crossover_paths(&name, files.clone());
+        if !crossed_paths.is_empty() {
+            return Err(ModError::DoubleOwnedFiles {
+                incoming: name,
+                root: self.root.clone(),
+                files: OwnedFile::from_crossover(&crossed_paths),
+            });
+        }
+
+        let hashes = hash::hash_files(&self.root, &files);
+        let stamps = FileStamp::of_files(&self.root, &files);
+
+        // Undoing moves the files back over their links, so only the moves are recorded.
+        let mut moves = vec![];
+        if self.deploy_mode.links() {
+            for file in &files {
+                let (from, to) = (self.root.join(file), self.staging.join(&name).join(file));
+                self.move_file(&from, &to, &self.root)?;
+                self.link(&to, &from)?;
+                moves.push((from, to));
+            }
+        }
+
+        let mut entry = ModEntry {
+            version: version.clone(),
+            installed: true,
+            installed_at: Some(Utc::now()),
+            files: files.clone(),
+            hashes,
+            stamps,
+            ..Default::default()
+        };
+        entry.record_install(None);
+        toml.mods.insert(name.clone().into(), entry);
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation: format!("adopt `{name}` {version}"),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created: vec![],
+            linked: vec![],
+            unlinked: vec![],
+        })?;
+
+        Ok(Operation::Adopted(version))
+    }
+
+    /// Hand files of other mods matching the glob `pattern` over to `new_owner`, like after
+    /// merging two mods into one archive by hand, without reinstalling anything.
+    ///
+    /// Every matched file has to be where its current owner keeps it, and both mods have to be
+    /// enabled or both disabled. With [`DeployMode::links`], files move to `new_owner`'s staging
+    /// directory. The report lists the files handed over, with their previous owner.
+    pub fn chown<S: Into<String>>(
+        &self,
+        pattern: &str,
+        new_owner: S,
+    ) -> Result<OperationReport, ModError> {
+        let new_owner = new_owner.into();
+        self.report(new_owner.clone(), || self.chown_files(pattern, new_owner))
+    }
+
+    fn chown_files(&self, pattern: &str, new_owner: String) -> Result<Operation, ModError> {
+        let pattern = Pattern::new(pattern)?;
+        let previous = self.fs.read_to_string(&self.toml)?;
+        let mut toml = self.load_toml()?;
+        let new_owner = toml.canonical_name(new_owner);
+
+        let Some(installed) = toml.get(&new_owner).map(|entry| entry.installed) else {
+            return Err(ModError::missing_mod(&toml, new_owner));
+        };
+
+        let matched = (toml.mods.iter())
+            .filter(|(name, _)| **name != new_owner)
+            .flat_map(|(name, entry)| {
+                entry
+                    .deployed_files()
+                    .filter(|file| pattern.matches(file))
+                    .map(|file| OwnedFile {
+                        path: PathBuf::from(file),
+                        owner: name.to_string(),
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        if matched.is_empty() {
+            return Err(ModError::NothingToChown {
+                new_owner,
+                pattern: pattern.to_string(),
+            });
+        }
+
+        if let Some(file) = matched.iter().find(|file| {
+            toml.get(&file.owner)
+                .is_some_and(|entry| entry.installed != installed)
+        }) {
+            return Err(ModError::ChownAcrossStates {
+                owner: file.owner.clone(),
+                new_owner,
+                installed,
+            });
+        }
+
+        let missing = matched
+            .iter()
+            .map(|file| self.stored_path(&file.owner, installed, &file.path))
+            .filter(|path| !self.fs.exists(path))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(ModError::ChownMissing { missing });
+        }
+
+        let mut moves = vec![];
+        let mut unlinked = vec![];
+        if self.deploy_mode.links() {
+            for file in &matched {
+                let from = self.staging.join(&file.owner).join(&file.path);
+                let to = self.staging.join(&new_owner).join(&file.path);
+                self.move_file(&from, &to, &self.staging.join(&file.owner))?;
+                if installed {
+                    let link = self.root.join(&file.path);
+                    self.link(&to, &link)?;
+                    unlinked.push((from.clone(), link));
+                }
+                moves.push((from, to));
+            }
+        }
+
+        for file in &matched {
+            let path = file.path.to_string_lossy().into_owned();
+            let Some(entry) = toml.get_mut(&file.owner) else {
+                continue;
+            };
+            entry.files.retain(|f| *f != path);
+            let hash = entry.hashes.remove(&path);
+            let stamp = entry.stamps.remove(&path);
+            let edited = entry.edited.remove(&path);
+
+            let Some(entry) = toml.get_mut(&new_owner) else {
+                continue;
+            };
+            entry.shadowed.retain(|f| *f != path);
+            if !entry.files.contains(&path) {
+                entry.files.push(path.clone());
+            }
+            if let Some(hash) = hash {
+                entry.hashes.insert(path.clone(), hash);
+            }
+            if let Some(stamp) = stamp {
+                entry.stamps.insert(path.clone(), stamp);
+            }
+            if let Some(edited) = edited {
+                entry.edited.insert(path, edited);
+            }
+        }
+
+        self.save_toml(&toml)?;
+        self.record(Record {
+            operation: format!("chown `{pattern}` to `{new_owner}`"),
+            registry: self.toml.clone(),
+            previous,
+            moves,
+            created: vec![],
+            linked: vec![],
+            unlinked,
+        })?;
+
+        Ok(Operation::Chowned(matched))
+    }
+
+    /// Where the contents of `file` of `name` are kept: its staging directory with
+    /// [`DeployMode::links`], otherwise where [`ModHandler::file_roots`] puts it.
+    fn stored_path(&self, name: &str, installed: bool, file: &Path) -> PathBuf {
+        if self.deploy_mode.links() {
+            self.staging.join(name).join(file)
+        } else {
+            self.file_roots(name, installed).0.join(file)
+        }
+    }
+
+    /// Files under [`ModHandler::root`] matching the glob `pattern`, relative to it.
+    ///
+    /// Patterns that are absolute or climb out with `..` are refused, since the files they match
+    /// would be deleted with the mod.
+    fn match_files(&self, pattern: &str) -> Result<Vec<String>, ModError> {
+        let escapes = Path::new(pattern)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if escapes {
+            return Err(ModError::OutsideGame(pattern.to_string()));
+        }
+
+        let root = Pattern::escape(&self.root.to_string_lossy());
+        let mut files = vec![];
+
+        for path in glob::glob(&format!("{root}/{pattern}"))?.flatten() {
+            let paths = if path.is_dir() {
+                let dir = Pattern::escape(&path.to_string_lossy());
+                glob::glob(&format!("{dir}/**/*"))?.flatten().collect()
+            } else {
+                vec![path]
+            };
+
+            files.extend(
+                paths
+                    .iter()
+                    .filter(|path| path.is_file() && !self.is_bookkeeping(path))
+                    .filter_map(|path| path.strip_prefix(&self.root).ok())
+                    .map(|path| {
+                        path.components()
+                            .map(|component| component.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/")
+                    }),
+            );
+        }
+
+        Ok(files)
+    }
+
+    /// Check if `path` belongs to Vapor rather than to a mod, like the registry or its backups.
+    fn is_bookkeeping(&self, path: &Path) -> bool {
+        let registry = self.toml.to_string_lossy();
+        [
+            &self.disabled,
+            &self.staging,
+            &self.excluded,
+            &self.removed,
+            &self.vanilla,
+        ]
+        .iter()
+        .any(|dir| path.starts_with(dir))
+            || path.to_string_lossy().starts_with(registry.as_ref())
+    }
+
+    /// Journaled operations on this registry that haven't been undone, oldest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, ModError> {
+        let Some(journal) = &self.journal else {
+            return Ok(vec![]);
+        };
+
+        Ok(journal
+            .history()?
+            .into_iter()
+            .filter(|entry| entry.record.registry == self.toml)
+            .collect())
+    }
+
+    /// Undo the last `n` journaled operations on this registry.
+    ///
+    /// Returns a report for each undone operation, newest first, about the first mod it changed.
+    pub fn undo(&self, n: usize) -> Result<Vec<OperationReport>, ModError> {
+        let Some(journal) = &self.journal else {
+            return Err(JournalError::NothingToUndo.into());
+        };
+
+        let history = self.history()?;
+
+        if history.is_empty() {
+            return Err(JournalError::NothingToUndo.into());
+        }
+
+        let mut undone = vec![];
+
+        for entry in history.iter().rev().take(n) {
+            info!(id = entry.id, operation = %entry.record.operation, "undoing");
+            let start = Instant::now();
+            let warned = self.warnings.borrow().len();
+            let current = self.load_toml()?;
+            let mods = Self::parse_registry(&entry.record.previous).map_or_else(
+                |_| vec![],
+                |previous| Self::changed_mods(&current, &previous),
+            );
+            let name = mods.first().cloned().unwrap_or_default();
+            let had = self.footprint(&current, &name);
+
+            let event = self.event(&entry.record, Outcome::Undone);
+            self.reverse(&entry.record)?;
+            self.log_event(event);
+            if !self.fs.is_dry_run() {
+                journal.mark_undone(entry.id)?;
+            }
+            let operation = Operation::Undone {
+                operation: entry.record.operation.clone(),
+                mods,
+            };
+            undone.push(self.finish_report(name, had, operation, start.elapsed(), warned)?);
+        }
+
+        Ok(undone)
+    }
+
+    /// Put files and the registry back the way they were before `record`.
+    fn reverse(&self, record: &Record) -> Result<(), ModError> {
+        // Links go first, since moves may put game files back where they were.
+        for (_, link) in &record.linked {
+            self.unlink(link)?;
+        }
+
+        for (from, to) in record.moves.iter().rev() {
+            if let Some(parent) = from.parent() {
+                self.fs.create_dir_all(parent)?;
+            }
+
+            self.fs.rename(to, from)?;
+
+            if let Some(parent) = to.parent() {
+                self.clean_upwards(parent, self.owning_root(to));
+            }
+        }
+
+        for (source, link) in &record.unlinked {
+            self.link(source, link)?;
+        }
+
+        for file in &record.created {
+            match self.fs.remove_file(file) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+
+            if let Some(parent) = file.parent() {
+                self.clean_upwards(parent, &self.root);
+            }
+        }
+
+        self.fs.write(&self.toml, &record.previous)?;
+
+        // Rebuilt from the fragments that are back, rather than put back file by file.
+        let toml = self.load_toml()?;
+        let merged = (toml.mods.values())
+            .flat_map(|entry| &entry.merged)
+            .chain(
+                self.merge_files
+                    .iter()
+                    .filter(|file| self.fs.exists(&self.merge_base.join(file))),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        self.remerge(&toml, &merged)?;
+        if self.modlist {
+            self.write_modlist()?;
+        }
+        Ok(())
+    }
+
+    /// Run `operations` as one operation named `operation`: journaled as a single entry, so one
+    /// `vapor undo` reverses all of it, and rolled back entirely if any of it fails.
+    ///
+    /// A single change keeps its own name. Hooks that already ran aren't undone by a rollback.
+    pub fn transaction<T>(
+        &self,
+        operation: String,
+        operations: impl FnOnce() -> Result<T, ModError>,
+    ) -> Result<T, ModError> {
+        if self.transaction.borrow().is_some() {
+            return operations();
+        }
+
+        self.transaction.replace(Some(vec![]));
+        let result = operations();
+        let mut records = self.transaction.take().unwrap_or_default();
+
+        let Some(first) = records.first() else {
+            if let Err(err) = &result {
+                let record = Record {
+                    operation,
+                    registry: self.toml.clone(),
+                    previous: String::new(),
+                    moves: vec![],
+                    created: vec![],
+                    linked: vec![],
+                    unlinked: vec![],
+                };
+                self.log_event(
+                    OperationEvent::new(&record, vec![], Outcome::Failed).failed(err.to_string()),
+                );
+            }
+            return result;
+        };
+        let mut record = if records.len() == 1 {
+            records.remove(0)
+        } else {
+            Record {
+                operation,
+                registry: first.registry.clone(),
+                previous: first.previous.clone(),
+                moves: vec![],
+                created: vec![],
+                linked: vec![],
+                unlinked: vec![],
+            }
+        };
+        for part in records {
+            record.moves.extend(part.moves);
+            record.created.extend(part.created);
+            record.linked.extend(part.linked);
+            record.unlinked.extend(part.unlinked);
+        }
+
+        match result {
+            Ok(value) => {
+                self.record(record)?;
+                Ok(value)
+            }
+            Err(err) => {
+                info!(operation = %record.operation, "rolling back");
+                let event = self.event(&record, Outcome::Failed);
+                if let Err(rollback) = self.reverse(&record) {
+                    warn!(%rollback, "rollback failed");
+                }
+                self.log_event(event.failed(err.to_string()));
+                Err(err)
+            }
+        }
+    }
+
+    /// Move `from` to `to`, cleaning up directories left empty under `stop`.
+    fn move_file(&self, from: &Path, to: &Path, stop: &Path) -> Result<(), std::io::Error> {
+        if let Some(parent) = to.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+
+        self.fs.rename(from, to)?;
+
+        if let Some(parent) = from.parent() {
+            self.clean_upwards(parent, stop);
+        }
+
+        Ok(())
+    }
+
+    /// Link `source` to `link` according to [`ModHandler::deploy_mode`], replacing whatever is
+    /// at `link`.
+    fn link(&self, source: &Path, link: &Path) -> Result<(), std::io::Error> {
+        if let Some(parent) = link.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+
+        if self.fs.exists(link) {
+            self.fs.remove_file(link)?;
+        }
+
+        self.fs
+            .link(source, link, self.deploy_mode == DeployMode::Hardlink)
+    }
+
+    /// Remove a deployed link, along with any directories left empty.
+    fn unlink(&self, link: &Path) -> Result<(), std::io::Error> {
+        if self.fs.exists(link) {
+            self.fs.remove_file(link)?;
+        }
+
+        if let Some(parent) = link.parent() {
+            self.clean_upwards(parent, &self.root);
+        }
+
+        Ok(())
+    }
+
+    fn save_toml(&self, toml: &ModRegistry) -> Result<(), ModError> {
+        info!(path = %self.toml.display(), mods = toml.mods.len(), "saving registry");
+        let contents = match self.fs.read_to_string(&self.toml) {
+            Ok(old) if self.minimal_diff => toml_format::to_string_minimal(toml, &old)?,
+            _ => toml_format::to_string(toml)?,
+        };
+        self.fs.write(&self.toml, &contents)?;
+
+        Ok(())
+    }
+
+    /// Delete the files of `caches`, so the game rebuilds them on the next start.
+    ///
+    /// Returns the deleted files.
+    pub fn clear_cache(&self, caches: &[GameCache]) -> Result<Vec<PathBuf>, ModError> {
+        let mut cleared = vec![];
+        for cache in caches {
+            for file in cache.files(&self.root) {
+                info!(%cache, file = %file.display(), "clearing cache");
+                self.fs.remove_file(&file)?;
+                cleared.push(file);
+            }
+        }
+        Ok(cleared)
+    }
+
+    /// With [`ModHandler::auto_clear_cache`], clear the caches built from any of `files`.
+    fn clear_stale_cache(&self, files: &[String]) -> Result<(), ModError> {
+        if self.auto_clear_cache {
+            self.clear_cache(&GameCache::touched_by(files))?;
+        }
+        Ok(())
+    }
+
+    /// Run `change` to the mod `name`, timing it and gathering what it did.
+    fn report(
+        &self,
+        name: String,
+        change: impl FnOnce() -> Result<Operation, ModError>,
+    ) -> Result<OperationReport, ModError> {
+        let start = Instant::now();
+        let warned = self.warnings.borrow().len();
+        let before = self.load_toml()?;
+        let name = before.canonical_name(name);
+        let had = self.footprint(&before, &name);
+        let operation = change()?;
+        self.finish_report(name, had, operation, start.elapsed(), warned)
+    }
+
+    /// Report `operation` on `name` with the files and bytes it has now, or `had` if it's gone,
+    /// and the warnings raised since there were `warned`.
+    fn finish_report(
+        &self,
+        name: String,
+        had: (usize, u64),
+        operation: Operation,
+        duration: Duration,
+        warned: usize,
+    ) -> Result<OperationReport, ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name);
+        let (files, bytes) = match toml.get(&name) {
+            Some(_) => self.footprint(&toml, &name),
+            None => had,
+        };
+
+        let warnings = self.warnings.borrow();
+        let raised = &warnings[warned.min(warnings.len())..];
+        Ok(OperationReport {
+            files,
+            bytes,
+            duration,
+            conflicts_resolved: raised
+                .iter()
+                .map(|warning| match warning {
+                    ModWarning::Overwrote { files, .. } => files.len(),
+                    _ => 0,
+                })
+                .sum(),
+            warnings: raised.iter().map(ToString::to_string).collect(),
+            name,
+            operation,
+        })
+    }
+
+    /// Deployed files of `name` in `toml` and their total size.
+    fn footprint(&self, toml: &ModRegistry, name: &str) -> (usize, u64) {
+        toml.get(name).map_or((0, 0), |entry| {
+            (entry.deployed_files().count(), self.mod_size(name, entry))
+        })
+    }
+
+    /// Run the `pre_run` hooks before the game is launched: the global ones, then those of every
+    /// enabled mod. A failing hook stops the launch.
+    pub fn run_launch_hooks(&self) -> Result<(), ModError> {
+        let toml = self.load_toml()?;
+
+        self.run_commands(Event::PreRun, "", "", self.hooks.hooks.get(Event::PreRun))?;
+        for (name, entry) in toml.mods.iter().filter(|(_, entry)| entry.installed) {
+            self.run_commands(
+                Event::PreRun,
+                name.as_str(),
+                &entry.version,
+                entry.hooks.get(Event::PreRun),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Run the global hooks for `event` followed by `hooks`, skipping commands that aren't allowed.
+    ///
+    /// A failing pre hook stops the operation, a failing post hook only warns since the operation
+    /// already happened.
+    fn run_hooks(
+        &self,
+        event: Event,
+        mod_name: &str,
+        version: &str,
+        hooks: &Hooks,
+    ) -> Result<(), ModError> {
+        let commands = self
+            .hooks
+            .hooks
+            .get(event)
+            .iter()
+            .chain(hooks.get(event))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.run_commands(event, mod_name, version, &commands)
+    }
+
+    /// Run `commands` for `event` on `mod_name`, empty for hooks not about one mod, see
+    /// [`ModHandler::run_hooks`].
+    fn run_commands(
+        &self,
+        event: Event,
+        mod_name: &str,
+        version: &str,
+        commands: &[String],
+    ) -> Result<(), ModError> {
+        let owner = if mod_name.is_empty() {
+            "every mod"
+        } else {
+            mod_name
+        };
+
+        for command in commands {
+            if !self.hooks.is_allowed(command) {
+                info!(%command, %event, "hook not allowed");
+                self.warnings.borrow_mut().push(ModWarning::HookNotAllowed {
+                    mod_name: owner.to_string(),
+                    event,
+                    command: command.clone(),
+                });
+                continue;
+            }
+
+            if self.fs.is_dry_run() {
+                info!(%command, %event, "would run hook");
+                continue;
+            }
+
+            let output = hooks::run(command, &self.root, mod_name, version, event)?;
+            if output.status.success() {
+                continue;
+            }
+
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lines = stderr.lines().collect::<Vec<_>>();
+            let output = format!(
+                "exited with {}\n{}",
+                output.status,
+                lines[lines.len().saturating_sub(HOOK_OUTPUT_LINES)..].join("\n")
+            )
+            .trim_end()
+            .to_string();
+
+            let failure = HookFailure {
+                mod_name: owner.to_string(),
+                event,
+                command: command.clone(),
+                output,
+            };
+            if event.is_pre() {
+                return Err(ModError::HookFailed(failure));
+            }
+            self.warnings
+                .borrow_mut()
+                .push(ModWarning::HookFailed(failure));
+        }
+
+        Ok(())
+    }
+
+    fn record(&self, record: Record) -> Result<(), ModError> {
+        if self.fs.is_dry_run() {
+            return Ok(());
+        }
+        if let Some(records) = self.transaction.borrow_mut().as_mut() {
+            records.push(record);
+            return Ok(());
+        }
+        self.log_event(self.event(&record, Outcome::Ok));
+        if let Some(journal) = &self.journal {
+            journal.append(record)?;
+        }
+        if self.modlist {
+            self.write_modlist()?;
+        }
+
+        Ok(())
+    }
+
+    /// Append `event` to [`ModHandler::events`]. A log that can't be written is only warned
+    /// about, it never fails the operation.
+    fn log_event(&self, event: OperationEvent) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        if self.fs.is_dry_run() {
+            return;
+        }
+        if let Err(err) = events.append(&event) {
+            warn!(%err, path = %events.path.display(), "couldn't log event");
+        }
+    }
+
+    /// The event of `record` ending with `result`, with the mods whose registry entries differ
+    /// between before `record` and now, and the deployed files whose contents did.
+    fn event(&self, record: &Record, result: Outcome) -> OperationEvent {
+        let mut event = OperationEvent::new(record, vec![], result);
+        if self.events.is_none() {
+            return event;
+        }
+        let (Ok(old), Ok(new)) = (
+            Self::parse_registry(&record.previous),
+            Self::read_registry(&record.registry),
+        ) else {
+            return event;
+        };
+        for name in Self::changed_mods(&old, &new) {
+            if let (Some(before), Some(after)) = (old.get(&name), new.get(&name)) {
+                let (home, _) = self.file_roots(&name, after.installed);
+                event.files.extend(
+                    (after.hashes.iter())
+                        .filter(|(file, hash)| before.hashes.get(*file) != Some(hash))
+                        .map(|(file, _)| home.join(file)),
+                );
+            }
+            event.mods.push(name);
+        }
+        event.files.sort();
+        event.files.dedup();
+        event
+    }
+
+    /// Mods whose entries differ between `old` and `new`, including added and removed ones, in
+    /// name order.
+    fn changed_mods(old: &ModRegistry, new: &ModRegistry) -> Vec<String> {
+        let entry = |toml: &ModRegistry, name: &str| {
+            toml.get(name).and_then(|entry| toml::to_string(entry).ok())
+        };
+        (old.mods.keys().chain(new.mods.keys()))
+            .map(ToString::to_string)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|name| entry(old, name) != entry(new, name))
+            .collect()
+    }
+
+    /// Where the files of `name` should be, and where stray files from an interrupted move
+    /// would be.
+    pub fn file_roots(&self, name: &str, installed: bool) -> (PathBuf, Option<PathBuf>) {
+        match (self.deploy_mode.links(), installed) {
+            (true, true) => (self.root.clone(), None),
+            (true, false) => (self.staging.join(name), None),
+            (false, true) => (self.root.clone(), Some(self.disabled.clone())),
+            (false, false) => (self.disabled.clone(), Some(self.root.clone())),
+        }
+    }
+
+    /// Total size in bytes of the deployed files of `name`.
+    pub fn mod_size(&self, name: &str, entry: &ModEntry) -> u64 {
+        let (home, _) = self.file_roots(name, entry.installed);
+        entry
+            .deployed_files()
+            .filter_map(|file| self.fs.metadata(&home.join(file)).ok())
+            .map(|meta| meta.len)
+            .sum()
+    }
+
+    /// Every file of `name` in registry order, with its size and whether it exists.
+    pub fn file_info(&self, name: &str, entry: &ModEntry) -> Vec<FileInfo> {
+        let (home, _) = self.file_roots(name, entry.installed);
+        entry
+            .files
+            .iter()
+            .map(|file| {
+                let excluded = entry.is_excluded(file);
+                let metadata = (!excluded)
+                    .then(|| self.fs.metadata(&home.join(file)).ok())
+                    .flatten();
+                FileInfo {
+                    path: file.clone(),
+                    kind: FileKind::of(file),
+                    size: metadata.as_ref().map(|meta| meta.len),
+                    exists: metadata.is_some(),
+                    excluded,
+                }
+            })
+            .collect()
+    }
+
+    /// Archives of enabled mods in the order the game should load them, see [`load_order`].
+    pub fn load_order(&self) -> Result<Vec<Archive>, ModError> {
+        Ok(load_order::load_order(&self.load_toml()?))
+    }
+
+    /// Entries of the [`MODLIST`] in the game directory, or `None` if there isn't one.
+    pub fn modlist_entries(&self) -> Option<Vec<String>> {
+        self.fs
+            .read_to_string(self.root.join(MODLIST))
+            .ok()
+            .map(|text| load_order::parse(&text))
+    }
+
+    /// Entries of [`MODLIST`] naming archives that aren't in the game directory, which the game
+    /// skips silently.
+    pub fn stale_modlist_entries(&self) -> Vec<String> {
+        self.modlist_entries()
+            .map(|entries| load_order::stale_entries(&self.root, &entries))
+            .unwrap_or_default()
+    }
+
+    /// Write [`MODLIST`] from [`ModHandler::load_order`], returning the order written.
+    ///
+    /// Nothing is written if it's already up to date, and with no archive mods enabled the file
+    /// is removed, so the game is back to loading archives alphabetically.
+    pub fn write_modlist(&self) -> Result<Vec<Archive>, ModError> {
+        let order = self.load_order()?;
+        let path = self.root.join(MODLIST);
+        let text = load_order::render(&order);
+
+        match self.fs.read_to_string(&path) {
+            Ok(current) if current == text => return Ok(order),
+            Err(_) if order.is_empty() => return Ok(order),
+            Ok(_) if order.is_empty() => {
+                self.fs.remove_file(&path)?;
+                return Ok(order);
+            }
+            _ => {}
+        }
+
+        info!(path = %path.display(), archives = order.len(), "writing load order");
+        if let Some(parent) = path.parent() {
+            self.fs.create_dir_all(parent)?;
+        }
+        self.fs.write(&path, &text)?;
+        Ok(order)
+    }
+
+    /// Deployed files of `name` that aren't where they should be, sorted.
+    pub fn missing_files(&self, name: &str, entry: &ModEntry) -> Vec<PathBuf> {
+        let (home, _) = self.file_roots(name, entry.installed);
+        let mut missing = entry
+            .deployed_files()
+            .map(|file| home.join(file))
+            .filter(|path| self.fs.metadata(path).is_err())
+            .collect::<Vec<_>>();
+        missing.sort();
+        missing
+    }
+
+    /// [`ModHandler::missing_files`] and [`ModHandler::modified_files`] of `name`, counted.
+    pub fn file_health(&self, name: &str, entry: &ModEntry) -> FileHealth {
+        FileHealth {
+            missing: self.missing_files(name, entry).len(),
+            modified: self.modified_files(name, entry, false).len(),
+            deep: None,
+        }
+    }
+
+    /// [`ModHandler::file_health`] of each of `mods`, stat'ing every deployed file where its
+    /// mod keeps it to also find empty ones, across the rayon pool.
+    pub fn deep_file_health(&self, mods: &[(&str, &ModEntry)]) -> BTreeMap<String, FileHealth> {
+        let files = mods
+            .iter()
+            .flat_map(|&(name, entry)| {
+                let (home, _) = self.file_roots(name, entry.installed);
+                entry
+                    .deployed_files()
+                    .map(move |file| (name, home.join(file)))
+            })
+            .collect::<Vec<_>>();
+
+        let fs = &self.fs;
+        let stats = files
+            .into_par_iter()
+            .map(|(name, path)| {
+                let len = fs.metadata(&path).map(|meta| meta.len).ok();
+                (name, path, len)
+            })
+            .collect::<Vec<_>>();
+
+        let mut checks = BTreeMap::<&str, FileCheck>::new();
+        for (name, path, len) in stats {
+            let check = checks.entry(name).or_default();
+            match len {
+                None => check.missing.push(path),
+                Some(0) => check.empty.push(path),
+                Some(_) => {}
+            }
+        }
+
+        mods.iter()
+            .map(|&(name, entry)| {
+                let mut check = checks.remove(name).unwrap_or_default();
+                check.missing.sort();
+                check.empty.sort();
+                let health = FileHealth {
+                    missing: check.missing.len(),
+                    modified: self.modified_files(name, entry, true).len(),
+                    deep: Some(check),
+                };
+                (name.to_string(), health)
+            })
+            .collect()
+    }
+
+    /// Deployed files of `name` meant to be edited by hand, see
+    /// [`mod_file_formats::is_editable`], with where they are now.
+    pub fn editable_files(&self, name: &str) -> Result<Vec<(String, PathBuf)>, ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name.to_string());
+        let Some(entry) = toml.get(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+        let (home, _) = self.file_roots(&name, entry.installed);
+
+        Ok(entry
+            .deployed_files()
+            .filter(|file| mod_file_formats::is_editable(file))
+            .map(|file| (file.clone(), home.join(file)))
+            .collect())
+    }
+
+    /// Give each of `paths` a copy of its own out of [`ModHandler::store`], so editing it in
+    /// place leaves other mods alone.
+    pub fn unshare(&self, paths: &[PathBuf]) -> Result<(), ModError> {
+        let store = self.store();
+        for path in paths {
+            if store.unshare(path)? {
+                debug!(path = %path.display(), "copied out of the store");
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the hashes of `files` of `name` as edits made on purpose, so
+    /// [`ModHandler::modified_files`] leaves them out. Returns the files that changed since
+    /// they were installed or last recorded.
+    pub fn record_edits(&self, name: &str, files: &[String]) -> Result<Vec<String>, ModError> {
+        let toml = self.load_toml()?;
+        let name = toml.canonical_name(name.to_string());
+        let Some(entry) = toml.get(&name) else {
+            return Err(ModError::missing_mod(&toml, name));
+        };
+        let (home, _) = self.file_roots(&name, entry.installed);
+
+        let mut edits = vec![];
+        // Saved even when the contents are back as they were, so a file that was only touched
+        // isn't reported as modified.
+        let mut stamps = vec![];
+        for file in files {
+            let path = home.join(file);
+            let hash = hash::sha256_of(self.fs.open(&path)?)?;
+            let known = entry.edited.get(file).or_else(|| entry.hashes.get(file));
+            if known != Some(&hash) {
+                edits.push((file.clone(), hash));
+            }
+            if let Some(stamp) = FileStamp::in_fs(&self.fs, &path)
+                && entry.stamps.get(file) != Some(&stamp)
+            {
+                stamps.push((file.clone(), stamp));
+            }
+        }
+        if edits.is_empty() && stamps.is_empty() {
+            return Ok(vec![]);
+        }
+
+        info!(%name, count = edits.len(), "recording edits");
+        self.edit_entry(&name, format!("edit `{name}`"), |entry| {
+            entry.stamps.extend(stamps);
+            edits
+                .into_iter()
+                .map(|(file, hash)| {
+                    entry.edited.insert(file.clone(), hash);
+                    file
+                })
+                .collect()
+        })
+    }
+
+    /// Deployed files of `name` changed since they were installed, or since they were last edited
+    /// with `vapor edit`, sorted.
+    ///
+    /// Only sizes and modification times are compared, against [`ModEntry::stamps`], unless
+    /// `deep` is set, which reads every file to compare its hash. Files without a stamp, like
+    /// ones installed by older versions of Vapor, are only checked when `deep` is set.
+    pub fn modified_files(&self, name: &str, entry: &ModEntry, deep: bool) -> Vec<PathBuf> {
+        let (home, _) = self.file_roots(name, entry.installed);
+        let hashed = entry
+            .deployed_files()
+            .filter_map(|file| Some((file, entry.hashes.get(file)?)))
+            .collect::<Vec<_>>();
+
+        let fs = &self.fs;
+        let mut modified = hashed
+            .into_par_iter()
+            .filter(|(file, hash)| {
+                let path = home.join(file);
+                if !deep {
+                    return entry.stamps.get(*file).is_some_and(|stamp| {
+                        FileStamp::in_fs(fs, &path).is_some_and(|actual| actual != *stamp)
+                    });
+                }
+                let actual = fs.open(&path).and_then(hash::sha256_of);
+                actual.is_ok_and(|actual| {
+                    actual != **hash && entry.edited.get(*file) != Some(&actual)
+                })
+            })
+            .map(|(file, _)| home.join(file))
+            .collect::<Vec<_>>();
+        modified.sort();
+        modified
+    }
+
+    /// Either the disabled directory or the root, whichever `path` lives in.
+    fn owning_root(&self, path: &Path) -> &Path {
+        if path.starts_with(&self.disabled) {
+            &self.disabled
+        } else if path.starts_with(&self.vanilla) {
+            &self.vanilla
+        } else {
+            &self.root
+        }
+    }
+
+    fn clean_upwards(&self, mut path: &Path, stop: &Path) {
+        while path.starts_with(stop) && path != stop {
+            if let Some(name) = path.file_name()
+                && self.root_dirs.iter().any(|dir| OsStr::new(dir) == name)
+            {
+                break;
+            }
+
+            match self.fs.remove_dir(path) {
+                Ok(()) => {}
+                Err(_) => break,
+            }
+
+            let Some(parent) = path.parent() else {
+                break;
+            };
+            path = parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use super::*;
+    use crate::{migrate::SCHEMA_VERSION, mod_manager::fs::MemoryFs};
+
+    const SCRIPTS: [&str; 2] = ["r6/scripts/my_mod/main.reds", "r6/scripts/my_mod/util.reds"];
+
+    /// A game at `/game` kept in memory, with `My Mod` and its files enabled.
+    fn memory_game() -> ModHandler {
+        let handler = ModHandler::new("/game").with_fs(MemoryFs::new());
+        handler
+            .fs
+            .create_dir_all(&handler.root.join("r6/scripts/my_mod"))
+            .unwrap();
+        handler.fs.create_dir_all(&handler.disabled).unwrap();
+        for file in SCRIPTS {
+            handler.fs.write(&handler.root.join(file), file).unwrap();
+        }
+
+        let mut toml: ModRegistry =
+            toml::from_str(&format!("schema_version = {SCHEMA_VERSION}")).unwrap();
+        let entry = ModEntry {
+            version: ModVersion::new("1.0"),
+            installed: true,
+            files: SCRIPTS.map(String::from).to_vec(),
+            ..Default::default()
+        };
+        toml.mods.insert("My Mod".into(), entry);
+        handler.save_toml(&toml).unwrap();
+        handler
+    }
+
+    fn installed(handler: &ModHandler) -> bool {
+        handler
+            .load_toml()
+            .unwrap()
+            .get("My Mod")
+            .unwrap()
+            .installed
+    }
+
+    #[test]
+    fn moves_clean_up_the_directories_they_empty() {
+        let handler = memory_game();
+
+        handler.move_mod("My Mod", Move::Disable).unwrap();
+        for file in SCRIPTS {
+            assert!(!handler.fs.exists(&handler.root.join(file)));
+            assert_eq!(
+                handler
+                    .fs
+                    .read_to_string(handler.disabled.join(file))
+                    .unwrap(),
+                file
+            );
+        }
+        assert!(!handler.fs.exists(&handler.root.join("r6/scripts")));
+        // Top level game directories are left, even empty.
+        assert!(handler.fs.is_dir(&handler.root.join("r6")));
+        assert!(!installed(&handler));
+
+        handler.move_mod("My Mod", Move::Enable).unwrap();
+        assert!(handler.fs.exists(&handler.root.join(SCRIPTS[0])));
+        assert!(!handler.fs.exists(&handler.disabled.join("r6/scripts")));
+        assert!(handler.fs.is_dir(&handler.disabled));
+        assert!(!handler.fs.exists(&handler.moving));
+        assert!(installed(&handler));
+    }
+
+    #[test]
+    fn a_missing_file_stops_a_move_before_anything_moves() {
+        let handler = memory_game();
+        handler
+            .fs
+            .remove_file(&handler.root.join(SCRIPTS[1]))
+            .unwrap();
+
+        assert!(matches!(
+            handler.move_mod("My Mod", Move::Disable),
+            Err(ModError::MissingFile { .. })
+        ));
+        assert!(handler.fs.exists(&handler.root.join(SCRIPTS[0])));
+        assert!(!handler.fs.exists(&handler.disabled.join("r6")));
+        assert!(installed(&handler));
+    }
+
+    #[test]
+    fn fix_pending_move_finishes_an_interrupted_disable() {
+        let handler = memory_game();
+        handler.start_move("My Mod", Move::Disable).unwrap();
+        let (from, to) = (
+            handler.root.join(SCRIPTS[0]),
+            handler.disabled.join(SCRIPTS[0]),
+        );
+        handler.move_file(&from, &to, &handler.root).unwrap();
+
+        let fixed = handler.fix_pending_move().unwrap().unwrap();
+        assert_eq!(fixed.name, "My Mod");
+        assert!(fixed.action == Move::Disable);
+        assert!(fixed.completed);
+        assert_eq!(fixed.files, 1);
+        for file in SCRIPTS {
+            assert!(handler.fs.exists(&handler.disabled.join(file)));
+        }
+        assert!(!handler.fs.exists(&handler.root.join("r6/scripts")));
+        assert!(!handler.fs.exists(&handler.moving));
+        assert!(!installed(&handler));
+
+        assert!(handler.fix_pending_move().unwrap().is_none());
+    }
+
+    #[test]
+    fn sizes_health_and_packs_come_from_the_backend() {
+        let handler = memory_game();
+        let entry = handler.load_toml().unwrap().get("My Mod").unwrap().clone();
+        let size = SCRIPTS.iter().map(|file| file.len() as u64).sum::<u64>();
+        assert_eq!(handler.mod_size("My Mod", &entry), size);
+
+        handler.pack("My Mod", Path::new("/My Mod.zip")).unwrap();
+        let mut packed = vec![];
+        (handler.fs.open(Path::new("/My Mod.zip")).unwrap())
+            .read_to_end(&mut packed)
+            .unwrap();
+        let mut zip = zip::ZipArchive::new(Cursor::new(packed)).unwrap();
+        assert_eq!(zip.file_names().collect::<BTreeSet<_>>(), SCRIPTS.into());
+        let mut main = String::new();
+        zip.by_name(SCRIPTS[0])
+            .unwrap()
+            .read_to_string(&mut main)
+            .unwrap();
+        assert_eq!(main, SCRIPTS[0]);
+        assert!(!handler.fs.exists(Path::new("/My Mod.zip.part")));
+
+        handler
+            .fs
+            .remove_file(&handler.root.join(SCRIPTS[1]))
+            .unwrap();
+        assert_eq!(
+            handler.missing_files("My Mod", &entry),
+            [handler.root.join(SCRIPTS[1])]
+        );
+        let info = handler.file_info("My Mod", &entry);
+        assert!(info[0].exists && !info[1].exists);
+        assert_eq!(info[0].size, Some(SCRIPTS[0].len() as u64));
+        let health = handler.deep_file_health(&[("My Mod", &entry)]);
+        assert_eq!(health["My Mod"].missing, 1);
+    }
+
+    #[test]
+    fn removing_keeps_files_and_cleans_up_after_them() {
+        let handler = memory_game();
+
+        let report = handler.remove_mod("My Mod").unwrap();
+        assert_eq!(report.files, 2);
+        assert!(!handler.fs.exists(&handler.root.join("r6/scripts")));
+        assert!(handler.fs.is_dir(&handler.removed));
+        assert!(handler.load_toml().unwrap().mods.is_empty());
+    }
+
+    #[test]
+    fn hook_failures_read_the_same_as_errors_and_warnings() {
+        let failure = || HookFailure {
+            mod_name: "My Mod".into(),
+            event: Event::PostInstall,
+            command: "./setup.sh".into(),
+            output: "exited with exit status: 1".into(),
+        };
+        let error = ModError::HookFailed(failure());
+        let warning = ModWarning::HookFailed(failure());
+
+        assert_eq!(error.to_string(), warning.to_string());
+        for diagnostic in [&error as &dyn Diagnostic, &warning] {
+            assert_eq!(
+                diagnostic.help().unwrap().to_string(),
+                "exited with exit status: 1"
+            );
+            assert_eq!(diagnostic.code().unwrap().to_string(), "ModHandler::hooks");
+        }
+        assert_eq!(error.severity(), None);
+        assert_eq!(warning.severity(), Some(miette::Severity::Warning));
     }
 }
@@ -0,0 +1,102 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use md5::Md5;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex SHA-256 of the file at `path`.
+pub fn sha256(path: &Path) -> io::Result<String> {
+    sha256_of(File::open(path)?)
+}
+
+/// Lowercase hex SHA-256 of everything `reader` reads.
+pub fn sha256_of(reader: impl Read) -> io::Result<String> {
+    hex_digest::<Sha256>(reader)
+}
+
+/// Lowercase hex MD5 of the file at `path`, which is how Nexus Mods identifies files.
+///
+/// Only for where MD5 is all there is, like Nexus lookups and collections: it catches corrupt and
+/// mixed up files, but not deliberate tampering.
+pub fn md5(path: &Path) -> io::Result<String> {
+    hex_digest::<Md5>(File::open(path)?)
+}
+
+fn hex_digest<D: Digest>(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buf = vec![0; 64 * 1024];
+
+    loop {
+        match reader.read(&mut buf)? {
+            0 => break,
+            n => hasher.update(&buf[..n]),
+        }
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Hash `files` under `root` across the rayon pool, skipping files that can't be read.
+pub fn hash_files<'a>(
+    root: &Path,
+    files: impl IntoParallelIterator<Item = &'a String>,
+) -> BTreeMap<String, String> {
+    files
+        .into_par_iter()
+        .filter_map(|file| Some((file.clone(), sha256(&root.join(file)).ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn md5_matches_rfc_1321() {
+        let suite = [
+            ("", "d41d8cd98f00b204e9800998ecf8427e"),
+            ("a", "0cc175b9c0f1b6a831c399e269772661"),
+            ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "c3fcd3d76192e4007dfb496cca67e13b",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ];
+
+        for (input, digest) in suite {
+            assert_eq!(hex_digest::<Md5>(input.as_bytes()).unwrap(), digest);
+        }
+    }
+
+    #[test]
+    fn sha256_of_reads_everything() {
+        assert_eq!(
+            sha256_of("abc".as_bytes()).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Longer than one read.
+        let long = vec![b'a'; 1_000_000];
+        assert_eq!(
+            sha256_of(long.as_slice()).unwrap(),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+}
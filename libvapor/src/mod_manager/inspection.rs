@@ -0,0 +1,444 @@
+//! Looks over the files of an archive before it's added, for ones a mod has no business
+//! shipping: executables outside the directories the game and its plugin loaders load code from,
+//! and scripts outside the directories their runtimes read. [`report`] goes further for
+//! `vapor inspect`, describing everything `vapor add` would do with it.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    fs::{self, File},
+    path::Path,
+};
+
+use inline_colorization::*;
+use miette::Diagnostic;
+use serde::Serialize;
+use thiserror::Error;
+use zip::{ZipArchive, result::ZipError};
+
+use super::{
+    metadata,
+    mod_file_formats::{FileKind, FormatError, check_format, dir_files, read_files_cached},
+    nested,
+    registry::ModRegistry,
+    variant,
+};
+use crate::{download_cache::format_size, render};
+
+/// Directories executables are expected in: the game's own, which CET and ASI loaders use, and
+/// RED4ext's plugins.
+pub const EXECUTABLE_DIRS: &[&str] = &["bin/x64/", "red4ext/plugins/"];
+
+/// Executables outside [`EXECUTABLE_DIRS`] that frameworks ship anyway.
+const KNOWN_EXECUTABLES: &[&str] = &["red4ext/red4ext.dll"];
+
+/// Extensions of files that run code, natively or through the shell.
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "asi", "com", "scr", "msi", "bat", "cmd", "ps1", "vbs", "sh",
+];
+
+/// Script extensions and the directory their runtime reads them from.
+const SCRIPT_DIRS: &[(&str, &str)] = &[
+    ("reds", "r6/scripts/"),
+    ("lua", "bin/x64/plugins/cyber_engine_tweaks/"),
+];
+
+/// Why a file stood out.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Concern {
+    /// An executable outside [`EXECUTABLE_DIRS`].
+    Executable,
+    /// A script outside `expected`, where its runtime would never load it.
+    MisplacedScript { expected: &'static str },
+}
+
+/// A file of an archive that stood out.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Finding {
+    /// As it is in the archive.
+    pub path: String,
+    pub concern: Concern,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.concern {
+            Concern::Executable => write!(f, "`{}` is an executable", self.path),
+            Concern::MisplacedScript { expected } => {
+                write!(f, "`{}` is a script outside `{expected}`", self.path)
+            }
+        }
+    }
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum InspectionError {
+    #[error("`{archive}` has {} unusual files", findings.len())]
+    #[diagnostic(
+        code(inspection::inspect),
+        help(
+            "{}\nCheck them before trusting the archive, then pass `--trust` to add it anyway",
+            findings.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+        )
+    )]
+    Suspicious {
+        archive: String,
+        findings: Vec<Finding>,
+    },
+    #[error("Could not read `{archive}`")]
+    #[diagnostic(
+        code(inspection::report),
+        help("The file may be corrupt, re-download it")
+    )]
+    Unreadable {
+        archive: String,
+        #[source]
+        source: ZipError,
+    },
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Format(#[from] FormatError),
+}
+
+/// Files of `files` that stood out, judged by where they'd land under the game's `root_dirs`
+/// whichever variant of the mod they belong to.
+pub fn inspect(files: &[String], root_dirs: &[String]) -> Vec<Finding> {
+    let variants = variant::variants(files, root_dirs);
+    files
+        .iter()
+        .filter_map(|file| {
+            let path = variant::strip(file, &variants)
+                .replace('\\', "/")
+                .to_lowercase();
+            let extension = path.rsplit_once('.').map_or("", |(_, extension)| extension);
+
+            let concern = if EXECUTABLE_EXTENSIONS.contains(&extension) {
+                let expected = EXECUTABLE_DIRS.iter().any(|dir| path.starts_with(dir))
+                    || KNOWN_EXECUTABLES.contains(&path.as_str());
+                (!expected).then_some(Concern::Executable)
+            } else {
+                SCRIPT_DIRS
+                    .iter()
+                    .find(|(script, dir)| *script == extension && !path.starts_with(dir))
+                    .map(|&(_, expected)| Concern::MisplacedScript { expected })
+            }?;
+
+            Some(Finding {
+                path: file.clone(),
+                concern,
+            })
+        })
+        .collect()
+}
+
+/// [`inspect`] the archive at `path`, failing with [`InspectionError::Suspicious`] if anything
+/// stood out.
+pub fn check_archive(path: &Path, root_dirs: &[String]) -> Result<(), InspectionError> {
+    let findings = inspect(&read_files_cached(path), root_dirs);
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    Err(InspectionError::Suspicious {
+        archive: file_name(path),
+        findings,
+    })
+}
+
+/// How an archive is packaged, which decides what `vapor add` makes of it.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Packaging {
+    /// Laid out like the game directory.
+    Plain,
+    /// A REDmod under `mods/<name>/` with an `info.json`, deployed by the game when launched
+    /// with `-modded`.
+    Redmod,
+    /// A FOMOD installer, whose `fomod/ModuleConfig.xml` picks which files to install.
+    Fomod,
+    /// Only other archives, like one per variant, see [`super::nested`].
+    Nested,
+}
+
+impl Packaging {
+    /// Judge from the paths of the files in an archive.
+    fn of(files: &[String]) -> Self {
+        let files = files
+            .iter()
+            .map(|file| file.replace('\\', "/").to_lowercase())
+            .collect::<Vec<_>>();
+
+        if files.iter().any(|file| {
+            file == "fomod/moduleconfig.xml" || file.ends_with("/fomod/moduleconfig.xml")
+        }) {
+            Self::Fomod
+        } else if files.iter().any(|file| {
+            file.strip_prefix("mods/")
+                .and_then(|rest| rest.split_once('/'))
+                .is_some_and(|(_, rest)| rest == "info.json")
+        }) {
+            Self::Redmod
+        } else {
+            Self::Plain
+        }
+    }
+}
+
+impl fmt::Display for Packaging {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Plain => "plain",
+            Self::Redmod => "REDmod",
+            Self::Fomod => "FOMOD",
+            Self::Nested => "archives inside an archive",
+        })
+    }
+}
+
+/// What adding an archive would do, see [`report`].
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// File name of the archive.
+    pub archive: String,
+    /// Read from the archive and its file name, like `vapor add` without `--name`.
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub packaging: Packaging,
+    /// Whether adding it takes a password.
+    pub encrypted: bool,
+    /// Archives inside, when that's all there is.
+    pub inner_archives: Vec<String>,
+    /// Variants bundled side by side, one of which `vapor add` installs.
+    pub variants: Vec<String>,
+    /// Top-level directories, with whether they are ones the game directory has.
+    pub roots: BTreeMap<String, bool>,
+    pub kinds: BTreeMap<FileKind, usize>,
+    pub files: usize,
+    /// Bytes once extracted.
+    pub size: u64,
+    /// Files other mods already own, with their owner.
+    pub conflicts: BTreeMap<String, String>,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    /// Render as a report, or as JSON.
+    #[allow(unused_must_use)]
+    pub fn render(&self, json: bool) -> String {
+        if json {
+            return serde_json::to_string_pretty(self).expect("could not format json");
+        }
+
+        let mut out = String::new();
+        writeln!(
+            &mut out,
+            "{style_bold}Archive{style_reset}: `{}`",
+            self.archive
+        );
+        for (label, value) in [
+            ("Name", &self.name),
+            ("Version", &self.version),
+            ("Author", &self.author),
+        ] {
+            if let Some(value) = value {
+                writeln!(&mut out, "{style_bold}{label}{style_reset}: {value}");
+            }
+        }
+        writeln!(
+            &mut out,
+            "{style_bold}Files{style_reset}: {}, {} extracted",
+            self.files,
+            format_size(self.size)
+        );
+        if !self.kinds.is_empty() {
+            writeln!(
+                &mut out,
+                "{style_bold}Kinds{style_reset}: {}",
+                self.kinds
+                    .iter()
+                    .map(|(kind, count)| format!("{count} {kind}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        writeln!(
+            &mut out,
+            "{style_bold}Packaging{style_reset}: {}",
+            self.packaging
+        );
+        if self.encrypted {
+            writeln!(
+                &mut out,
+                "  {color_yellow}Encrypted, `vapor add` asks for its password, or pass `--password`{color_reset}"
+            );
+        }
+        match self.packaging {
+            Packaging::Plain => {}
+            Packaging::Redmod => {
+                writeln!(
+                    &mut out,
+                    "  {color_yellow}The game deploys it on launch with `vapor run --modded`{color_reset}"
+                );
+            }
+            Packaging::Fomod => {
+                writeln!(
+                    &mut out,
+                    "  {color_yellow}`vapor add` installs every file, not just the options the installer would pick{color_reset}"
+                );
+            }
+            Packaging::Nested => {
+                writeln!(
+                    &mut out,
+                    "  {color_yellow}`vapor add` asks which to install, or pass `--inner`{color_reset}"
+                );
+                for inner in &self.inner_archives {
+                    writeln!(&mut out, "    {inner}");
+                }
+            }
+        }
+
+        if !self.variants.is_empty() {
+            writeln!(
+                &mut out,
+                "\n{style_bold}Variants{style_reset} (`vapor add` asks which to install, or pass `--variant`)"
+            );
+            for variant in &self.variants {
+                writeln!(&mut out, "  {variant}/");
+            }
+        }
+
+        if !self.roots.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Roots{style_reset}");
+            for (root, known) in &self.roots {
+                if *known {
+                    writeln!(&mut out, "  {root}/");
+                } else {
+                    writeln!(
+                        &mut out,
+                        "  {color_yellow}{root}/{color_reset} (not a game directory)"
+                    );
+                }
+            }
+        }
+
+        if !self.conflicts.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Conflicts{style_reset}");
+            for (path, owner) in &self.conflicts {
+                writeln!(
+                    &mut out,
+                    "  {color_red}{path}{color_reset}, owned by `{owner}`"
+                );
+            }
+        }
+
+        if !self.findings.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Unusual files{style_reset}");
+            for finding in &self.findings {
+                writeln!(&mut out, "  {color_yellow}{finding}{color_reset}");
+            }
+        }
+
+        render::finish(out)
+    }
+}
+
+/// Describe the archive, or directory of loose files, at `path` without extracting it: what it
+/// is, what's in it, and which of its files mods in `registry` already own. `root_dirs` are the
+/// game's top-level directories.
+pub fn report(
+    path: &Path,
+    registry: &ModRegistry,
+    root_dirs: &[String],
+) -> Result<Report, InspectionError> {
+    let unreadable = |source| InspectionError::Unreadable {
+        archive: file_name(path),
+        source,
+    };
+    check_format(path)?;
+    let mut files = vec![];
+    let mut size = 0;
+    let mut encrypted = false;
+    if path.is_dir() {
+        files = dir_files(path);
+        size = (files.iter())
+            .filter_map(|file| fs::metadata(path.join(file)).ok())
+            .map(|meta| meta.len())
+            .sum();
+    } else {
+        let mut archive = File::open(path)
+            .map_err(ZipError::from)
+            .and_then(ZipArchive::new)
+            .map_err(unreadable)?;
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i).map_err(unreadable)?;
+            if !entry.is_dir() {
+                files.push(entry.name().to_string());
+                size += entry.size();
+                encrypted |= entry.encrypted();
+            }
+        }
+    }
+
+    // The game looks for REDmods in `mods/` itself.
+    let inner_archives = nested::inner_archives(path, root_dirs);
+    let packaging = if inner_archives.is_empty() {
+        Packaging::of(&files)
+    } else {
+        Packaging::Nested
+    };
+    let variants = variant::variants(&files, root_dirs);
+    let mut kinds = BTreeMap::new();
+    let mut roots = BTreeMap::new();
+    for file in &files {
+        *kinds.entry(FileKind::of(file)).or_default() += 1;
+        if let Some((root, _)) = variant::strip(file, &variants)
+            .replace('\\', "/")
+            .split_once('/')
+        {
+            let known = root_dirs.iter().any(|dir| dir.eq_ignore_ascii_case(root))
+                || (packaging == Packaging::Redmod && root.eq_ignore_ascii_case("mods"));
+            roots.insert(root.to_string(), known);
+        }
+    }
+
+    let detected = metadata::detect(path);
+    let name = detected
+        .name
+        .clone()
+        .map(|name| registry.canonical_name(name))
+        .unwrap_or_default();
+    let conflicts = registry
+        .crossover_paths(
+            &name,
+            files.iter().map(|file| variant::strip(file, &variants)),
+        )
+        .into_iter()
+        .map(|(owner, path)| (path, owner))
+        .collect();
+
+    Ok(Report {
+        archive: file_name(path),
+        name: detected.name,
+        version: detected.version,
+        author: detected.author,
+        packaging,
+        encrypted,
+        inner_archives,
+        findings: inspect(&files, root_dirs),
+        variants,
+        roots,
+        kinds,
+        size,
+        conflicts,
+        files: files.len(),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,193 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use chrono_humanize::HumanTime;
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::platform;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum JournalError {
+    #[error("io error: `{0}`")]
+    Io(#[from] std::io::Error),
+    #[error("Corrupt journal entry: `{0}`")]
+    #[diagnostic(help("Remove the broken line from `journal.jsonl`"))]
+    Json(#[from] serde_json::Error),
+    #[error("Nothing to undo")]
+    NothingToUndo,
+}
+
+/// A mutating operation, with enough state to reverse it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Record {
+    /// Human readable summary.
+    pub operation: String,
+    /// Registry the operation changed.
+    pub registry: PathBuf,
+    /// Contents of the registry before the operation.
+    pub previous: String,
+    /// Files moved, as `(from, to)`.
+    #[serde(default)]
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    /// Files that did not exist before the operation.
+    #[serde(default)]
+    pub created: Vec<PathBuf>,
+    /// Links created, as `(source, link)`.
+    #[serde(default)]
+    pub linked: Vec<(PathBuf, PathBuf)>,
+    /// Links removed, as `(source, link)`.
+    #[serde(default)]
+    pub unlinked: Vec<(PathBuf, PathBuf)>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum EntryKind {
+    Operation(Record),
+    Undo { target: u64 },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    id: u64,
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    kind: EntryKind,
+}
+
+/// A journaled operation that hasn't been undone.
+pub struct HistoryEntry {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub record: Record,
+}
+
+/// Append-only log of every mutating operation.
+pub struct Journal {
+    pub path: PathBuf,
+}
+
+impl Journal {
+    pub fn new<T: Into<PathBuf>>(path: T) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The journal in the config directory.
+    pub fn open_default() -> Result<Self, JournalError> {
+        Ok(Self::new(platform::place_config_file("journal.jsonl")?))
+    }
+
+    /// Record an operation, returning its ID.
+    pub fn append(&self, record: Record) -> Result<u64, JournalError> {
+        self.write(EntryKind::Operation(record))
+    }
+
+    /// Mark the operation `target` as undone.
+    pub fn mark_undone(&self, target: u64) -> Result<(), JournalError> {
+        self.write(EntryKind::Undo { target })?;
+        Ok(())
+    }
+
+    /// Operations that haven't been undone, oldest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, JournalError> {
+        let mut history = vec![];
+
+        for entry in self.entries()? {
+            match entry.kind {
+                EntryKind::Operation(record) => history.push(HistoryEntry {
+                    id: entry.id,
+                    at: entry.at,
+                    record,
+                }),
+                EntryKind::Undo { target } => history.retain(|op| op.id != target),
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// The journal as it would be without the operations on `registry` and their undos, keeping
+    /// the history of every other game and instance.
+    pub fn without(&self, registry: &Path) -> Result<String, JournalError> {
+        let entries = self.entries()?;
+        let dropped = entries
+            .iter()
+            .filter_map(|entry| match &entry.kind {
+                EntryKind::Operation(record) if record.registry == registry => Some(entry.id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut out = String::new();
+        for entry in entries {
+            let id = match &entry.kind {
+                EntryKind::Operation(_) => entry.id,
+                EntryKind::Undo { target } => *target,
+            };
+            if !dropped.contains(&id) {
+                out.push_str(&serde_json::to_string(&entry)?);
+                out.push('\n');
+            }
+        }
+        Ok(out)
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>, JournalError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    fn write(&self, kind: EntryKind) -> Result<u64, JournalError> {
+        let id = self.entries()?.last().map_or(1, |entry| entry.id + 1);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            &mut file,
+            "{}",
+            serde_json::to_string(&Entry {
+                id,
+                at: Utc::now(),
+                kind,
+            })?
+        )?;
+
+        Ok(id)
+    }
+}
+
+/// Render `history` one operation per line, newest last.
+#[allow(unused_must_use)]
+pub fn report(history: &[HistoryEntry]) -> String {
+    let mut out = String::new();
+
+    for entry in history {
+        writeln!(
+            &mut out,
+            "{:>4}  {}  {}",
+            entry.id,
+            HumanTime::from(entry.at - Utc::now()),
+            entry.record.operation
+        );
+    }
+
+    out
+}
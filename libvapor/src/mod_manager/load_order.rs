@@ -0,0 +1,159 @@
+//! The order the game loads `.archive` mods in, kept in `archive/pc/mod/modlist.txt`.
+//!
+//! The game loads the archives listed there first, in order, with earlier ones winning when two
+//! replace the same asset, and then any others alphabetically. Without the file, which of two
+//! archives wins comes down to their names. Vapor orders them like every other conflict: the mod
+//! installed last wins, so its archives are listed first.
+//!
+//! An entry naming an archive that isn't there is skipped without a word, so a stale `modlist.txt`
+//! left behind by a removed mod or another manager is worth flagging.
+
+use std::{fmt::Write, path::Path};
+
+use inline_colorization::*;
+
+use super::registry::ModRegistry;
+use crate::render;
+
+/// Directory the game loads `.archive` mods from.
+pub const ARCHIVE_DIR: &str = "archive/pc/mod";
+
+/// The load order, relative to the game directory.
+pub const MODLIST: &str = "archive/pc/mod/modlist.txt";
+
+/// An archive in the load order, with the mod that ships it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Archive {
+    pub mod_name: String,
+    /// File name, as listed in `modlist.txt`.
+    pub file: String,
+}
+
+/// Archives of enabled mods in the order they should load, the most recently installed mod's
+/// first.
+pub fn load_order(toml: &ModRegistry) -> Vec<Archive> {
+    let mut mods = toml
+        .mods
+        .iter()
+        .filter(|(_, entry)| entry.installed)
+        .collect::<Vec<_>>();
+    mods.sort_by(|(a_name, a), (b_name, b)| {
+        b.installed_at
+            .cmp(&a.installed_at)
+            .then_with(|| a_name.cmp(b_name))
+    });
+
+    let mut order: Vec<Archive> = vec![];
+    for (name, entry) in mods {
+        let mut files = entry
+            .deployed_files()
+            .filter_map(|file| archive_name(file))
+            .collect::<Vec<_>>();
+        files.sort();
+
+        for file in files {
+            if !order
+                .iter()
+                .any(|archive| archive.file.eq_ignore_ascii_case(file))
+            {
+                order.push(Archive {
+                    mod_name: name.to_string(),
+                    file: file.to_string(),
+                });
+            }
+        }
+    }
+    order
+}
+
+/// The file name of `path` if it's an archive loaded from [`ARCHIVE_DIR`] itself.
+fn archive_name(path: &str) -> Option<&str> {
+    let (dir, file) = path.rsplit_once('/')?;
+    (dir.eq_ignore_ascii_case(ARCHIVE_DIR)
+        && Path::new(file)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("archive")))
+    .then_some(file)
+}
+
+/// `modlist.txt` listing `order`.
+pub fn render(order: &[Archive]) -> String {
+    order
+        .iter()
+        .map(|archive| format!("{}\n", archive.file))
+        .collect()
+}
+
+/// Entries of a `modlist.txt`.
+pub fn parse(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Entries of a `modlist.txt` in the game directory at `root` without an archive to load.
+pub fn stale_entries(root: &Path, entries: &[String]) -> Vec<String> {
+    let present = std::fs::read_dir(root.join(ARCHIVE_DIR))
+        .map(|dir| {
+            dir.flatten()
+                .filter(|entry| entry.path().is_file())
+                .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    entries
+        .iter()
+        .filter(|entry| !present.contains(&entry.to_lowercase()))
+        .cloned()
+        .collect()
+}
+
+/// Render the load order, and how the `modlist.txt` in place differs from it.
+#[allow(unused_must_use)]
+pub fn report(order: &[Archive], current: Option<&[String]>, stale: &[String]) -> String {
+    let mut out = String::new();
+
+    if order.is_empty() {
+        writeln!(&mut out, "No archive mods are enabled");
+    }
+    for (index, archive) in order.iter().enumerate() {
+        writeln!(
+            &mut out,
+            "{:>3}. {style_bold}{}{style_reset} ({})",
+            index + 1,
+            archive.file,
+            archive.mod_name
+        );
+    }
+
+    for entry in stale {
+        writeln!(
+            &mut out,
+            "{color_red}✖{style_reset} `{MODLIST}` lists `{entry}`, which isn't installed"
+        );
+    }
+    let files = order
+        .iter()
+        .map(|archive| archive.file.clone())
+        .collect::<Vec<_>>();
+    match current {
+        None if !order.is_empty() => {
+            writeln!(
+                &mut out,
+                "There is no `{MODLIST}`, so the game loads archives alphabetically. Write it with `vapor modlist --write`"
+            );
+        }
+        Some(current) if current != files => {
+            writeln!(
+                &mut out,
+                "{color_yellow}▲{style_reset} `{MODLIST}` is out of date, rewrite it with `vapor modlist --write`"
+            );
+        }
+        _ => {}
+    }
+
+    render::finish(out)
+}
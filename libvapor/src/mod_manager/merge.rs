@@ -0,0 +1,391 @@
+//! Config files several mods legitimately edit, like `r6/config/inputUserMappings.xml`, merged
+//! from each mod's copy instead of owned by one of them.
+//!
+//! Each mod's copy is kept as a fragment under `.vapor/fragments/<mod>/`, and the file in the game
+//! directory is rebuilt from the fragments of enabled mods whenever one of them changes. What was
+//! there before any mod touched it is kept under `.vapor/merge-base/` and merged first.
+
+use std::path::Path;
+
+use miette::Diagnostic;
+use serde_json::Value;
+use thiserror::Error;
+
+/// Config files of `Cyberpunk 2077` that are merged rather than owned.
+pub const MERGE_FILES: &[&str] = &[
+    "r6/config/inputContexts.xml",
+    "r6/config/inputUserMappings.xml",
+    "r6/config/uiInputActions.xml",
+    "r6/config/settings/platform/pc/options.json",
+];
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum MergeError {
+    #[error("Malformed XML: {0}")]
+    #[diagnostic(code(merge::xml))]
+    Xml(&'static str),
+    #[error(transparent)]
+    #[diagnostic(code(merge::json))]
+    Json(#[from] serde_json::Error),
+}
+
+/// How a mergeable file is merged, from its extension.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// Top level elements are merged, with a later one replacing an earlier one of the same tag
+    /// and `name`.
+    Xml,
+    /// Objects are merged key by key, with later values winning.
+    Json,
+}
+
+impl Format {
+    pub fn of(path: &str) -> Option<Self> {
+        match Path::new(path)
+            .extension()?
+            .to_string_lossy()
+            .to_lowercase()
+            .as_str()
+        {
+            "xml" => Some(Self::Xml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Check if `file` is one of `merge_files`, ignoring case, and in a format that can be merged.
+pub fn is_mergeable(file: &str, merge_files: &[String]) -> bool {
+    Format::of(file).is_some()
+        && merge_files
+            .iter()
+            .any(|merged| merged.eq_ignore_ascii_case(file))
+}
+
+/// Merge `fragments` of `file` in order, later ones winning.
+pub fn merge(file: &str, fragments: &[&str]) -> Result<String, MergeError> {
+    match Format::of(file) {
+        Some(Format::Json) => merge_json(fragments),
+        Some(Format::Xml) | None => merge_xml(fragments),
+    }
+}
+
+fn merge_json(fragments: &[&str]) -> Result<String, MergeError> {
+    let mut merged = Value::Null;
+    for fragment in fragments {
+        deep_merge(&mut merged, serde_json::from_str(fragment)?);
+    }
+    Ok(serde_json::to_string_pretty(&merged)? + "\n")
+}
+
+fn deep_merge(into: &mut Value, from: Value) {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (key, value) in from {
+                deep_merge(into.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (into, from) => *into = from,
+    }
+}
+
+/// A document split around its root element.
+struct Document<'a> {
+    /// Everything up to and including the root's opening tag.
+    head: &'a str,
+    /// Top level children of the root, trimmed.
+    children: Vec<&'a str>,
+    /// Indentation of the first child.
+    indent: &'a str,
+    /// The root's closing tag and everything after it.
+    tail: &'a str,
+}
+
+fn merge_xml(fragments: &[&str]) -> Result<String, MergeError> {
+    let Some((first, rest)) = fragments.split_first() else {
+        return Ok(String::new());
+    };
+    let base = parse(first)?;
+
+    let mut children = base.children.clone();
+    for fragment in rest {
+        for child in parse(fragment)?.children {
+            match children
+                .iter()
+                .position(|existing| identity(existing) == identity(child))
+            {
+                Some(index) => children[index] = child,
+                None => children.push(child),
+            }
+        }
+    }
+
+    let mut out = base.head.to_string();
+    for child in children {
+        out.push('\n');
+        out.push_str(base.indent);
+        out.push_str(child);
+    }
+    out.push('\n');
+    out.push_str(base.tail);
+    Ok(out)
+}
+
+/// What makes two top level elements the same one: the tag and `name` attribute, or the whole
+/// element if it has no `name`.
+fn identity(element: &str) -> String {
+    let open = element.split('>').next().unwrap_or(element);
+    match attribute(open, "name") {
+        Some(name) => format!("{} {name}", tag_name(element)),
+        None => element.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn attribute<'a>(open: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = open;
+    while let Some(index) = rest.find(name) {
+        let before = rest[..index].chars().next_back();
+        let after = rest[index + name.len()..].trim_start();
+        rest = &rest[index + name.len()..];
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value = &after[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+    None
+}
+
+fn parse(text: &str) -> Result<Document<'_>, MergeError> {
+    // Past the declaration, comments and doctype to the root element.
+    let mut at = 0;
+    let root = loop {
+        let start = at
+            + text[at..]
+                .find('<')
+                .ok_or(MergeError::Xml("no root element"))?;
+        let rest = &text[start..];
+        at = if rest.starts_with("<?") {
+            start + end_of(rest, "?>")?
+        } else if rest.starts_with("<!--") {
+            start + end_of(rest, "-->")?
+        } else if rest.starts_with("<!") {
+            start + end_of(rest, ">")?
+        } else {
+            break start;
+        };
+    };
+
+    let open_end = root + end_of(&text[root..], ">")?;
+    if text[..open_end].ends_with("/>") {
+        return Ok(Document {
+            head: &text[..open_end],
+            children: vec![],
+            indent: "",
+            tail: "",
+        });
+    }
+    let name = tag_name(&text[root..]);
+    let close = text
+        .rfind(&format!("</{name}"))
+        .filter(|&close| close >= open_end)
+        .ok_or(MergeError::Xml("root element isn't closed"))?;
+
+    let body = &text[open_end..close];
+    let indent = body
+        .trim_start_matches(['\r', '\n'])
+        .split(|c: char| !c.is_whitespace())
+        .next()
+        .unwrap_or_default();
+
+    let mut children = vec![];
+    let mut at = 0;
+    while at < body.len() {
+        let rest = &body[at..];
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        let start = at + (rest.len() - trimmed.len());
+        let end = start + element_len(trimmed)?;
+        children.push(body[start..end].trim());
+        at = end;
+    }
+
+    Ok(Document {
+        head: &text[..open_end],
+        children,
+        indent,
+        tail: &text[close..],
+    })
+}
+
+/// Length of the element, comment or text at the start of `text`.
+fn element_len(text: &str) -> Result<usize, MergeError> {
+    if !text.starts_with('<') {
+        return Ok(text.find('<').unwrap_or(text.len()));
+    }
+    for (open, close) in [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")] {
+        if text.starts_with(open) {
+            return end_of(text, close);
+        }
+    }
+
+    let mut depth = 0usize;
+    let mut at = 0;
+    loop {
+        let start = at
+            + text[at..]
+                .find('<')
+                .ok_or(MergeError::Xml("element isn't closed"))?;
+        let rest = &text[start..];
+        if rest.starts_with("<!--") {
+            at = start + end_of(rest, "-->")?;
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            at = start + end_of(rest, "]]>")?;
+            continue;
+        }
+        at = start + end_of(rest, ">")?;
+        if rest.starts_with("</") {
+            depth = depth
+                .checked_sub(1)
+                .ok_or(MergeError::Xml("unexpected closing tag"))?;
+        } else if !text[..at].ends_with("/>") {
+            depth += 1;
+        }
+        if depth == 0 {
+            return Ok(at);
+        }
+    }
+}
+
+/// Index just past the first `pattern` in `text`.
+fn end_of(text: &str, pattern: &str) -> Result<usize, MergeError> {
+    text.find(pattern)
+        .map(|index| index + pattern.len())
+        .ok_or(MergeError::Xml("tag isn't closed"))
+}
+
+fn tag_name(tag: &str) -> &str {
+    tag.trim_start_matches('<')
+        .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .next()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_xml_and_json_files_merge() {
+        let files = MERGE_FILES
+            .iter()
+            .map(|file| file.to_string())
+            .chain(["r6/config/notes.txt".to_string()])
+            .collect::<Vec<_>>();
+
+        assert!(is_mergeable("R6/Config/InputUserMappings.XML", &files));
+        assert!(is_mergeable(
+            "r6/config/settings/platform/pc/options.json",
+            &files
+        ));
+        assert!(!is_mergeable("r6/config/notes.txt", &files));
+        assert!(!is_mergeable("r6/config/other.xml", &files));
+    }
+
+    #[test]
+    fn merges_json_key_by_key() {
+        let merged = merge(
+            "options.json",
+            &[
+                r#"{"version": 1, "groups": {"audio": {"volume": 80, "muted": false}}}"#,
+                r#"{"groups": {"audio": {"volume": 50}, "video": {"fov": 90}}}"#,
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&merged).unwrap(),
+            serde_json::json!({
+                "version": 1,
+                "groups": {
+                    "audio": {"volume": 50, "muted": false},
+                    "video": {"fov": 90},
+                },
+            })
+        );
+        assert!(merged.ends_with("}\n"));
+    }
+
+    #[test]
+    fn merges_xml_by_tag_and_name() {
+        let base = r#"<?xml version="1.0"?>
+<!-- Game bindings -->
+<bindings>
+    <mapping name="Jump" type="Button">
+        <button id="IK_Space" />
+    </mapping>
+    <mapping name="Crouch" type="Button" />
+    <hold name="Jump" />
+</bindings>
+"#;
+        let mod_a = r#"<bindings>
+  <mapping name="Jump" type="Button"><button id="IK_J" /></mapping>
+  <mapping name='Dash' type="Button" />
+</bindings>"#;
+        let mod_b = r#"<bindings>
+  <!-- Replaces nothing, comments are whole elements -->
+  <hold name="Jump" timeout="0.5" />
+</bindings>"#;
+
+        assert_eq!(
+            merge("inputUserMappings.xml", &[base, mod_a, mod_b]).unwrap(),
+            r#"<?xml version="1.0"?>
+<!-- Game bindings -->
+<bindings>
+    <mapping name="Jump" type="Button"><button id="IK_J" /></mapping>
+    <mapping name="Crouch" type="Button" />
+    <hold name="Jump" timeout="0.5" />
+    <mapping name='Dash' type="Button" />
+    <!-- Replaces nothing, comments are whole elements -->
+</bindings>
+"#
+        );
+    }
+
+    #[test]
+    fn reads_names_only_from_the_name_attribute() {
+        assert_eq!(identity(r#"<a rename="x" name="y">"#), "a y");
+        assert_eq!(identity(r#"<a  name = "y" />"#), "a y");
+        assert_eq!(identity("<a   b='1'  />"), "<a b='1' />");
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        for text in [
+            "no tags",
+            "<root><child></root>",
+            "<root><child/>",
+            "<root></child></root>",
+        ] {
+            assert!(
+                matches!(merge("a.xml", &["<root/>", text]), Err(MergeError::Xml(_))),
+                "{text}"
+            );
+        }
+        assert!(matches!(
+            merge("a.json", &["{", "{}"]),
+            Err(MergeError::Json(_))
+        ));
+    }
+}
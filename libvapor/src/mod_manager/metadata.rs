@@ -0,0 +1,294 @@
+//! Working out a mod's name, version and author from what its archive ships, so they don't have to
+//! be typed in.
+//!
+//! Sources, most trusted first: a fomod `info.xml`, a REDmod `info.json` or other `mod.json`, the
+//! header comments of a Cyber Engine Tweaks `init.lua`, and finally the archive's file name. A
+//! directory of loose files is read the same way.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use serde_json::Value;
+use tracing::debug;
+use zip::ZipArchive;
+
+use super::mod_file_formats::read_files;
+use crate::download_cache;
+
+/// Largest metadata file read, so a huge `.json` in an archive isn't loaded for nothing.
+const MAX_SIZE: u64 = 1024 * 1024;
+
+/// Where Cyber Engine Tweaks mods live.
+const CET_MODS: &str = "bin/x64/plugins/cyber_engine_tweaks/mods/";
+
+/// What could be found out about a mod, each field unset when nothing says.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// Homepage, like the mod's Nexus page.
+    pub url: Option<String>,
+}
+
+impl Metadata {
+    /// Fill fields unset in `self` from `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            name: self.name.or(other.name),
+            version: self.version.or(other.version),
+            author: self.author.or(other.author),
+            description: self.description.or(other.description),
+            url: self.url.or(other.url),
+        }
+    }
+}
+
+/// Metadata of the mod in the zip archive, or directory of loose files, at `path`.
+pub fn detect(path: &Path) -> Metadata {
+    let from_name = path
+        .file_name()
+        .map(|name| {
+            let name = name.to_string_lossy();
+            if path.is_dir() {
+                // Named like the archive it was unpacked from, without the extension.
+                from_file_name(&format!("{name}.zip"))
+            } else {
+                from_file_name(&name)
+            }
+        })
+        .unwrap_or_default();
+
+    detect_contents(path).or(from_name)
+}
+
+/// Like [`detect`], going only by what's in the archive, for archives whose file name means
+/// nothing, like one piped into `vapor add -`.
+pub fn detect_contents(path: &Path) -> Metadata {
+    let mut found = Found::default();
+
+    if path.is_dir() {
+        for name in read_files(path) {
+            let file = path.join(&name);
+            if !is_metadata_file(&name)
+                || fs::metadata(&file).is_ok_and(|meta| meta.len() > MAX_SIZE)
+            {
+                continue;
+            }
+            if let Ok(bytes) = fs::read(&file) {
+                found.read(&name, &decode(&bytes));
+            }
+        }
+        return found.into_metadata();
+    }
+
+    let Some(mut archive) = File::open(path)
+        .ok()
+        .and_then(|file| ZipArchive::new(file).ok())
+    else {
+        return Metadata::default();
+    };
+
+    for i in 0..archive.len() {
+        let Ok(mut entry) = archive.by_index(i) else {
+            continue;
+        };
+        let name = entry.name().replace('\\', "/");
+        if entry.is_dir() || entry.size() > MAX_SIZE || !is_metadata_file(&name) {
+            continue;
+        }
+
+        let mut bytes = vec![];
+        if entry.read_to_end(&mut bytes).is_err() {
+            continue;
+        }
+        found.read(&name, &decode(&bytes));
+    }
+
+    found.into_metadata()
+}
+
+/// Check if `name` is a file metadata is read from, by its file name.
+fn is_metadata_file(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or_default();
+    ["info.xml", "info.json", "mod.json", "init.lua"]
+        .iter()
+        .any(|known| file_name.eq_ignore_ascii_case(known))
+}
+
+/// Metadata found so far in each kind of file, kept apart since they're trusted differently.
+#[derive(Default)]
+struct Found {
+    fomod: Metadata,
+    json: Metadata,
+    lua: Metadata,
+}
+
+impl Found {
+    /// Take what the file at `name` with `contents` says.
+    fn read(&mut self, name: &str, contents: &str) {
+        let lower = name.to_ascii_lowercase();
+        let file_name = lower.rsplit('/').next().unwrap_or_default();
+        debug!(file = %name, "reading metadata");
+
+        match file_name {
+            "info.xml" if lower.contains("fomod/") => {
+                self.fomod = std::mem::take(&mut self.fomod).or(from_fomod(contents))
+            }
+            "info.json" | "mod.json" => {
+                self.json = std::mem::take(&mut self.json).or(from_json(contents))
+            }
+            "init.lua" => {
+                let mut found = from_lua(contents);
+                // The mod's folder is its name to Cyber Engine Tweaks.
+                if let Some(folder) = lower
+                    .find(CET_MODS)
+                    .and_then(|start| name[start + CET_MODS.len()..].split('/').next())
+                {
+                    found.name = found.name.or(Some(folder.to_string()));
+                }
+                self.lua = std::mem::take(&mut self.lua).or(found);
+            }
+            _ => {}
+        }
+    }
+
+    fn into_metadata(self) -> Metadata {
+        self.fomod.or(self.json).or(self.lua)
+    }
+}
+
+/// Text of a metadata file, which fomod tools like to write as UTF-16.
+fn decode(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => String::from_utf16_lossy(
+            &rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        [0xFE, 0xFF, rest @ ..] => String::from_utf16_lossy(
+            &rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Text of the first `<tag>` element in `xml`.
+fn xml_tag(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{tag}"))?;
+    let open_end = start + xml[start..].find('>')? + 1;
+    let close = open_end + xml[open_end..].find(&format!("</{tag}>"))?;
+    let text = xml[open_end..close]
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'");
+    non_empty(&text)
+}
+
+/// Metadata from a fomod `info.xml`.
+fn from_fomod(xml: &str) -> Metadata {
+    Metadata {
+        name: xml_tag(xml, "Name"),
+        version: xml_tag(xml, "Version"),
+        author: xml_tag(xml, "Author"),
+        description: xml_tag(xml, "Description"),
+        url: xml_tag(xml, "Website"),
+    }
+}
+
+/// Metadata from a REDmod `info.json`, or a `mod.json` with similar keys.
+fn from_json(json: &str) -> Metadata {
+    let Ok(Value::Object(map)) = serde_json::from_str::<Value>(json) else {
+        return Metadata::default();
+    };
+    let text = |keys: &[&str]| {
+        keys.iter().find_map(|key| match map.get(*key)? {
+            Value::String(value) => non_empty(value),
+            Value::Number(value) => Some(value.to_string()),
+            Value::Array(values) => non_empty(
+                &values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            _ => None,
+        })
+    };
+
+    Metadata {
+        name: text(&["name", "title"]),
+        version: text(&["version"]),
+        author: text(&["author", "authors"]),
+        description: text(&["description"]),
+        url: text(&["url", "homepage", "website"]),
+    }
+}
+
+/// Metadata from `-- Key: value` comments at the top of a Lua file.
+fn from_lua(lua: &str) -> Metadata {
+    let mut metadata = Metadata::default();
+    for line in lua
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("--"))
+    {
+        let Some((key, value)) = line.trim_start_matches('-').split_once([':', '=']) else {
+            continue;
+        };
+        let value = non_empty(value);
+        match key.trim().to_lowercase().as_str() {
+            "name" | "mod" => metadata.name = metadata.name.or(value),
+            "version" => metadata.version = metadata.version.or(value),
+            "author" | "authors" => metadata.author = metadata.author.or(value),
+            "description" => metadata.description = metadata.description.or(value),
+            "url" | "homepage" | "website" => metadata.url = metadata.url.or(value),
+            _ => {}
+        }
+    }
+    metadata
+}
+
+/// Name and version from an archive's file name.
+///
+/// Nexus names downloads like `Mod Name-1234-2-1-1700000000.zip`, with the mod ID, the version
+/// split on `-` and an upload time, and GitHub releases usually look like `mod-v1.2.zip`.
+pub fn from_file_name(file_name: &str) -> Metadata {
+    let name = download_cache::family(file_name);
+    let stem = file_name
+        .strip_suffix(".zip")
+        .or_else(|| file_name.rsplit_once('.').map(|(stem, _)| stem))
+        .unwrap_or(file_name);
+    let rest = stem[name.len()..].trim_start_matches(['-', '_']);
+
+    let parts = rest.split('-').collect::<Vec<_>>();
+    let all_digits = |part: &&str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    let version = if parts.len() >= 3 && parts.iter().all(all_digits) {
+        // Mod ID first and upload time last.
+        Some(parts[1..parts.len() - 1].join("."))
+    } else {
+        non_empty(rest.trim_start_matches(['v', 'V']))
+    };
+
+    Metadata {
+        name: non_empty(name),
+        version,
+        ..Default::default()
+    }
+}
@@ -1,3 +1,25 @@
+pub mod case;
+pub mod collection;
+pub mod diff;
+pub mod events;
+pub mod file_tree;
+pub mod freeze;
+pub mod fs;
 pub mod handler;
+pub mod hash;
+pub mod inspection;
+pub mod journal;
+pub mod load_order;
+pub mod merge;
+pub mod metadata;
 pub mod mod_file_formats;
+pub mod name;
+pub mod nested;
+pub mod preview;
 pub mod registry;
+pub mod search;
+pub mod split;
+pub mod store;
+pub mod toml_format;
+pub mod variant;
+pub mod version;
@@ -1,23 +1,542 @@
-use std::{fs::File, path::Path};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
 
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::debug;
 use zip::ZipArchive;
 
+use crate::platform;
+
+/// Paths of the files in the zip archive, or directory of loose files, at `file`.
 pub fn read_files<P: AsRef<Path>>(file: P) -> Vec<String> {
-    let mut paths = vec![];
-    let Ok(file) = File::open(file.as_ref()) else {
-        return paths;
-    };
+    list(file.as_ref()).0
+}
 
-    let Some(mut archive) = ZipArchive::new(file).ok() else {
-        return paths;
+/// Entries of the archive, or files of the directory, at `file` that [`read_files`] leaves out
+/// since they can't be installed: unreadable ones, ones that would land outside the mod, and ones
+/// with names that aren't UTF-8.
+pub fn skipped_files<P: AsRef<Path>>(file: P) -> Vec<String> {
+    list(file.as_ref()).1
+}
+
+/// Paths of the files under `dir`, relative to it and separated by `/` like archive entries,
+/// sorted. Symlinked directories aren't followed.
+pub fn dir_files(dir: &Path) -> Vec<String> {
+    walk(dir).0
+}
+
+/// Files at `file` to install, and those skipped.
+fn list(file: &Path) -> (Vec<String>, Vec<String>) {
+    if file.is_dir() {
+        return walk(file);
+    }
+
+    let (mut paths, mut skipped) = (vec![], vec![]);
+    let Some(mut archive) = File::open(file)
+        .ok()
+        .and_then(|file| ZipArchive::new(file).ok())
+    else {
+        return (paths, skipped);
     };
 
     for i in 0..archive.len() {
-        let file = archive.by_index(i).expect("Oops");
-        if !file.is_dir() {
+        // Raw, so listing an encrypted archive doesn't need its password.
+        let file = match archive.by_index_raw(i) {
+            Ok(file) => file,
+            Err(err) => {
+                debug!(entry = i, %err, "skipping unreadable entry");
+                skipped.push(format!("entry {i}"));
+                continue;
+            }
+        };
+        // Extraction skips these as well, so they'd never be installed.
+        if file.enclosed_name().is_none() {
+            skipped.push(file.name().to_string());
+        } else if !file.is_dir() {
             paths.push(file.name().to_string());
         }
     }
 
+    (paths, skipped)
+}
+
+/// Files under `dir` to install, and those skipped.
+fn walk(dir: &Path) -> (Vec<String>, Vec<String>) {
+    let (mut paths, mut skipped) = (vec![], vec![]);
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(current) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => dirs.push(path),
+                Ok(_) if path.is_file() => {
+                    let Ok(relative) = path.strip_prefix(dir) else {
+                        continue;
+                    };
+                    // `mods.toml` can only record UTF-8 paths, and copying goes by the recorded one.
+                    match relative
+                        .components()
+                        .map(|component| component.as_os_str().to_str())
+                        .collect::<Option<Vec<_>>>()
+                    {
+                        Some(components) => paths.push(components.join("/")),
+                        None => skipped.push(relative.to_string_lossy().into_owned()),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    paths.sort();
+    skipped.sort();
+    (paths, skipped)
+}
+
+/// Bumped whenever [`read_files`] lists archives differently, so older cached listings are
+/// ignored.
+const LISTING_VERSION: u32 = 1;
+
+/// Like [`read_files`], but remembers listings of archives in the XDG cache, so adding the same
+/// archive again doesn't list it again.
+///
+/// Listings are keyed by the archive's path, size and modification time, which is cheap to check
+/// even for archives of several gigabytes. Directories are always listed again, since a change
+/// deep inside one doesn't show on the directory itself.
+pub fn read_files_cached<P: AsRef<Path>>(file: P) -> Vec<String> {
+    let file = file.as_ref();
+    let Some(cached) = listing_dir().and_then(|dir| Some(dir.join(listing_key(file)?))) else {
+        return read_files(file);
+    };
+
+    if let Some(paths) = fs::read_to_string(&cached)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+    {
+        debug!(archive = %file.display(), "using cached listing");
+        return paths;
+    }
+
+    let paths = read_files(file);
+    if !paths.is_empty()
+        && let Ok(contents) = serde_json::to_string(&paths)
+        && let Err(err) = fs::write(&cached, contents)
+    {
+        debug!(%err, "could not cache listing");
+    }
+
+    paths
+}
+
+/// Name of the cached listing of the archive at `file`, if it is one.
+pub(crate) fn listing_key(file: &Path) -> Option<String> {
+    let metadata = fs::metadata(file)
+        .ok()
+        .filter(|metadata| metadata.is_file())?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    let path = fs::canonicalize(file).ok()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(LISTING_VERSION.to_le_bytes());
+    hasher.update(path.as_os_str().as_encoded_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.to_le_bytes());
+    let hash = (hasher.finalize().iter())
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    Some(format!("{hash}.json"))
+}
+
+/// Characters Windows, and so the game, doesn't allow in file names.
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// Names Windows keeps for devices, whatever their extension.
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// `path`, an archive entry, with every component made a name Windows accepts, so a mod installs
+/// the same on every system.
+///
+/// Reserved and control characters become `_`, trailing dots and spaces are dropped, and device
+/// names like `NUL` get a `_` after them. Borrowed if nothing had to change.
+pub fn sanitize(path: &str) -> Cow<'_, str> {
+    let mut sanitized = String::with_capacity(path.len());
+    for component in path.split_inclusive(['/', '\\']) {
+        let (name, separator) = match component.strip_suffix(['/', '\\']) {
+            Some(name) => (name, &component[name.len()..]),
+            None => (component, ""),
+        };
+        if name.is_empty() || name == "." || name == ".." {
+            sanitized.push_str(component);
+            continue;
+        }
+
+        let mut name = name
+            .chars()
+            .map(|c| {
+                if RESERVED_CHARS.contains(&c) || c.is_control() {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect::<String>()
+            .trim_end_matches(['.', ' '])
+            .to_string();
+        let stem = name.split('.').next().unwrap_or_default();
+        if name.is_empty() || RESERVED_NAMES.contains(&stem.to_ascii_lowercase().as_str()) {
+            name.insert(stem.len(), '_');
+        }
+        sanitized.push_str(&name);
+        sanitized.push_str(separator);
+    }
+
+    if sanitized == path {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(sanitized)
+    }
+}
+
+/// Each of `paths` [`sanitize`]d, in the same order, with ` (2)`, ` (3)`, ... before the extension
+/// of files that would otherwise land on a name another entry already has, like `a?.txt` and
+/// `a*.txt` both becoming `a_.txt`.
+///
+/// Entries that needed no sanitizing keep their names, and the rest are numbered in archive
+/// order, so the same archive always installs the same files.
+pub fn sanitize_unique(paths: &[String]) -> Vec<String> {
+    let mut taken = paths
+        .iter()
+        .filter(|path| matches!(sanitize(path), Cow::Borrowed(_)))
+        .cloned()
+        .collect::<BTreeSet<_>>();
+
     paths
+        .iter()
+        .map(|path| {
+            let Cow::Owned(sanitized) = sanitize(path) else {
+                return path.clone();
+            };
+            if path.ends_with(['/', '\\']) || taken.insert(sanitized.clone()) {
+                return sanitized;
+            }
+            let (dir, name) = match sanitized.rfind(['/', '\\']) {
+                Some(i) => sanitized.split_at(i + 1),
+                None => ("", sanitized.as_str()),
+            };
+            let (stem, ext) = match name.rfind('.') {
+                Some(i) if i > 0 => name.split_at(i),
+                _ => (name, ""),
+            };
+            (2..)
+                .map(|n| format!("{dir}{stem} ({n}){ext}"))
+                .find(|candidate| taken.insert(candidate.clone()))
+                .expect("Some number is free")
+        })
+        .collect()
+}
+
+/// What kind of archive a file is, judged by its first bytes rather than its extension.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Rar,
+    Tar,
+    Gzip,
+    Xz,
+    Zstd,
+    /// Anything else, like an HTML error page saved in place of a download.
+    Unknown,
+}
+
+impl ArchiveFormat {
+    /// Leading bytes of each format, and where in the file they are.
+    const MAGIC: &[(Self, usize, &[u8])] = &[
+        (Self::Zip, 0, b"PK\x03\x04"),
+        // Empty, and spanned across several files.
+        (Self::Zip, 0, b"PK\x05\x06"),
+        (Self::Zip, 0, b"PK\x07\x08"),
+        (Self::SevenZip, 0, b"7z\xBC\xAF\x27\x1C"),
+        (Self::Rar, 0, b"Rar!\x1A\x07"),
+        (Self::Gzip, 0, b"\x1F\x8B"),
+        (Self::Xz, 0, b"\xFD7zXZ\x00"),
+        (Self::Zstd, 0, b"\x28\xB5\x2F\xFD"),
+        (Self::Tar, 257, b"ustar"),
+    ];
+
+    /// Sniff the file at `path`.
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let mut head = Vec::with_capacity(262);
+        File::open(path)?.take(262).read_to_end(&mut head)?;
+
+        Ok(Self::MAGIC
+            .iter()
+            .find(|(_, offset, magic)| head.get(*offset..offset + magic.len()) == Some(*magic))
+            .map_or(Self::Unknown, |&(format, _, _)| format))
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Zip => "zip archive",
+            Self::SevenZip => "7z archive",
+            Self::Rar => "RAR archive",
+            Self::Tar => "tar archive",
+            Self::Gzip => "gzip file",
+            Self::Xz => "xz file",
+            Self::Zstd => "zstd file",
+            Self::Unknown => "file in an unknown format",
+        })
+    }
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum FormatError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("`{archive}` is a {format}, which Vapor can't install")]
+    #[diagnostic(
+        code(mod_file_formats::check_format),
+        help(
+            "Vapor installs zip archives and directories of loose files. Extract it with another tool, then add the directory it extracts to"
+        )
+    )]
+    UnsupportedFormat {
+        archive: String,
+        format: ArchiveFormat,
+    },
+}
+
+/// Check that the file at `path` is a zip archive whatever its extension says, or that it's a
+/// directory of loose files.
+pub fn check_format(path: &Path) -> Result<(), FormatError> {
+    if path.is_dir() {
+        return Ok(());
+    }
+
+    match ArchiveFormat::of(path)? {
+        ArchiveFormat::Zip => Ok(()),
+        format => Err(FormatError::UnsupportedFormat {
+            archive: path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            format,
+        }),
+    }
+}
+
+/// Directory cached archive listings are kept in.
+pub fn listing_dir() -> Option<PathBuf> {
+    platform::cache_dir("listings").ok()
+}
+
+/// What an installed file is for, judged by its path.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// `.archive` files and their ArchiveXL `.xl` companions.
+    Archive,
+    /// Redscript `.reds` files.
+    Script,
+    /// Cyber Engine Tweaks Lua mods.
+    Lua,
+    /// Native plugins, like RED4ext `.dll`s and `.asi` loaders.
+    Plugin,
+    /// TweakXL files in `r6/tweaks`.
+    Tweak,
+    /// XML config, like input bindings in `r6/input`.
+    Config,
+    Other,
+}
+
+impl FileKind {
+    pub const ALL: [Self; 7] = [
+        Self::Archive,
+        Self::Script,
+        Self::Lua,
+        Self::Plugin,
+        Self::Tweak,
+        Self::Config,
+        Self::Other,
+    ];
+
+    /// Classify `path`, relative to the game directory.
+    pub fn of(path: &str) -> Self {
+        let path = path.replace('\\', "/").to_lowercase();
+        let extension = path.rsplit_once('.').map_or("", |(_, extension)| extension);
+
+        match extension {
+            "archive" | "xl" => Self::Archive,
+            "reds" => Self::Script,
+            "lua" => Self::Lua,
+            "dll" | "asi" => Self::Plugin,
+            "yaml" | "yml" | "tweak" if path.starts_with("r6/tweaks/") => Self::Tweak,
+            "xml" => Self::Config,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Check if `path`, relative to the game directory, is a config file meant to be edited by hand,
+/// like a CET mod's `config.json`, a RED4ext plugin's `config.ini` or a redscript mod's
+/// `Settings.reds`.
+pub fn is_editable(path: &str) -> bool {
+    let path = path.replace('\\', "/").to_lowercase();
+    let file = path
+        .rsplit_once('/')
+        .map_or(path.as_str(), |(_, file)| file);
+    let Some((stem, _)) = file.rsplit_once('.') else {
+        return false;
+    };
+
+    // TweakXL files are records of the game, not settings.
+    !path.starts_with("r6/tweaks/")
+        && is_config(&path)
+        && ["config", "settings", "options"]
+            .iter()
+            .any(|word| stem.contains(word))
+}
+
+/// Check if `path` has the extension of a file that may be changed in place, by the game, a mod
+/// or the player, whatever its name.
+pub fn is_config(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, extension)| {
+        matches!(
+            extension.to_lowercase().as_str(),
+            "json" | "ini" | "toml" | "yaml" | "yml" | "cfg" | "conf" | "reds"
+        )
+    })
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Archive => "archive",
+            Self::Script => "script",
+            Self::Lua => "lua",
+            Self::Plugin => "plugin",
+            Self::Tweak => "tweak",
+            Self::Config => "config",
+            Self::Other => "other",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn unique(paths: &[&str]) -> Vec<String> {
+        sanitize_unique(&paths.iter().map(ToString::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn sanitizes_each_component_for_windows() {
+        assert!(matches!(
+            sanitize("r6/scripts/my_mod/main.reds"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(sanitize("r6/scripts/what?.reds"), "r6/scripts/what_.reds");
+        assert_eq!(sanitize("bin/x64/plugins. /a.dll"), "bin/x64/plugins/a.dll");
+        assert_eq!(sanitize("r6/NUL.txt"), "r6/NUL_.txt");
+        assert_eq!(sanitize("r6/aux/file"), "r6/aux_/file");
+        assert_eq!(sanitize("r6/tab\there"), "r6/tab_here");
+        assert_eq!(sanitize("r6/../x"), "r6/../x");
+    }
+
+    #[test]
+    fn numbers_entries_that_collide_after_sanitizing() {
+        assert_eq!(
+            unique(&["r6/a?.txt", "r6/a*.txt", "r6/a_.txt", "r6/b|"]),
+            ["r6/a_ (2).txt", "r6/a_ (3).txt", "r6/a_.txt", "r6/b_"]
+        );
+        assert_eq!(unique(&["x?", "x*"]), ["x_", "x_ (2)"]);
+    }
+
+    #[test]
+    fn directory_entries_keep_their_sanitized_name() {
+        assert_eq!(unique(&["r6/a?/", "r6/a*/"]), ["r6/a_/", "r6/a_/"]);
+    }
+
+    fn written(dir: &TempDir, name: &str, contents: &[u8]) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn sniffs_formats_by_their_first_bytes() {
+        let dir = TempDir::new().unwrap();
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+
+        for (name, contents, format) in [
+            ("mod.zip", b"PK\x03\x04rest".to_vec(), ArchiveFormat::Zip),
+            ("empty.zip", b"PK\x05\x06".to_vec(), ArchiveFormat::Zip),
+            (
+                "mod.7z",
+                b"7z\xBC\xAF\x27\x1C".to_vec(),
+                ArchiveFormat::SevenZip,
+            ),
+            (
+                "mod.rar",
+                b"Rar!\x1A\x07\x01\x00".to_vec(),
+                ArchiveFormat::Rar,
+            ),
+            ("mod.tar.gz", b"\x1F\x8B\x08".to_vec(), ArchiveFormat::Gzip),
+            ("mod.tar", tar, ArchiveFormat::Tar),
+            (
+                "mod.html",
+                b"<!DOCTYPE html>".to_vec(),
+                ArchiveFormat::Unknown,
+            ),
+            ("empty", vec![], ArchiveFormat::Unknown),
+        ] {
+            let path = written(&dir, name, &contents);
+            assert_eq!(ArchiveFormat::of(&path).unwrap(), format, "{name}");
+        }
+    }
+
+    #[test]
+    fn only_zips_and_directories_pass_the_format_check() {
+        let dir = TempDir::new().unwrap();
+        // Whatever the extension says.
+        let zip = written(&dir, "mod.7z", b"PK\x03\x04");
+        assert!(check_format(&zip).is_ok());
+        assert!(check_format(dir.path()).is_ok());
+
+        let rar = written(&dir, "mod.zip", b"Rar!\x1A\x07\x00");
+        assert!(matches!(
+            check_format(&rar),
+            Err(FormatError::UnsupportedFormat { archive, format: ArchiveFormat::Rar })
+                if archive == "mod.zip"
+        ));
+    }
 }
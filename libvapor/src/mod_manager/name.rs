@@ -0,0 +1,286 @@
+//! Mod names, compared so that `Cyber Engine Tweaks` and `cyber  engine tweaks` are one mod.
+//!
+//! How loosely names match is [`NameFolding`], set once at startup with [`set_folding`] before
+//! any name is made, since each name is folded when it's made.
+
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    path::Path,
+    sync::atomic::{AtomicU8, Ordering as AtomicOrdering},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// What is ignored when comparing mod names.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum NameFolding {
+    /// Names have to match exactly.
+    None,
+    /// Upper and lower case.
+    Case,
+    /// Leading and trailing whitespace, and how long runs of it are.
+    Whitespace,
+    /// Both case and whitespace.
+    #[default]
+    All,
+}
+
+impl NameFolding {
+    fn case(self) -> bool {
+        matches!(self, Self::Case | Self::All)
+    }
+
+    fn whitespace(self) -> bool {
+        matches!(self, Self::Whitespace | Self::All)
+    }
+}
+
+static FOLDING: AtomicU8 = AtomicU8::new(NameFolding::All as u8);
+
+/// Compare names with `folding` from now on.
+pub fn set_folding(folding: NameFolding) {
+    FOLDING.store(folding as u8, AtomicOrdering::Relaxed);
+}
+
+pub fn folding() -> NameFolding {
+    match FOLDING.load(AtomicOrdering::Relaxed) {
+        0 => NameFolding::None,
+        1 => NameFolding::Case,
+        2 => NameFolding::Whitespace,
+        _ => NameFolding::All,
+    }
+}
+
+/// `name` the way it is compared under the current [`NameFolding`].
+pub fn fold(name: &str) -> Cow<'_, str> {
+    fold_with(name, folding())
+}
+
+fn fold_with(name: &str, folding: NameFolding) -> Cow<'_, str> {
+    let mut name = Cow::Borrowed(name);
+    if folding.whitespace() {
+        name = Cow::Owned(name.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    if folding.case() {
+        name = Cow::Owned(name.to_lowercase());
+    }
+    name
+}
+
+/// The name of a mod, spelled the way it was given but compared after [`fold`].
+///
+/// The folded name is worked out once, when the name is made, so comparing and hashing names
+/// doesn't fold them again each time, and a map of names keeps its order even if
+/// [`set_folding`] is called after it was built.
+#[derive(Clone, Default)]
+pub struct ModName {
+    name: String,
+    key: String,
+}
+
+impl ModName {
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let key = fold(&name).into_owned();
+        Self { name, key }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    pub fn into_string(self) -> String {
+        self.name
+    }
+
+    /// Whether `other` names this mod.
+    pub fn matches(&self, other: &str) -> bool {
+        self.key == fold(other)
+    }
+}
+
+impl Serialize for ModName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.name.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ModName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl PartialEq for ModName {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ModName {}
+
+impl PartialEq<str> for ModName {
+    fn eq(&self, other: &str) -> bool {
+        self.matches(other)
+    }
+}
+
+impl PartialEq<&str> for ModName {
+    fn eq(&self, other: &&str) -> bool {
+        self.matches(other)
+    }
+}
+
+impl PartialEq<String> for ModName {
+    fn eq(&self, other: &String) -> bool {
+        self.matches(other)
+    }
+}
+
+impl PartialOrd for ModName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ModName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl Hash for ModName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl Deref for ModName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl AsRef<str> for ModName {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl AsRef<Path> for ModName {
+    fn as_ref(&self) -> &Path {
+        self.name.as_ref()
+    }
+}
+
+impl fmt::Display for ModName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl fmt::Debug for ModName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.name, f)
+    }
+}
+
+impl From<String> for ModName {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<&str> for ModName {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<&String> for ModName {
+    fn from(name: &String) -> Self {
+        Self::new(name.clone())
+    }
+}
+
+impl From<ModName> for String {
+    fn from(name: ModName) -> Self {
+        name.name
+    }
+}
+
+impl From<&ModName> for String {
+    fn from(name: &ModName) -> Self {
+        name.name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+
+    use super::*;
+
+    #[test]
+    fn folding_modes() {
+        let name = "  Cyber   Engine Tweaks ";
+        assert_eq!(fold_with(name, NameFolding::None), name);
+        assert_eq!(
+            fold_with(name, NameFolding::Case),
+            "  cyber   engine tweaks "
+        );
+        assert_eq!(
+            fold_with(name, NameFolding::Whitespace),
+            "Cyber Engine Tweaks"
+        );
+        assert_eq!(fold_with(name, NameFolding::All), "cyber engine tweaks");
+    }
+
+    // Names fold with the default, `All`, here. Other tests make names too, so changing the
+    // folding for the whole process would race with them.
+    #[test]
+    fn names_match_however_they_are_spelled() {
+        let name = ModName::new("Cyber Engine Tweaks");
+        assert_eq!(name, ModName::new("cyber  engine tweaks "));
+        assert!(name.matches("CYBER ENGINE TWEAKS"));
+        assert!(!name.matches("CyberEngineTweaks"));
+        assert_eq!(name.as_str(), "Cyber Engine Tweaks");
+    }
+
+    #[test]
+    fn maps_keep_one_entry_per_mod_in_folded_order() {
+        let mut mods = BTreeMap::new();
+        mods.insert(ModName::new("beta"), 1);
+        mods.insert(ModName::new("Alpha"), 2);
+        mods.insert(ModName::new("BETA"), 3);
+        assert_eq!(mods.len(), 2);
+        let (first, _) = mods.first_key_value().unwrap();
+        assert_eq!(first.as_str(), "Alpha");
+        // The first spelling stays, with the latest value.
+        assert_eq!(
+            mods.get_key_value(&ModName::new("Beta"))
+                .unwrap()
+                .0
+                .as_str(),
+            "beta"
+        );
+        assert_eq!(mods[&ModName::new("Beta")], 3);
+
+        let set = HashSet::from([ModName::new("Mod"), ModName::new(" mod")]);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn serializes_as_spelled() {
+        let name: ModName = serde_json::from_str("\"My  Mod\"").unwrap();
+        assert_eq!(serde_json::to_string(&name).unwrap(), "\"My  Mod\"");
+        assert!(name.matches("my mod"));
+    }
+}
@@ -0,0 +1,387 @@
+//! Archives that wrap other archives, like Nexus downloads bundling a zip per variant, and
+//! getting the mod out of them.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use glob::Pattern;
+use miette::Diagnostic;
+use thiserror::Error;
+use tracing::info;
+use zip::{ZipArchive, result::ZipError};
+
+use super::{
+    fs::{ExtractOptions, RealFs, VaporFs, is_checksum_error},
+    mod_file_formats::read_files,
+};
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum NestedError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Could not unpack `{entry}` from `{archive}`")]
+    #[diagnostic(code(nested::extract))]
+    Unreadable {
+        archive: String,
+        entry: String,
+        #[source]
+        source: ZipError,
+    },
+    #[error("`{entry}` inside `{archive}` doesn't match its checksum")]
+    #[diagnostic(help(
+        "The archive may be corrupt, re-download it. Pass `--ignore-crc` to install it anyway"
+    ))]
+    Checksum { archive: String, entry: String },
+    #[error("`{pattern}` does not match any archive inside `{archive}`")]
+    #[diagnostic(help("Inside are: {}", inner.join(", ")))]
+    NoMatch {
+        archive: String,
+        pattern: String,
+        inner: Vec<String>,
+    },
+    #[error("`{archive}` holds {} archives", inner.len())]
+    #[diagnostic(help(
+        "Pick which to install with `--inner`, like `--inner '{}'`. Inside are: {}",
+        inner[0],
+        inner.join(", ")
+    ))]
+    Ambiguous { archive: String, inner: Vec<String> },
+    #[error("`{archive}` nests archives more than {MAX_DEPTH} deep")]
+    #[diagnostic(help("An archive that contains itself never ends, extract it with another tool"))]
+    TooDeep { archive: String },
+}
+
+/// How many archives deep [`unpack`] goes before giving up, far more than any real mod nests.
+pub const MAX_DEPTH: usize = 4;
+
+/// Check if `entry` is an archive Vapor can open.
+fn is_archive(entry: &str) -> bool {
+    entry.to_ascii_lowercase().ends_with(".zip")
+}
+
+/// Archives inside the archive at `path`, by entry name, if that's all it is.
+///
+/// An archive with files under any of `root_dirs` is a mod of its own, which may happen to ship a
+/// zip, so it has none.
+pub fn inner_archives(path: &Path, root_dirs: &[String]) -> Vec<String> {
+    let files = read_files(path);
+    let is_mod_file = |file: &String| {
+        file.replace('\\', "/")
+            .split_once('/')
+            .is_some_and(|(root, _)| root_dirs.iter().any(|dir| dir.eq_ignore_ascii_case(root)))
+    };
+    if files.iter().any(is_mod_file) {
+        return vec![];
+    }
+
+    files.into_iter().filter(|file| is_archive(file)).collect()
+}
+
+/// Inner archives of `inner` matching `patterns`, which are globs or plain names, matched
+/// against both the entry and its file name.
+pub fn select(
+    archive: &Path,
+    inner: &[String],
+    patterns: &[String],
+) -> Result<Vec<String>, NestedError> {
+    let mut selected = vec![];
+    for pattern in patterns {
+        let glob = Pattern::new(pattern).ok();
+        let matches = |entry: &str| {
+            let file_name = entry.rsplit(['/', '\\']).next().unwrap_or(entry);
+            [entry, file_name].iter().any(|candidate| {
+                candidate.eq_ignore_ascii_case(pattern)
+                    || glob.as_ref().is_some_and(|glob| glob.matches(candidate))
+            })
+        };
+
+        let matched = inner
+            .iter()
+            .filter(|entry| matches(entry))
+            .collect::<Vec<_>>();
+        if matched.is_empty() {
+            return Err(NestedError::NoMatch {
+                archive: file_name(archive),
+                pattern: pattern.clone(),
+                inner: inner.to_vec(),
+            });
+        }
+        for entry in matched {
+            if !selected.contains(entry) {
+                selected.push(entry.clone());
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// Unpack the inner archives `entries` of `archive` into a directory under `dir` named after it,
+/// reading them as `options` say. `depth` is how many archives `archive` is inside of, see
+/// [`MAX_DEPTH`].
+///
+/// A lone archive is extracted to its own file, and several are extracted together into one
+/// directory of loose files, so they install as one mod. Returns what to add.
+pub fn unpack(
+    archive: &Path,
+    entries: &[String],
+    dir: &Path,
+    depth: usize,
+    options: ExtractOptions<'_>,
+) -> Result<PathBuf, NestedError> {
+    let name = file_name(archive);
+    if depth >= MAX_DEPTH {
+        return Err(NestedError::TooDeep { archive: name });
+    }
+    let stem = name.strip_suffix(".zip").unwrap_or(&name);
+    let dir = dir.join(stem);
+    fs::create_dir_all(&dir)?;
+
+    let mut unpacked = vec![];
+    for entry in entries {
+        let dest = dir.join(unpacked_name(entry, entries));
+        unpacked.push(extract_inner(archive, entry, &dest, options)?);
+    }
+
+    if let [single] = unpacked.as_slice() {
+        return Ok(single.clone());
+    }
+
+    // Inner archives all end in `.zip`, so this is never one of them.
+    let combined = dir.join(stem);
+    if combined.exists() {
+        fs::remove_dir_all(&combined)?;
+    }
+    info!(archive = %archive.display(), count = unpacked.len(), "combining inner archives");
+    for inner in &unpacked {
+        RealFs
+            .extract(inner, &combined, &|file| Some(file.to_string()), options)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        fs::remove_file(inner)?;
+    }
+    Ok(combined)
+}
+
+/// File name `entry` is unpacked to: its own, unless another of `entries` in a different folder
+/// has the same one, when it is the whole entry path with folders joined by `-`.
+fn unpacked_name(entry: &str, entries: &[String]) -> String {
+    let base = |entry: &str| -> String {
+        entry
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(entry)
+            .to_ascii_lowercase()
+    };
+    let own = base(entry);
+    if entries
+        .iter()
+        .filter(|other| base(other) == own)
+        .nth(1)
+        .is_none()
+    {
+        return entry
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(entry)
+            .to_string();
+    }
+    entry.trim_matches(['/', '\\']).replace(['/', '\\'], "-")
+}
+
+/// Extract the inner archive `entry` of `archive` to `dest`.
+fn extract_inner(
+    archive: &Path,
+    entry: &str,
+    dest: &Path,
+    options: ExtractOptions<'_>,
+) -> Result<PathBuf, NestedError> {
+    let unreadable = |source| NestedError::Unreadable {
+        archive: file_name(archive),
+        entry: entry.to_string(),
+        source,
+    };
+    let mut zip = File::open(archive)
+        .map_err(ZipError::from)
+        .and_then(ZipArchive::new)
+        .map_err(unreadable)?;
+    let mut inner = match options.password {
+        Some(password) => zip.by_name_decrypt(entry, password.as_bytes()),
+        None => zip.by_name(entry),
+    }
+    .map_err(unreadable)?;
+
+    info!(archive = %archive.display(), %entry, dest = %dest.display(), "unpacking inner archive");
+    match io::copy(&mut inner, &mut File::create(dest)?) {
+        Err(err) if is_checksum_error(&err) && options.ignore_crc => {}
+        Err(err) if is_checksum_error(&err) => {
+            return Err(NestedError::Checksum {
+                archive: file_name(archive),
+                entry: entry.to_string(),
+            });
+        }
+        result => {
+            result?;
+        }
+    }
+    Ok(dest.to_path_buf())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mod_manager::mod_file_formats::dir_files,
+        testing::{TempDir, zip_archive},
+    };
+
+    /// A zip at `dir/name` holding a zip per entry of `inner`, each with the files given.
+    fn bundle(dir: &Path, name: &str, inner: &[(&str, &[(&str, &str)])]) -> PathBuf {
+        let built = dir.join("built");
+        let mut entries = vec![];
+        for (i, (entry, files)) in inner.iter().enumerate() {
+            let path = built.join(format!("{i}.zip"));
+            zip_archive(&path, files).unwrap();
+            entries.push((*entry, fs::read(&path).unwrap()));
+        }
+        let archive = dir.join(name);
+        zip_archive(&archive, &entries).unwrap();
+        archive
+    }
+
+    #[test]
+    fn lists_inner_archives_unless_it_is_a_mod() {
+        let dir = TempDir::new().unwrap();
+        let roots = ["r6".to_string(), "archive".to_string()];
+        let outer = bundle(
+            dir.path(),
+            "Outer.zip",
+            &[("Main.zip", &[]), ("optional/Extra.ZIP", &[])],
+        );
+        assert_eq!(
+            inner_archives(&outer, &roots),
+            ["Main.zip", "optional/Extra.ZIP"]
+        );
+
+        let shipping = dir.path().join("Mod.zip");
+        zip_archive(
+            &shipping,
+            &[("r6/scripts/mod.reds", ""), ("extras/source.zip", "")],
+        )
+        .unwrap();
+        assert!(inner_archives(&shipping, &roots).is_empty());
+    }
+
+    #[test]
+    fn selects_by_name_or_glob() {
+        let inner = ["Main.zip".to_string(), "optional/Extra.zip".to_string()];
+        let archive = Path::new("Outer.zip");
+        let pick = |patterns: &[&str]| {
+            let patterns = patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+            select(archive, &inner, &patterns)
+        };
+
+        assert_eq!(pick(&["extra.zip"]).unwrap(), ["optional/Extra.zip"]);
+        assert_eq!(pick(&["*.zip", "Main.zip"]).unwrap(), inner);
+        assert!(matches!(
+            pick(&["Missing.zip"]),
+            Err(NestedError::NoMatch { pattern, .. }) if pattern == "Missing.zip"
+        ));
+    }
+
+    #[test]
+    fn names_colliding_inner_archives_by_their_folders() {
+        let entries = [
+            "a/Mod.zip".to_string(),
+            "b\\mod.zip".to_string(),
+            "Other.zip".to_string(),
+        ];
+        assert_eq!(unpacked_name(&entries[0], &entries), "a-Mod.zip");
+        assert_eq!(unpacked_name(&entries[1], &entries), "b-mod.zip");
+        assert_eq!(unpacked_name(&entries[2], &entries), "Other.zip");
+    }
+
+    #[test]
+    fn unpacks_a_lone_archive_to_its_own_file() {
+        let dir = TempDir::new().unwrap();
+        let outer = bundle(
+            dir.path(),
+            "Outer.zip",
+            &[("variants/Main.zip", &[("r6/scripts/main.reds", "main")])],
+        );
+        let out = dir.path().join("out");
+
+        let unpacked = unpack(
+            &outer,
+            &["variants/Main.zip".to_string()],
+            &out,
+            0,
+            ExtractOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(unpacked, out.join("Outer").join("Main.zip"));
+        assert_eq!(read_files(&unpacked), ["r6/scripts/main.reds"]);
+    }
+
+    #[test]
+    fn combines_colliding_inner_archives() {
+        let dir = TempDir::new().unwrap();
+        let outer = bundle(
+            dir.path(),
+            "Outer.zip",
+            &[
+                ("a/Mod.zip", &[("r6/scripts/a.reds", "a")]),
+                ("b/Mod.zip", &[("r6/scripts/b.reds", "b")]),
+            ],
+        );
+        let out = dir.path().join("out");
+        let entries = ["a/Mod.zip".to_string(), "b/Mod.zip".to_string()];
+
+        let combined = unpack(&outer, &entries, &out, 0, ExtractOptions::default()).unwrap();
+        assert_eq!(combined, out.join("Outer").join("Outer"));
+        assert_eq!(
+            dir_files(&combined),
+            ["r6/scripts/a.reds", "r6/scripts/b.reds"]
+        );
+        // The inner archives are gone once combined.
+        assert_eq!(
+            dir_files(&out.join("Outer")),
+            ["Outer/r6/scripts/a.reds", "Outer/r6/scripts/b.reds"]
+        );
+    }
+
+    #[test]
+    fn stops_at_the_depth_cap() {
+        let dir = TempDir::new().unwrap();
+        let outer = bundle(
+            dir.path(),
+            "Outer.zip",
+            &[("Main.zip", &[("r6/scripts/main.reds", "main")])],
+        );
+        let entries = ["Main.zip".to_string()];
+        let out = dir.path().join("out");
+
+        assert!(
+            unpack(
+                &outer,
+                &entries,
+                &out,
+                MAX_DEPTH - 1,
+                ExtractOptions::default()
+            )
+            .is_ok()
+        );
+        assert!(matches!(
+            unpack(&outer, &entries, &out, MAX_DEPTH, ExtractOptions::default()),
+            Err(NestedError::TooDeep { archive }) if archive == "Outer.zip"
+        ));
+    }
+}
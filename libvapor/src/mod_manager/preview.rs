@@ -0,0 +1,93 @@
+//! What enabling or disabling a mod would do to its files, worked out without touching them, for
+//! `vapor enable --preview` and `vapor disable --preview`.
+
+use std::{fmt::Write, path::PathBuf};
+
+use inline_colorization::*;
+
+use super::handler::Move;
+use crate::render;
+
+/// Files one mod would move.
+pub struct Preview {
+    pub name: String,
+    pub action: Move,
+    /// Files that would be moved, or linked and unlinked, as `(from, to)`.
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    /// Targets that already exist and would be overwritten.
+    pub clobbers: Vec<PathBuf>,
+    /// Files that aren't where the registry says, which would stop the move with
+    /// [`super::handler::ModError::MissingFile`].
+    pub missing: Vec<PathBuf>,
+}
+
+impl Preview {
+    /// Whether the move would go through without overwriting anything.
+    pub fn is_clean(&self) -> bool {
+        self.clobbers.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// `previews` as text, with a line per file.
+#[allow(unused_must_use)]
+pub fn report(previews: &[Preview]) -> String {
+    let mut out = String::new();
+
+    for preview in previews {
+        writeln!(
+            &mut out,
+            "{style_bold}{}{style_reset} `{}`: {} {}",
+            match preview.action {
+                Move::Enable => "Enable",
+                Move::Disable => "Disable",
+            },
+            preview.name,
+            preview.moves.len(),
+            files(preview.moves.len())
+        );
+        for (from, to) in &preview.moves {
+            writeln!(
+                &mut out,
+                "  {color_cyan}move{style_reset}     {} ~> {}",
+                from.display(),
+                to.display()
+            );
+        }
+        for path in &preview.clobbers {
+            writeln!(
+                &mut out,
+                "  {color_yellow}clobber{style_reset}  {}",
+                path.display()
+            );
+        }
+        for path in &preview.missing {
+            writeln!(
+                &mut out,
+                "  {color_red}missing{style_reset}  {}",
+                path.display()
+            );
+        }
+    }
+
+    if previews.iter().all(Preview::is_clean) {
+        writeln!(
+            &mut out,
+            "{color_green}Nothing would be overwritten{style_reset}"
+        );
+    } else {
+        let clobbers = previews.iter().map(|p| p.clobbers.len()).sum::<usize>();
+        let missing = previews.iter().map(|p| p.missing.len()).sum::<usize>();
+        writeln!(
+            &mut out,
+            "{color_yellow}{clobbers} {} would be overwritten, {missing} {} missing{style_reset}",
+            files(clobbers),
+            files(missing)
+        );
+    }
+
+    render::finish(out)
+}
+
+fn files(count: usize) -> &'static str {
+    if count == 1 { "file" } else { "files" }
+}
@@ -1,27 +1,257 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write;
 use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 use chrono::{DateTime, Utc};
 use chrono_humanize::HumanTime;
+use glob::Pattern;
 use inline_colorization::*;
 use ptree::{TreeBuilder, write_tree};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    download_cache::format_size,
+    exit::ErrorClass,
+    game,
+    hooks::Hooks,
+    i18n::{tr, tr_with},
+    render,
+};
+
+use super::{
+    collection::Collection,
+    freeze,
+    fs::{Fs, Metadata, RealFs, VaporFs},
+    mod_file_formats::FileKind,
+    name::ModName,
+    version::ModVersion,
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModRegistry {
+    /// Layout version, see [`crate::migrate`].
+    #[serde(default)]
+    pub schema_version: u32,
     #[serde(default)]
-    pub mods: BTreeMap<String, ModEntry>,
+    pub mods: BTreeMap<ModName, ModEntry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub collections: BTreeMap<String, Collection>,
+    /// Fields from newer versions of Vapor, kept so they survive a rewrite.
+    #[serde(flatten)]
+    pub extra: toml::Table,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ModEntry {
-    pub version: String,
+    pub version: ModVersion,
     pub file: String,
     pub installed: bool,
     pub installed_at: Option<DateTime<Utc>>,
-    pub dependencies: Option<Vec<String>>,
+    pub dependencies: Option<Vec<ModName>>,
+    /// Mods this one works best with, which are never required.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub optional_dependencies: Vec<ModName>,
+    /// Game versions this mod is known to work with.
+    pub game_versions: Option<Vec<String>>,
+    /// Other names this mod satisfies dependencies on, like the mod it was forked from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<ModName>,
+    /// Mods that can't be enabled alongside this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<ModName>,
+    /// Glob patterns of files kept out of the game directory.
+    pub excludes: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Homepage, like the mod's Nexus page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Kept out of updates and cascades unless forced, see `vapor pin`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Subtree of the archive installed, for archives bundling several variants of the mod.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Every version installed, oldest first, see [`ModEntry::history`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<InstallRecord>,
     pub files: Vec<String>,
+    /// Files this mod ships that another mod took over, see [`crate::init::ConflictPolicy`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shadowed: Vec<String>,
+    /// Config files this mod has a fragment of, merged into the game directory rather than owned,
+    /// see [`super::merge`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merged: Vec<String>,
+    /// SHA-256 of each deployed file when it was installed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<String, String>,
+    /// Size and modification time of each deployed file when it was installed or last edited,
+    /// so changes show without reading the files.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stamps: BTreeMap<String, FileStamp>,
+    /// SHA-256 of each file after it was last changed with `vapor edit`, so doctor tells those
+    /// edits apart from corruption.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub edited: BTreeMap<String, String>,
+    /// Commands run when this mod is installed or enabled, see [`crate::hooks`].
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Fields from newer versions of Vapor, kept so they survive a rewrite.
+    #[serde(flatten)]
+    pub extra: toml::Table,
+}
+
+/// A version of a mod that was installed, kept in [`ModEntry::history`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstallRecord {
+    pub version: ModVersion,
+    pub installed_at: Option<DateTime<Utc>>,
+    /// Archive or directory it was installed from, empty for adopted mods.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    /// Variant picked from the archive, see [`ModEntry::variant`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl InstallRecord {
+    /// The version `entry` has installed now.
+    pub fn of(entry: &ModEntry) -> Self {
+        Self {
+            version: entry.version.clone(),
+            installed_at: entry.installed_at,
+            source: entry.file.clone(),
+            variant: entry.variant.clone(),
+        }
+    }
+}
+
+impl ModEntry {
+    /// Record the version installed now in [`ModEntry::history`]. Entries from before there was
+    /// a history start it with the version they had.
+    pub fn record_install(&mut self, previous: Option<&ModEntry>) {
+        if self.history.is_empty()
+            && let Some(previous) = previous
+        {
+            self.history.push(InstallRecord::of(previous));
+        }
+        self.history.push(InstallRecord::of(self));
+    }
+
+    /// Every version installed, newest first, for `vapor info --history`.
+    #[allow(unused_must_use)]
+    pub fn history(&self, name: &str) -> String {
+        let mut out = String::new();
+        if self.history.is_empty() {
+            writeln!(&mut out, "No versions of `{name}` recorded");
+            return render::finish(out);
+        }
+
+        writeln!(&mut out, "{style_bold}Versions of `{name}`{style_reset}:");
+        for record in self.history.iter().rev() {
+            let when = record.installed_at.map_or_else(
+                || "some time".to_string(),
+                |at| at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            );
+            write!(
+                &mut out,
+                "  {color_cyan}{}{style_reset} on {when}",
+                record.version
+            );
+            if !record.source.is_empty() {
+                write!(&mut out, " from `{}`", record.source);
+            }
+            writeln!(&mut out);
+        }
+        render::finish(out)
+    }
+
+    /// Check if `file` matches one of [`ModEntry::excludes`].
+    pub fn is_excluded(&self, file: &str) -> bool {
+        self.excludes
+            .iter()
+            .flatten()
+            .any(|pattern| Pattern::new(pattern).is_ok_and(|pattern| pattern.matches(file)))
+    }
+
+    /// Check if the mod is tagged with `tag`, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Files that are deployed to the game directory.
+    pub fn deployed_files(&self) -> impl Iterator<Item = &String> {
+        self.files.iter().filter(|file| !self.is_excluded(file))
+    }
+
+    /// How many deployed files there are of each [`FileKind`].
+    pub fn file_kinds(&self) -> BTreeMap<FileKind, usize> {
+        let mut kinds = BTreeMap::new();
+        for file in self.deployed_files() {
+            *kinds.entry(FileKind::of(file)).or_default() += 1;
+        }
+        kinds
+    }
+
+    /// Check if any deployed file hooks into the game's code, see [`freeze::SCRIPT_PATHS`].
+    pub fn affects_scripts(&self) -> bool {
+        self.deployed_files()
+            .any(|file| freeze::is_script_file(file))
+    }
+}
+
+/// Enabled mod in `mods` that provides `name`.
+fn provider<'a>(
+    mods: &'a BTreeMap<ModName, ModEntry>,
+    name: &str,
+) -> Option<(&'a str, &'a ModEntry)> {
+    mods.iter()
+        .find(|(_, entry)| entry.installed && entry.provides.iter().any(|p| p == name))
+        .map(|(provider, entry)| (provider.as_str(), entry))
+}
+
+/// Minimum Jaro-Winkler similarity for [`ModRegistry::similar_names`].
+const SUGGESTION_THRESHOLD: f64 = 0.8;
+
+/// Fold case and separators so `apearance-menu` lines up with `Appearance Menu`.
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase().replace(['-', '_'], " ")
+}
+
+/// Which mods [`ModRegistry::query`] selects.
+#[derive(Default)]
+pub struct ModQuery {
+    /// Only enabled (`true`) or disabled (`false`) mods.
+    pub enabled: Option<bool>,
+    /// Only mods with unsatisfied dependencies.
+    pub missing_deps: bool,
+    pub tag: Option<String>,
+    /// Only mods with files of this kind.
+    pub kind: Option<FileKind>,
+    /// Only mods whose name matches.
+    pub pattern: Option<Pattern>,
+    pub sort: SortKey,
+}
+
+/// Order of [`ModRegistry::query`] results.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    #[default]
+    Name,
+    /// Oldest first, disabled mods last.
+    InstalledAt,
+    Version,
+    /// Smallest first.
+    Size,
 }
 
 /// Used for output for [`ModRegistry::status`].
@@ -32,10 +262,325 @@ struct ModStatus<'a> {
     version: &'a str,
     installed_at: Option<String>,
     missing_dependencies: Vec<String>,
-    dependencies: Vec<String>,
+    dependencies: Vec<&'a str>,
+    optional_dependencies: &'a [ModName],
+    /// Optional dependencies that aren't enabled.
+    missing_optional_dependencies: Vec<&'a str>,
+    /// Enabled mods this one is declared to conflict with.
+    conflicts: Vec<&'a str>,
+    incompatible: bool,
+    health: HealthLevel,
+    missing_files: usize,
+    modified_files: usize,
+    /// Paths of missing and empty files, with `--deep`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deep: Option<&'a FileCheck>,
+    pinned: bool,
+    author: Option<&'a str>,
+    description: Option<&'a str>,
+    url: Option<&'a str>,
+    tags: &'a [String],
+    note: &'a str,
+    /// Number of deployed files of each kind.
+    files: BTreeMap<FileKind, usize>,
+}
+
+/// Size and modification time of a file, see [`ModEntry::stamps`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch.
+    pub modified: i64,
+}
+
+impl FileStamp {
+    /// Stamp of the file at `path`, following links, if it can be read.
+    pub fn of(path: &Path) -> Option<Self> {
+        Self::from_metadata(&RealFs.metadata(path).ok()?)
+    }
+
+    /// Like [`FileStamp::of`], for the file at `path` in `fs`.
+    pub fn in_fs(fs: &Fs, path: &Path) -> Option<Self> {
+        Self::from_metadata(&fs.metadata(path).ok()?)
+    }
+
+    fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        let modified = metadata.modified?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: metadata.len,
+            modified: i64::try_from(modified.as_nanos()).ok()?,
+        })
+    }
+
+    /// Stamps of each of `files` under `root`, leaving out ones that can't be read.
+    pub fn of_files(root: &Path, files: &[String]) -> BTreeMap<String, Self> {
+        files
+            .iter()
+            .filter_map(|file| Some((file.clone(), Self::of(&root.join(file))?)))
+            .collect()
+    }
+}
+
+/// Files of a mod that aren't as installed, filled in by the caller since it takes reading them.
+#[derive(Clone, Default, Debug)]
+pub struct FileHealth {
+    pub missing: usize,
+    /// Changed since they were installed, and not with `vapor edit`.
+    pub modified: usize,
+    /// Every file stat'd, from `vapor status --deep`.
+    pub deep: Option<FileCheck>,
+}
+
+/// Deployed files of a mod that are missing or empty, found by stat'ing each one.
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct FileCheck {
+    pub missing: Vec<PathBuf>,
+    /// Zero bytes long, like after a copy that ran out of disk space.
+    pub empty: Vec<PathBuf>,
+}
+
+/// The worst problem a mod has, shown as a glyph beside it by `vapor status`.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthLevel {
+    #[default]
+    Healthy,
+    /// Nothing wrong found, but the mod doesn't say which game versions it supports.
+    Unknown,
+    /// Modified or empty files, or not marked compatible with the game version.
+    Warning,
+    /// Missing dependencies or files, or conflicting with an enabled mod.
+    Broken,
+}
+
+impl HealthLevel {
+    pub fn glyph(self) -> String {
+        match self {
+            Self::Healthy => format!("{color_green}✔{style_reset}"),
+            Self::Unknown => format!("{color_cyan}?{style_reset}"),
+            Self::Warning => format!("{color_yellow}▲{style_reset}"),
+            Self::Broken => format!("{color_red}✖{style_reset}"),
+        }
+    }
+
+    /// Whether this is worth fixing, which [`HealthLevel::Unknown`] isn't.
+    pub fn is_problem(self) -> bool {
+        self >= Self::Warning
+    }
+}
+
+/// Counts behind `vapor status --summary`, see [`ModRegistry::summary`].
+#[derive(Serialize, Default, Debug)]
+pub struct Summary {
+    pub enabled: usize,
+    pub disabled: usize,
+    /// Mods with missing dependencies.
+    pub broken: usize,
+    /// Enabled mods that conflict with other enabled mods.
+    pub conflicting: usize,
+    /// Mods not marked compatible with the game version.
+    pub incompatible: usize,
+    /// Deployed files changed since they were installed, filled in by the caller since it takes
+    /// reading them.
+    pub modified: usize,
+    /// Mods with an update available, if checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updates: Option<usize>,
+}
+
+impl Summary {
+    /// Exit code bit set along with the others whenever a problem is found, which puts the codes
+    /// above every [`ErrorClass`](crate::exit::ErrorClass) one.
+    pub const FOUND: i32 = 64;
+    /// Exit code bit set when mods have missing dependencies.
+    pub const BROKEN: i32 = 1;
+    /// Exit code bit set when enabled mods conflict.
+    pub const CONFLICTING: i32 = 2;
+    /// Exit code bit set when mods aren't marked compatible with the game version.
+    pub const INCOMPATIBLE: i32 = 4;
+    /// Exit code bit set when deployed files were modified.
+    pub const MODIFIED: i32 = 8;
+    /// Exit code bit set when updates are available.
+    pub const UPDATES: i32 = 16;
+
+    /// [`Summary::FOUND`] and one bit per class of problem found, `0` if there are none.
+    pub fn exit_code(&self) -> i32 {
+        [
+            (self.broken, Self::BROKEN),
+            (self.conflicting, Self::CONFLICTING),
+            (self.incompatible, Self::INCOMPATIBLE),
+            (self.modified, Self::MODIFIED),
+            (self.updates.unwrap_or_default(), Self::UPDATES),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .fold(0, |code, (_, bit)| code | Self::FOUND | bit)
+    }
+
+    /// Render on a single line, or as JSON.
+    pub fn render(&self, json: bool) -> String {
+        if json {
+            return serde_json::to_string(self).expect("could not format json");
+        }
+
+        let mut parts = vec![
+            format!("{} enabled", self.enabled),
+            format!("{} disabled", self.disabled),
+            format!("{} broken", self.broken),
+            format!("{} conflicting", self.conflicting),
+            format!("{} incompatible", self.incompatible),
+            format!("{} modified", self.modified),
+        ];
+        if let Some(updates) = self.updates {
+            parts.push(format!("{updates} updates"));
+        }
+
+        parts.join(", ")
+    }
+}
+
+/// Aggregates behind `vapor stats`, see [`ModRegistry::stats`].
+#[derive(Serialize, Default, Debug)]
+pub struct Stats<'a> {
+    pub mods: usize,
+    pub enabled: usize,
+    pub disabled: usize,
+    /// Files tracked across every mod.
+    pub files: usize,
+    /// Bytes of deployed files across every mod.
+    pub size: u64,
+    /// Biggest first.
+    pub largest: Vec<(&'a str, u64)>,
+    /// Most dependents first.
+    pub most_depended_on: Vec<(&'a str, usize)>,
+    /// Oldest first.
+    pub oldest: Vec<(&'a str, DateTime<Utc>)>,
+    /// Dependencies no mod satisfies, with the mods declaring them.
+    pub orphaned_dependencies: BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+impl Stats<'_> {
+    /// Render as a report, or as JSON.
+    #[allow(unused_must_use)]
+    pub fn render(&self, json: bool) -> String {
+        if json {
+            return serde_json::to_string_pretty(self).expect("could not format json");
+        }
+
+        let mut out = String::new();
+        writeln!(
+            &mut out,
+            "{style_bold}Mods{style_reset}: {} ({color_green}{} enabled{color_reset}, {color_red}{} disabled{color_reset})",
+            self.mods, self.enabled, self.disabled
+        );
+        writeln!(&mut out, "{style_bold}Files{style_reset}: {}", self.files);
+        writeln!(
+            &mut out,
+            "{style_bold}Size{style_reset}: {}",
+            format_size(self.size)
+        );
+
+        if !self.largest.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Largest{style_reset}");
+            for (name, size) in &self.largest {
+                writeln!(
+                    &mut out,
+                    "  {color_cyan}{:>10}{color_reset}  {name}",
+                    format_size(*size)
+                );
+            }
+        }
+
+        if !self.most_depended_on.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Most depended on{style_reset}");
+            for (name, count) in &self.most_depended_on {
+                writeln!(&mut out, "  {color_cyan}{count:>10}{color_reset}  {name}");
+            }
+        }
+
+        if !self.oldest.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Oldest installs{style_reset}");
+            for (name, at) in &self.oldest {
+                writeln!(
+                    &mut out,
+                    "  {color_cyan}{:>10}{color_reset}  {name}",
+                    at.format("%Y-%m-%d")
+                );
+            }
+        }
+
+        if !self.orphaned_dependencies.is_empty() {
+            writeln!(&mut out, "\n{style_bold}Orphaned dependencies{style_reset}");
+            for (dep, wanted_by) in &self.orphaned_dependencies {
+                writeln!(
+                    &mut out,
+                    "  {color_red}{dep}{color_reset}, wanted by {}",
+                    wanted_by.join(", ")
+                );
+            }
+        }
+
+        render::finish(out)
+    }
 }
 
 impl ModRegistry {
+    /// Entry of the mod called `name`, see [`ModName`] for how names match.
+    pub fn get(&self, name: &str) -> Option<&ModEntry> {
+        self.mods.get(&ModName::from(name))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ModEntry> {
+        self.mods.get_mut(&ModName::from(name))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.mods.contains_key(&ModName::from(name))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ModEntry> {
+        self.mods.remove(&ModName::from(name))
+    }
+
+    /// `name` spelled the way it is in the registry, or as given when no mod matches.
+    ///
+    /// Files of a mod are kept under its name on disk, so operations look them up by this.
+    pub fn canonical_name(&self, name: String) -> String {
+        match self.mods.get_key_value(&ModName::from(name.as_str())) {
+            Some((key, _)) => key.to_string(),
+            None => name,
+        }
+    }
+
+    /// Count enabled and disabled mods among `names`, and those with problems the registry alone
+    /// can tell.
+    pub fn summary(&self, game_version: Option<&str>, names: &[&str]) -> Summary {
+        let mut summary = Summary::default();
+
+        for &name in names {
+            let Some(entry) = self.get(name) else {
+                continue;
+            };
+
+            if entry.installed {
+                summary.enabled += 1;
+            } else {
+                summary.disabled += 1;
+            }
+            if !self.unsatisfied_deps(name).is_empty() {
+                summary.broken += 1;
+            }
+            if entry.installed && !self.enabled_conflicts(name).is_empty() {
+                summary.conflicting += 1;
+            }
+            if game_version.is_some_and(|version| self.incompatible(name, version)) {
+                summary.incompatible += 1;
+            }
+        }
+
+        summary
+    }
+
     /// Check if dependencies are satisfied.
     ///
     /// Returns a list of dependencies that could not be found.
@@ -43,7 +588,7 @@ impl ModRegistry {
         let name = name.into();
         let mut broken_deps = vec![];
 
-        let Some(mod_entry) = self.mods.get(&name) else {
+        let Some(mod_entry) = self.get(&name) else {
             return broken_deps;
         };
 
@@ -52,14 +597,207 @@ impl ModRegistry {
         };
 
         for dep in dependencies {
-            if !self.mods.contains_key(dep) {
-                broken_deps.push(dep.to_owned());
+            if !self.contains(dep) && self.provider_of(dep).is_none() {
+                broken_deps.push(dep.to_string());
             }
         }
 
         broken_deps
     }
 
+    /// Index of every dependency name to the mods depending on it.
+    pub fn dependents(&self) -> BTreeMap<&ModName, Vec<&str>> {
+        let mut index = BTreeMap::<_, Vec<_>>::new();
+
+        for (name, entry) in &self.mods {
+            for dep in entry.dependencies.iter().flatten() {
+                index.entry(dep).or_default().push(name.as_str());
+            }
+        }
+
+        index
+    }
+
+    /// The `top` mods with the most dependents, most first.
+    pub fn most_depended_on(&self, top: usize) -> Vec<(&str, usize)> {
+        let mut counts = self
+            .dependents()
+            .into_iter()
+            .filter_map(|(dep, dependents)| {
+                let name = self
+                    .mods
+                    .get_key_value(dep)
+                    .map(|(name, _)| name.as_str())
+                    .or_else(|| self.provider_of(dep))?;
+                Some((name, dependents.len()))
+            })
+            .fold(
+                BTreeMap::<&str, usize>::new(),
+                |mut counts, (name, count)| {
+                    *counts.entry(name).or_default() += count;
+                    counts
+                },
+            )
+            .into_iter()
+            .collect::<Vec<_>>();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts.truncate(top);
+        counts
+    }
+
+    /// The `top` mods installed longest ago, oldest first.
+    pub fn oldest(&self, top: usize) -> Vec<(&str, DateTime<Utc>)> {
+        let mut mods = self
+            .mods
+            .iter()
+            .filter_map(|(name, entry)| Some((name.as_str(), entry.installed_at?)))
+            .collect::<Vec<_>>();
+        mods.sort_by_key(|(_, at)| *at);
+        mods.truncate(top);
+        mods
+    }
+
+    /// Dependencies that no mod in the registry is or provides, with the mods declaring them.
+    pub fn orphaned_dependencies(&self) -> BTreeMap<&str, Vec<&str>> {
+        self.dependents()
+            .into_iter()
+            .filter(|(dep, _)| {
+                !self.mods.contains_key(*dep)
+                    && !self
+                        .mods
+                        .values()
+                        .any(|entry| entry.provides.iter().any(|p| p == *dep))
+            })
+            .map(|(dep, dependents)| (dep.as_str(), dependents))
+            .collect()
+    }
+
+    /// Aggregates over every mod, listing `top` of each ranking.
+    pub fn stats(&self, top: usize, size_of: impl Fn(&str, &ModEntry) -> u64) -> Stats<'_> {
+        let mut largest = self
+            .mods
+            .iter()
+            .map(|(name, entry)| (name.as_str(), size_of(name, entry)))
+            .collect::<Vec<_>>();
+        let size = largest.iter().map(|(_, size)| size).sum();
+        largest.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        largest.truncate(top);
+
+        let enabled = self.mods.values().filter(|entry| entry.installed).count();
+        Stats {
+            mods: self.mods.len(),
+            enabled,
+            disabled: self.mods.len() - enabled,
+            files: self.mods.values().map(|entry| entry.files.len()).sum(),
+            size,
+            largest,
+            most_depended_on: self.most_depended_on(top),
+            oldest: self.oldest(top),
+            orphaned_dependencies: self.orphaned_dependencies(),
+        }
+    }
+
+    /// Enabled mod that lists `name` in [`ModEntry::provides`].
+    pub fn provider_of(&self, name: &str) -> Option<&str> {
+        provider(&self.mods, name).map(|(provider, _)| provider)
+    }
+
+    /// Enabled mods that can't be enabled alongside `name`, declared by either side.
+    pub fn enabled_conflicts(&self, name: &str) -> Vec<&str> {
+        let declared = self
+            .get(name)
+            .map(|entry| entry.conflicts.as_slice())
+            .unwrap_or_default();
+
+        self.declared_conflicts(name, declared)
+    }
+
+    /// Like [`ModRegistry::enabled_conflicts`], with `name` declaring `declared`.
+    pub fn declared_conflicts(&self, name: &str, declared: &[ModName]) -> Vec<&str> {
+        self.mods
+            .iter()
+            .filter(|(other, entry)| {
+                *other != name
+                    && entry.installed
+                    && (declared.contains(other) || entry.conflicts.iter().any(|c| c == name))
+            })
+            .map(|(other, _)| other.as_str())
+            .collect()
+    }
+
+    /// Mod names similar to `name`, closest first.
+    pub fn similar_names(&self, name: &str) -> Vec<&str> {
+        let name = normalize_name(name);
+        let mut scored = self
+            .mods
+            .keys()
+            .map(|key| {
+                (
+                    strsim::jaro_winkler(&name, &normalize_name(key)),
+                    key.as_str(),
+                )
+            })
+            .filter(|(score, _)| *score >= SUGGESTION_THRESHOLD)
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        scored.into_iter().map(|(_, key)| key).collect()
+    }
+
+    /// Find the registry key for `name`.
+    ///
+    /// With `fuzzy`, a single similar name is accepted as well.
+    pub fn resolve_name(&self, name: &str, fuzzy: bool) -> Option<&str> {
+        if let Some((key, _)) = self.mods.get_key_value(&ModName::from(name)) {
+            return Some(key);
+        }
+
+        match self.similar_names(name).as_slice() {
+            [only] if fuzzy => Some(only),
+            _ => None,
+        }
+    }
+
+    /// Find the mod owning `path`.
+    pub fn owner_of(&self, path: &str) -> Option<&str> {
+        self.mods
+            .iter()
+            .find(|(_, entry)| entry.files.iter().any(|f| f == path))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Index of every path to the mods shipping it.
+    ///
+    /// The mod whose copy is deployed comes first, followed by those it shadows.
+    pub fn ownership(&self) -> BTreeMap<&str, Vec<&str>> {
+        let mut index = BTreeMap::<_, Vec<_>>::new();
+
+        for (name, entry) in &self.mods {
+            for file in &entry.files {
+                index
+                    .entry(file.as_str())
+                    .or_default()
+                    .insert(0, name.as_str());
+            }
+        }
+        for (name, entry) in &self.mods {
+            for file in &entry.shadowed {
+                index.entry(file.as_str()).or_default().push(name.as_str());
+            }
+        }
+
+        index
+    }
+
+    /// Paths shipped by more than one mod, limited to those shipped by `name` if given.
+    pub fn conflicts(&self, name: Option<&str>) -> BTreeMap<&str, Vec<&str>> {
+        self.ownership()
+            .into_iter()
+            .filter(|(_, owners)| owners.len() > 1)
+            .filter(|(_, owners)| name.is_none_or(|name| owners.contains(&name)))
+            .collect()
+    }
+
     /// Check if paths are owned by another mod already.
     ///
     /// Returns a [`Vec`] with the tuple `(owned_mod_name, path)`.
@@ -70,40 +808,201 @@ impl ModRegistry {
         S: AsRef<str>,
     {
         let mod_name = mod_name.as_ref();
-        let mut overlaps = vec![];
-        let incoming = paths.into_iter().map(Into::into).collect::<Vec<_>>();
+        let owners = self
+            .mods
+            .iter()
+            .filter(|(name, _)| *name != mod_name)
+            .flat_map(|(name, entry)| entry.files.iter().map(move |file| (file.as_str(), name)))
+            .collect::<HashMap<_, _>>();
 
-        for path in incoming {
-            for (name, mod_entry) in &self.mods {
-                if mod_entry.files.iter().any(|f| f == &path) && *name != mod_name {
-                    overlaps.push((name.to_owned(), path.clone()));
-                }
+        paths
+            .into_iter()
+            .map(Into::into)
+            .filter_map(|path| Some((owners.get(path.as_str())?.to_string(), path)))
+            .collect()
+    }
+
+    /// Render [`ModRegistry::conflicts`], marking which mod's copy of each path is deployed.
+    #[allow(unused_must_use)]
+    pub fn conflicts_report(&self, name: Option<&str>) -> String {
+        let conflicts = self.conflicts(name);
+        let mut out = String::new();
+
+        if conflicts.is_empty() {
+            writeln!(&mut out, "No conflicts");
+            return out;
+        }
+
+        let mut pairs = BTreeMap::<_, usize>::new();
+        for owners in conflicts.values() {
+            for &loser in &owners[1..] {
+                *pairs.entry((owners[0], loser)).or_default() += 1;
+            }
+        }
+
+        for ((winner, loser), count) in pairs {
+            writeln!(
+                &mut out,
+                "{style_bold}{color_green}{winner}{style_reset} overrides {style_bold}{color_red}{loser}{style_reset} ({count} {})",
+                if count == 1 { "file" } else { "files" }
+            );
+        }
+        writeln!(&mut out);
+
+        for (path, owners) in &conflicts {
+            writeln!(&mut out, "{style_bold}{path}{style_reset}");
+            writeln!(&mut out, "  {color_green}✔{style_reset} {}", owners[0]);
+            for loser in &owners[1..] {
+                writeln!(&mut out, "  {color_red}✘{style_reset} {loser}");
+            }
+        }
+
+        render::finish(out)
+    }
+
+    /// Select and sort mods.
+    ///
+    /// `size_of` is only called when sorting by [`SortKey::Size`].
+    pub fn query<'a>(
+        &'a self,
+        query: &ModQuery,
+        size_of: impl Fn(&str, &ModEntry) -> u64,
+    ) -> Vec<&'a str> {
+        let mut mods = self
+            .mods
+            .iter()
+            .filter(|(_, entry)| {
+                query
+                    .enabled
+                    .is_none_or(|enabled| entry.installed == enabled)
+            })
+            .filter(|(name, _)| !query.missing_deps || !self.unsatisfied_deps(*name).is_empty())
+            .filter(|(_, entry)| query.tag.as_ref().is_none_or(|tag| entry.has_tag(tag)))
+            .filter(|(_, entry)| {
+                query.kind.is_none_or(|kind| {
+                    entry
+                        .deployed_files()
+                        .any(|file| FileKind::of(file) == kind)
+                })
+            })
+            .filter(|(name, _)| {
+                query
+                    .pattern
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.matches(name))
+            })
+            .collect::<Vec<_>>();
+
+        match query.sort {
+            SortKey::Name => {}
+            SortKey::InstalledAt => {
+                mods.sort_by_key(|(_, entry)| (entry.installed_at.is_none(), entry.installed_at))
             }
+            SortKey::Version => mods.sort_by(|(_, a), (_, b)| a.version.cmp(&b.version)),
+            SortKey::Size => mods.sort_by_cached_key(|(name, entry)| size_of(name, entry)),
         }
 
-        overlaps
+        mods.into_iter().map(|(name, _)| name.as_str()).collect()
     }
 
+    /// Check if an enabled mod is flagged as incompatible with `game_version`.
+    ///
+    /// Mods without any listed game versions are assumed to be compatible.
+    pub fn incompatible(&self, name: &str, game_version: &str) -> bool {
+        self.get(name).is_some_and(|entry| {
+            entry.installed
+                && entry
+                    .game_versions
+                    .as_ref()
+                    .is_some_and(|versions| !game::supports(versions, game_version))
+        })
+    }
+
+    /// How healthy `name` is, from its dependencies, conflicts and game versions, and the state
+    /// of its `files`.
+    pub fn health(
+        &self,
+        name: &str,
+        game_version: Option<&str>,
+        files: &FileHealth,
+    ) -> HealthLevel {
+        let Some(entry) = self.get(name) else {
+            return HealthLevel::Healthy;
+        };
+
+        if !self.unsatisfied_deps(name).is_empty()
+            || (entry.installed && !self.enabled_conflicts(name).is_empty())
+            || files.missing > 0
+        {
+            HealthLevel::Broken
+        } else if files.modified > 0
+            || files
+                .deep
+                .as_ref()
+                .is_some_and(|deep| !deep.empty.is_empty())
+            || game_version.is_some_and(|version| self.incompatible(name, version))
+        {
+            HealthLevel::Warning
+        } else if game_version.is_some() && entry.game_versions.is_none() {
+            HealthLevel::Unknown
+        } else {
+            HealthLevel::Healthy
+        }
+    }
+
+    /// Render the status of `names`, usually picked with [`ModRegistry::query`], with the
+    /// state of their files from `files`.
     #[allow(unused_must_use)]
-    pub fn status(&self, json: bool) -> (String, i32) {
+    pub fn status(
+        &self,
+        json: bool,
+        game_version: Option<&str>,
+        names: &[&str],
+        files: &BTreeMap<&str, FileHealth>,
+    ) -> (String, i32) {
         use inline_colorization::*;
 
         let mut ret = 0;
         let mut out = String::new();
         let mut statuses = vec![];
 
-        for (mod_name, contents) in &self.mods {
+        for &mod_name in names {
+            let Some(contents) = self.get(mod_name) else {
+                continue;
+            };
+
             let deps: HashSet<_> = self.unsatisfied_deps(mod_name).into_iter().collect();
             let dependencies: Vec<_> = contents
                 .dependencies
                 .iter()
                 .flat_map(|deps| deps.iter())
-                .filter(|dep| !deps.contains(*dep))
-                .cloned()
+                .filter(|dep| !deps.contains(dep.as_str()))
+                .map(ModName::as_str)
                 .collect();
 
-            if !deps.is_empty() {
-                ret = 1;
+            let missing_optional = contents
+                .optional_dependencies
+                .iter()
+                .filter(|dep| {
+                    !self.get(dep).is_some_and(|entry| entry.installed)
+                        && self.provider_of(dep).is_none()
+                })
+                .map(ModName::as_str)
+                .collect::<Vec<_>>();
+
+            let conflicts = if contents.installed {
+                self.enabled_conflicts(mod_name)
+            } else {
+                vec![]
+            };
+
+            let incompatible =
+                game_version.is_some_and(|version| self.incompatible(mod_name, version));
+            let file_health = files.get(mod_name).cloned().unwrap_or_default();
+            let health = self.health(mod_name, game_version, &file_health);
+
+            if health == HealthLevel::Broken {
+                ret = ErrorClass::Problems.exit_code().into();
             }
 
             if json {
@@ -114,15 +1013,33 @@ impl ModRegistry {
                     installed_at: contents.installed_at.map(|dt| dt.to_rfc3339()),
                     missing_dependencies: deps.into_iter().collect(),
                     dependencies,
+                    optional_dependencies: &contents.optional_dependencies,
+                    missing_optional_dependencies: missing_optional,
+                    conflicts,
+                    incompatible,
+                    health,
+                    missing_files: file_health.missing,
+                    modified_files: file_health.modified,
+                    deep: files.get(mod_name).and_then(|health| health.deep.as_ref()),
+                    pinned: contents.pinned,
+                    author: contents.author.as_deref(),
+                    description: contents.description.as_deref(),
+                    url: contents.url.as_deref(),
+                    tags: &contents.tags,
+                    note: &contents.note,
+                    files: contents.file_kinds(),
                 });
             } else {
                 writeln!(
                     &mut out,
-                    "{style_bold}*{style_reset} {style_bold}{color_yellow}Name{style_reset}: `{mod_name}`"
+                    "{} {style_bold}{color_yellow}{}{style_reset}: `{mod_name}`",
+                    health.glyph(),
+                    tr("status-name")
                 );
                 writeln!(
                     &mut out,
-                    "  - Enabled: {}",
+                    "  - {}: {}",
+                    tr("status-enabled"),
                     if contents.installed {
                         format!("{color_green}true{style_reset}")
                     } else {
@@ -131,26 +1048,157 @@ impl ModRegistry {
                 );
                 writeln!(
                     &mut out,
-                    "  - Version: {color_cyan}{}{style_reset}",
+                    "  - {}: {color_cyan}{}{style_reset}",
+                    tr("status-version"),
                     contents.version
                 );
+                if let Some(author) = &contents.author {
+                    writeln!(&mut out, "  - {}: {author}", tr("status-author"));
+                }
                 if let Some(installed_at) = contents.installed_at {
                     writeln!(
                         &mut out,
-                        "  - Installed: {}",
+                        "  - {}: {}",
+                        tr("status-installed"),
                         HumanTime::from(installed_at - Utc::now())
                     );
                 }
+                if let Some(description) = &contents.description {
+                    // Fomod descriptions often run over several lines.
+                    writeln!(
+                        &mut out,
+                        "  - {}: {}",
+                        tr("status-description"),
+                        description.lines().collect::<Vec<_>>().join("\n    ")
+                    );
+                }
+                if let Some(url) = &contents.url {
+                    writeln!(
+                        &mut out,
+                        "  - {}: {color_blue}{url}{style_reset}",
+                        tr("status-homepage")
+                    );
+                }
+                if contents.pinned {
+                    writeln!(
+                        &mut out,
+                        "  - {}: {color_yellow}true{style_reset}",
+                        tr("status-pinned")
+                    );
+                }
+                if !contents.tags.is_empty() {
+                    writeln!(
+                        &mut out,
+                        "  - {}: {color_magenta}{}{style_reset}",
+                        tr("status-tags"),
+                        contents.tags.join(", ")
+                    );
+                }
+                if !contents.note.is_empty() {
+                    writeln!(&mut out, "  - {}: {}", tr("status-note"), contents.note);
+                }
+                let kinds = contents.file_kinds();
+                if !kinds.is_empty() {
+                    writeln!(
+                        &mut out,
+                        "  - {}: {}",
+                        tr("status-files"),
+                        kinds
+                            .iter()
+                            .map(|(kind, count)| format!("{count} {kind}"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                if file_health.missing > 0 {
+                    writeln!(
+                        &mut out,
+                        "  - {color_red}{}: {}{style_reset}",
+                        tr("status-missing-files"),
+                        file_health.missing
+                    );
+                }
+                if let Some(deep) = &file_health.deep {
+                    for path in &deep.missing {
+                        writeln!(
+                            &mut out,
+                            "      > `{color_red}{}{style_reset}`",
+                            path.display()
+                        );
+                    }
+                    if !deep.empty.is_empty() {
+                        writeln!(
+                            &mut out,
+                            "  - {color_yellow}{}: {}{style_reset}",
+                            tr("status-empty-files"),
+                            deep.empty.len()
+                        );
+                        for path in &deep.empty {
+                            writeln!(
+                                &mut out,
+                                "      > `{color_yellow}{}{style_reset}`",
+                                path.display()
+                            );
+                        }
+                    }
+                }
+                if file_health.modified > 0 {
+                    writeln!(
+                        &mut out,
+                        "  - {color_yellow}{}: {}{style_reset}",
+                        tr("status-modified-files"),
+                        file_health.modified
+                    );
+                }
+                if incompatible {
+                    writeln!(
+                        &mut out,
+                        "  - {color_yellow}{}{style_reset}",
+                        tr_with(
+                            "status-incompatible",
+                            &[("version", &game_version.unwrap_or_default())]
+                        )
+                    );
+                }
+                if !conflicts.is_empty() {
+                    writeln!(&mut out, "  - {}:", tr("status-conflicts"));
+                    for other in &conflicts {
+                        writeln!(&mut out, "      > `{color_red}{other}{style_reset}`");
+                    }
+                }
                 if !deps.is_empty() {
-                    writeln!(&mut out, "  - Missing dependencies:");
+                    writeln!(&mut out, "  - {}:", tr("status-missing-dependencies"));
                     for dep in &deps {
                         writeln!(&mut out, "      > `{color_red}{dep}{style_reset}`");
                     }
                 }
                 if !dependencies.is_empty() {
-                    writeln!(&mut out, "  - Dependencies:");
+                    writeln!(&mut out, "  - {}:", tr("status-dependencies"));
                     for dep in dependencies {
-                        writeln!(&mut out, "      > `{dep}`");
+                        match self.provider_of(dep) {
+                            Some(provider) if !self.contains(dep) => {
+                                writeln!(
+                                    &mut out,
+                                    "      > `{dep}` ({})",
+                                    tr_with("status-provided-by", &[("provider", &provider)])
+                                )
+                            }
+                            _ => writeln!(&mut out, "      > `{dep}`"),
+                        };
+                    }
+                }
+                if !contents.optional_dependencies.is_empty() {
+                    writeln!(&mut out, "  - {}:", tr("status-optional-dependencies"));
+                    for dep in &contents.optional_dependencies {
+                        if missing_optional.contains(&dep.as_str()) {
+                            writeln!(
+                                &mut out,
+                                "      ◇ `{color_cyan}{dep}{style_reset}` ({})",
+                                tr("status-not-enabled")
+                            );
+                        } else {
+                            writeln!(&mut out, "      ◇ `{dep}`");
+                        }
                     }
                 }
             }
@@ -162,7 +1210,7 @@ impl ModRegistry {
                 ret,
             )
         } else {
-            (out, ret)
+            (render::finish(out), ret)
         }
     }
 
@@ -174,7 +1222,7 @@ impl ModRegistry {
                 "* {style_bold}{mod_name}{style_reset} v{}",
                 entry.version
             ));
-            Self::build_tree(mod_name, &self.mods, &mut builder, &mut seen);
+            self.build_tree(mod_name, &mut builder, &mut seen);
 
             let mut buffer = Cursor::new(Vec::new());
             let _ = write_tree(&builder.build(), &mut buffer);
@@ -183,20 +1231,15 @@ impl ModRegistry {
             out.push('\n');
         }
 
-        out
+        render::finish(out)
     }
 
-    fn build_tree(
-        mod_name: &str,
-        map: &BTreeMap<String, ModEntry>,
-        builder: &mut TreeBuilder,
-        seen: &mut HashSet<String>,
-    ) {
-        if !seen.insert(mod_name.to_string()) {
+    fn build_tree(&self, mod_name: &str, builder: &mut TreeBuilder, seen: &mut HashSet<ModName>) {
+        if !seen.insert(mod_name.into()) {
             return;
         }
 
-        if let Some(entry) = map.get(mod_name) {
+        if let Some(entry) = self.get(mod_name) {
             if !entry.installed {
                 builder
                 .begin_child(format!(
@@ -209,7 +1252,7 @@ impl ModRegistry {
             let deps = entry.dependencies.as_deref().unwrap_or(&[]);
 
             for dep in deps {
-                if let Some(dep_entry) = map.get(dep) {
+                if let Some(dep_entry) = self.get(dep) {
                     if !dep_entry.installed {
                         builder.begin_child(format!(
                         "{style_bold}{color_yellow}⚠{style_reset} {style_bold}{dep}{style_reset} v{} (disabled)",
@@ -221,9 +1264,16 @@ impl ModRegistry {
                         "{style_bold}{color_green}✔{style_reset} {style_bold}{dep}{style_reset} v{}",
                         dep_entry.version
                     ));
-                        Self::build_tree(dep, map, builder, seen);
+                        self.build_tree(dep, builder, seen);
                         builder.end_child();
                     }
+                } else if let Some((provider, provider_entry)) = provider(&self.mods, dep) {
+                    builder.begin_child(format!(
+                        "{style_bold}{color_green}✔{style_reset} {style_bold}{dep}{style_reset} provided by {style_bold}{provider}{style_reset} v{}",
+                        provider_entry.version
+                    ));
+                    self.build_tree(provider, builder, seen);
+                    builder.end_child();
                 } else {
                     builder
                         .begin_child(format!(
@@ -232,6 +1282,33 @@ impl ModRegistry {
                         .end_child();
                 }
             }
+
+            for dep in &entry.optional_dependencies {
+                match self.get(dep) {
+                    Some(dep_entry) if dep_entry.installed => {
+                        builder.begin_child(format!(
+                            "{style_bold}{color_cyan}◇{style_reset} {style_bold}{dep}{style_reset} v{} (optional)",
+                            dep_entry.version
+                        ));
+                        self.build_tree(dep, builder, seen);
+                        builder.end_child();
+                    }
+                    Some(_) => {
+                        builder
+                            .begin_child(format!(
+                                "{style_bold}{color_cyan}◇{style_reset} {style_bold}{dep}{style_reset} (optional, disabled)"
+                            ))
+                            .end_child();
+                    }
+                    None => {
+                        builder
+                            .begin_child(format!(
+                                "{style_bold}{color_cyan}◇{style_reset} {style_bold}{dep}{style_reset} (optional, not installed)"
+                            ))
+                            .end_child();
+                    }
+                }
+            }
         } else {
             builder
                 .begin_child(format!("{style_bold}{color_red}✘{style_reset} {mod_name}"))
@@ -0,0 +1,221 @@
+//! Finding mods by what they are rather than their exact name, behind `vapor search`.
+//!
+//! Every word of the query has to match something about a mod: its name, tags, description,
+//! author, note, files or dependencies. Matches in the name count most and matches in file paths
+//! least, and a whole word counts more than part of one, which counts more than a near miss.
+
+use std::fmt::Write;
+
+use inline_colorization::*;
+use serde::Serialize;
+
+use super::registry::{ModEntry, ModRegistry};
+use crate::render;
+
+/// How close a word has to be to count as a near miss of a query word.
+const FUZZY_THRESHOLD: f64 = 0.88;
+
+/// What a query word matched.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Name,
+    Tag,
+    Description,
+    Author,
+    Note,
+    Dependency,
+    File,
+}
+
+impl Field {
+    fn weight(self) -> f64 {
+        match self {
+            Self::Name => 4.0,
+            Self::Tag => 3.0,
+            Self::Description => 2.0,
+            Self::Author | Self::Note | Self::Dependency => 1.5,
+            Self::File => 1.0,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Tag => "tag",
+            Self::Description => "description",
+            Self::Author => "author",
+            Self::Note => "note",
+            Self::Dependency => "dependency",
+            Self::File => "file",
+        }
+    }
+}
+
+/// A mod matching a search, see [`search`].
+#[derive(Serialize, Debug)]
+pub struct SearchHit<'a> {
+    pub name: &'a str,
+    pub enabled: bool,
+    pub score: f64,
+    /// The best match of each query word, as the field and the text it matched in.
+    pub matches: Vec<(Field, String)>,
+}
+
+/// Mods matching every word of `query`, best first, at most `limit` of them.
+pub fn search<'a>(toml: &'a ModRegistry, query: &str, limit: usize) -> Vec<SearchHit<'a>> {
+    let words = query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        return vec![];
+    }
+
+    let mut hits = toml
+        .mods
+        .iter()
+        .filter_map(|(name, entry)| {
+            let fields = fields(name.as_str(), entry);
+            let mut score = 0.0;
+            let mut matches: Vec<(Field, String)> = vec![];
+
+            for word in &words {
+                let (best, field, text) = fields
+                    .iter()
+                    .map(|(field, text)| (field.weight() * similarity(word, text), *field, text))
+                    .max_by(|(a, ..), (b, ..)| a.total_cmp(b))?;
+                if best <= 0.0 {
+                    return None;
+                }
+                score += best;
+                if !matches.iter().any(|(f, t)| *f == field && t == text) {
+                    matches.push((field, text.clone()));
+                }
+            }
+
+            Some(SearchHit {
+                name: name.as_str(),
+                enabled: entry.installed,
+                score,
+                matches,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.name.cmp(b.name)));
+    hits.truncate(limit);
+    hits
+}
+
+/// Everything about a mod a query word can match.
+fn fields(name: &str, entry: &ModEntry) -> Vec<(Field, String)> {
+    let mut fields = vec![(Field::Name, name.to_string())];
+    fields.extend(entry.tags.iter().map(|tag| (Field::Tag, tag.clone())));
+    fields.extend(
+        entry
+            .description
+            .iter()
+            .map(|description| (Field::Description, description.clone())),
+    );
+    fields.extend(
+        entry
+            .author
+            .iter()
+            .map(|author| (Field::Author, author.clone())),
+    );
+    if !entry.note.is_empty() {
+        fields.push((Field::Note, entry.note.clone()));
+    }
+    fields.extend(
+        entry
+            .dependencies
+            .iter()
+            .flatten()
+            .chain(&entry.optional_dependencies)
+            .map(|dependency| (Field::Dependency, dependency.to_string())),
+    );
+    fields.extend(entry.files.iter().map(|file| (Field::File, file.clone())));
+    fields
+}
+
+/// How well `word`, lowercase, matches `text`: 1 for a whole word, less for part of one or a
+/// near miss, and 0 for no match.
+fn similarity(word: &str, text: &str) -> f64 {
+    let text = text.to_lowercase();
+    let mut best = 0.0_f64;
+
+    for part in text.split(|c: char| !c.is_alphanumeric()) {
+        if part.is_empty() {
+            continue;
+        }
+        let score = if part == word {
+            1.0
+        } else if part.starts_with(word) {
+            0.8
+        } else if part.contains(word) {
+            0.6
+        } else {
+            let similar = strsim::jaro_winkler(word, part);
+            if similar >= FUZZY_THRESHOLD {
+                0.5 * similar
+            } else {
+                0.0
+            }
+        };
+        best = best.max(score);
+    }
+
+    // Words with punctuation of their own, like `mod.archive`, span several parts.
+    if best < 0.6 && text.contains(word) {
+        best = 0.6;
+    }
+    best
+}
+
+/// Render `hits` as a list, or as JSON.
+#[allow(unused_must_use)]
+pub fn render(hits: &[SearchHit], json: bool) -> String {
+    if json {
+        return serde_json::to_string_pretty(hits).expect("could not format json");
+    }
+
+    let mut out = String::new();
+    if hits.is_empty() {
+        writeln!(&mut out, "No mods found");
+        return out;
+    }
+
+    for hit in hits {
+        let state = if hit.enabled {
+            format!("{color_green}enabled{color_reset}")
+        } else {
+            format!("{color_red}disabled{color_reset}")
+        };
+        writeln!(&mut out, "{style_bold}{}{style_reset} ({state})", hit.name);
+        for (field, text) in &hit.matches {
+            if *field == Field::Name {
+                continue;
+            }
+            writeln!(
+                &mut out,
+                "  {color_cyan}{}:{color_reset} {}",
+                field.label(),
+                excerpt(text)
+            );
+        }
+    }
+
+    render::finish(out)
+}
+
+/// `text` on one line, cut short if long.
+fn excerpt(text: &str) -> String {
+    const MAX: usize = 80;
+
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(MAX) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line,
+    }
+}
@@ -0,0 +1,339 @@
+//! Archives split into numbered parts, like `mod.zip.001`, `mod.zip.002`, ..., and joining them
+//! back into one.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+use tracing::info;
+use zip::ZipArchive;
+
+use crate::download;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum SplitError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(
+        "{} {} of `{archive}` missing",
+        missing.len(),
+        if missing.len() == 1 { "part is" } else { "parts are" }
+    )]
+    #[diagnostic(help(
+        "Download the missing parts next to the first one: {}",
+        missing.join(", ")
+    ))]
+    MissingParts {
+        archive: String,
+        missing: Vec<String>,
+    },
+    #[error("`{archive}` is cut off after `{last}`")]
+    #[diagnostic(help("Download the parts after `{last}` next to the first one"))]
+    Incomplete { archive: String, last: String },
+    #[error("`{archive}` is a {format} split into parts, which Vapor can't extract")]
+    #[diagnostic(help("Extract it with another tool, then add the directory it extracts to"))]
+    Unsupported { archive: String, format: Format },
+}
+
+/// What a split archive is once joined.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// `mod.zip.001`, split by 7-Zip and the like.
+    Zip,
+    /// `mod.7z.001`.
+    SevenZip,
+    /// `mod.part1.rar`.
+    Rar,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Zip => "zip",
+            Self::SevenZip => "7z archive",
+            Self::Rar => "RAR archive",
+        })
+    }
+}
+
+/// One file of a split archive, from its name.
+struct Part {
+    /// The name with the part number and extension taken off.
+    base: String,
+    format: Format,
+    number: u32,
+    /// Digits in the part number, as some tools pad them.
+    width: usize,
+}
+
+impl Part {
+    fn parse(name: &str) -> Option<Self> {
+        let number = |digits: &str| {
+            (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| digits.parse().ok())
+                .flatten()
+        };
+
+        let lower = name.to_ascii_lowercase();
+        if let Some(stem) = lower.strip_suffix(".rar")
+            && let Some(at) = stem.rfind(".part")
+            && let Some(n) = number(&stem[at + 5..])
+        {
+            return Some(Self {
+                base: name[..at].to_string(),
+                format: Format::Rar,
+                number: n,
+                width: stem.len() - at - 5,
+            });
+        }
+
+        let (rest, digits) = lower.rsplit_once('.')?;
+        let n = number(digits)?;
+        let (format, extension) = if rest.ends_with(".zip") {
+            (Format::Zip, ".zip")
+        } else if rest.ends_with(".7z") {
+            (Format::SevenZip, ".7z")
+        } else {
+            return None;
+        };
+        Some(Self {
+            base: name[..rest.len() - extension.len()].to_string(),
+            format,
+            number: n,
+            width: digits.len(),
+        })
+    }
+
+    /// File name of part `number` of the same archive.
+    fn name(&self, number: u32) -> String {
+        let (base, width) = (&self.base, self.width);
+        match self.format {
+            Format::Zip => format!("{base}.zip.{number:0width$}"),
+            Format::SevenZip => format!("{base}.7z.{number:0width$}"),
+            Format::Rar => format!("{base}.part{number:0width$}.rar"),
+        }
+    }
+}
+
+/// Every part of the split archive `path` is one of, in order, found next to it. `None` if it
+/// isn't part of a split archive.
+///
+/// Fails with [`SplitError::MissingParts`] if parts are missing before the last one found.
+pub fn parts(path: &Path) -> Result<Option<(Format, Vec<PathBuf>)>, SplitError> {
+    let Some(part) = path
+        .file_name()
+        .and_then(|name| Part::parse(&name.to_string_lossy()))
+    else {
+        return Ok(None);
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut found = vec![];
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(sibling) = Part::parse(&name)
+            && sibling.format == part.format
+            && sibling.base.eq_ignore_ascii_case(&part.base)
+        {
+            found.push((sibling.number, entry.path()));
+        }
+    }
+    found.sort();
+    found.dedup_by_key(|(number, _)| *number);
+    if found.is_empty() {
+        return Ok(None);
+    }
+
+    let last = found.last().map_or(part.number, |&(number, _)| number);
+    let missing = (1..=last)
+        .filter(|n| !found.iter().any(|(number, _)| number == n))
+        .map(|n| part.name(n))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(SplitError::MissingParts {
+            archive: part.name(1),
+            missing,
+        });
+    }
+
+    Ok(Some((
+        part.format,
+        found.into_iter().map(|(_, path)| path).collect(),
+    )))
+}
+
+/// Join the split archive `path` is part of into `dir`, returning the joined archive, or `path`
+/// itself if it isn't split.
+///
+/// Fails with [`SplitError::Incomplete`] if the joined archive doesn't open, as happens when the
+/// last parts are missing, and with [`SplitError::Unsupported`] for formats other than zip.
+pub fn join(path: &Path, dir: &Path) -> Result<PathBuf, SplitError> {
+    let Some((format, parts)) = parts(path)? else {
+        return Ok(path.to_path_buf());
+    };
+    let first = Part::parse(&file_name(&parts[0])).expect("parts are named like parts");
+    if format != Format::Zip {
+        return Err(SplitError::Unsupported {
+            archive: first.name(1),
+            format,
+        });
+    }
+
+    info!(archive = %path.display(), parts = parts.len(), "joining split archive");
+    let mut reader: Box<dyn Read> = Box::new(io::empty());
+    for part in &parts {
+        reader = Box::new(reader.chain(File::open(part)?));
+    }
+    let joined = download::save_to(reader, dir, &format!("{}.zip", first.base))?;
+
+    if File::open(&joined)
+        .map_err(zip::result::ZipError::from)
+        .and_then(ZipArchive::new)
+        .is_err()
+    {
+        let _ = fs::remove_file(&joined);
+        return Err(SplitError::Incomplete {
+            archive: first.name(1),
+            last: file_name(parts.last().unwrap_or(&parts[0])),
+        });
+    }
+    Ok(joined)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, zip_archive};
+
+    fn parsed(name: &str) -> Option<(String, Format, u32, usize)> {
+        Part::parse(name).map(|part| (part.base, part.format, part.number, part.width))
+    }
+
+    #[test]
+    fn parses_part_names() {
+        assert_eq!(
+            parsed("My Mod.zip.001"),
+            Some(("My Mod".to_string(), Format::Zip, 1, 3))
+        );
+        assert_eq!(
+            parsed("mod.7Z.12"),
+            Some(("mod".to_string(), Format::SevenZip, 12, 2))
+        );
+        assert_eq!(
+            parsed("mod.v1.2.part03.RAR"),
+            Some(("mod.v1.2".to_string(), Format::Rar, 3, 2))
+        );
+        for name in [
+            "mod.zip",
+            "mod.rar",
+            "mod.part.rar",
+            "mod.tar.001",
+            "mod.zip.1a",
+        ] {
+            assert!(parsed(name).is_none(), "{name}");
+        }
+    }
+
+    #[test]
+    fn names_other_parts_with_the_same_padding() {
+        let part = Part::parse("mod.zip.007").unwrap();
+        assert_eq!(part.name(1), "mod.zip.001");
+        assert_eq!(part.name(1000), "mod.zip.1000");
+        assert_eq!(
+            Part::parse("mod.part2.rar").unwrap().name(1),
+            "mod.part1.rar"
+        );
+    }
+
+    /// A zip archive of one file split into `count` parts in `dir`, returning their paths.
+    fn split_zip(dir: &Path, count: usize) -> Vec<PathBuf> {
+        let whole = dir.join("whole.zip");
+        zip_archive(&whole, &[("r6/scripts/mod.reds", "// ".repeat(100))]).unwrap();
+        let bytes = fs::read(&whole).unwrap();
+        fs::remove_file(&whole).unwrap();
+
+        bytes
+            .chunks(bytes.len().div_ceil(count))
+            .enumerate()
+            .map(|(i, chunk)| {
+                let path = dir.join(format!("mod.zip.{:03}", i + 1));
+                fs::write(&path, chunk).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_part_from_any_of_them() {
+        let dir = TempDir::new().unwrap();
+        let split = split_zip(dir.path(), 3);
+        fs::write(dir.path().join("other.zip.001"), "").unwrap();
+
+        let (format, found) = parts(&split[1]).unwrap().unwrap();
+        assert_eq!(format, Format::Zip);
+        assert_eq!(found, split);
+        assert!(parts(&dir.path().join("mod.zip")).unwrap().is_none());
+    }
+
+    #[test]
+    fn names_parts_missing_before_the_last() {
+        let dir = TempDir::new().unwrap();
+        let split = split_zip(dir.path(), 3);
+        fs::remove_file(&split[1]).unwrap();
+
+        assert!(matches!(
+            parts(&split[0]),
+            Err(SplitError::MissingParts { missing, .. }) if missing == ["mod.zip.002"]
+        ));
+    }
+
+    #[test]
+    fn joins_zips_and_notices_missing_last_parts() {
+        let dir = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        let split = split_zip(dir.path(), 3);
+
+        let joined = join(&split[0], out.path()).unwrap();
+        assert_eq!(
+            ZipArchive::new(File::open(&joined).unwrap()).unwrap().len(),
+            1
+        );
+        fs::remove_file(&joined).unwrap();
+
+        fs::remove_file(&split[2]).unwrap();
+        assert!(matches!(
+            join(&split[0], out.path()),
+            Err(SplitError::Incomplete { last, .. }) if last == "mod.zip.002"
+        ));
+    }
+
+    #[test]
+    fn refuses_to_join_other_formats() {
+        let dir = TempDir::new().unwrap();
+        let part = dir.path().join("mod.7z.001");
+        fs::write(&part, "7z").unwrap();
+
+        assert!(matches!(
+            join(&part, dir.path()),
+            Err(SplitError::Unsupported {
+                format: Format::SevenZip,
+                ..
+            })
+        ));
+    }
+}
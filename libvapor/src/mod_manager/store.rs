@@ -0,0 +1,246 @@
+//! Content-addressed storage of installed files under `.vapor/objects/`, so a file shipped by
+//! several mods, or kept across updates, is only on disk once.
+//!
+//! Each object is named by its SHA-256, and installed files are hardlinks to it. Removing an object
+//! never touches the files linked to it, it only stops new ones from sharing it.
+//!
+//! Since a write to one link shows up in all of them, files that are edited in place, like
+//! configs, are never stored, and [`Store::unshare`] copies a file out before `vapor edit` opens
+//! it.
+
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use tracing::debug;
+
+use super::fs::Fs;
+
+/// Objects of the store at `dir`, under `.vapor/objects` by default.
+pub struct Store<'a> {
+    pub dir: &'a Path,
+    pub fs: &'a Fs,
+}
+
+impl Store<'_> {
+    /// Where the object with SHA-256 `hash` lives, split on the first two digits so no one
+    /// directory gets too big.
+    pub fn object(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2.min(hash.len()));
+        self.dir.join(prefix).join(rest)
+    }
+
+    /// Share the file at `path`, whose SHA-256 is `hash`, with the store: link it to the object
+    /// if there is one, else make it the object. Returns whether it was already stored.
+    ///
+    /// A file that can't be linked, like one on another filesystem than the store, is left as it
+    /// is.
+    pub fn insert(&self, path: &Path, hash: &str) -> io::Result<bool> {
+        let object = self.object(hash);
+        if !object.exists() {
+            if let Some(parent) = object.parent() {
+                self.fs.create_dir_all(parent)?;
+            }
+            if let Err(err) = self.fs.link(path, &object, true) {
+                debug!(path = %path.display(), %err, "not storing file");
+            }
+            return Ok(false);
+        }
+
+        // Linked beside it first and renamed over it, so the file is never missing.
+        let temp = path.with_file_name(format!(
+            ".{}.vapor-link",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        if let Err(err) = self.fs.link(&object, &temp, true) {
+            debug!(path = %path.display(), %err, "not linking file to store");
+            return Ok(false);
+        }
+        self.fs.rename(&temp, path)?;
+        Ok(true)
+    }
+
+    /// Give the file at `path` a copy of its own if it's linked to an object, so writing to it
+    /// in place doesn't change every other file sharing it. Returns whether it was copied.
+    ///
+    /// The copy is made beside it and renamed over it, so the file is never missing.
+    pub fn unshare(&self, path: &Path) -> io::Result<bool> {
+        if self.fs.is_dry_run() || !is_shared(path)? {
+            return Ok(false);
+        }
+
+        let temp = path.with_file_name(format!(
+            ".{}.vapor-copy",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        fs::copy(path, &temp)?;
+        self.fs.rename(&temp, path)?;
+        Ok(true)
+    }
+
+    /// Every object in the store, as its SHA-256 and size.
+    pub fn objects(&self) -> io::Result<Vec<(String, u64)>> {
+        let mut objects = vec![];
+        let prefixes = match fs::read_dir(self.dir) {
+            Ok(prefixes) => prefixes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(objects),
+            Err(err) => return Err(err),
+        };
+
+        for prefix in prefixes.filter_map(Result::ok) {
+            let Ok(entries) = fs::read_dir(prefix.path()) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let size = entry.metadata().map_or(0, |meta| meta.len());
+                objects.push((
+                    format!(
+                        "{}{}",
+                        prefix.file_name().to_string_lossy(),
+                        entry.file_name().to_string_lossy()
+                    ),
+                    size,
+                ));
+            }
+        }
+
+        objects.sort();
+        Ok(objects)
+    }
+
+    /// Delete every object whose hash isn't in `referenced`, returning them with their sizes.
+    pub fn gc(&self, referenced: &BTreeSet<&str>) -> io::Result<Vec<(PathBuf, u64)>> {
+        let mut deleted = vec![];
+
+        for (hash, size) in self.objects()? {
+            if referenced.contains(hash.as_str()) {
+                continue;
+            }
+            let object = self.object(&hash);
+            self.fs.remove_file(&object)?;
+            deleted.push((object, size));
+        }
+
+        // Prefix directories left empty, which is the only way removing one succeeds.
+        if let Ok(prefixes) = fs::read_dir(self.dir) {
+            for prefix in prefixes.filter_map(Result::ok) {
+                let _ = self.fs.remove_dir(&prefix.path());
+            }
+        }
+
+        Ok(deleted)
+    }
+}
+
+/// Whether the file at `path` has other hardlinks to it.
+///
+/// Without a link count to go by, every file is taken to be shared.
+fn is_shared(path: &Path) -> io::Result<bool> {
+    let metadata = fs::metadata(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(metadata.nlink() > 1)
+    }
+    #[cfg(not(unix))]
+    Ok(metadata.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const HASH_A: &str = "aa11";
+    const HASH_B: &str = "bb22";
+
+    #[test]
+    fn splits_objects_by_prefix() {
+        let fs = Fs::default();
+        let store = Store {
+            dir: Path::new("objects"),
+            fs: &fs,
+        };
+        assert_eq!(store.object("abcdef"), Path::new("objects/ab/cdef"));
+        assert_eq!(store.object("a"), Path::new("objects/a"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_files_with_the_same_hash() {
+        let dir = TempDir::new().unwrap();
+        let fs = Fs::default();
+        let objects = dir.path().join("objects");
+        let store = Store {
+            dir: &objects,
+            fs: &fs,
+        };
+        let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+        fs::write(&first, "same").unwrap();
+        fs::write(&second, "same").unwrap();
+
+        assert!(!store.insert(&first, HASH_A).unwrap());
+        assert!(store.insert(&second, HASH_A).unwrap());
+        assert!(is_shared(&first).unwrap());
+        assert_eq!(fs::read_to_string(&second).unwrap(), "same");
+        assert_eq!(store.objects().unwrap(), [(HASH_A.to_string(), 4)]);
+        // Nothing is left beside the linked file.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unsharing_copies_the_file_out() {
+        let dir = TempDir::new().unwrap();
+        let fs = Fs::default();
+        let objects = dir.path().join("objects");
+        let store = Store {
+            dir: &objects,
+            fs: &fs,
+        };
+        let config = dir.path().join("config.ini");
+        fs::write(&config, "stored").unwrap();
+        store.insert(&config, HASH_A).unwrap();
+
+        let dry_run = Fs::dry_run();
+        let dry_store = Store {
+            dir: &objects,
+            fs: &dry_run,
+        };
+        assert!(!dry_store.unshare(&config).unwrap());
+        assert!(is_shared(&config).unwrap());
+
+        assert!(store.unshare(&config).unwrap());
+        assert!(!is_shared(&config).unwrap());
+        fs::write(&config, "edited").unwrap();
+        assert_eq!(fs::read_to_string(store.object(HASH_A)).unwrap(), "stored");
+        assert!(!store.unshare(&config).unwrap());
+    }
+
+    #[test]
+    fn collects_unreferenced_objects() {
+        let dir = TempDir::new().unwrap();
+        let fs = Fs::default();
+        let objects = dir.path().join("objects");
+        let store = Store {
+            dir: &objects,
+            fs: &fs,
+        };
+        assert!(store.objects().unwrap().is_empty());
+
+        let (kept, dropped) = (dir.path().join("kept"), dir.path().join("dropped"));
+        fs::write(&kept, "kept").unwrap();
+        fs::write(&dropped, "gone").unwrap();
+        store.insert(&kept, HASH_A).unwrap();
+        store.insert(&dropped, HASH_B).unwrap();
+
+        let deleted = store.gc(&BTreeSet::from([HASH_A])).unwrap();
+        assert_eq!(deleted, [(store.object(HASH_B), 4)]);
+        assert_eq!(store.objects().unwrap(), [(HASH_A.to_string(), 4)]);
+        assert!(!objects.join("bb").exists());
+        // Installed files outlive their objects.
+        assert_eq!(fs::read_to_string(&dropped).unwrap(), "gone");
+    }
+}
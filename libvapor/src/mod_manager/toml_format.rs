@@ -0,0 +1,150 @@
+//! Writing `mods.toml` so its diffs stay small enough to keep it in git.
+
+use toml_edit::{DocumentMut, Item, Table};
+
+use super::registry::ModRegistry;
+
+/// `registry` as TOML, formatted the same every time for the same registry.
+///
+/// Mods are sorted by name and fields keep their declaration order, so two saves of one registry
+/// are byte for byte equal.
+pub fn to_string(registry: &ModRegistry) -> Result<String, toml::ser::Error> {
+    let mut out = toml::to_string_pretty(registry)?;
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// `registry` as TOML, keeping the text of `old` for everything that didn't change.
+///
+/// Unchanged keys keep their order, formatting and comments, changed ones are rewritten in place,
+/// and new ones are added at the end of their table. Falls back to [`to_string`] if `old` isn't
+/// valid TOML.
+pub fn to_string_minimal(registry: &ModRegistry, old: &str) -> Result<String, toml::ser::Error> {
+    let new = to_string(registry)?;
+    let (Ok(mut old), Ok(new)) = (old.parse::<DocumentMut>(), new.parse::<DocumentMut>()) else {
+        return Ok(new);
+    };
+
+    merge(old.as_table_mut(), new.as_table());
+    Ok(old.to_string())
+}
+
+/// Make `old` hold what `new` does, touching only what differs.
+fn merge(old: &mut Table, new: &Table) {
+    let removed = old
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new.contains_key(key))
+        .collect::<Vec<_>>();
+    for key in removed {
+        old.remove(&key);
+    }
+
+    for (key, new_item) in new {
+        match old.get_mut(key) {
+            Some(Item::Table(old_table)) if new_item.is_table() => {
+                merge(old_table, new_item.as_table().expect("checked above"));
+            }
+            Some(old_item) if same(old_item, new_item) => {}
+            Some(old_item) => {
+                // Keep the comments and spacing around the key, only the value changes.
+                let decor = old_item.as_value().map(|value| value.decor().clone());
+                *old_item = new_item.clone();
+                if let (Some(decor), Some(value)) = (decor, old_item.as_value_mut()) {
+                    *value.decor_mut() = decor;
+                }
+            }
+            None => {
+                old.insert(key, new_item.clone());
+            }
+        }
+    }
+}
+
+/// Whether `a` and `b` are the same value, however they are formatted.
+fn same(a: &Item, b: &Item) -> bool {
+    let parse = |item: &Item| {
+        let mut doc = DocumentMut::new();
+        doc["value"] = item.clone();
+        doc.to_string().parse::<toml::Table>().ok()
+    };
+
+    matches!((parse(a), parse(b)), (Some(a), Some(b)) if a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mod_manager::{registry::ModEntry, version::ModVersion};
+
+    fn registry() -> ModRegistry {
+        let mut registry: ModRegistry = toml::from_str("schema_version = 2").unwrap();
+        for name in ["Alpha", "Beta"] {
+            let entry = ModEntry {
+                version: ModVersion::new("1.0"),
+                installed: true,
+                files: vec![format!("r6/scripts/{name}.reds")],
+                ..Default::default()
+            };
+            registry.mods.insert(name.into(), entry);
+        }
+        registry
+    }
+
+    fn parsed(text: &str) -> toml::Table {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn saves_are_byte_for_byte_equal() {
+        let registry = registry();
+        let text = to_string(&registry).unwrap();
+        assert_eq!(text, to_string(&registry).unwrap());
+        assert!(text.ends_with('\n'));
+        assert!(text.find("[mods.Alpha]").unwrap() < text.find("[mods.Beta]").unwrap());
+    }
+
+    #[test]
+    fn an_unchanged_registry_keeps_its_text() {
+        let registry = registry();
+        let old = format!("# Kept in git\n{}", to_string(&registry).unwrap());
+        assert_eq!(to_string_minimal(&registry, &old).unwrap(), old);
+    }
+
+    #[test]
+    fn changes_keep_comments_and_formatting_around_them() {
+        let mut registry = registry();
+        let old = to_string(&registry)
+            .unwrap()
+            .replacen(
+                "version = \"1.0\"",
+                "version  =  \"1.0\" # from the Nexus",
+                1,
+            )
+            .replace("[mods.Beta]", "# Beta goes last\n[mods.Beta]");
+
+        registry.mods.get_mut(&"Alpha".into()).unwrap().version = ModVersion::new("2.0");
+        registry.mods.remove(&"Beta".into());
+        registry.mods.insert("Gamma".into(), ModEntry::default());
+
+        let new = to_string_minimal(&registry, &old).unwrap();
+        assert!(
+            new.contains("version  =  \"2.0\" # from the Nexus"),
+            "{new}"
+        );
+        assert!(!new.contains("[mods.Beta]"));
+        assert!(new.find("[mods.Alpha]").unwrap() < new.find("[mods.Gamma]").unwrap());
+        assert_eq!(parsed(&new), parsed(&to_string(&registry).unwrap()));
+    }
+
+    #[test]
+    fn broken_text_is_rewritten_whole() {
+        let registry = registry();
+        assert_eq!(
+            to_string_minimal(&registry, "[mods\nbroken").unwrap(),
+            to_string(&registry).unwrap()
+        );
+    }
+}
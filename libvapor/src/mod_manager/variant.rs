@@ -0,0 +1,81 @@
+//! Archives bundling several variants of a mod side by side, like `Option A/r6/...` and
+//! `Option B/r6/...`, and installing just one of them.
+
+/// Directories above the first of `root_dirs` in `files`, when there are several, sorted.
+///
+/// A single one is just how the archive was packed, not a choice.
+pub fn variants(files: &[String], root_dirs: &[String]) -> Vec<String> {
+    let mut variants = files
+        .iter()
+        .filter_map(|file| prefix(file, root_dirs))
+        .collect::<Vec<_>>();
+    variants.sort();
+    variants.dedup();
+
+    if variants.len() < 2 {
+        return vec![];
+    }
+    variants
+}
+
+/// Where `file` goes when installing `variant` out of `variants`: under the variant with it
+/// stripped, kept as is outside of all of them, and nowhere under any other.
+pub fn place(file: &str, variant: Option<&str>, variants: &[String]) -> Option<String> {
+    let Some(variant) = variant else {
+        return Some(file.to_string());
+    };
+
+    let normalized = file.replace('\\', "/");
+    let under = |dir: &str| {
+        normalized
+            .strip_prefix(dir)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .map(ToString::to_string)
+    };
+    if let Some(rest) = under(variant) {
+        return Some(rest);
+    }
+    if variants.iter().any(|other| under(other).is_some()) {
+        return None;
+    }
+    Some(file.to_string())
+}
+
+/// `file` relative to the variant of `variants` it's under, if any, so relative to the game
+/// directory either way.
+pub fn strip(file: &str, variants: &[String]) -> String {
+    variants
+        .iter()
+        .find_map(|variant| place(file, Some(variant), variants))
+        .unwrap_or_else(|| file.to_string())
+}
+
+/// `variants` for a diagnostic.
+pub fn listing(variants: &[String]) -> String {
+    if variants.is_empty() {
+        return "It doesn't bundle any".to_string();
+    }
+    format!(
+        "Variants are: {}",
+        variants
+            .iter()
+            .map(|variant| format!("`{variant}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// The directories above the first of `root_dirs` in `file`, if it's under one at all.
+fn prefix(file: &str, root_dirs: &[String]) -> Option<String> {
+    let components = file.split(['/', '\\']).collect::<Vec<_>>();
+    // The last component is the file itself, and REDmods sit in `mods/` with a root of their own.
+    let root = components[..components.len() - 1]
+        .iter()
+        .position(|component| {
+            component.eq_ignore_ascii_case("mods")
+                || root_dirs
+                    .iter()
+                    .any(|dir| dir.eq_ignore_ascii_case(component))
+        })?;
+    (root > 0).then(|| components[..root].join("/"))
+}
@@ -0,0 +1,257 @@
+//! Mod versions, ordered like semver where they look like it, so updates can be told apart from
+//! downgrades whatever scheme a mod author picked.
+
+use std::{cmp::Ordering, fmt, ops::Deref};
+
+use serde::{Deserialize, Serialize};
+
+/// The version of a mod, spelled the way it was given.
+///
+/// Versions like `v1.2`, `1.2.0` and `1.2.0-beta.1` are ordered by semver rules, with missing
+/// components counting as `0`. Only `alpha`, `beta`, `pre` and `rc` start a pre-release, so
+/// `1.2-hotfix` comes after `1.2`. Letters after a number, like in `1.2a`, order after the number
+/// alone and before the next one, and words order alphabetically among themselves. Two versions
+/// are only equal if they're spelled the same.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct ModVersion(String);
+
+/// What versions are ordered by: components of the version, then of its pre-release.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    core: Vec<Part>,
+    pre: Pre,
+}
+
+/// One component, numbers ordering before words like semver pre-release identifiers.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Part {
+    Number(u64),
+    Word(String),
+}
+
+/// A pre-release comes before the release it leads up to.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Pre {
+    Release(Vec<Part>),
+    Final,
+}
+
+/// Words that start a pre-release, like the `beta` in `1.2-beta.1` or `1.2beta1`.
+const PRE_RELEASE: [&str; 4] = ["alpha", "beta", "pre", "rc"];
+
+impl Key {
+    fn of(version: &str) -> Self {
+        let version = version.trim().trim_start_matches(['v', 'V']);
+        // Build metadata doesn't order, as in semver.
+        let version = version
+            .split_once('+')
+            .map_or(version, |(version, _)| version);
+
+        let mut core = version
+            .split(['.', '_', '-'])
+            .flat_map(split_digits)
+            .collect::<Vec<_>>();
+        let pre = match core.iter().position(
+            |part| matches!(part, Part::Word(word) if PRE_RELEASE.contains(&word.as_str())),
+        ) {
+            Some(start) => Pre::Release(core.split_off(start)),
+            None => Pre::Final,
+        };
+        // `1.2` is `1.2.0`.
+        while core.len() > 1 && core.last() == Some(&Part::Number(0)) {
+            core.pop();
+        }
+        Self { core, pre }
+    }
+}
+
+/// Split `component` where it goes from digits to letters or back, so `2a` is `2` then `a`.
+fn split_digits(component: &str) -> Vec<Part> {
+    let mut parts = vec![];
+    let mut rest = component;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        parts.push(match part.parse() {
+            Ok(number) if digits => Part::Number(number),
+            _ => Part::Word(part.to_lowercase()),
+        });
+        rest = tail;
+    }
+    parts
+}
+
+impl ModVersion {
+    pub fn new(version: impl Into<String>) -> Self {
+        Self(version.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Compare by version alone, so `1.0` and `1.0.0` are the same version.
+    pub fn cmp_version(&self, other: &Self) -> Ordering {
+        Key::of(&self.0).cmp(&Key::of(&other.0))
+    }
+
+    /// Whether going from `self` to `new` goes back to an older version.
+    pub fn is_downgrade_to(&self, new: &Self) -> bool {
+        self.cmp_version(new) == Ordering::Greater
+    }
+}
+
+impl PartialOrd for ModVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ModVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Spelling breaks ties, to agree with `Eq`.
+        self.cmp_version(other).then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialEq<str> for ModVersion {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for ModVersion {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for ModVersion {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl Deref for ModVersion {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for ModVersion {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ModVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for ModVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl From<String> for ModVersion {
+    fn from(version: String) -> Self {
+        Self(version)
+    }
+}
+
+impl From<&str> for ModVersion {
+    fn from(version: &str) -> Self {
+        Self(version.to_string())
+    }
+}
+
+impl From<&String> for ModVersion {
+    fn from(version: &String) -> Self {
+        Self(version.clone())
+    }
+}
+
+impl From<ModVersion> for String {
+    fn from(version: ModVersion) -> Self {
+        version.0
+    }
+}
+
+impl From<&ModVersion> for String {
+    fn from(version: &ModVersion) -> Self {
+        version.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmp(old: &str, new: &str) -> Ordering {
+        ModVersion::new(old).cmp_version(&ModVersion::new(new))
+    }
+
+    #[test]
+    fn orders_like_semver() {
+        for (older, newer) in [
+            ("1.2", "1.10"),
+            ("1.2.9", "1.3"),
+            ("0.9", "1"),
+            ("1.2-beta.1", "1.2-beta.2"),
+            ("1.2-alpha", "1.2-beta"),
+            ("1.2-rc.1", "1.2"),
+            ("1.2beta1", "1.2"),
+        ] {
+            assert_eq!(cmp(older, newer), Ordering::Less, "{older} < {newer}");
+            assert_eq!(cmp(newer, older), Ordering::Greater, "{newer} > {older}");
+        }
+    }
+
+    #[test]
+    fn missing_components_prefixes_and_build_metadata_dont_count() {
+        assert_eq!(cmp("1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(cmp("v1.2", "1.2"), Ordering::Equal);
+        assert_eq!(cmp("V1.2.0", "1.2"), Ordering::Equal);
+        assert_eq!(cmp("1.2+build.5", "1.2"), Ordering::Equal);
+        assert_eq!(cmp("1_2", "1.2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn suffixes_that_arent_pre_releases_come_after() {
+        assert_eq!(cmp("1.2", "1.2-hotfix"), Ordering::Less);
+        assert_eq!(cmp("1.2", "1.2a"), Ordering::Less);
+        assert_eq!(cmp("1.2a", "1.2b"), Ordering::Less);
+        assert_eq!(cmp("1.2b", "1.3"), Ordering::Less);
+        // Numbers before words, like semver identifiers.
+        assert_eq!(cmp("1.2.1", "1.2.a"), Ordering::Less);
+    }
+
+    #[test]
+    fn equal_versions_are_told_apart_by_spelling() {
+        let (short, long) = (ModVersion::new("1.2"), ModVersion::new("1.2.0"));
+        assert_ne!(short, long);
+        assert_eq!(short.cmp(&long), Ordering::Less);
+        assert!(!short.is_downgrade_to(&long));
+        assert!(!long.is_downgrade_to(&short));
+    }
+
+    #[test]
+    fn downgrades() {
+        assert!(ModVersion::new("2.0").is_downgrade_to(&ModVersion::new("1.9.9")));
+        assert!(ModVersion::new("2.0").is_downgrade_to(&ModVersion::new("2.0-rc.1")));
+        assert!(!ModVersion::new("2.0-rc.1").is_downgrade_to(&ModVersion::new("2.0")));
+    }
+}
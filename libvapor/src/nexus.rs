@@ -0,0 +1,603 @@
+//! Nexus Mods: searching for mods, downloading files through the API, and installing Nexus
+//! collections from their `collection.json` manifest.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use chrono_humanize::HumanTime;
+use inline_colorization::*;
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use tracing::{debug, info};
+
+use crate::{
+    download::{self, DownloadError},
+    mod_manager::{
+        handler::{AddOptions, ModError, ModHandler, OperationReport},
+        hash,
+        metadata::Metadata,
+        name::ModName,
+    },
+    render,
+};
+
+const API: &str = "https://api.nexusmods.com/v1";
+
+/// The v2 API, which unlike v1 can search mods by name.
+const GRAPHQL: &str = "https://api.nexusmods.com/v2/graphql";
+
+/// Nexus domain of `Cyberpunk 2077`, used when a manifest doesn't name one.
+pub const DEFAULT_DOMAIN: &str = "cyberpunk2077";
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum NexusError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Download(DownloadError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Mod(#[from] ModError),
+    #[error("io error: `{0}`")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid collection manifest: `{0}`")]
+    #[diagnostic(help("Pass the `collection.json` from a Nexus collection"))]
+    Manifest(#[from] serde_json::Error),
+    #[error("No Nexus API key")]
+    #[diagnostic(help("Run `vapor auth login`, or set `nexus_api_key` with `vapor config set`"))]
+    MissingKey,
+    #[error("Nexus won't link `{0}` for download")]
+    #[diagnostic(help(
+        "The API only hands out download links to premium accounts, download it from the website and `vapor add` it instead"
+    ))]
+    NotPremium(String),
+    #[error("`{0}` isn't hosted on Nexus")]
+    #[diagnostic(help("Only mods with a Nexus source can be installed from a collection"))]
+    UnsupportedSource(String),
+    #[error("Collection rules form a cycle between {0}")]
+    Cycle(String),
+    #[error("Nexus search failed: {0}")]
+    Search(String),
+    #[error("`{name}` doesn't match the collection: {algorithm} of `{}` is {actual}, expected {expected}", file.display())]
+    #[diagnostic(help(
+        "The archive changed since the collection was made, or was tampered with. Delete it to download it again, or pass `--allow-mismatch` to install it anyway"
+    ))]
+    ChecksumMismatch {
+        name: String,
+        file: PathBuf,
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
+    #[error("`{0}` has no files to download")]
+    #[diagnostic(help("It may have been hidden or removed, check its Nexus page"))]
+    NoFiles(String),
+}
+
+impl From<DownloadError> for NexusError {
+    fn from(err: DownloadError) -> Self {
+        match err {
+            DownloadError::Http(ureq::Error::StatusCode(401)) => Self::MissingKey,
+            err => Self::Download(err),
+        }
+    }
+}
+
+/// A mod found by [`search`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteMod {
+    pub mod_id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub downloads: u64,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+    data: Option<SearchData>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SearchData {
+    mods: SearchPage,
+}
+
+#[derive(Deserialize)]
+struct SearchPage {
+    nodes: Vec<RemoteMod>,
+}
+
+/// Mods of the game `domain` whose names match `query`, most downloaded first.
+///
+/// Searching doesn't need an API key.
+pub fn search(domain: &str, query: &str, count: usize) -> Result<Vec<RemoteMod>, NexusError> {
+    let body = json!({
+        "query": "query Search($filter: ModsFilter, $sort: [ModsSort!], $count: Int) {
+            mods(filter: $filter, sort: $sort, count: $count) {
+                nodes { modId name version summary downloads updatedAt }
+            }
+        }",
+        "variables": {
+            "filter": {
+                "gameDomainName": [{ "value": domain, "op": "EQUALS" }],
+                "name": [{ "value": query, "op": "WILDCARD" }],
+            },
+            "sort": [{ "downloads": { "direction": "DESC" } }],
+            "count": count,
+        },
+    });
+
+    info!(%domain, %query, "searching Nexus");
+    let response: GraphqlResponse = download::post_json_with(GRAPHQL, &[], &body)?;
+    if let Some(error) = response.errors.into_iter().next() {
+        return Err(NexusError::Search(error.message));
+    }
+    Ok(response
+        .data
+        .map(|data| data.mods.nodes)
+        .unwrap_or_default())
+}
+
+/// Render [`search`] results as a numbered list, or as JSON.
+#[allow(unused_must_use)]
+pub fn search_report(mods: &[RemoteMod], json: bool) -> String {
+    if json {
+        return serde_json::to_string_pretty(mods).expect("could not format json");
+    }
+
+    let mut out = String::new();
+    if mods.is_empty() {
+        writeln!(&mut out, "No mods found");
+        return out;
+    }
+
+    for (index, found) in mods.iter().enumerate() {
+        writeln!(
+            &mut out,
+            "{:>3}. {style_bold}{}{style_reset} {color_cyan}#{}{color_reset} {}",
+            index + 1,
+            found.name,
+            found.mod_id,
+            found.version
+        );
+        let updated = found
+            .updated_at
+            .map(|at| format!(", updated {}", HumanTime::from(at)))
+            .unwrap_or_default();
+        writeln!(&mut out, "     {} downloads{updated}", found.downloads);
+    }
+
+    render::finish(out)
+}
+
+/// A file of a mod, as listed by [`Client::main_file`].
+#[derive(Deserialize, Clone, Debug)]
+pub struct RemoteFile {
+    pub file_id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub category_name: Option<String>,
+    #[serde(default)]
+    pub is_primary: bool,
+    #[serde(default)]
+    pub uploaded_timestamp: i64,
+}
+
+#[derive(Deserialize)]
+struct FileList {
+    files: Vec<RemoteFile>,
+}
+
+/// A file found by its MD5, with the mod it belongs to.
+#[derive(Deserialize)]
+struct Md5Match {
+    #[serde(rename = "mod")]
+    details: Md5Mod,
+    file_details: Md5File,
+}
+
+#[derive(Deserialize)]
+struct Md5Mod {
+    mod_id: u64,
+    name: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    summary: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Md5File {
+    #[serde(default)]
+    version: Option<String>,
+}
+
+/// Authenticated access to the Nexus API.
+pub struct Client {
+    api_key: String,
+}
+
+#[derive(Deserialize)]
+struct FileInfo {
+    file_name: String,
+}
+
+#[derive(Deserialize)]
+struct DownloadLink {
+    #[serde(rename = "URI")]
+    uri: String,
+}
+
+impl Client {
+    pub fn new(api_key: String) -> Self {
+        Self { api_key }
+    }
+
+    fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, DownloadError> {
+        download::get_json_with(&format!("{API}{path}"), &[("apikey", &self.api_key)])
+    }
+
+    /// The file of mod `mod_id` to install: the one marked primary, or else the newest main file,
+    /// or else the newest file of any kind.
+    pub fn main_file(&self, domain: &str, mod_id: u64) -> Result<RemoteFile, NexusError> {
+        let list = self.get::<FileList>(&format!("/games/{domain}/mods/{mod_id}/files.json"))?;
+
+        let newest = |main_only: bool| {
+            list.files
+                .iter()
+                .filter(|file| !main_only || file.category_name.as_deref() == Some("MAIN"))
+                .max_by_key(|file| file.uploaded_timestamp)
+        };
+        list.files
+            .iter()
+            .find(|file| file.is_primary)
+            .or_else(|| newest(true))
+            .or_else(|| newest(false))
+            .cloned()
+            .ok_or_else(|| NexusError::NoFiles(format!("#{mod_id}")))
+    }
+
+    /// Name, version, author, description and page of the mod the archive at `path` was
+    /// downloaded as, looked up by its MD5. `None` if Nexus doesn't know the file.
+    pub fn identify(&self, domain: &str, path: &Path) -> Result<Option<Metadata>, NexusError> {
+        let md5 = hash::md5(path)?;
+        info!(archive = %path.display(), %md5, "looking up archive on Nexus");
+
+        let found = match self
+            .get::<Vec<Md5Match>>(&format!("/games/{domain}/mods/md5_search/{md5}.json"))
+        {
+            Err(DownloadError::Http(ureq::Error::StatusCode(404))) => return Ok(None),
+            found => found?,
+        };
+        let Some(found) = found.into_iter().next() else {
+            return Ok(None);
+        };
+
+        Ok(Some(Metadata {
+            name: Some(found.details.name),
+            version: found.file_details.version.or(found.details.version),
+            author: found.details.author,
+            description: found.details.summary,
+            url: Some(format!(
+                "https://www.nexusmods.com/{domain}/mods/{}",
+                found.details.mod_id
+            )),
+        }))
+    }
+
+    /// Download file `file_id` of mod `mod_id` into `dir`, reusing an earlier download.
+    pub fn download(
+        &self,
+        domain: &str,
+        mod_id: u64,
+        file_id: u64,
+        dir: &Path,
+    ) -> Result<std::path::PathBuf, NexusError> {
+        let files = format!("/games/{domain}/mods/{mod_id}/files/{file_id}");
+        let info = self.get::<FileInfo>(&format!("{files}.json"))?;
+
+        let dest = dir.join(&info.file_name);
+        if dest.exists() {
+            info!(file = %dest.display(), "already downloaded");
+            return Ok(dest);
+        }
+
+        let links = match self.get::<Vec<DownloadLink>>(&format!("{files}/download_link.json")) {
+            Err(DownloadError::Http(ureq::Error::StatusCode(403))) => {
+                return Err(NexusError::NotPremium(info.file_name));
+            }
+            links => links?,
+        };
+        let Some(link) = links.first() else {
+            return Err(NexusError::NotPremium(info.file_name));
+        };
+
+        Ok(download::download_to(&link.uri, dir, &info.file_name)?)
+    }
+}
+
+/// A Nexus collection's `collection.json`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub info: ManifestInfo,
+    pub mods: Vec<ManifestMod>,
+    #[serde(default)]
+    pub mod_rules: Vec<ModRule>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestInfo {
+    pub name: String,
+    #[serde(default)]
+    pub domain_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestMod {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub domain_name: Option<String>,
+    pub source: ModSource,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ModSource {
+    /// `nexus` for mods hosted there.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub mod_id: Option<u64>,
+    pub file_id: Option<u64>,
+    pub md5: Option<String>,
+    /// SHA-256 of the archive. Not part of Nexus's format, but added by hand it makes the
+    /// collection tamper-evident, which MD5 alone doesn't.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    pub logical_filename: Option<String>,
+}
+
+/// A rule between two mods, like `source` requiring or loading after `reference`.
+#[derive(Deserialize, Debug)]
+pub struct ModRule {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub source: RuleReference,
+    pub reference: RuleReference,
+}
+
+/// How a rule refers to a mod.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleReference {
+    #[serde(rename = "fileMD5")]
+    pub file_md5: Option<String>,
+    pub logical_file_name: Option<String>,
+}
+
+/// A mod of a collection, ready to install.
+#[derive(Debug)]
+pub struct PlannedMod {
+    pub name: String,
+    pub version: String,
+    pub domain: String,
+    pub mod_id: u64,
+    pub file_id: u64,
+    /// Checksums of the archive the collection was made with.
+    pub md5: Option<String>,
+    pub sha256: Option<String>,
+    /// Dependencies and conflicts from the collection's rules.
+    pub options: AddOptions,
+}
+
+impl PlannedMod {
+    /// Download the mod into `downloads`, or find an earlier download there.
+    pub fn download(&self, client: &Client, downloads: &Path) -> Result<PathBuf, NexusError> {
+        client.download(&self.domain, self.mod_id, self.file_id, downloads)
+    }
+
+    /// Check `archive` is the one the collection was made with, by SHA-256 if the collection has
+    /// it and MD5 otherwise. Archives the collection has no checksum for always pass.
+    pub fn verify(&self, archive: &Path) -> Result<(), NexusError> {
+        let (algorithm, expected, actual) = match (&self.sha256, &self.md5) {
+            (Some(expected), _) => ("SHA-256", expected, hash::sha256(archive)?),
+            (None, Some(expected)) => ("MD5", expected, hash::md5(archive)?),
+            (None, None) => {
+                debug!(name = %self.name, "no checksum to verify");
+                return Ok(());
+            }
+        };
+
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(NexusError::ChecksumMismatch {
+                name: self.name.clone(),
+                file: archive.to_path_buf(),
+                algorithm,
+                expected: expected.to_lowercase(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Install the downloaded `archive` through `handler`.
+    pub fn install(
+        &self,
+        handler: &ModHandler,
+        archive: &Path,
+    ) -> Result<OperationReport, NexusError> {
+        Ok(handler.add_mod(
+            archive,
+            self.name.as_str(),
+            self.version.as_str(),
+            &self.options,
+        )?)
+    }
+}
+
+impl Manifest {
+    /// Read a manifest from a file, or fetch it if `source` is a URL.
+    pub fn load(source: &str) -> Result<Self, NexusError> {
+        if source.starts_with("https://") || source.starts_with("http://") {
+            Ok(download::get_json(source)?)
+        } else {
+            Ok(serde_json::from_str(&fs::read_to_string(source)?)?)
+        }
+    }
+
+    /// Mods to install in order, so each comes after what it requires or loads after.
+    ///
+    /// Optional mods are left out unless `optional`. `requires` rules become dependencies,
+    /// `recommends` optional dependencies and `conflicts` conflicts.
+    pub fn plan(&self, optional: bool) -> Result<Vec<PlannedMod>, NexusError> {
+        let default_domain = self.info.domain_name.as_deref().unwrap_or(DEFAULT_DOMAIN);
+
+        let mut planned = vec![];
+        for entry in self.mods.iter().filter(|entry| optional || !entry.optional) {
+            let (Some(mod_id), Some(file_id)) = (entry.source.mod_id, entry.source.file_id) else {
+                return Err(NexusError::UnsupportedSource(entry.name.clone()));
+            };
+            if entry.source.kind != "nexus" {
+                return Err(NexusError::UnsupportedSource(entry.name.clone()));
+            }
+
+            planned.push(PlannedMod {
+                name: entry.name.clone(),
+                version: entry.version.clone(),
+                domain: entry
+                    .domain_name
+                    .clone()
+                    .unwrap_or_else(|| default_domain.to_string()),
+                mod_id,
+                file_id,
+                md5: entry.source.md5.clone(),
+                sha256: entry.source.sha256.clone(),
+                options: AddOptions::default(),
+            });
+        }
+
+        // Each mod and what has to be installed before it.
+        let mut after = BTreeMap::<usize, Vec<usize>>::new();
+        for rule in &self.mod_rules {
+            let (Some(source), Some(reference)) = (
+                self.find(&planned, &rule.source),
+                self.find(&planned, &rule.reference),
+            ) else {
+                debug!(kind = %rule.kind, "rule refers to a mod that isn't installed");
+                continue;
+            };
+            let reference_name = ModName::from(&planned[reference].name);
+            let options = &mut planned[source].options;
+
+            match rule.kind.as_str() {
+                "requires" => {
+                    options.dependencies.push(reference_name);
+                    after.entry(source).or_default().push(reference);
+                }
+                "after" => after.entry(source).or_default().push(reference),
+                "before" => after.entry(reference).or_default().push(source),
+                "recommends" => options.optional_dependencies.push(reference_name),
+                "conflicts" => options.conflicts.push(reference_name),
+                kind => debug!(%kind, "ignoring collection rule"),
+            }
+        }
+
+        let order = install_order(planned.len(), &after).map_err(|cycle| {
+            NexusError::Cycle(
+                cycle
+                    .iter()
+                    .map(|index| format!("`{}`", planned[*index].name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        })?;
+
+        let mut planned = planned.into_iter().map(Some).collect::<Vec<_>>();
+        Ok(order
+            .into_iter()
+            .filter_map(|index| planned[index].take())
+            .collect())
+    }
+
+    /// Index into `planned` of the mod `reference` points at.
+    fn find(&self, planned: &[PlannedMod], reference: &RuleReference) -> Option<usize> {
+        let entry = self.mods.iter().find(|entry| {
+            let md5 = reference
+                .file_md5
+                .as_ref()
+                .is_some_and(|md5| entry.source.md5.as_ref() == Some(md5));
+            let file_name = reference
+                .logical_file_name
+                .as_ref()
+                .is_some_and(|name| entry.source.logical_filename.as_ref() == Some(name));
+            md5 || file_name
+        })?;
+
+        planned
+            .iter()
+            .position(|planned| planned.name == entry.name)
+    }
+}
+
+/// Order `0..len` so each index comes after those in `after`, keeping the original order where
+/// nothing says otherwise.
+///
+/// Fails with the indices left over when the rules form a cycle.
+fn install_order(
+    len: usize,
+    after: &BTreeMap<usize, Vec<usize>>,
+) -> Result<Vec<usize>, Vec<usize>> {
+    let mut order = vec![];
+    let mut placed = vec![false; len];
+
+    while order.len() < len {
+        let ready = (0..len).find(|index| {
+            !placed[*index]
+                && after
+                    .get(index)
+                    .into_iter()
+                    .flatten()
+                    .all(|before| placed[*before])
+        });
+
+        match ready {
+            Some(index) => {
+                placed[index] = true;
+                order.push(index);
+            }
+            None => return Err((0..len).filter(|index| !placed[*index]).collect()),
+        }
+    }
+
+    Ok(order)
+}
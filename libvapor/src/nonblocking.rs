@@ -0,0 +1,103 @@
+//! Async variants of long running operations, for frontends that have to stay responsive.
+//!
+//! Blocking work runs on tokio's blocking pool, and every function can be cancelled through a
+//! [`CancellationToken`].
+
+use std::path::PathBuf;
+
+use miette::Diagnostic;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use tokio::task::JoinError;
+
+use crate::{
+    doctor::health::{self, HealthIssue},
+    download::{self, DownloadError},
+    mod_manager::handler::{AddOptions, ModError, ModHandler, OperationReport},
+};
+
+pub use tokio_util::sync::CancellationToken;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum TaskError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Download(#[from] DownloadError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Mod(#[from] ModError),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Background task failed: `{0}`")]
+    Join(#[from] JoinError),
+}
+
+/// Run `f` on the blocking pool, giving up on it once `cancel` fires.
+///
+/// `f` itself keeps running, so it must be safe to abandon.
+async fn abandonable<T: Send + 'static>(
+    cancel: &CancellationToken,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, TaskError> {
+    tokio::select! {
+        () = cancel.cancelled() => Err(TaskError::Cancelled),
+        result = tokio::task::spawn_blocking(f) => Ok(result?),
+    }
+}
+
+/// Async [`download::get_json`].
+pub async fn get_json<T: DeserializeOwned + Send + 'static>(
+    url: String,
+    cancel: &CancellationToken,
+) -> Result<T, TaskError> {
+    Ok(abandonable(cancel, move || download::get_json(&url)).await??)
+}
+
+/// Async [`download::download_to`], removing the partial file if cancelled.
+pub async fn download_to(
+    url: String,
+    dir: PathBuf,
+    file_name: String,
+    cancel: &CancellationToken,
+) -> Result<PathBuf, TaskError> {
+    let token = cancel.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        download::download_until(&url, &dir, &file_name, || token.is_cancelled())
+    })
+    .await?;
+
+    match result {
+        Err(DownloadError::Cancelled) => Err(TaskError::Cancelled),
+        result => Ok(result?),
+    }
+}
+
+/// Async [`ModHandler::add_mod`].
+///
+/// Cancelling only works until extraction starts. From then on the install runs to completion, so
+/// a mod is never left half deployed.
+pub async fn add_mod(
+    handler: ModHandler,
+    path: PathBuf,
+    name: String,
+    version: String,
+    options: AddOptions,
+    cancel: &CancellationToken,
+) -> Result<OperationReport, TaskError> {
+    if cancel.is_cancelled() {
+        return Err(TaskError::Cancelled);
+    }
+
+    Ok(
+        tokio::task::spawn_blocking(move || handler.add_mod(&path, name, version, &options))
+            .await??,
+    )
+}
+
+/// Async [`health::check`].
+pub async fn verify(
+    game: Option<String>,
+    cancel: &CancellationToken,
+) -> Result<Vec<HealthIssue>, TaskError> {
+    abandonable(cancel, move || health::check(game.as_deref())).await
+}
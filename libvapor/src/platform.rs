@@ -0,0 +1,240 @@
+//! Where Vapor keeps its own files, and the differences between filesystems it runs on.
+//!
+//! On Linux these are the XDG directories, like `$XDG_CONFIG_HOME/vapor`. On Windows, config, the
+//! journal and save backups go to `%APPDATA%\vapor` and caches, logs and state to
+//! `%LOCALAPPDATA%\vapor`.
+//!
+//! `VAPOR_PROFILE=name` keeps every one of these apart from other setups by using `vapor-name`
+//! instead of `vapor`, and `VAPOR_CONFIG_HOME` replaces the config directory outright.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+};
+
+const APP: &str = "vapor";
+
+fn missing(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("could not find the {what} directory"),
+    )
+}
+
+/// Name of Vapor's directories, `vapor` or `vapor-<profile>` with `VAPOR_PROFILE` set.
+pub(crate) fn app_name() -> io::Result<String> {
+    match env::var("VAPOR_PROFILE") {
+        Ok(profile) if profile.is_empty() => Ok(APP.to_string()),
+        Ok(profile)
+            if profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                && !profile.starts_with('.') =>
+        {
+            Ok(format!("{APP}-{profile}"))
+        }
+        Ok(profile) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{profile}` in `VAPOR_PROFILE` is not a valid profile name"),
+        )),
+        Err(env::VarError::NotPresent) => Ok(APP.to_string()),
+        Err(env::VarError::NotUnicode(_)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`VAPOR_PROFILE` is not valid UTF-8",
+        )),
+    }
+}
+
+/// Vapor's directory in `base`.
+fn home(base: Option<PathBuf>, what: &str) -> io::Result<PathBuf> {
+    Ok(base.ok_or_else(|| missing(what))?.join(app_name()?))
+}
+
+fn place(base: Option<PathBuf>, what: &str, file: &str) -> io::Result<PathBuf> {
+    create_parent(home(base, what)?.join(file))
+}
+
+fn create_parent(path: PathBuf) -> io::Result<PathBuf> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Vapor's config directory, which may not exist yet.
+///
+/// This is `VAPOR_CONFIG_HOME` if set, whatever the profile.
+pub fn config_home() -> io::Result<PathBuf> {
+    match env::var_os("VAPOR_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => home(dirs::config_dir(), "config"),
+    }
+}
+
+/// `file` in the config directory, if it exists.
+pub fn config_file(file: &str) -> Option<PathBuf> {
+    let path = config_home().ok()?.join(file);
+    path.exists().then_some(path)
+}
+
+/// Path for `file` in the config directory, creating its parent directories.
+pub fn place_config_file(file: &str) -> io::Result<PathBuf> {
+    create_parent(config_home()?.join(file))
+}
+
+/// `dir` in the cache directory, created if missing.
+pub fn cache_dir(dir: &str) -> io::Result<PathBuf> {
+    let path = home(dirs::cache_dir(), "cache")?.join(dir);
+    fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+/// Path for `file` in the state directory, creating its parent directories.
+///
+/// Windows has no state directory, so this is in the local data directory there.
+pub fn place_state_file(file: &str) -> io::Result<PathBuf> {
+    place(
+        dirs::state_dir().or_else(dirs::data_local_dir),
+        "state",
+        file,
+    )
+}
+
+/// `dir` in the state directory, created if missing.
+///
+/// Windows has no state directory, so this is in the local data directory there.
+pub fn state_dir(dir: &str) -> io::Result<PathBuf> {
+    let path = home(dirs::state_dir().or_else(dirs::data_local_dir), "state")?.join(dir);
+    fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+/// `dir` in the data directory, created if missing.
+pub fn data_dir(dir: &str) -> io::Result<PathBuf> {
+    let path = home(dirs::data_dir(), "data")?.join(dir);
+    fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+/// Path for `file` in the runtime directory, creating its parent directories.
+pub fn place_runtime_file(file: &str) -> io::Result<PathBuf> {
+    place(dirs::runtime_dir(), "runtime", file)
+}
+
+/// `path` spelled consistently, so prefix checks against it work.
+///
+/// On Windows, the drive letter is uppercased and `/` turned into `\`, so `c:/Games` and
+/// `C:\Games` are the same root. Elsewhere `path` is returned unchanged.
+pub fn normalize(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+
+    let mut path = path.to_string_lossy().replace('/', "\\");
+    if let [drive, b':', ..] = path.as_bytes()
+        && drive.is_ascii_lowercase()
+    {
+        path[..1].make_ascii_uppercase();
+    }
+    PathBuf::from(path)
+}
+
+/// `path` as a `file://` URL, with `file:///C:/...` for drive-letter paths.
+pub fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{path}")
+    } else {
+        format!("file:///{path}")
+    }
+}
+
+/// Open `url` in the default browser, without waiting for it to close.
+///
+/// Only `http` and `https` URLs are opened, since they come from archives anyone can make, and
+/// they are handed to the opener as one argument, never through a shell.
+pub fn open_url(url: &str) -> io::Result<()> {
+    let web = ["http://", "https://"].iter().any(|scheme| {
+        url.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    });
+    if !web || url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{url}` is not a web address"),
+        ));
+    }
+
+    let mut cmd = if cfg!(windows) {
+        Command::new("explorer.exe")
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    cmd.arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(drop)
+}
+
+/// Open `files` in `$VISUAL` or `$EDITOR`, falling back to `notepad` on Windows and `vi`
+/// elsewhere, and wait for it to close.
+pub fn edit(files: &[PathBuf]) -> io::Result<ExitStatus> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    // Editors are often set with arguments, like `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    Command::new(program).args(words).args(files).status()
+}
+
+/// Whether the filesystem `dir` is on treats names differing only in case as the same file.
+///
+/// Always the case on Windows and macOS. On Linux it can be, for an NTFS or exFAT drive, so this
+/// checks whether `dir` can be found with its name in another case.
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        return true;
+    }
+
+    let Some(name) = dir.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+    let swapped = name
+        .chars()
+        .map(|c| {
+            if c.is_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect::<String>();
+    if swapped == name {
+        return false;
+    }
+
+    same_file(dir, &dir.with_file_name(swapped))
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    a.exists() && b.exists()
+}
@@ -0,0 +1,191 @@
+//! The game's Proton prefix, and whether the DLLs frameworks hook the game through will load in it.
+//!
+//! Frameworks like Cyber Engine Tweaks ship a `version.dll` that Wine ignores in favour of its own
+//! unless told otherwise, either by `WINEDLLOVERRIDES` in the game's Steam launch options or by an
+//! override set with `winecfg` in the prefix.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{init::CyberToml, launch::STEAM_APP_ID, steam};
+
+/// DLLs in `bin/x64` that frameworks use to load into the game, without `.dll`.
+pub const OVERRIDE_DLLS: &[&str] = &["version", "winmm", "dxgi", "d3d11"];
+
+/// Oldest Proton major version the frameworks are known to work with.
+pub const MIN_VERSION: u32 = 7;
+
+/// The game's compatdata prefix, `.../compatdata/1091500`, if it exists.
+///
+/// This is `prefix` in `[launch]` if set, else the one in the Steam library holding the game, else
+/// the first one in any library.
+pub fn find_prefix(config: &CyberToml) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return None;
+    }
+    if let Some(launch) = &config.launch {
+        let prefix = steam::resolve(Path::new(&launch.prefix));
+        return prefix.is_dir().then_some(prefix);
+    }
+
+    let compatdata = |library: &Path| {
+        library
+            .join("steamapps/compatdata")
+            .join(STEAM_APP_ID.to_string())
+    };
+    let game = steam::resolve(Path::new(config.game_path()));
+    steam::library_of(&game)
+        .map(compatdata)
+        .into_iter()
+        .chain(steam::libraries().iter().map(|library| compatdata(library)))
+        .find(|prefix| prefix.is_dir())
+}
+
+/// Proton version that last ran the game in `prefix`, like `9.0-203`.
+pub fn prefix_version(prefix: &Path) -> Option<String> {
+    let version = fs::read_to_string(prefix.join("version")).ok()?;
+    Some(version.trim().to_string()).filter(|version| !version.is_empty())
+}
+
+/// Version of the Proton install whose `proton` script is at `proton`, spelled like
+/// [`prefix_version`].
+pub fn proton_version(proton: &Path) -> Option<String> {
+    // Proton's `version` file reads like `1712345678 proton-9.0-203`.
+    let version = fs::read_to_string(proton.parent()?.join("version")).ok()?;
+    let version = version.split_whitespace().last()?;
+    Some(
+        version
+            .strip_prefix("proton-")
+            .unwrap_or(version)
+            .to_string(),
+    )
+}
+
+/// Major version of a Proton version string, like 9 for `9.0-203` or `GE-Proton9-20`.
+///
+/// `None` for versions without one, like Proton Experimental's.
+pub fn major_version(version: &str) -> Option<u32> {
+    let start = version.find(|c: char| c.is_ascii_digit())?;
+    let digits = version[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>();
+    // Experimental versions are dated, like `experimental-20240105`.
+    (digits.len() < 4).then(|| digits.parse().ok())?
+}
+
+/// Which of [`OVERRIDE_DLLS`] are in the game's `bin/x64`.
+pub fn deployed_overrides(game: &Path) -> Vec<&'static str> {
+    let bin = game.join("bin").join("x64");
+    OVERRIDE_DLLS
+        .iter()
+        .copied()
+        .filter(|dll| bin.join(format!("{dll}.dll")).exists())
+        .collect()
+}
+
+/// `WINEDLLOVERRIDES` value loading `dlls` native first, like `version,winmm=n,b`.
+pub fn dll_overrides(dlls: &[&str]) -> String {
+    format!("{}=n,b", dlls.join(","))
+}
+
+/// DLLs overridden to load native first by a `WINEDLLOVERRIDES` value.
+fn parse_overrides(value: &str) -> impl Iterator<Item = String> + '_ {
+    value.split(';').flat_map(|entry| {
+        let (names, mode) = entry.split_once('=').unwrap_or((entry, ""));
+        let native = mode.trim().starts_with('n');
+        names
+            .split(',')
+            .filter(move |_| native)
+            .map(|name| name.trim().trim_end_matches(".dll").to_lowercase())
+    })
+}
+
+/// The game's launch options in each Steam user's `localconfig.vdf`.
+fn launch_options() -> Vec<String> {
+    let app = format!("\"{STEAM_APP_ID}\"");
+    let mut options = vec![];
+
+    for root in steam::roots() {
+        let Ok(users) = fs::read_dir(root.join("userdata")) else {
+            continue;
+        };
+        for user in users.filter_map(Result::ok) {
+            let Ok(config) = fs::read_to_string(user.path().join("config/localconfig.vdf")) else {
+                continue;
+            };
+
+            // Track how deep into the game's block we are, `None` outside of it.
+            let mut depth: Option<usize> = None;
+            for line in config.lines().map(str::trim) {
+                match (line, depth) {
+                    (line, None) if line == app => depth = Some(0),
+                    ("{", Some(d)) => depth = Some(d + 1),
+                    ("}", Some(1)) => depth = None,
+                    ("}", Some(d)) => depth = Some(d - 1),
+                    (_, Some(0)) => depth = None,
+                    (line, Some(1)) => {
+                        if let Some(value) = line.strip_prefix("\"LaunchOptions\"") {
+                            let value = value.trim();
+                            let value = value.strip_prefix('"').unwrap_or(value);
+                            let value = value.strip_suffix('"').unwrap_or(value);
+                            options.push(value.replace("\\\"", "\""));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    options
+}
+
+/// DLLs set to load native first for the game, through its launch options or the prefix.
+pub fn overridden(prefix: &Path) -> BTreeSet<String> {
+    let mut dlls = BTreeSet::new();
+
+    for options in launch_options() {
+        let Some((_, rest)) = options.split_once("WINEDLLOVERRIDES=") else {
+            continue;
+        };
+        let value = match rest.strip_prefix(['"', '\'']) {
+            Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or_default(),
+            None => rest.split_whitespace().next().unwrap_or_default(),
+        };
+        dlls.extend(parse_overrides(value));
+    }
+
+    // `winecfg` writes overrides to `user.reg` like `"version"="native,builtin"`.
+    let registry = fs::read_to_string(prefix.join("pfx").join("user.reg")).unwrap_or_default();
+    let mut in_overrides = false;
+    for line in registry.lines() {
+        if line.starts_with('[') {
+            in_overrides = line.starts_with(r"[Software\\Wine\\DllOverrides]");
+            continue;
+        }
+        if in_overrides
+            && let Some((name, mode)) = line.split_once('=')
+            && mode.trim_matches('"').starts_with('n')
+        {
+            dlls.insert(
+                name.trim_matches('"')
+                    .trim_start_matches('*')
+                    .to_lowercase(),
+            );
+        }
+    }
+
+    dlls
+}
+
+/// Which of the DLLs deployed in `game` won't be loaded in `prefix`.
+pub fn missing_overrides(game: &Path, prefix: &Path) -> Vec<&'static str> {
+    let overridden = overridden(prefix);
+    deployed_overrides(game)
+        .into_iter()
+        .filter(|dll| !overridden.contains(*dll))
+        .collect()
+}
@@ -0,0 +1,129 @@
+//! Whether output gets colors and terminal hyperlinks, decided once at startup with [`init`].
+//!
+//! Reports are written with colors, and [`finish`] takes them out again when they are off.
+
+use std::{
+    borrow::Cow,
+    env, fmt,
+    io::{self, IsTerminal},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{init::ColorChoice, platform};
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+static LINKS: AtomicBool = AtomicBool::new(true);
+
+/// Decide on colors and hyperlinks for `choice`.
+///
+/// [`ColorChoice::Auto`] colors only a terminal that isn't `dumb`, and never with `NO_COLOR` set.
+/// Hyperlinks also need a terminal known to support them, or `FORCE_HYPERLINK=1`.
+pub fn init(choice: ColorChoice) {
+    let color = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                && io::stdout().is_terminal()
+                && env::var("TERM").map_or(true, |term| term != "dumb")
+        }
+    };
+
+    let links = match env::var("FORCE_HYPERLINK") {
+        Ok(force) => force != "0",
+        Err(_) => color && supports_hyperlinks(),
+    };
+
+    COLOR.store(color, Ordering::Relaxed);
+    LINKS.store(links, Ordering::Relaxed);
+}
+
+/// Terminals known to understand OSC 8 hyperlinks.
+fn supports_hyperlinks() -> bool {
+    let var = |name| env::var(name).unwrap_or_default();
+
+    matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper" | "terminology"
+    ) || matches!(
+        var("TERM").as_str(),
+        "xterm-kitty" | "alacritty" | "foot" | "xterm-ghostty"
+    ) || var("VTE_VERSION")
+        .parse::<u32>()
+        .is_ok_and(|version| version >= 5000)
+        || env::var_os("WT_SESSION").is_some()
+        || env::var_os("KONSOLE_VERSION").is_some()
+        || env::var_os("DOMTERM").is_some()
+}
+
+pub fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+pub fn links() -> bool {
+    LINKS.load(Ordering::Relaxed)
+}
+
+/// `text` linking to `path`, or just `text` where hyperlinks are off.
+pub fn link(path: &Path, text: impl fmt::Display) -> String {
+    if links() {
+        format!(
+            "\x1b]8;;{}\x1b\\{text}\x1b]8;;\x1b\\",
+            platform::file_url(path)
+        )
+    } else {
+        text.to_string()
+    }
+}
+
+/// `out` as it should be printed, without colors if they are off.
+pub fn finish(out: String) -> String {
+    if color() {
+        out
+    } else {
+        strip(&out).into_owned()
+    }
+}
+
+/// `text` without ANSI escape sequences.
+pub fn strip(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI, like colors: parameters up to a final byte in `@`..=`~`.
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, like hyperlinks: up to BEL or `ESC \`.
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Cow::Owned(out)
+}
@@ -0,0 +1,372 @@
+//! Plans which mods to enable and disable so a requested change leaves every enabled mod with its
+//! dependencies met and no declared conflicts.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::mod_manager::{name::ModName, registry::ModRegistry};
+
+/// A change asked for by the user.
+#[derive(Debug, Clone)]
+pub enum Request {
+    Enable(String),
+    Disable(String),
+    Remove(String),
+}
+
+/// Everything that has to change to carry out a [`Request`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// Mods to enable, dependencies first.
+    pub enable: Vec<String>,
+    /// Mods to disable, dependents first.
+    pub disable: Vec<String>,
+    /// Mods to remove.
+    pub remove: Vec<String>,
+}
+
+impl Plan {
+    /// Mods changed on top of the requested one.
+    pub fn extra(&self, request: &Request) -> Vec<&str> {
+        let requested = match request {
+            Request::Enable(name) | Request::Disable(name) | Request::Remove(name) => name,
+        };
+
+        self.enable
+            .iter()
+            .chain(&self.disable)
+            .chain(&self.remove)
+            .filter(|name| *name != requested)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum ResolveError {
+    #[error("Missing mod: `{0}`")]
+    MissingMod(String),
+    #[error("Cannot enable `{name}`")]
+    #[diagnostic(code(resolver::plan))]
+    Unsatisfiable {
+        name: String,
+        /// Why, as the chain of requirements ending in the problem.
+        #[help]
+        core: String,
+    },
+}
+
+/// Plan `request` against `registry`.
+pub fn plan(registry: &ModRegistry, request: &Request) -> Result<Plan, ResolveError> {
+    match request {
+        Request::Enable(name) => plan_enable(registry, name),
+        Request::Disable(name) => Ok(Plan {
+            disable: plan_removal(registry, name)?,
+            ..Default::default()
+        }),
+        Request::Remove(name) => {
+            let mut disable = plan_removal(registry, name)?;
+            disable.retain(|dependent| dependent != name);
+            Ok(Plan {
+                disable,
+                remove: vec![name.clone()],
+                ..Default::default()
+            })
+        }
+    }
+}
+
+fn enabled(registry: &ModRegistry) -> BTreeSet<&str> {
+    registry
+        .mods
+        .iter()
+        .filter(|(_, entry)| entry.installed)
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
+/// Mod in `enabled` that is or provides `dep`.
+fn satisfier<'a>(
+    registry: &'a ModRegistry,
+    enabled: &BTreeSet<&str>,
+    dep: &str,
+) -> Option<&'a str> {
+    registry
+        .mods
+        .iter()
+        .filter(|(name, _)| enabled.contains(name.as_str()))
+        .find(|(name, entry)| *name == dep || entry.provides.iter().any(|p| p == dep))
+        .map(|(name, _)| name.as_str())
+}
+
+fn plan_enable(registry: &ModRegistry, name: &str) -> Result<Plan, ResolveError> {
+    let Some((name, _)) = registry.mods.get_key_value(&ModName::from(name)) else {
+        return Err(ResolveError::MissingMod(name.to_string()));
+    };
+    let name = name.as_str();
+
+    let mut enabled = enabled(registry);
+    // Which mod pulled each newly enabled one in, to explain failures.
+    let mut pulled_by = BTreeMap::<&str, &str>::new();
+    let mut order = vec![];
+
+    let unsatisfiable = |core: Vec<String>| ResolveError::Unsatisfiable {
+        name: name.to_string(),
+        core: core.join("\n"),
+    };
+
+    let mut stack = vec![(name, false)];
+    while let Some((current, visited)) = stack.pop() {
+        if visited {
+            order.push(current.to_string());
+            continue;
+        }
+        if !enabled.insert(current) {
+            continue;
+        }
+        stack.push((current, true));
+
+        let entry = &registry.mods[&ModName::from(current)];
+        for dep in entry.dependencies.iter().flatten() {
+            if satisfier(registry, &enabled, dep).is_some() {
+                continue;
+            }
+
+            // The mod itself, or else anything that provides it.
+            let candidate = registry
+                .mods
+                .get_key_value(dep)
+                .or_else(|| {
+                    registry
+                        .mods
+                        .iter()
+                        .find(|(_, entry)| entry.provides.iter().any(|p| p == dep))
+                })
+                .map(|(name, _)| name.as_str());
+
+            let Some(candidate) = candidate else {
+                let mut core = chain(&pulled_by, current);
+                core.push(format!(
+                    "`{current}` depends on `{dep}`, which isn't installed"
+                ));
+                return Err(unsatisfiable(core));
+            };
+
+            pulled_by.entry(candidate).or_insert(current);
+            stack.push((candidate, false));
+        }
+    }
+
+    for newly in &order {
+        let entry = &registry.mods[&ModName::from(newly)];
+        for other in &enabled {
+            let declared = entry.conflicts.iter().any(|c| c == other)
+                || registry.mods[&ModName::from(*other)]
+                    .conflicts
+                    .iter()
+                    .any(|c| c == newly);
+            if declared && other != newly {
+                let mut core = chain(&pulled_by, newly);
+                core.push(format!("`{newly}` conflicts with `{other}`"));
+                return Err(unsatisfiable(core));
+            }
+        }
+    }
+
+    Ok(Plan {
+        enable: order,
+        ..Default::default()
+    })
+}
+
+/// Why `name` is being enabled, starting from the requested mod.
+fn chain<'a>(pulled_by: &BTreeMap<&'a str, &'a str>, mut name: &'a str) -> Vec<String> {
+    let mut chain = vec![];
+    while let Some(parent) = pulled_by.get(name) {
+        chain.push(format!("`{parent}` depends on `{name}`"));
+        name = parent;
+    }
+    chain.reverse();
+    chain
+}
+
+/// `name` and every enabled mod left without a dependency once it's gone, dependents first.
+fn plan_removal(registry: &ModRegistry, name: &str) -> Result<Vec<String>, ResolveError> {
+    let Some((name, _)) = registry.mods.get_key_value(&ModName::from(name)) else {
+        return Err(ResolveError::MissingMod(name.to_string()));
+    };
+
+    let index = registry.dependents();
+    let mut after = enabled(registry);
+    let was_enabled = after.remove(name.as_str());
+    let mut removed = vec![name.to_string()];
+
+    // Only enabled mods satisfy dependencies, so disabled ones take nothing along.
+    let mut queue = if was_enabled { vec![name] } else { vec![] };
+    while let Some(gone) = queue.pop() {
+        for dep in std::iter::once(gone).chain(&registry.mods[gone].provides) {
+            if satisfier(registry, &after, dep).is_some() {
+                continue;
+            }
+
+            for &dependent in index.get(dep).into_iter().flatten() {
+                let Some((dependent, _)) = registry.mods.get_key_value(&ModName::from(dependent))
+                else {
+                    continue;
+                };
+                if after.remove(dependent.as_str()) {
+                    removed.push(dependent.to_string());
+                    queue.push(dependent);
+                }
+            }
+        }
+    }
+
+    removed.reverse();
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mod_manager::registry::ModEntry;
+
+    /// A registry of `mods`, each with whether it's enabled and its dependencies.
+    fn registry(mods: &[(&str, bool, &[&str])]) -> ModRegistry {
+        let mut registry: ModRegistry = toml::from_str("").unwrap();
+        for &(name, installed, dependencies) in mods {
+            let entry = ModEntry {
+                installed,
+                dependencies: Some(dependencies.iter().copied().map(ModName::from).collect()),
+                ..Default::default()
+            };
+            registry.mods.insert(name.into(), entry);
+        }
+        registry
+    }
+
+    fn entry<'a>(registry: &'a mut ModRegistry, name: &str) -> &'a mut ModEntry {
+        registry.mods.get_mut(&ModName::from(name)).unwrap()
+    }
+
+    fn unsatisfiable(result: Result<Plan, ResolveError>) -> String {
+        match result {
+            Err(ResolveError::Unsatisfiable { core, .. }) => core,
+            other => panic!("expected an unsatisfiable plan, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn enables_dependencies_first() {
+        let registry = registry(&[
+            ("App", false, &["Lib"]),
+            ("Lib", false, &["Core"]),
+            ("Core", false, &[]),
+            ("Other", true, &[]),
+        ]);
+
+        let planned = plan(&registry, &Request::Enable("app".to_string())).unwrap();
+        assert_eq!(planned.enable, ["Core", "Lib", "App"]);
+        assert_eq!(
+            planned.extra(&Request::Enable("App".to_string())),
+            ["Core", "Lib"]
+        );
+    }
+
+    #[test]
+    fn enabled_mods_and_providers_satisfy_dependencies() {
+        let mut registry = registry(&[
+            ("App", false, &["Lib"]),
+            ("Fork", true, &[]),
+            ("Lib", false, &[]),
+        ]);
+        entry(&mut registry, "Fork").provides = vec!["Lib".into()];
+
+        let planned = plan(&registry, &Request::Enable("App".to_string())).unwrap();
+        assert_eq!(planned.enable, ["App"]);
+    }
+
+    #[test]
+    fn a_disabled_provider_is_pulled_in() {
+        let mut registry = registry(&[("App", false, &["Lib"]), ("Fork", false, &[])]);
+        entry(&mut registry, "Fork").provides = vec!["Lib".into()];
+
+        let planned = plan(&registry, &Request::Enable("App".to_string())).unwrap();
+        assert_eq!(planned.enable, ["Fork", "App"]);
+    }
+
+    #[test]
+    fn explains_a_missing_dependency_down_the_chain() {
+        let registry = registry(&[("App", false, &["Lib"]), ("Lib", false, &["Core"])]);
+
+        let core = unsatisfiable(plan(&registry, &Request::Enable("App".to_string())));
+        assert_eq!(
+            core,
+            "`App` depends on `Lib`\n`Lib` depends on `Core`, which isn't installed"
+        );
+    }
+
+    #[test]
+    fn conflicts_either_way_are_unsatisfiable() {
+        let mut registry = registry(&[
+            ("App", false, &["Lib"]),
+            ("Lib", false, &[]),
+            ("Rival", true, &[]),
+        ]);
+        entry(&mut registry, "Rival").conflicts = vec!["Lib".into()];
+
+        let core = unsatisfiable(plan(&registry, &Request::Enable("App".to_string())));
+        assert_eq!(core, "`App` depends on `Lib`\n`Lib` conflicts with `Rival`");
+
+        entry(&mut registry, "Rival").conflicts.clear();
+        entry(&mut registry, "App").conflicts = vec!["Rival".into()];
+        let core = unsatisfiable(plan(&registry, &Request::Enable("App".to_string())));
+        assert_eq!(core, "`App` conflicts with `Rival`");
+    }
+
+    #[test]
+    fn disabling_takes_dependents_along_unless_something_else_provides() {
+        let mut registry = registry(&[
+            ("Core", true, &[]),
+            ("Lib", true, &["Core"]),
+            ("App", true, &["Lib"]),
+            ("Tool", true, &["Core"]),
+            ("Off", false, &["Core"]),
+            ("Fork", false, &[]),
+        ]);
+
+        let planned = plan(&registry, &Request::Disable("Core".to_string())).unwrap();
+        assert_eq!(planned.disable, ["App", "Tool", "Lib", "Core"]);
+
+        let fork = entry(&mut registry, "Fork");
+        fork.installed = true;
+        fork.provides = vec!["Core".into()];
+        let planned = plan(&registry, &Request::Disable("Core".to_string())).unwrap();
+        assert_eq!(planned.disable, ["Core"]);
+    }
+
+    #[test]
+    fn removing_disables_dependents_and_removes_the_mod() {
+        let registry = registry(&[("Lib", true, &[]), ("App", true, &["Lib"])]);
+
+        let planned = plan(&registry, &Request::Remove("Lib".to_string())).unwrap();
+        assert_eq!(planned.disable, ["App"]);
+        assert_eq!(planned.remove, ["Lib"]);
+    }
+
+    #[test]
+    fn unknown_mods_are_missing() {
+        let registry = registry(&[]);
+        for request in [
+            Request::Enable("Nope".to_string()),
+            Request::Disable("Nope".to_string()),
+        ] {
+            assert!(matches!(
+                plan(&registry, &request),
+                Err(ResolveError::MissingMod(name)) if name == "Nope"
+            ));
+        }
+    }
+}
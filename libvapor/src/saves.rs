@@ -0,0 +1,360 @@
+//! Backing up and restoring the game's saves, for `vapor saves`.
+//!
+//! Backups are `tar.zst` archives of the whole save directory in the data directory, named after
+//! when they were taken so they sort oldest first.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use miette::Diagnostic;
+use serde::Serialize;
+use thiserror::Error;
+use tracing::info;
+
+use crate::{init::CyberToml, launch::STEAM_APP_ID, platform, steam};
+
+/// Where the game keeps its saves, relative to the user's home directory on Windows.
+const SAVES: &str = "Saved Games/CD Projekt Red/Cyberpunk 2077";
+
+/// Suffix of backup archives.
+const EXTENSION: &str = ".tar.zst";
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum SavesError {
+    #[error("io error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Could not find the game's saves")]
+    #[diagnostic(help("Set their directory with `vapor config set saves_dir <path>`"))]
+    NotFound,
+    #[error("No save backups yet")]
+    #[diagnostic(help("Take one with `vapor saves backup`"))]
+    NoBackups,
+    #[error("No save backup named `{0}`")]
+    #[diagnostic(help("Run `vapor saves list` to see backups"))]
+    UnknownBackup(String),
+}
+
+/// A backup of the saves.
+#[derive(Serialize, Debug, Clone)]
+pub struct Backup {
+    /// File name without its extension, like `2026-10-15T18-30-00`.
+    pub name: String,
+    pub path: PathBuf,
+    /// Size of the archive in bytes.
+    pub size: u64,
+}
+
+/// `Saved Games/...` of every user in the Wine prefix `pfx`.
+fn in_prefix(pfx: &Path) -> Vec<PathBuf> {
+    let Ok(users) = fs::read_dir(pfx.join("drive_c").join("users")) else {
+        return vec![];
+    };
+    users
+        .filter_map(Result::ok)
+        .map(|user| user.path().join(SAVES))
+        .filter(|saves| saves.is_dir())
+        .collect()
+}
+
+/// Directories the game may keep its saves in, most likely first.
+///
+/// Steam's are in the game's Proton prefix, either the one in `[launch]` or the one in each Steam
+/// library. GOG installs through Heroic have a Wine prefix per game, and on Windows saves are in
+/// the user's `Saved Games` whichever store the game came from.
+pub fn candidates(config: &CyberToml) -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if cfg!(windows) {
+        dirs.extend(std::env::home_dir().map(|home| home.join(SAVES)));
+        return dirs;
+    }
+
+    if let Some(launch) = &config.launch {
+        dirs.extend(in_prefix(
+            &steam::resolve(Path::new(&launch.prefix)).join("pfx"),
+        ));
+    }
+    for library in steam::libraries() {
+        dirs.extend(in_prefix(
+            &library
+                .join("steamapps/compatdata")
+                .join(STEAM_APP_ID.to_string())
+                .join("pfx"),
+        ));
+    }
+    if let Some(home) = std::env::home_dir()
+        && let Ok(prefixes) = fs::read_dir(home.join("Games/Heroic/Prefixes"))
+    {
+        for prefix in prefixes.filter_map(Result::ok) {
+            dirs.extend(in_prefix(&prefix.path().join("pfx")));
+        }
+    }
+    dirs
+}
+
+/// The game's save directory, `saves_dir` from the config if set.
+pub fn find(config: &CyberToml) -> Result<PathBuf, SavesError> {
+    if let Some(dir) = &config.main.saves_dir {
+        return Ok(steam::resolve(Path::new(dir)));
+    }
+    candidates(config)
+        .into_iter()
+        .find(|dir| dir.is_dir())
+        .ok_or(SavesError::NotFound)
+}
+
+/// Where backups are kept.
+pub fn backup_dir() -> io::Result<PathBuf> {
+    platform::data_dir("saves")
+}
+
+/// Every backup, oldest first.
+pub fn list() -> Result<Vec<Backup>, SavesError> {
+    let mut backups = fs::read_dir(backup_dir()?)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = file_name.strip_suffix(EXTENSION)?.to_string();
+            Some(Backup {
+                name,
+                path: entry.path(),
+                size: entry.metadata().ok()?.len(),
+            })
+        })
+        .collect::<Vec<_>>();
+    backups.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(backups)
+}
+
+/// The backup called `name`, or the newest one.
+pub fn get(name: Option<&str>) -> Result<Backup, SavesError> {
+    let backups = list()?;
+    match name {
+        Some(name) => backups
+            .into_iter()
+            .find(|backup| backup.name == name || backup.name == format!("{name}{EXTENSION}"))
+            .ok_or_else(|| SavesError::UnknownBackup(name.to_string())),
+        None => backups.into_iter().next_back().ok_or(SavesError::NoBackups),
+    }
+}
+
+/// Back up `saves`, with `label` after the time in the backup's name.
+pub fn backup(saves: &Path, label: Option<&str>) -> Result<Backup, SavesError> {
+    if !saves.is_dir() {
+        return Err(SavesError::NotFound);
+    }
+
+    let mut name = Local::now().format("%Y-%m-%dT%H-%M-%S").to_string();
+    if let Some(label) = label {
+        name.push('-');
+        name.push_str(label);
+    }
+    let dir = backup_dir()?;
+    // Two backups in one second get a counter rather than overwriting each other.
+    let mut n = 1;
+    let (name, file) = loop {
+        let candidate = if n == 1 {
+            name.clone()
+        } else {
+            format!("{name}.{n}")
+        };
+        match File::create_new(dir.join(format!("{candidate}{EXTENSION}"))) {
+            Ok(file) => break (candidate, file),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(err) => return Err(err.into()),
+        }
+    };
+    let path = dir.join(format!("{name}{EXTENSION}"));
+
+    info!(saves = %saves.display(), backup = %path.display(), "backing up saves");
+    let encoder = pack(zstd::Encoder::new(file, 0)?, saves)?;
+    encoder.finish()?.sync_all()?;
+
+    Ok(Backup {
+        name,
+        size: fs::metadata(&path)?.len(),
+        path,
+    })
+}
+
+/// Replace `saves` with what is in `backup`.
+///
+/// The backup is unpacked next to `saves` first, so a broken archive leaves the saves untouched,
+/// and the saves it replaces are only deleted once it's in their place.
+pub fn restore(saves: &Path, backup: &Backup) -> Result<(), SavesError> {
+    let staging = saves.with_file_name(".vapor-restore");
+    let replaced = saves.with_file_name(".vapor-replaced");
+    for leftover in [&staging, &replaced] {
+        if leftover.exists() {
+            fs::remove_dir_all(leftover)?;
+        }
+    }
+    fs::create_dir_all(&staging)?;
+
+    info!(backup = %backup.path.display(), saves = %saves.display(), "restoring saves");
+    let unpacked =
+        zstd::Decoder::new(File::open(&backup.path)?).and_then(|decoder| unpack(decoder, &staging));
+    if let Err(err) = unpacked {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(err.into());
+    }
+
+    let had_saves = saves.exists();
+    if had_saves {
+        fs::rename(saves, &replaced)?;
+    }
+    if let Err(err) = fs::rename(&staging, saves) {
+        if had_saves {
+            fs::rename(&replaced, saves)?;
+        }
+        let _ = fs::remove_dir_all(&staging);
+        return Err(err.into());
+    }
+    if had_saves {
+        fs::remove_dir_all(&replaced)?;
+    }
+    Ok(())
+}
+
+/// Write everything in `dir` to `out` as a tar archive, with paths relative to `dir`.
+fn pack<W: Write>(out: W, dir: &Path) -> io::Result<W> {
+    let mut builder = tar::Builder::new(out);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    builder.into_inner()
+}
+
+/// Unpack the tar archive in `input` into `dir`, keeping modification times.
+///
+/// Entries that would land outside `dir` are refused.
+fn unpack(input: impl Read, dir: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(input);
+    archive.set_preserve_mtime(true);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.unpack_in(dir)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "backup entry outside the save directory: {}",
+                    entry.path()?.display()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// `dir` packed and unpacked into a fresh directory.
+    fn round_trip(dir: &Path) -> io::Result<TempDir> {
+        let archive = pack(vec![], dir)?;
+        let out = TempDir::new()?;
+        unpack(archive.as_slice(), out.path())?;
+        Ok(out)
+    }
+
+    #[test]
+    fn round_trips_files_and_directories() {
+        let saves = TempDir::new().unwrap();
+        let slot = saves.path().join("ManualSave-0");
+        fs::create_dir_all(&slot).unwrap();
+        fs::write(slot.join("sav.dat"), vec![7u8; 512 * 3 + 1]).unwrap();
+        fs::write(slot.join("metadata.9.json"), "{}").unwrap();
+        fs::create_dir_all(saves.path().join("Empty")).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::options()
+            .write(true)
+            .open(slot.join("sav.dat"))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let out = round_trip(saves.path()).unwrap();
+        let restored = out.path().join("ManualSave-0");
+        assert_eq!(
+            fs::read(restored.join("sav.dat")).unwrap(),
+            vec![7u8; 512 * 3 + 1]
+        );
+        assert_eq!(
+            fs::read_to_string(restored.join("metadata.9.json")).unwrap(),
+            "{}"
+        );
+        assert!(out.path().join("Empty").is_dir());
+        assert_eq!(
+            fs::metadata(restored.join("sav.dat"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+    }
+
+    #[test]
+    fn round_trips_long_paths() {
+        let saves = TempDir::new().unwrap();
+        let dir = saves.path().join("d".repeat(90)).join("e".repeat(40));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("f".repeat(120)), "save").unwrap();
+
+        let out = round_trip(saves.path()).unwrap();
+        let restored = out
+            .path()
+            .join("d".repeat(90))
+            .join("e".repeat(40))
+            .join("f".repeat(120));
+        assert_eq!(fs::read_to_string(restored).unwrap(), "save");
+    }
+
+    #[test]
+    fn refuses_entries_outside_the_directory() {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..10].copy_from_slice(b"../escaped");
+        header.set_size(0);
+        header.set_cksum();
+        let mut archive = header.as_bytes().to_vec();
+        archive.extend([0; 1024]);
+
+        let out = TempDir::new().unwrap();
+        let err = unpack(archive.as_slice(), out.path()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(!out.path().with_file_name("escaped").exists());
+    }
+
+    #[test]
+    fn restoring_replaces_the_saves() {
+        let dir = TempDir::new().unwrap();
+        let saves = dir.path().join("Cyberpunk 2077");
+        fs::create_dir_all(saves.join("ManualSave-0")).unwrap();
+        fs::write(saves.join("ManualSave-0/sav.dat"), "old").unwrap();
+
+        let path = dir.path().join("backup.tar.zst");
+        let encoder = zstd::Encoder::new(File::create(&path).unwrap(), 0).unwrap();
+        pack(encoder, &saves).unwrap().finish().unwrap();
+        fs::write(saves.join("ManualSave-0/sav.dat"), "new").unwrap();
+        fs::write(saves.join("stray.dat"), "").unwrap();
+
+        let backup = Backup {
+            name: "backup".to_string(),
+            size: 0,
+            path,
+        };
+        restore(&saves, &backup).unwrap();
+        assert_eq!(
+            fs::read_to_string(saves.join("ManualSave-0/sav.dat")).unwrap(),
+            "old"
+        );
+        assert!(!saves.join("stray.dat").exists());
+        assert!(!dir.path().join(".vapor-replaced").exists());
+        assert!(!dir.path().join(".vapor-restore").exists());
+    }
+}
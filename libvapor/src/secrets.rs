@@ -0,0 +1,220 @@
+//! Credentials, kept out of `Vapor.toml`.
+//!
+//! Secrets go to the system keyring through the Secret Service when one is running, and to
+//! `secrets.toml` in the config directory, readable only by the user, otherwise.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::platform;
+
+/// Name the Nexus API key is stored under.
+pub const NEXUS: &str = "nexus";
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum SecretError {
+    #[error("io error: `{0}`")]
+    Io(#[from] io::Error),
+    #[error("Deserialization error: `{0}`")]
+    De(#[from] toml::de::Error),
+    #[error("Serialization error: `{0}`")]
+    Ser(#[from] toml::ser::Error),
+    #[error("Environment variable `{0}` is not set")]
+    MissingVar(String),
+    #[error("Invalid secret reference `{0}`")]
+    #[diagnostic(help(
+        "Use `env:VARIABLE` or `file:PATH`, or unset it to use the key from `vapor auth login`"
+    ))]
+    InvalidReference(String),
+}
+
+/// Where a secret is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Store {
+    Keyring,
+    File(PathBuf),
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keyring => write!(f, "the system keyring"),
+            Self::File(path) => write!(f, "`{}`", path.display()),
+        }
+    }
+}
+
+/// Store `secret` under `name`, returning where it went.
+pub fn store(name: &str, secret: &str) -> Result<Store, SecretError> {
+    if os_keyring::set(name, secret) {
+        // Don't leave an older copy behind in plain text.
+        remove_from_file(name)?;
+        return Ok(Store::Keyring);
+    }
+
+    let path = file_path()?;
+    let mut secrets = read_file()?;
+    secrets.insert(name.to_string(), secret.to_string());
+    write_file(&secrets)?;
+
+    Ok(Store::File(path))
+}
+
+/// The secret stored under `name`, and where it was found.
+pub fn load(name: &str) -> Result<Option<(String, Store)>, SecretError> {
+    if let Some(secret) = os_keyring::get(name) {
+        return Ok(Some((secret, Store::Keyring)));
+    }
+
+    Ok(read_file()?
+        .remove(name)
+        .map(|secret| (secret, Store::File(file_path().unwrap_or_default()))))
+}
+
+/// Remove the secret stored under `name` everywhere, returning where it was.
+pub fn remove(name: &str) -> Result<Vec<Store>, SecretError> {
+    let mut removed = vec![];
+
+    if os_keyring::delete(name) {
+        removed.push(Store::Keyring);
+    }
+    if remove_from_file(name)? {
+        removed.push(Store::File(file_path()?));
+    }
+
+    Ok(removed)
+}
+
+/// Resolve a secret from a config reference, falling back to the one stored under `name`.
+///
+/// References are `env:VARIABLE` or `file:PATH`.
+pub fn resolve(reference: Option<&str>, name: &str) -> Result<Option<String>, SecretError> {
+    let Some(reference) = reference else {
+        return Ok(load(name)?.map(|(secret, _)| secret));
+    };
+
+    if let Some(var) = reference.strip_prefix("env:") {
+        std::env::var(var)
+            .map(Some)
+            .map_err(|_| SecretError::MissingVar(var.to_string()))
+    } else if let Some(path) = reference.strip_prefix("file:") {
+        let path = shellexpand::tilde(path);
+        Ok(Some(fs::read_to_string(path.as_ref())?.trim().to_string()))
+    } else {
+        Err(SecretError::InvalidReference(reference.to_string()))
+    }
+}
+
+fn file_path() -> Result<PathBuf, io::Error> {
+    platform::place_config_file("secrets.toml")
+}
+
+fn read_file() -> Result<BTreeMap<String, String>, SecretError> {
+    match fs::read_to_string(file_path()?) {
+        Ok(contents) => Ok(toml::from_str(&contents)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_file(secrets: &BTreeMap<String, String>) -> Result<(), SecretError> {
+    let path = file_path()?;
+
+    if secrets.is_empty() {
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+            _ => return Ok(()),
+        }
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // `mode` only applies to new files.
+        if path.exists() {
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    options
+        .open(&path)?
+        .write_all(toml::to_string_pretty(secrets)?.as_bytes())?;
+
+    Ok(())
+}
+
+fn remove_from_file(name: &str) -> Result<bool, SecretError> {
+    let mut secrets = read_file()?;
+    if secrets.remove(name).is_none() {
+        return Ok(false);
+    }
+
+    write_file(&secrets)?;
+    Ok(true)
+}
+
+/// The system keyring, where one is supported and running.
+///
+/// Every failure counts as the keyring being unavailable, so callers fall back to the file.
+#[cfg(target_os = "linux")]
+mod os_keyring {
+    use keyring::Entry;
+
+    use super::{debug, platform};
+
+    /// `name` under the service named after the profile, so profiles don't share secrets.
+    fn entry(name: &str) -> keyring::Result<Entry> {
+        let service =
+            platform::app_name().map_err(|err| keyring::Error::PlatformFailure(Box::new(err)))?;
+        Entry::new(&service, name)
+    }
+
+    pub fn set(name: &str, secret: &str) -> bool {
+        entry(name)
+            .and_then(|entry| entry.set_password(secret))
+            .inspect_err(|err| debug!(%err, "keyring unavailable"))
+            .is_ok()
+    }
+
+    pub fn get(name: &str) -> Option<String> {
+        entry(name)
+            .and_then(|entry| entry.get_password())
+            .inspect_err(|err| debug!(%err, "no secret in keyring"))
+            .ok()
+    }
+
+    pub fn delete(name: &str) -> bool {
+        entry(name)
+            .and_then(|entry| entry.delete_credential())
+            .is_ok()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod os_keyring {
+    use super::debug;
+
+    pub fn set(_name: &str, _secret: &str) -> bool {
+        debug!("no keyring support on this platform");
+        false
+    }
+
+    pub fn get(_name: &str) -> Option<String> {
+        None
+    }
+
+    pub fn delete(_name: &str) -> bool {
+        false
+    }
+}
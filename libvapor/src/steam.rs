@@ -0,0 +1,134 @@
+//! Finding Steam libraries, including those of Flatpak Steam, on SD cards and on Windows.
+//!
+//! Flatpak Steam keeps its data under `~/.var/app`, but shows it to games and users as
+//! `~/.local/share/Steam`, so paths copied from it may not exist outside the sandbox.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{launch::STEAM_APP_ID, platform};
+
+/// Where Flatpak Steam keeps what it calls `~/.local/share/Steam`, relative to home.
+const FLATPAK_DATA: &str = ".var/app/com.valvesoftware.Steam";
+
+/// Where Steam may be installed, most likely first.
+#[cfg(not(windows))]
+fn candidates() -> Vec<PathBuf> {
+    let Some(home) = std::env::home_dir() else {
+        return vec![];
+    };
+
+    vec![
+        home.join(".local/share/Steam"),
+        home.join(".steam/steam"),
+        home.join(FLATPAK_DATA).join(".local/share/Steam"),
+    ]
+}
+
+/// Where Steam may be installed, most likely first.
+///
+/// Steam records where it is in the registry, which `reg` reads without needing a crate for it.
+#[cfg(windows)]
+fn candidates() -> Vec<PathBuf> {
+    let registered = std::process::Command::new("reg")
+        .args(["query", r"HKCU\Software\Valve\Steam", "/v", "SteamPath"])
+        .output()
+        .ok()
+        .and_then(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            let line = stdout.lines().find(|line| line.contains("REG_SZ"))?;
+            let (_, path) = line.split_once("REG_SZ")?;
+            Some(PathBuf::from(path.trim()))
+        });
+
+    registered
+        .into_iter()
+        .chain([PathBuf::from(r"C:\Program Files (x86)\Steam")])
+        .collect()
+}
+
+/// Steam installs, native ones first.
+pub fn roots() -> Vec<PathBuf> {
+    let mut roots = Vec::<PathBuf>::new();
+    for root in candidates() {
+        if root.is_dir() {
+            let root = resolve(&root);
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+    }
+    roots
+}
+
+/// Every library of every Steam install, from `steamapps/libraryfolders.vdf`.
+pub fn libraries() -> Vec<PathBuf> {
+    let mut libraries = Vec::<PathBuf>::new();
+    for root in roots() {
+        let folders = fs::read_to_string(root.join("steamapps").join("libraryfolders.vdf"))
+            .unwrap_or_default();
+        let listed = folders.lines().filter_map(|line| {
+            let mut parts = line.split('"').filter(|part| !part.trim().is_empty());
+            // Windows paths come with their backslashes escaped.
+            (parts.next()? == "path").then(|| {
+                parts
+                    .next()
+                    .map(|path| resolve(Path::new(&path.replace(r"\\", r"\"))))
+            })?
+        });
+
+        for library in std::iter::once(root.clone()).chain(listed) {
+            if library.join("steamapps").is_dir() && !libraries.contains(&library) {
+                libraries.push(library);
+            }
+        }
+    }
+    libraries
+}
+
+/// The `Cyberpunk 2077` directory in any Steam library.
+pub fn find_game() -> Option<PathBuf> {
+    libraries().into_iter().find_map(|library| {
+        let steamapps = library.join("steamapps");
+        let manifest =
+            fs::read_to_string(steamapps.join(format!("appmanifest_{STEAM_APP_ID}.acf"))).ok()?;
+        let installdir = manifest.lines().find_map(|line| {
+            let mut parts = line.split('"').filter(|part| !part.trim().is_empty());
+            (parts.next()? == "installdir").then(|| parts.next().map(ToString::to_string))?
+        })?;
+
+        let game = steamapps.join("common").join(installdir);
+        game.is_dir().then_some(game)
+    })
+}
+
+/// `path` as it is outside the Flatpak sandbox, with `~` expanded and symlinks resolved.
+///
+/// Paths that don't exist as given are looked up in Flatpak Steam's data, and returned unchanged
+/// if they aren't there either. On Windows, `path` is only [`platform::normalize`]d, since
+/// canonical paths there are `\\?\` paths that can't be joined with `/`.
+pub fn resolve(path: &Path) -> PathBuf {
+    let path = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref());
+    if cfg!(windows) {
+        return platform::normalize(&path);
+    }
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+
+    std::env::home_dir()
+        .and_then(|home| {
+            let inside = path.strip_prefix(&home).ok()?;
+            home.join(FLATPAK_DATA).join(inside).canonicalize().ok()
+        })
+        .unwrap_or(path)
+}
+
+/// The library `path` is in, the directory holding its `steamapps`.
+pub fn library_of(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "steamapps"))?
+        .parent()
+}
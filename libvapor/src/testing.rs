@@ -0,0 +1,185 @@
+//! Fake games and archives for scenario tests, so tests of Vapor and of frontends built on it can
+//! drive a [`ModHandler`] end to end without real mod files. Behind the `test-util` feature.
+//!
+//! ```
+//! use libvapor::testing::FakeGame;
+//!
+//! let game = FakeGame::new().unwrap();
+//! let handler = game.handler();
+//! game.add(&handler, "My Mod", "1.0", &[("r6/scripts/my_mod/main.reds", "// hi")])
+//!     .unwrap();
+//! assert!(game.exists("r6/scripts/my_mod/main.reds"));
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    migrate::SCHEMA_VERSION,
+    mod_manager::handler::{AddOptions, DISABLED_DIR, ModError, ModHandler, OperationReport},
+};
+
+/// Tells apart fake games made by one process at the same time.
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// Files every fake game starts with, like a fresh install.
+const GAME_FILES: &[&str] = &[
+    "bin/x64/Cyberpunk2077.exe",
+    "archive/pc/content/basegame_1_engine.archive",
+    "r6/config/inputUserMappings.xml",
+];
+
+/// An empty temporary directory, deleted when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> io::Result<Self> {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!(
+            "vapor-test-{}-{stamp}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A `Cyberpunk 2077` install in a temporary directory, with an empty `mods.toml`, deleted when
+/// dropped.
+pub struct FakeGame {
+    root: PathBuf,
+    archives: PathBuf,
+    _dir: TempDir,
+}
+
+impl FakeGame {
+    pub fn new() -> io::Result<Self> {
+        let dir = TempDir::new()?;
+        let game = Self {
+            root: dir.path().join("game"),
+            archives: dir.path().join("archives"),
+            _dir: dir,
+        };
+
+        for file in GAME_FILES {
+            game.write(file, "")?;
+        }
+        fs::write(
+            game.root.join("mods.toml"),
+            format!("schema_version = {SCHEMA_VERSION}"),
+        )?;
+        fs::create_dir_all(game.root.join(DISABLED_DIR))?;
+        fs::create_dir_all(&game.archives)?;
+
+        Ok(game)
+    }
+
+    /// The game directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// A handler for the game, with default settings.
+    pub fn handler(&self) -> ModHandler {
+        ModHandler::new(&self.root)
+    }
+
+    /// Write a zip archive named `file_name` outside the game directory, see [`zip_archive`].
+    pub fn archive<C: AsRef<[u8]>>(
+        &self,
+        file_name: &str,
+        entries: &[(&str, C)],
+    ) -> io::Result<PathBuf> {
+        let path = self.archives.join(file_name);
+        zip_archive(&path, entries)?;
+        Ok(path)
+    }
+
+    /// Add a mod with `entries` through `handler`.
+    pub fn add<C: AsRef<[u8]>>(
+        &self,
+        handler: &ModHandler,
+        name: &str,
+        version: &str,
+        entries: &[(&str, C)],
+    ) -> Result<OperationReport, ModError> {
+        self.add_with(handler, name, version, entries, &AddOptions::default())
+    }
+
+    /// Like [`FakeGame::add`], with `options` like dependencies.
+    pub fn add_with<C: AsRef<[u8]>>(
+        &self,
+        handler: &ModHandler,
+        name: &str,
+        version: &str,
+        entries: &[(&str, C)],
+        options: &AddOptions,
+    ) -> Result<OperationReport, ModError> {
+        let archive = self.archive(&format!("{name}-{version}.zip"), entries)?;
+        handler.add_mod(&archive, name, version, options)
+    }
+
+    /// Write `contents` to `path`, relative to the game directory, like a file the game or
+    /// another tool made.
+    pub fn write(&self, path: &str, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let path = self.root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+
+    /// Contents of `path`, relative to the game directory.
+    pub fn read(&self, path: &str) -> io::Result<String> {
+        fs::read_to_string(self.root.join(path))
+    }
+
+    /// Check if there's anything at `path`, relative to the game directory.
+    pub fn exists(&self, path: &str) -> bool {
+        self.root.join(path).symlink_metadata().is_ok()
+    }
+}
+
+/// Write a zip archive to `path` with each of `entries`, as its path and contents. Paths ending
+/// in `/` are directories, with their contents ignored.
+pub fn zip_archive<C: AsRef<[u8]>>(path: &Path, entries: &[(&str, C)]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default();
+
+    for (name, contents) in entries {
+        if name.ends_with('/') {
+            zip.add_directory(*name, options)
+                .map_err(io::Error::other)?;
+        } else {
+            zip.start_file(*name, options).map_err(io::Error::other)?;
+            zip.write_all(contents.as_ref())?;
+        }
+    }
+
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! Moving files to the desktop trash rather than deleting them, through the `trash` crate: the
+//! freedesktop.org trash on Linux and the BSDs, the Recycle Bin on Windows and the Finder's trash
+//! on macOS.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use tracing::debug;
+
+/// Move `path` to the trash, returning where it ended up when the trash keeps it somewhere it can
+/// be moved back from, like the freedesktop.org trash does.
+pub fn put(path: &Path) -> io::Result<Option<PathBuf>> {
+    let path = std::path::absolute(path)?;
+    // Earlier trashes of the same path, told apart from this one by what was there before.
+    let before = trashed(&path);
+    debug!(path = %path.display(), "moving to trash");
+    ::trash::delete(&path).map_err(io::Error::other)?;
+
+    Ok(trashed(&path)
+        .into_iter()
+        .find(|trashed| !before.contains(trashed)))
+}
+
+/// Where the freedesktop.org trash keeps everything trashed from `path`.
+#[cfg(all(
+    unix,
+    not(target_os = "macos"),
+    not(target_os = "ios"),
+    not(target_os = "android")
+))]
+fn trashed(path: &Path) -> Vec<PathBuf> {
+    let Ok(items) = ::trash::os_limited::list() else {
+        return vec![];
+    };
+    items
+        .into_iter()
+        .filter(|item| item.original_path() == path)
+        .filter_map(|item| {
+            // The id is the item's `.trashinfo` file, in `info` beside the `files` it describes.
+            let info = Path::new(&item.id);
+            Some(
+                info.parent()?
+                    .parent()?
+                    .join("files")
+                    .join(info.file_stem()?),
+            )
+        })
+        .collect()
+}
+
+#[cfg(not(all(
+    unix,
+    not(target_os = "macos"),
+    not(target_os = "ios"),
+    not(target_os = "android")
+)))]
+fn trashed(_: &Path) -> Vec<PathBuf> {
+    vec![]
+}
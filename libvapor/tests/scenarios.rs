@@ -0,0 +1,296 @@
+//! Mods installed, moved and undone end to end on a [`FakeGame`].
+
+use libvapor::{
+    init::DeployMode,
+    mod_manager::{
+        handler::{AddOptions, DISABLED_DIR, Impact, ModError, Move, Operation, STAGING_DIR},
+        journal::Journal,
+    },
+    resolver::Request,
+    testing::FakeGame,
+};
+
+const SCRIPT: &str = "r6/scripts/my_mod/main.reds";
+
+#[test]
+fn add_disable_enable_remove() {
+    let game = FakeGame::new().unwrap();
+    let handler = game.handler();
+
+    game.add(&handler, "My Mod", "1.0", &[(SCRIPT, "// hi")])
+        .unwrap();
+    assert_eq!(game.read(SCRIPT).unwrap(), "// hi");
+
+    handler.move_mod("My Mod", Move::Disable).unwrap();
+    assert!(!game.exists(SCRIPT));
+    assert!(game.exists(&format!("{DISABLED_DIR}/{SCRIPT}")));
+
+    handler.move_mod("My Mod", Move::Enable).unwrap();
+    assert!(game.exists(SCRIPT));
+
+    let report = handler.remove_mod("My Mod").unwrap();
+    assert!(matches!(report.operation, Operation::Removed(_)));
+    assert_eq!((report.files, report.bytes), (1, 5));
+    assert!(!game.exists(SCRIPT));
+    assert!(handler.load_toml().unwrap().mods.is_empty());
+}
+
+#[test]
+fn undo_puts_game_files_back() {
+    let game = FakeGame::new().unwrap();
+    let journal = game.root().with_file_name("journal.jsonl");
+    let handler = game.handler().with_journal(Journal::new(&journal));
+    let engine = "archive/pc/content/basegame_1_engine.archive";
+    game.write(engine, "vanilla").unwrap();
+
+    game.add(&handler, "Engine", "1.0", &[(engine, "modded")])
+        .unwrap();
+    assert_eq!(game.read(engine).unwrap(), "modded");
+
+    let undone = handler.undo(1).unwrap();
+    assert_eq!(undone[0].name, "Engine");
+    assert!(matches!(undone[0].operation, Operation::Undone { .. }));
+    assert_eq!(game.read(engine).unwrap(), "vanilla");
+    assert!(handler.load_toml().unwrap().mods.is_empty());
+}
+
+/// Flip the checksum of every entry of the archive at `path`, so extracting it fails after
+/// everything read before extraction passed.
+fn break_checksums(path: &std::path::Path) {
+    let mut bytes = std::fs::read(path).unwrap();
+    for i in 0..bytes.len().saturating_sub(4) {
+        match &bytes[i..i + 4] {
+            b"PK\x03\x04" => bytes[i + 14] ^= 0xff,
+            b"PK\x01\x02" => bytes[i + 16] ^= 0xff,
+            _ => {}
+        }
+    }
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn failed_linked_update_keeps_the_installed_version() {
+    let game = FakeGame::new().unwrap();
+    let mut handler = game.handler();
+    handler.deploy_mode = DeployMode::Symlink;
+
+    game.add(&handler, "My Mod", "1.0", &[(SCRIPT, "// one")])
+        .unwrap();
+    let archive = game
+        .archive("My Mod-2.0.zip", &[(SCRIPT, "// two")])
+        .unwrap();
+    break_checksums(&archive);
+
+    let result = handler.add_mod(&archive, "My Mod", "2.0", &AddOptions::default());
+    assert!(matches!(result, Err(ModError::Checksum { .. })));
+    assert_eq!(game.read(SCRIPT).unwrap(), "// one");
+    assert_eq!(
+        handler.load_toml().unwrap().get("My Mod").unwrap().version,
+        "1.0"
+    );
+}
+
+#[test]
+fn undoing_a_linked_update_relinks_the_old_version() {
+    let game = FakeGame::new().unwrap();
+    let journal = game.root().with_file_name("journal.jsonl");
+    let mut handler = game.handler().with_journal(Journal::new(&journal));
+    handler.deploy_mode = DeployMode::Symlink;
+
+    game.add(&handler, "My Mod", "1.0", &[(SCRIPT, "// one")])
+        .unwrap();
+    game.add(&handler, "My Mod", "2.0", &[(SCRIPT, "// two")])
+        .unwrap();
+    assert_eq!(game.read(SCRIPT).unwrap(), "// two");
+    assert!(game.root().join(SCRIPT).is_symlink());
+
+    handler.undo(1).unwrap();
+    assert_eq!(game.read(SCRIPT).unwrap(), "// one");
+    assert!(game.root().join(SCRIPT).is_symlink());
+    assert_eq!(
+        handler.load_toml().unwrap().get("My Mod").unwrap().version,
+        "1.0"
+    );
+}
+
+#[test]
+fn disabling_a_dependency_needs_cascade_and_stops_at_pins() {
+    let game = FakeGame::new().unwrap();
+    let handler = game.handler();
+    game.add(&handler, "Base", "1.0", &[("r6/scripts/base.reds", "")])
+        .unwrap();
+    let options = AddOptions {
+        dependencies: vec!["Base".into()],
+        ..Default::default()
+    };
+    game.add_with(&handler, "Addon", "1.0", &[(SCRIPT, "")], &options)
+        .unwrap();
+
+    let planned = || {
+        handler
+            .plan(&Request::Disable("Base".to_string()))
+            .unwrap()
+            .disable
+    };
+    assert!(matches!(
+        handler.dependents_to_disable(&["Base"], planned(), Impact::Refuse),
+        Err(ModError::HasDependents { .. })
+    ));
+    assert!(
+        handler
+            .dependents_to_disable(&["Base"], planned(), Impact::Force)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        handler
+            .dependents_to_disable(&["Base"], planned(), Impact::Cascade)
+            .unwrap(),
+        ["Addon"]
+    );
+
+    // Dependents being disabled too don't count.
+    assert!(
+        handler
+            .dependents_to_disable(&["Base", "Addon"], planned(), Impact::Refuse)
+            .unwrap()
+            .is_empty()
+    );
+
+    handler.pin("Addon", true).unwrap();
+    assert!(matches!(
+        handler.dependents_to_disable(&["Base"], planned(), Impact::Cascade),
+        Err(ModError::PinnedDependent { .. })
+    ));
+}
+
+#[test]
+fn linked_mods_are_staged_and_relinked() {
+    let game = FakeGame::new().unwrap();
+    let mut handler = game.handler();
+    handler.deploy_mode = DeployMode::Symlink;
+    let staged = game.root().join(STAGING_DIR).join("My Mod").join(SCRIPT);
+
+    game.add(&handler, "My Mod", "1.0", &[(SCRIPT, "// hi")])
+        .unwrap();
+    assert!(game.root().join(SCRIPT).is_symlink());
+    assert_eq!(game.read(SCRIPT).unwrap(), "// hi");
+    assert!(staged.is_file());
+
+    handler.move_mod("My Mod", Move::Disable).unwrap();
+    assert!(!game.exists(SCRIPT));
+    assert!(!game.exists(&format!("{DISABLED_DIR}/{SCRIPT}")));
+    assert!(staged.is_file());
+
+    handler.move_mod("My Mod", Move::Enable).unwrap();
+    assert!(game.root().join(SCRIPT).is_symlink());
+    assert_eq!(game.read(SCRIPT).unwrap(), "// hi");
+
+    handler.remove_mod("My Mod").unwrap();
+    assert!(!game.exists(SCRIPT));
+    assert!(!staged.exists());
+}
+
+#[test]
+fn failed_transactions_roll_back_every_change() {
+    let game = FakeGame::new().unwrap();
+    let handler = game.handler();
+    game.add(&handler, "Base", "1.0", &[("r6/scripts/base.reds", "")])
+        .unwrap();
+
+    let result = handler.transaction("install".to_string(), || {
+        game.add(&handler, "My Mod", "1.0", &[(SCRIPT, "// hi")])?;
+        handler.move_mod("Base", Move::Disable)?;
+        handler.move_mod("Missing", Move::Disable)
+    });
+    assert!(matches!(result, Err(ModError::MissingMod { .. })));
+    assert!(!game.exists(SCRIPT));
+    assert!(game.exists("r6/scripts/base.reds"));
+
+    let toml = handler.load_toml().unwrap();
+    assert!(!toml.contains("My Mod"));
+    assert!(toml.get("Base").unwrap().installed);
+}
+
+#[test]
+fn transactions_undo_as_one() {
+    let game = FakeGame::new().unwrap();
+    let journal = game.root().with_file_name("journal.jsonl");
+    let handler = game.handler().with_journal(Journal::new(&journal));
+
+    handler
+        .transaction("install".to_string(), || {
+            game.add(&handler, "Base", "1.0", &[("r6/scripts/base.reds", "")])?;
+            game.add(&handler, "My Mod", "1.0", &[(SCRIPT, "// hi")])
+        })
+        .unwrap();
+    assert_eq!(handler.load_toml().unwrap().mods.len(), 2);
+
+    handler.undo(1).unwrap();
+    assert!(!game.exists(SCRIPT));
+    assert!(!game.exists("r6/scripts/base.reds"));
+    assert!(handler.load_toml().unwrap().mods.is_empty());
+}
+
+#[test]
+fn fix_moves_gathers_split_mods() {
+    let game = FakeGame::new().unwrap();
+    let handler = game.handler();
+    let files = [
+        "r6/scripts/a.reds",
+        "r6/scripts/b.reds",
+        "r6/scripts/c.reds",
+    ];
+    game.add(&handler, "My Mod", "1.0", &files.map(|file| (file, "")))
+        .unwrap();
+
+    // Most of a disable that was cut short.
+    for file in &files[..2] {
+        let disabled = game.root().join(DISABLED_DIR).join(file);
+        std::fs::create_dir_all(disabled.parent().unwrap()).unwrap();
+        std::fs::rename(game.root().join(file), disabled).unwrap();
+    }
+
+    let fixed = handler.fix_moves().unwrap();
+    assert_eq!(fixed.len(), 1);
+    assert!(fixed[0].action == Move::Disable && fixed[0].completed);
+    assert_eq!(fixed[0].files, 1);
+    for file in files {
+        assert!(!game.exists(file));
+        assert!(game.exists(&format!("{DISABLED_DIR}/{file}")));
+    }
+    assert!(
+        !handler
+            .load_toml()
+            .unwrap()
+            .get("My Mod")
+            .unwrap()
+            .installed
+    );
+
+    assert!(handler.fix_moves().unwrap().is_empty());
+}
+
+#[test]
+fn adopting_stays_inside_the_game() {
+    let game = FakeGame::new().unwrap();
+    let handler = game.handler();
+    let outside = game.root().with_file_name("outside.txt");
+    std::fs::write(&outside, "").unwrap();
+
+    for pattern in [
+        "../outside.txt",
+        "r6/../../*.txt",
+        &outside.to_string_lossy(),
+    ] {
+        assert!(matches!(
+            handler.adopt_mod("Stray", "1.0", &[pattern.to_string()]),
+            Err(ModError::OutsideGame(_))
+        ));
+    }
+
+    let report = handler
+        .adopt_mod("Config", "1.0", &["./r6/config/*.xml".to_string()])
+        .unwrap();
+    assert_eq!(report.files, 1);
+}
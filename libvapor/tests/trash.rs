@@ -0,0 +1,36 @@
+//! Trashing into a trash of its own, in a test binary of its own since the trash is found through
+//! `XDG_DATA_HOME`.
+
+#![cfg(all(
+    unix,
+    not(target_os = "macos"),
+    not(target_os = "ios"),
+    not(target_os = "android")
+))]
+
+use libvapor::{testing::TempDir, trash};
+
+#[test]
+fn trashed_files_can_be_found_again() {
+    let dir = TempDir::new().unwrap();
+    // SAFETY: the only test here, so nothing else reads the environment meanwhile.
+    unsafe { std::env::set_var("XDG_DATA_HOME", dir.path().join("data")) };
+
+    let mods = dir.path().join("removed/My Mod");
+    std::fs::create_dir_all(&mods).unwrap();
+    std::fs::write(mods.join("main.reds"), "// hi").unwrap();
+
+    let trashed = trash::put(&mods).unwrap().unwrap();
+    assert!(!mods.exists());
+    assert!(trashed.starts_with(dir.path().join("data/Trash/files")));
+    assert_eq!(
+        std::fs::read_to_string(trashed.join("main.reds")).unwrap(),
+        "// hi"
+    );
+
+    // A second one of the same name is kept apart.
+    std::fs::create_dir_all(&mods).unwrap();
+    let again = trash::put(&mods).unwrap().unwrap();
+    assert_ne!(again, trashed);
+    assert!(again.is_dir() && trashed.is_dir());
+}
@@ -1,58 +1,969 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use libvapor::download_cache;
+use libvapor::hooks::Event;
+use libvapor::init::ColorChoice;
+use libvapor::mod_manager::handler::Impact;
+use libvapor::mod_manager::mod_file_formats::FileKind;
+use libvapor::mod_manager::name::ModName;
 
 /// A Cyberpunk 2077 mod manager for Linux.
 #[derive(Parser, Debug)]
 pub struct CyberArgs {
     #[command(subcommand)]
     pub cmds: Command,
+
+    /// Game to manage, defaults to `cyberpunk2077`.
+    #[arg(long, global = true)]
+    pub game: Option<String>,
+
+    /// Install of `Cyberpunk 2077` to manage, defaults to the one picked with `vapor instance use`.
+    #[arg(long, global = true)]
+    pub instance: Option<String>,
+
+    /// Game directory to manage instead of the configured one, even without a working `Vapor.toml`.
+    ///
+    /// Settings still come from the config when it loads, but instances and its staging directory
+    /// are ignored.
+    #[arg(long, global = true, env = "VAPOR_ROOT", conflicts_with = "instance")]
+    pub root: Option<PathBuf>,
+
+    /// Accept a single close match when a mod name isn't found.
+    #[arg(long, global = true)]
+    pub fuzzy: bool,
+
+    /// Print what would change on disk without changing anything.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Keep removed files for `vapor undo` instead of moving them to the trash, whatever `trash`
+    /// in the config says.
+    #[arg(long, global = true)]
+    pub no_trash: bool,
+
+    /// Skip confirmation prompts.
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// Never go online, failing anything that needs Nexus, GitHub or a download, whatever
+    /// `[network]` in the config says.
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Threads used to extract and hash files, defaults to the number of CPUs.
+    #[arg(short, long, global = true)]
+    pub jobs: Option<usize>,
+
+    /// Language of output, like `pl` or `pt-BR`, instead of the one from `LC_MESSAGES`.
+    #[arg(long, global = true, value_name = "LANG")]
+    pub lang: Option<String>,
+
+    /// When to color output, overriding `color` in the config.
+    #[arg(long, global = true, value_name = "WHEN")]
+    pub color: Option<Color>,
+
+    /// Log more to stderr, repeat for even more.
+    ///
+    /// Logs are always written to `$XDG_STATE_HOME/vapor/logs` as well.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
 }
 
+// Parsed once, so `add` carrying most of the flags doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Initialize `vapor`.
-    Init,
+    ///
+    /// With `--game`, adds another game to an existing setup.
+    Init {
+        /// Top level directories mods install into for the new game.
+        ///
+        /// This should be passed by a comma (`,`) delimited list.
+        #[arg(long, value_delimiter = ',')]
+        root_dirs: Vec<String>,
+
+        /// Keep staged and removed files next to the Steam library the game is in rather than in
+        /// the game directory, for games on an SD card.
+        #[arg(long)]
+        sdcard: bool,
+    },
     /// Get status of mods.
     Status {
+        /// Only show mods whose name matches this glob pattern.
+        pattern: Option<String>,
+
         /// JSON output.
         #[arg(long)]
         json: bool,
+
+        /// Print counts on one line instead, exiting with a bit set per kind of problem.
+        ///
+        /// Bits are 1 for missing dependencies, 2 for conflicts, 4 for game version
+        /// incompatibilities, 8 for modified files and 16 for available updates.
+        #[arg(long)]
+        summary: bool,
+
+        /// With `--summary`, also check frameworks for updates.
+        #[arg(long, requires = "summary")]
+        updates: bool,
+
+        /// Only show mods with problems: missing dependencies or files, conflicts, modified
+        /// files, or game version incompatibilities.
+        #[arg(long, conflicts_with = "summary")]
+        problems_only: bool,
+
+        /// Stat every file of each mod, listing missing and empty ones.
+        #[arg(long, conflicts_with = "summary")]
+        deep: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
     },
     /// Add a mod.
     Add {
-        /// Path to mod archive.
+        /// Path to mod archive, a directory of loose files, or `-` to read an archive from stdin.
         file: PathBuf,
 
-        /// Name of mod.
+        /// Name of mod, read from the archive if not given.
         #[arg(short, long)]
-        name: String,
+        name: Option<String>,
 
-        /// Mod version.
+        /// Mod version, read from the archive if not given.
         #[arg(short, long)]
-        version: String,
+        version: Option<String>,
 
         /// Dependencies.
         ///
         /// This should be passed by a comma (`,`) delimited list.
         #[arg(short, long, value_delimiter = ',')]
-        dependencies: Vec<String>,
+        dependencies: Vec<ModName>,
+
+        /// Mods this one works best with, which are never required.
+        ///
+        /// This should be passed by a comma (`,`) delimited list.
+        #[arg(short, long, value_delimiter = ',')]
+        optional_deps: Vec<ModName>,
+
+        /// Game versions this mod is known to work with.
+        ///
+        /// This should be passed by a comma (`,`) delimited list.
+        #[arg(short, long, value_delimiter = ',')]
+        game_versions: Vec<String>,
+
+        /// Other names this mod satisfies dependencies on, like the mod it was forked from.
+        ///
+        /// This should be passed by a comma (`,`) delimited list.
+        #[arg(short, long, value_delimiter = ',')]
+        provides: Vec<ModName>,
+
+        /// Mods that can't be enabled alongside this one.
+        ///
+        /// This should be passed by a comma (`,`) delimited list.
+        #[arg(short, long, value_delimiter = ',')]
+        conflicts: Vec<ModName>,
+
+        /// Update the mod even if it is pinned.
+        #[arg(long)]
+        force: bool,
+
+        /// Replace the mod with an older version than the one installed.
+        #[arg(long)]
+        allow_downgrade: bool,
+
+        /// Add the archive even if it has executables or scripts where mods don't put them.
+        #[arg(long)]
+        trust: bool,
+
+        /// Archive to install from an archive that only holds other archives, by name or glob
+        /// pattern, asked for when there are several. Repeat to install several as one mod.
+        #[arg(long)]
+        inner: Vec<String>,
+
+        /// Directory to install from an archive that bundles several variants of the mod side by
+        /// side, like `Option A`, asked for when not given. Updates reuse the last one.
+        #[arg(long)]
+        variant: Option<String>,
+
+        /// Password of an encrypted archive, asked for when not given.
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Install files that don't match the checksum the archive has for them, as long as
+        /// they're the size it says. Only for archives known to be fine otherwise.
+        #[arg(long)]
+        ignore_crc: bool,
+
+        #[command(flatten)]
+        info: InfoArgs,
     },
-    /// Disable a mod.
-    Disable {
+    /// Go back to a version of a mod installed before, from the archive it came from.
+    Downgrade {
         /// Mod name.
         name: String,
+
+        /// Version to go back to, the one before the installed version if not given.
+        version: Option<String>,
+
+        /// Downgrade the mod even if it is pinned.
+        #[arg(long)]
+        force: bool,
+
+        /// Password of an encrypted archive, asked for when not given.
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Show what adding an archive would do, without installing anything.
+    Inspect {
+        /// Path to mod archive, or a directory of loose files.
+        file: PathBuf,
+
+        /// JSON output.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Claim files already in the game directory as a mod, without extracting anything.
+    Adopt {
+        /// Name of mod.
+        name: String,
+
+        /// Mod version.
+        #[arg(short, long)]
+        version: String,
+
+        /// Glob patterns of files relative to the game directory, directories match every file
+        /// under them.
+        #[arg(required = true)]
+        patterns: Vec<String>,
     },
-    /// Enable a mod.
+    /// Hand files recorded under other mods over to a mod, without reinstalling anything.
+    Chown {
+        /// Glob pattern of files, as shown by `vapor list <mod>`.
+        pattern: String,
+
+        /// Mod to own the files.
+        new_owner: String,
+    },
+    /// Disable mods.
+    Disable {
+        /// Mod names or glob patterns, like `'HD*'`.
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        /// List the files that would move, and any that would be overwritten or are missing,
+        /// without moving them.
+        #[arg(long)]
+        preview: bool,
+
+        #[command(flatten)]
+        impact: ImpactArgs,
+    },
+    /// Enable mods, along with their dependencies.
     Enable {
+        /// Mod names or glob patterns, like `'HD*'`.
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        /// List the files that would move, and any that would be overwritten or are missing,
+        /// without moving them.
+        #[arg(long)]
+        preview: bool,
+    },
+    /// Put back together mods left split between the game and disabled mods by an interrupted
+    /// enable or disable.
+    ///
+    /// A move Vapor was in the middle of is also finished whenever it loads.
+    FixMoves,
+    /// Remove mods and their files.
+    ///
+    /// Files are kept in `.vapor/removed` so this can be undone.
+    Remove {
+        /// Mod names or glob patterns, like `'HD*'`.
+        #[arg(required = true)]
+        names: Vec<String>,
+
+        #[command(flatten)]
+        impact: ImpactArgs,
+    },
+    /// Remove every mod, putting the game back the way it was before Vapor.
+    ///
+    /// Files are kept in `.vapor/removed` so this can be undone one mod at a time.
+    Purge,
+    /// Remove every mod and everything Vapor keeps, like downloads, staged files and history.
+    Reset {
+        /// Keep `Vapor.toml`, so Vapor is ready to use again afterwards.
+        #[arg(long)]
+        keep_config: bool,
+    },
+    /// Delete files in the `dedupe` store that no mod uses anymore.
+    Gc,
+    /// Disable mods that hook into the game's code before a game patch, until `vapor thaw`.
+    ///
+    /// Mods with files in `r6/scripts`, `red4ext` or `bin/x64/plugins` are disabled, along with
+    /// mods that depend on them.
+    Freeze,
+    /// Enable the mods `vapor freeze` disabled again.
+    Thaw,
+    /// Zip up a mod's files as they are installed now, hand edits included.
+    Pack {
+        /// Mod name.
+        name: String,
+
+        /// Archive to write, `<name>-<version>.zip` in the current directory if not given.
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Rename a mod.
+    Rename {
+        /// Current mod name.
+        old: String,
+
+        /// New mod name.
+        new: String,
+    },
+    /// Keep some of a mod's files out of the game directory.
+    Exclude {
         /// Mod name.
         name: String,
+
+        /// Glob pattern matching files from `vapor list <name>`.
+        pattern: String,
     },
     /// List mods or a mod's files
+    ///
+    /// Without a name, lists enabled mods. A glob pattern lists matching mods instead.
     List {
-        /// Mod name.
+        /// Mod name or glob pattern.
         name: Option<String>,
+
+        /// List disabled mods as well.
+        #[arg(short, long)]
+        all: bool,
+
+        /// Show a mod's files as a directory tree, with their sizes and whether they exist.
+        #[arg(long, conflicts_with = "json")]
+        tree: bool,
+
+        /// Print a mod's files as JSON.
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Show what's recorded about a mod.
+    Info {
+        /// Mod name.
+        name: String,
+
+        /// List every version of the mod installed instead, newest first.
+        #[arg(long)]
+        history: bool,
+
+        /// JSON output.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add or remove tags on a mod.
+    Tag {
+        /// Mod name.
+        name: String,
+
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Set a note on a mod, or clear it if empty.
+    Note {
+        /// Mod name.
+        name: String,
+
+        /// Note text.
+        #[arg(default_value = "")]
+        text: String,
+    },
+    /// Set a mod's author, description or homepage, or clear them by passing them empty.
+    Describe {
+        /// Mod name.
+        name: String,
+
+        #[command(flatten)]
+        info: InfoArgs,
+    },
+    /// Open a mod's config files in `$EDITOR`, keeping the changes from being reported as
+    /// corruption.
+    Edit {
+        /// Mod name.
+        name: String,
+
+        /// Files to open instead of the mod's config files, as listed by `vapor list <NAME>`.
+        files: Vec<String>,
+    },
+    /// Open a mod's homepage in the browser.
+    Open {
+        /// Mod name.
+        name: String,
+    },
+    /// Keep mods as they are: out of updates, and not disabled along with their dependencies.
+    Pin {
+        /// Mod names or glob patterns, like `'HD*'`.
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Let pinned mods be updated and disabled again.
+    Unpin {
+        /// Mod names or glob patterns, like `'HD*'`.
+        #[arg(required = true)]
+        names: Vec<String>,
     },
     /// Get a graph of mods installed.
     Graph,
+    /// Find mods by name, tags, description, files or dependencies, best match first.
+    Search {
+        /// Words to look for, all of which have to match.
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// How many mods to show at most.
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print as JSON.
+        #[arg(long)]
+        json: bool,
+
+        /// Search Nexus Mods instead of the installed mods.
+        #[arg(long)]
+        remote: bool,
+
+        /// Download and install the result with this number, with `--remote`.
+        #[arg(
+            long,
+            value_name = "RESULT",
+            requires = "remote",
+            conflicts_with = "json"
+        )]
+        install: Option<usize>,
+    },
+    /// Summarize the registry: counts, sizes, and the largest, most depended on and oldest mods.
+    Stats {
+        /// How many mods to list in each ranking.
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        /// Print as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show which mods ship the same files, and whose copy is deployed.
+    Conflicts {
+        /// Only show conflicts involving this mod.
+        name: Option<String>,
+    },
+    /// Show the order archive mods load in, and check `archive/pc/mod/modlist.txt` against it.
+    Modlist {
+        /// Write the order to `modlist.txt`.
+        #[arg(long)]
+        write: bool,
+    },
+    /// Undo the last operations.
+    Undo {
+        /// Number of operations to undo.
+        #[arg(default_value_t = 1)]
+        n: usize,
+    },
+    /// List operations that can be undone.
+    History {
+        /// Print every logged operation as JSON, including undone and failed ones.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show how mods changed since a snapshot.
+    Diff {
+        /// An operation ID from `vapor history`, to compare against the registry right before it,
+        /// or the path of another `mods.toml`.
+        snapshot: String,
+    },
+    /// Show the detected game version.
+    GameVersion {
+        /// Detect the version again and save it to `Vapor.toml`.
+        #[arg(long)]
+        detect: bool,
+    },
+    /// Launch the game.
+    Run {
+        /// Launch through the Proton prefix in `Vapor.toml` instead of Steam.
+        #[arg(long)]
+        proton: bool,
+
+        /// Pass `-modded` to deploy REDmod mods.
+        #[arg(long)]
+        modded: bool,
+
+        /// Launch even if mods have missing dependencies.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Diagnose problems with the install.
+    Doctor {
+        #[command(subcommand)]
+        cmd: Option<DoctorCommand>,
+    },
+    /// Manage core modding frameworks.
+    Frameworks {
+        #[command(subcommand)]
+        cmd: FrameworksCommand,
+    },
+    /// Serve mod management over D-Bus as `org.vapor.ModManager`.
+    #[cfg(feature = "daemon")]
+    Daemon,
+    /// Serve JSON-RPC on a Unix socket.
+    #[cfg(unix)]
+    Serve {
+        /// Socket path, defaults to `$XDG_RUNTIME_DIR/vapor/vapor.sock`.
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Read and change settings in `Vapor.toml`.
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCommand,
+    },
+    /// Group mods to enable, disable and share together.
+    Collection {
+        #[command(subcommand)]
+        cmd: CollectionCommand,
+    },
+    /// Manage installs of `Cyberpunk 2077`, each with its own mods.
+    Instance {
+        #[command(subcommand)]
+        cmd: InstanceCommand,
+    },
+    /// Back up and restore the game's saves.
+    Saves {
+        #[command(subcommand)]
+        cmd: SavesCommand,
+    },
+    /// Manage caches the game builds from mods.
+    Cache {
+        #[command(subcommand)]
+        cmd: CacheCommand,
+    },
+    /// Manage commands run when mods are installed or enabled.
+    Hook {
+        #[command(subcommand)]
+        cmd: HookCommand,
+    },
+    /// Manage the Nexus API key.
+    Auth {
+        #[command(subcommand)]
+        cmd: AuthCommand,
+    },
+    /// Print a shell completion script.
+    Completions {
+        /// Shell to generate completions for.
+        shell: Shell,
+    },
+    /// Print installed mod names for shell completion.
+    #[command(name = "__complete", hide = true)]
+    Complete,
+}
+
+impl Command {
+    /// Whether the command may change the game directory or registry, so has to wait for other
+    /// Vapor processes on the same game and finish a move one of them left interrupted.
+    pub fn mutates(&self) -> bool {
+        match self {
+            #[cfg(feature = "daemon")]
+            Command::Daemon => return false,
+            #[cfg(unix)]
+            Command::Serve { .. } => return false,
+            _ => {}
+        }
+
+        !matches!(
+            self,
+            Command::Status { .. }
+                | Command::Inspect { .. }
+                | Command::List { .. }
+                | Command::Info { .. }
+                | Command::Graph
+                | Command::Search { install: None, .. }
+                | Command::Stats { .. }
+                | Command::Conflicts { .. }
+                | Command::Modlist { write: false }
+                | Command::History { .. }
+                | Command::Diff { .. }
+                | Command::Open { .. }
+                | Command::Pack { .. }
+                | Command::Run { .. }
+                | Command::Doctor { .. }
+                | Command::Config { .. }
+                | Command::Auth { .. }
+                | Command::Completions { .. }
+                | Command::Complete
+        )
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FrameworksCommand {
+    /// Install frameworks along with their dependencies.
+    Install {
+        /// Frameworks to install, all of them if empty.
+        names: Vec<String>,
+    },
+    /// Show which frameworks are installed.
+    Status,
+    /// Update installed frameworks to their latest release.
+    Update,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print a setting, or every setting without a key.
+    Get {
+        /// One of `path`, `deploy_mode`, `download_dir`, `nexus_api_key`, `color` or
+        /// `conflict_policy`.
+        key: Option<String>,
+    },
+    /// Change a setting, an empty value unsets optional ones.
+    Set {
+        /// Setting to change.
+        key: String,
+
+        /// New value.
+        value: String,
+    },
+    /// Print where `Vapor.toml` is.
+    Path,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthCommand {
+    /// Store the Nexus API key, read from stdin when it isn't a terminal.
+    Login,
+    /// Forget the stored Nexus API key.
+    Logout,
+    /// Show where the Nexus API key comes from.
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CollectionCommand {
+    /// Create a collection.
+    Create {
+        /// Collection name.
+        name: String,
+        /// Mods in it.
+        ///
+        /// This should be passed by a comma (`,`) delimited list.
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        mods: Vec<String>,
+    },
+    /// Delete a collection, leaving its mods alone.
+    Delete {
+        /// Collection name.
+        name: String,
+    },
+    /// List collections and their mods.
+    List,
+    /// Enable every mod in a collection, along with their dependencies.
+    Enable {
+        /// Collection name.
+        name: String,
+    },
+    /// Disable every mod in a collection.
+    Disable {
+        /// Collection name.
+        name: String,
+
+        #[command(flatten)]
+        impact: ImpactArgs,
+    },
+    /// Write a collection to a file to share it.
+    Export {
+        /// Collection name.
+        name: String,
+        /// File to write.
+        file: PathBuf,
+    },
+    /// Create a collection from an exported file.
+    Import {
+        /// File to read.
+        file: PathBuf,
+    },
+    /// Download and install a Nexus collection, then create a collection of its mods.
+    #[command(name = "import-nexus")]
+    ImportNexus {
+        /// The collection's `collection.json`, as a path or URL.
+        manifest: String,
+        /// Install the mods the collection marks optional too.
+        #[arg(long)]
+        optional: bool,
+        /// Install archives whose checksum doesn't match the collection, with a warning.
+        #[arg(long)]
+        allow_mismatch: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Delete caches so the game rebuilds them, the script cache by default.
+    Clear {
+        /// Clear the compiled redscript cache.
+        #[arg(long, group = "which")]
+        scripts: bool,
+        /// Clear the modded TweakDB.
+        #[arg(long, group = "which")]
+        tweaks: bool,
+        /// Clear every cache.
+        #[arg(long, group = "which")]
+        all: bool,
+    },
+    /// Show how much disk downloaded archives and cached listings use.
+    Stats,
+    /// Delete old downloads, never the archives installed mods came from.
+    Gc {
+        /// Newest archives to keep of each mod.
+        #[arg(long, default_value_t = 2)]
+        keep: usize,
+        /// Delete the oldest archives until the rest fit, like `5G` or `500MiB`.
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum InstanceCommand {
+    /// Add an install, creating its `mods.toml` if it has none.
+    Add {
+        /// Name to refer to it by.
+        name: String,
+        /// Path to its `Cyberpunk 2077` directory.
+        path: PathBuf,
+    },
+    /// List installs, marking the one in use.
+    List,
+    /// Manage an install from now on, `default` being the one from `vapor init`.
+    Use {
+        /// Instance name.
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SavesCommand {
+    /// Snapshot the save directory.
+    Backup,
+    /// Replace the saves with a backup, backing up the current ones first.
+    Restore {
+        /// Backup name from `vapor saves list`, the newest if not given.
+        backup: Option<String>,
+    },
+    /// List backups, oldest first.
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HookCommand {
+    /// List hooks and whether they are allowed to run.
+    List,
+    /// Add a hook and allow its command to run.
+    Add {
+        /// When to run it.
+        event: HookEvent,
+        /// Shell command, run in the game directory.
+        command: String,
+        /// Mod to run it for, every mod if not given.
+        #[arg(short, long = "mod")]
+        mod_name: Option<String>,
+    },
+    /// Remove a hook.
+    Remove {
+        /// When it runs.
+        event: HookEvent,
+        /// Shell command.
+        command: String,
+        /// Mod it runs for, every mod if not given.
+        #[arg(short, long = "mod")]
+        mod_name: Option<String>,
+    },
+    /// Allow a command to run as a hook, like one set up by editing `mods.toml`.
+    Allow {
+        /// Shell command, exactly as in the hook.
+        command: String,
+    },
+    /// Stop allowing a command to run as a hook.
+    Deny {
+        /// Shell command, exactly as in the hook.
+        command: String,
+    },
+}
+
+/// When a hook runs, named as in `Vapor.toml` and `mods.toml`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum HookEvent {
+    PreInstall,
+    PostInstall,
+    PreEnable,
+    PostEnable,
+    PreRun,
+}
+
+impl From<HookEvent> for Event {
+    fn from(event: HookEvent) -> Self {
+        match event {
+            HookEvent::PreInstall => Self::PreInstall,
+            HookEvent::PostInstall => Self::PostInstall,
+            HookEvent::PreEnable => Self::PreEnable,
+            HookEvent::PostEnable => Self::PostEnable,
+            HookEvent::PreRun => Self::PreRun,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DoctorCommand {
+    /// Find mods that are erroring in redscript and CET logs.
+    Logs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TagAction {
+    /// Add a tag.
+    Add {
+        /// Tag name.
+        tag: String,
+    },
+    /// Remove a tag.
+    Remove {
+        /// Tag name.
+        tag: String,
+    },
+}
+
+/// What `disable` and `remove` do with enabled mods depending on the one taken down.
+#[derive(Debug, Args)]
+pub struct ImpactArgs {
+    /// Disable mods that depend on it as well.
+    #[arg(long, conflicts_with = "force")]
+    pub cascade: bool,
+
+    /// Leave mods that depend on it with a missing dependency.
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl From<&ImpactArgs> for Impact {
+    fn from(args: &ImpactArgs) -> Self {
+        if args.cascade {
+            Self::Cascade
+        } else if args.force {
+            Self::Force
+        } else {
+            Self::Refuse
+        }
+    }
+}
+
+/// Mod details shared by `add` and `describe`.
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// Mod author, read from the archive if not given.
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// What the mod does, read from the archive if not given.
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Homepage, like the mod's Nexus page, read from the archive if not given.
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+/// Filters shared by `status` and `list`.
+#[derive(Debug, Args)]
+pub struct FilterArgs {
+    /// Only enabled mods.
+    #[arg(long, conflicts_with = "disabled")]
+    pub enabled: bool,
+
+    /// Only disabled mods.
+    #[arg(long)]
+    pub disabled: bool,
+
+    /// Only mods with missing dependencies.
+    #[arg(long)]
+    pub missing_deps: bool,
+
+    /// Only mods with this tag.
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Only mods with files of this kind, or only files of this kind when listing a mod's files.
+    #[arg(long, value_enum)]
+    pub kind: Option<Kind>,
+
+    /// Sort order.
+    #[arg(long, value_enum, default_value_t = Sort::Name)]
+    pub sort: Sort,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<Color> for ColorChoice {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Auto => Self::Auto,
+            Color::Always => Self::Always,
+            Color::Never => Self::Never,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Kind {
+    Archive,
+    Script,
+    Lua,
+    Plugin,
+    Tweak,
+    Config,
+    Other,
+}
+
+impl From<Kind> for FileKind {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Archive => Self::Archive,
+            Kind::Script => Self::Script,
+            Kind::Lua => Self::Lua,
+            Kind::Plugin => Self::Plugin,
+            Kind::Tweak => Self::Tweak,
+            Kind::Config => Self::Config,
+            Kind::Other => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Sort {
+    Name,
+    #[value(name = "installed_at")]
+    InstalledAt,
+    Version,
+    Size,
+}
+
+fn parse_size(size: &str) -> Result<u64, String> {
+    download_cache::parse_size(size).ok_or_else(|| format!("invalid size `{size}`, like `5G`"))
 }
@@ -0,0 +1,99 @@
+use std::io::Write;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::args::CyberArgs;
+
+/// Subcommands whose first positional argument is an installed mod name.
+const MOD_NAME_COMMANDS: &[&str] = &[
+    "enable",
+    "disable",
+    "list",
+    "rename",
+    "exclude",
+    "tag",
+    "note",
+    "describe",
+    "edit",
+    "open",
+    "pin",
+    "pack",
+    "unpin",
+    "info",
+    "downgrade",
+];
+
+/// Completes mod names from `vapor __complete`, falling back to the generated `_vapor`.
+const BASH: &str = r#"
+_vapor_mods() {
+    local i cmd game
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            --game) game="${COMP_WORDS[i + 1]}"; ((i++)) ;;
+            -*) ;;
+            *) cmd="${COMP_WORDS[i]}"; break ;;
+        esac
+    done
+
+    if [[ " @COMMANDS@ " == *" $cmd "* && $i -eq $((COMP_CWORD - 1)) && "${COMP_WORDS[COMP_CWORD]}" != -* ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(compgen -W "$(vapor ${game:+--game "$game"} __complete 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+        COMPREPLY=("${COMPREPLY[@]// /\\ }")
+        return 0
+    fi
+
+    _vapor "$@"
+}
+
+complete -F _vapor_mods -o nosort -o bashdefault -o default vapor
+"#;
+
+/// Wraps the generated `_vapor` so mod names are offered first.
+const ZSH: &str = r#"
+functions -c _vapor _vapor_static
+
+_vapor() {
+    local i cmd game
+    for ((i = 2; i < CURRENT; i++)); do
+        case "${words[i]}" in
+            --game) game="${words[i + 1]}"; ((i++)) ;;
+            -*) ;;
+            *) cmd="${words[i]}"; break ;;
+        esac
+    done
+
+    if [[ " @COMMANDS@ " == *" $cmd "* && $i -eq $((CURRENT - 1)) && "${words[CURRENT]}" != -* ]]; then
+        local -a mods
+        mods=("${(@f)$(vapor ${game:+--game "$game"} __complete 2>/dev/null)}")
+        compadd -a mods
+        return
+    fi
+
+    _vapor_static "$@"
+}
+
+compdef _vapor vapor
+"#;
+
+const FISH: &str = r#"
+complete -c vapor -n "__fish_seen_subcommand_from @COMMANDS@; and test (count (commandline -opc | string match -v -- '-*')) -le 2" -f -a "(vapor __complete 2>/dev/null)"
+"#;
+
+/// Write the completion script for `shell`, with mod name completion where supported.
+pub fn generate(shell: Shell, out: &mut impl Write) -> std::io::Result<()> {
+    clap_complete::generate(shell, &mut CyberArgs::command(), "vapor", out);
+
+    let dynamic = match shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+        _ => return Ok(()),
+    };
+
+    write!(
+        out,
+        "{}",
+        dynamic.replace("@COMMANDS@", &MOD_NAME_COMMANDS.join(" "))
+    )
+}
@@ -0,0 +1,239 @@
+use std::sync::{Arc, OnceLock};
+
+use libvapor::jobs::{JobContext, JobId, JobQueue, JobStatus};
+use libvapor::mod_manager::handler::{Impact, Move};
+use zbus::{
+    blocking::{Connection, connection},
+    fdo, interface,
+    object_server::SignalEmitter,
+};
+
+use crate::service;
+
+/// Well-known bus name of the daemon.
+pub const NAME: &str = "org.vapor.ModManager";
+
+/// Object path the manager is served at.
+pub const PATH: &str = "/org/vapor/ModManager";
+
+/// Mod management over D-Bus.
+///
+/// Every operation that changes mods is a job on one [`JobQueue`], so they run one at a time and
+/// never race on `mods.toml`, while other calls, like cancelling one of them, are still answered.
+/// An empty `game` means `Cyberpunk 2077`.
+struct ModManager {
+    jobs: Arc<JobQueue>,
+}
+
+/// Run `f` on a blocking thread, so the bus keeps being serviced.
+async fn unblock<T, F>(f: F) -> fdo::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> miette::Result<T> + Send + 'static,
+{
+    blocking::unblock(f)
+        .await
+        .map_err(|err| fdo::Error::Failed(err.to_string()))
+}
+
+/// The version out of an add's report, see [`service::add_mod`].
+fn installed_version(report: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(report)
+        .ok()
+        .and_then(|report| report["version"].as_str().map(ToString::to_string))
+        .unwrap_or_default()
+}
+
+impl ModManager {
+    /// Queue `work` and wait for it, returning what it returned.
+    async fn run(
+        &self,
+        operation: String,
+        work: impl FnOnce(&JobContext) -> miette::Result<String> + Send + 'static,
+    ) -> fdo::Result<String> {
+        let jobs = Arc::clone(&self.jobs);
+        let id = jobs.submit(operation, work);
+
+        match blocking::unblock(move || jobs.wait(id)).await {
+            Some(JobStatus::Done { result }) => Ok(result),
+            Some(JobStatus::Failed { error }) => Err(fdo::Error::Failed(error)),
+            Some(status) => Err(fdo::Error::Failed(status.to_string())),
+            None => Err(fdo::Error::Failed(format!("job {id} was forgotten"))),
+        }
+    }
+}
+
+#[interface(name = "org.vapor.ModManager")]
+impl ModManager {
+    /// Install an archive, returning the installed version.
+    async fn add_mod(
+        &self,
+        game: String,
+        path: String,
+        name: String,
+        version: String,
+        dependencies: Vec<String>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<String> {
+        let id = game.clone();
+        let report = self
+            .run(format!("add `{name}`"), move |job| {
+                service::add_mod(job, Some(&id), path.as_ref(), name, version, &dependencies)
+            })
+            .await?;
+        Self::registry_changed(&emitter, &game).await?;
+
+        Ok(installed_version(&report))
+    }
+
+    /// Download an archive and install it, returning the installed version.
+    async fn add_url(
+        &self,
+        game: String,
+        url: String,
+        name: String,
+        version: String,
+        dependencies: Vec<String>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<String> {
+        let id = game.clone();
+        let report = self
+            .run(format!("add `{name}`"), move |job| {
+                service::add_url(job, Some(&id), &url, name, version, &dependencies)
+            })
+            .await?;
+        Self::registry_changed(&emitter, &game).await?;
+
+        Ok(installed_version(&report))
+    }
+
+    async fn enable(
+        &self,
+        game: String,
+        name: String,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let id = game.clone();
+        self.run(format!("enable `{name}`"), move |_| {
+            service::move_mod(Some(&id), name, Move::Enable, Impact::Refuse)
+        })
+        .await?;
+        Self::registry_changed(&emitter, &game).await?;
+
+        Ok(())
+    }
+
+    /// Disable `name`, refusing if enabled mods depend on it.
+    async fn disable(
+        &self,
+        game: String,
+        name: String,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let id = game.clone();
+        self.run(format!("disable `{name}`"), move |_| {
+            service::move_mod(Some(&id), name, Move::Disable, Impact::Refuse)
+        })
+        .await?;
+        Self::registry_changed(&emitter, &game).await?;
+
+        Ok(())
+    }
+
+    /// Undo the last `n` operations, returning their summaries.
+    async fn undo(
+        &self,
+        game: String,
+        n: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<Vec<String>> {
+        let id = game.clone();
+        let undone = self
+            .run(format!("undo {n}"), move |_| {
+                Ok(service::undo(Some(&id), n as usize)?.join("\n"))
+            })
+            .await?;
+        Self::registry_changed(&emitter, &game).await?;
+
+        Ok(undone.lines().map(String::from).collect())
+    }
+
+    /// Every job still remembered, as its ID, operation and status, oldest first.
+    async fn jobs(&self) -> Vec<(JobId, String, String)> {
+        self.jobs
+            .jobs()
+            .into_iter()
+            .map(|job| (job.id, job.operation, job.status.to_string()))
+            .collect()
+    }
+
+    /// Status of job `id`, like `downloading 40%` or `failed: ...`.
+    async fn job_status(&self, id: JobId) -> fdo::Result<String> {
+        self.jobs
+            .status(id)
+            .map(|status| status.to_string())
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no job {id}")))
+    }
+
+    /// Cancel job `id`, returning `false` if it already finished.
+    async fn cancel_job(&self, id: JobId) -> bool {
+        self.jobs.cancel(id)
+    }
+
+    /// Names of every mod.
+    async fn list_mods(&self, game: String) -> fdo::Result<Vec<String>> {
+        unblock(move || service::list(Some(&game))).await
+    }
+
+    /// Status of every mod as JSON, like `vapor status --json`.
+    async fn status(&self, game: String) -> fdo::Result<String> {
+        unblock(move || service::status(Some(&game))).await
+    }
+
+    /// Job `job` was queued or changed status, like to `downloading 40%` or `done`.
+    #[zbus(signal)]
+    async fn progress(
+        emitter: &SignalEmitter<'_>,
+        job: JobId,
+        operation: &str,
+        stage: &str,
+    ) -> zbus::Result<()>;
+
+    /// The registry of `game` changed.
+    #[zbus(signal)]
+    async fn registry_changed(emitter: &SignalEmitter<'_>, game: &str) -> zbus::Result<()>;
+}
+
+/// Serve [`NAME`] on the session bus until killed.
+pub fn serve() -> zbus::Result<()> {
+    let bus = Arc::new(OnceLock::<Connection>::new());
+    let jobs = JobQueue::new().with_listener({
+        let bus = Arc::clone(&bus);
+        move |job| {
+            let Some(bus) = bus.get() else {
+                return;
+            };
+            let body = (job.id, job.operation.as_str(), job.status.to_string());
+            if let Err(err) = bus.emit_signal(None::<()>, PATH, NAME, "Progress", &body) {
+                tracing::warn!(%err, job = job.id, "couldn't signal progress");
+            }
+        }
+    });
+
+    let connection = connection::Builder::session()?
+        .name(NAME)?
+        .serve_at(
+            PATH,
+            ModManager {
+                jobs: Arc::new(jobs),
+            },
+        )?
+        .build()?;
+    let _ = bus.set(connection);
+
+    tracing::info!(name = NAME, path = PATH, "serving");
+
+    loop {
+        std::thread::park();
+    }
+}
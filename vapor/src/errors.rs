@@ -0,0 +1,258 @@
+//! Terminal rendering of library errors that carry files, listing them with hyperlinks, and the
+//! exit code and diagnostic code of every error, see [`ErrorClass`].
+
+use std::{error::Error, fmt, process::ExitCode};
+
+use libvapor::exit::ErrorClass;
+use libvapor::i18n::{tr, tr_with};
+use libvapor::mod_manager::handler::ModError;
+use libvapor::nexus::NexusError;
+use libvapor::render::link;
+use miette::{Diagnostic, LabeledSpan, MietteDiagnostic, NamedSource, Report, SourceCode};
+
+/// An error shown with its files as source code, labeled as a whole.
+#[derive(Debug)]
+struct FileList {
+    message: String,
+    code: Option<String>,
+    help: Option<String>,
+    files: NamedSource<String>,
+    label: &'static str,
+}
+
+impl fmt::Display for FileList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FileList {}
+
+impl Diagnostic for FileList {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.code
+            .as_ref()
+            .map(|code| Box::new(code) as Box<dyn fmt::Display>)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|help| Box::new(help) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.files)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let len = self.files.inner().len();
+        Some(Box::new(std::iter::once(LabeledSpan::new(
+            Some(self.label.to_string()),
+            0,
+            len,
+        ))))
+    }
+}
+
+/// Like [`miette::IntoDiagnostic`], but keeping the error so [`class`] can tell what it was.
+pub trait IntoDiagnostic<T> {
+    fn into_diagnostic(self) -> Result<T, Report>;
+}
+
+impl<T, E: Error + Send + Sync + 'static> IntoDiagnostic<T> for Result<T, E> {
+    fn into_diagnostic(self) -> Result<T, Report> {
+        self.map_err(|err| Plain(Box::new(err)).into())
+    }
+}
+
+/// An error that isn't a [`Diagnostic`] itself.
+#[derive(Debug)]
+struct Plain(Box<dyn Error + Send + Sync>);
+
+impl fmt::Display for Plain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for Plain {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl Diagnostic for Plain {}
+
+/// An error given the diagnostic code of its class, as it doesn't have one of its own.
+struct Coded {
+    report: Report,
+    class: ErrorClass,
+}
+
+impl fmt::Debug for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.report, f)
+    }
+}
+
+impl fmt::Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.report, f)
+    }
+}
+
+impl Error for Coded {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.report.source()
+    }
+}
+
+impl Diagnostic for Coded {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.class))
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        self.report.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.report.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.report.url()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.report.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.report.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.report.related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.report.diagnostic_source()
+    }
+}
+
+/// What kind of error `report` is: from its diagnostic code if that's one of a class, like for
+/// errors made with `miette!`, and otherwise from the error itself.
+pub fn class(report: &Report) -> ErrorClass {
+    if let Some(class) = report
+        .code()
+        .and_then(|code| ErrorClass::from_code(&code.to_string()))
+    {
+        return class;
+    }
+    match report.chain().next() {
+        Some(err) => match err.downcast_ref::<Plain>() {
+            Some(Plain(err)) => ErrorClass::of(err.as_ref()),
+            None => ErrorClass::of(err),
+        },
+        None => ErrorClass::Other,
+    }
+}
+
+/// Print `report` and exit with the code of its class.
+pub fn exit(report: Report) -> ExitCode {
+    let class = class(&report);
+    let report = render(report);
+    let report = if report.code().is_some() {
+        report
+    } else {
+        Coded { report, class }.into()
+    };
+    eprintln!("Error: {report:?}");
+    ExitCode::from(class.exit_code())
+}
+
+/// Errors with files rendered as a list, anything else unchanged.
+pub fn render(report: Report) -> Report {
+    let report = match report.downcast::<NexusError>() {
+        Ok(NexusError::Mod(err)) => return render_mod_error(err),
+        Ok(err) => return err.into(),
+        Err(report) => report,
+    };
+
+    match report.downcast::<ModError>() {
+        Ok(err) => render_mod_error(err),
+        Err(report) => report,
+    }
+}
+
+fn render_mod_error(err: ModError) -> Report {
+    let message = err.to_string();
+    let code = err.code().map(|code| code.to_string());
+    let help = err.help().map(|help| help.to_string());
+
+    // The errors run into most, in the language of the output.
+    let translated = match &err {
+        ModError::MissingMod { name, suggestion } => Some((
+            tr_with("error-missing-mod", &[("name", name)]),
+            suggestion.clone(),
+        )),
+        ModError::AlreadyExists(name) => Some((
+            tr_with("error-already-exists", &[("name", name)]),
+            Some(tr("help-already-exists")),
+        )),
+        ModError::Pinned(name) => Some((
+            tr_with("error-pinned", &[("name", name)]),
+            Some(tr_with("help-pinned", &[("name", name)])),
+        )),
+        _ => None,
+    };
+    if let Some((message, help)) = translated {
+        let mut diagnostic = MietteDiagnostic::new(message);
+        if let Some(code) = code {
+            diagnostic = diagnostic.with_code(code);
+        }
+        if let Some(help) = help {
+            diagnostic = diagnostic.with_help(help);
+        }
+        return diagnostic.into();
+    }
+
+    let (name, text, label) = match err {
+        ModError::DoubleOwnedFiles { root, files, .. } => (
+            "conflicting files",
+            files
+                .iter()
+                .map(|file| {
+                    format!(
+                        "{} | {}",
+                        file.owner,
+                        link(&root.join(&file.path), file.path.display())
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "Files(s) listed here are already owned by another mod",
+        ),
+        ModError::ExtractionIncomplete { missing, .. } => (
+            "missing files",
+            missing
+                .iter()
+                .map(|path| link(path, path.display()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "Files(s) listed here are could not be found after extraction",
+        ),
+        err => return err.into(),
+    };
+
+    FileList {
+        message,
+        code,
+        help,
+        files: NamedSource::new(name, text),
+        label,
+    }
+    .into()
+}
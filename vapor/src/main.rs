@@ -1,113 +1,2315 @@
-use std::{fs, str::FromStr};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::IsTerminal;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use args::{Command, CyberArgs};
+use args::{
+    AuthCommand, CacheCommand, CollectionCommand, Command, ConfigCommand, CyberArgs, DoctorCommand,
+    FilterArgs, FrameworksCommand, HookCommand, InstanceCommand, SavesCommand, Sort, TagAction,
+};
 use clap::Parser;
-use libvapor::init::{CyberToml, Init};
-use libvapor::mod_manager::handler::{ModHandler, Move, Operation};
-use miette::{IntoDiagnostic, LabeledSpan, Result, miette};
+use demand::{Confirm, DemandOption, Input, MultiSelect, Select};
+use errors::IntoDiagnostic;
+use glob::Pattern;
+use libvapor::config::{self, Config};
+use libvapor::exit::ErrorClass;
+use libvapor::game_cache::GameCache;
+use libvapor::hooks::Event;
+use libvapor::i18n::{self, tr, tr_with};
+use libvapor::init::{ColorChoice, CyberToml, DEFAULT_GAME, DEFAULT_INSTANCE, Init, init_root};
+use libvapor::launch::{self, Via};
+use libvapor::mod_manager::handler::{
+    AddOptions, FixedMove, ModError, ModHandler, Move, Operation,
+};
+use libvapor::mod_manager::metadata::Metadata;
+use libvapor::mod_manager::mod_file_formats::{self, FileKind};
+use libvapor::mod_manager::name::{self, ModName};
+use libvapor::mod_manager::registry::{ModQuery, SortKey};
+use libvapor::mod_manager::version::ModVersion;
+use libvapor::mod_manager::{
+    diff, file_tree, fs, inspection, metadata, nested, preview, search, split, variant,
+};
+use libvapor::mod_manager::{
+    events::EventLog,
+    journal,
+    load_order::{self, MODLIST},
+};
+use libvapor::resolver::Request;
+use libvapor::secrets::{self, NEXUS};
+use libvapor::{
+    doctor, download, download_cache, frameworks, game, nexus, platform, render, saves,
+};
+use miette::{LabeledSpan, Result, miette};
 
 mod args;
+mod completions;
+#[cfg(feature = "daemon")]
+mod daemon;
+mod errors;
+mod logging;
+#[cfg(unix)]
+mod rpc;
+mod service;
 
-fn load_config() -> Result<CyberToml> {
-    let config_path = Init::get_config()?;
-    CyberToml::from_str(&fs::read_to_string(&config_path).into_diagnostic()?).into_diagnostic()
+fn load_config(instance: Option<&str>, root: Option<&Path>) -> Result<CyberToml> {
+    if let Some(root) = root {
+        let mut config = CyberToml::load().unwrap_or_else(|err| {
+            tracing::warn!(%err, "using default settings with `--root`");
+            CyberToml::for_root(root)
+        });
+        config.set_root(root)?;
+        return Ok(config);
+    }
+
+    let mut config = CyberToml::load()?;
+    if instance.is_some() {
+        config.select_instance(instance)?;
+    }
+    Ok(config)
+}
+
+/// Handler for `game`, locked and with any interrupted move finished first if the command
+/// `mutates` the game, see [`Command::mutates`].
+fn load_handler(
+    config: &CyberToml,
+    game: Option<&str>,
+    dry_run: bool,
+    no_trash: bool,
+    mutates: bool,
+) -> Result<ModHandler> {
+    let handler = ModHandler::from_config(config, &config.game(game)?)?
+        .with_trash(config.main.trash && !no_trash);
+
+    if dry_run {
+        return Ok(handler.with_dry_run());
+    }
+    if !mutates {
+        return Ok(handler);
+    }
+    let handler = handler.lock()?;
+    if let Some(fixed) = handler.fix_pending_move()? {
+        eprintln!("{}", fixed_message(&fixed));
+    }
+    Ok(handler)
+}
+
+/// What [`ModHandler::fix_moves`] did to a mod.
+fn fixed_message(fixed: &FixedMove) -> String {
+    let name = &fixed.name;
+    match (fixed.completed, fixed.action) {
+        (true, Move::Enable) => format!("Finished enabling `{name}` ({} files)", fixed.files),
+        (true, Move::Disable) => format!("Finished disabling `{name}` ({} files)", fixed.files),
+        (false, _) => format!("Moved {} stray files of `{name}` back", fixed.files),
+    }
+}
+
+/// Print what a dry run would have changed.
+fn dry_run_report(handler: &ModHandler) {
+    if handler.fs.is_dry_run() {
+        print!("{}", fs::report(&handler.fs.changes()));
+        println!("Dry run, nothing was changed");
+    }
+}
+
+/// Print what `handler` had to work around.
+fn print_warnings(handler: &ModHandler) {
+    for warning in handler.take_warnings() {
+        eprintln!("{:?}", miette::Report::new(warning));
+    }
+}
+
+/// What Nexus knows about the archive at `path`, looked up by its MD5, for `add` without a name
+/// or version.
+///
+/// Only done for `Cyberpunk 2077` with an API key, while online. When the lookup fails, `add` goes
+/// by what the archive and its file name say instead.
+fn identify(config: &CyberToml, game: Option<&str>, path: &Path) -> Metadata {
+    if cyberpunk_only(game, "add").is_err() || download::is_offline() {
+        return Metadata::default();
+    }
+    let Ok(Some(api_key)) = secrets::resolve(config.main.nexus_api_key.as_deref(), NEXUS) else {
+        return Metadata::default();
+    };
+
+    match nexus::Client::new(api_key).identify(nexus::DEFAULT_DOMAIN, path) {
+        Ok(Some(found)) => {
+            if let Some(name) = &found.name {
+                eprintln!("Found `{name}` on Nexus");
+            }
+            found
+        }
+        Ok(None) => Metadata::default(),
+        Err(err) => {
+            eprintln!("Could not look the archive up on Nexus, going by its contents: {err}");
+            Metadata::default()
+        }
+    }
+}
+
+/// Back up the saves before `action`, if `backup_saves` is on.
+///
+/// A failed backup is reported but doesn't stop `action`, since there may be no saves yet.
+fn backup_saves(config: &CyberToml, game: Option<&str>, dry_run: bool, action: &str) {
+    if !config.main.backup_saves || dry_run || cyberpunk_only(game, action).is_err() {
+        return;
+    }
+    match saves::find(config).and_then(|dir| saves::backup(&dir, Some(&format!("before-{action}"))))
+    {
+        Ok(backup) => println!("Backed up saves as `{}`", backup.name),
+        Err(err) => eprintln!("{:?}", miette::Report::new(err)),
+    }
+}
+
+/// Save an archive piped into `vapor add -` to `downloads`.
+///
+/// It's kept as a `.part` file until the mod's name is known, so `vapor cache gc` cleans it up if
+/// adding it fails.
+fn read_stdin(downloads: &Path) -> Result<PathBuf> {
+    let stdin = std::io::stdin();
+    let nothing_piped = || {
+        miette!(
+            code = ErrorClass::Usage.code(),
+            help = "Pipe an archive in, like `curl -L <url> | vapor add - --name <name> --version <version>`",
+            "Nothing was piped into `vapor add -`"
+        )
+    };
+    if stdin.is_terminal() {
+        return Err(nothing_piped());
+    }
+
+    let path = download::save_to(
+        stdin.lock(),
+        downloads,
+        &format!("stdin-{}.part", std::process::id()),
+    )
+    .into_diagnostic()?;
+    if std::fs::metadata(&path).into_diagnostic()?.len() == 0 {
+        let _ = std::fs::remove_file(&path);
+        return Err(nothing_piped());
+    }
+    Ok(path)
+}
+
+/// Name an archive from [`read_stdin`] after the mod it turned out to be, like a download, unless
+/// this is a dry run.
+fn keep_piped(path: PathBuf, name: &str, version: &str, dry_run: bool) -> Result<PathBuf> {
+    if dry_run {
+        return Ok(path);
+    }
+    let file_name = if path.is_dir() {
+        format!("{name}-{version}")
+    } else {
+        format!("{name}-{version}.zip")
+    }
+    .replace(['/', '\\'], "_");
+    let dest = path.with_file_name(file_name);
+    std::fs::rename(&path, &dest).into_diagnostic()?;
+    Ok(dest)
+}
+
+/// A directory under the system's temporary one, deleted when dropped, for what a dry run has to
+/// unpack to look inside but mustn't keep.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("vapor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).into_diagnostic()?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The mod to add from `file`, unpacking archives inside it into `downloads` when that's all it
+/// holds, over and over for archives of archives of archives.
+///
+/// A lone inner archive is taken as is, otherwise they're picked with `wanted`, or asked for.
+/// They're read as `options` say.
+fn unpack_nested(
+    mut file: PathBuf,
+    wanted: &[String],
+    root_dirs: &[String],
+    downloads: &Path,
+    options: fs::ExtractOptions<'_>,
+) -> Result<PathBuf> {
+    for depth in 0.. {
+        if file.is_dir() {
+            break;
+        }
+        let inner = nested::inner_archives(&file, root_dirs);
+        let entries = match inner.len() {
+            0 => break,
+            1 => inner,
+            _ if !wanted.is_empty() => nested::select(&file, &inner, wanted)?,
+            _ if std::io::stdin().is_terminal() => MultiSelect::new(format!(
+                "`{}` holds {} archives, which should be installed?",
+                file.file_name().unwrap_or_default().to_string_lossy(),
+                inner.len()
+            ))
+            .options(inner.into_iter().map(DemandOption::new).collect())
+            .min(1)
+            .run()
+            .into_diagnostic()?,
+            _ => {
+                return Err(nested::NestedError::Ambiguous {
+                    archive: file
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into(),
+                    inner,
+                }
+                .into());
+            }
+        };
+        // Each level goes next to the archive it came out of, never over it.
+        let dir = match file.parent() {
+            Some(parent) if depth > 0 => parent.to_path_buf(),
+            _ => downloads.to_path_buf(),
+        };
+        file = nested::unpack(&file, &entries, &dir, depth, options)?;
+    }
+    Ok(file)
+}
+
+/// `handler` decrypting with `password`, or with one asked for when there is none and `file` is
+/// encrypted. Without a terminal, adding fails pointing at `--password` instead.
+fn unlock(handler: ModHandler, file: &Path, password: Option<String>) -> Result<ModHandler> {
+    if handler.fs.password.is_some() {
+        return Ok(handler);
+    }
+    if password.is_some() {
+        return Ok(handler.with_password(password));
+    }
+    let Err(ModError::PasswordRequired { archive }) = handler.check_password(file) else {
+        return Ok(handler);
+    };
+    if !std::io::stdin().is_terminal() {
+        return Ok(handler);
+    }
+
+    let password = Input::new(format!("Password for `{archive}`"))
+        .password(true)
+        .run()
+        .into_diagnostic()?;
+    Ok(handler.with_password(Some(password)))
+}
+
+/// The variant of `file` to install as `name`, asked for when it bundles several and `name`
+/// wasn't installed from one of them before. Without a terminal, adding fails pointing at
+/// `--variant` instead.
+fn pick_variant(handler: &ModHandler, file: &Path, name: &str) -> Result<Option<String>> {
+    let variants = variant::variants(
+        &mod_file_formats::read_files_cached(file),
+        &handler.root_dirs,
+    );
+    if variants.is_empty() || !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let toml = handler.load_toml()?;
+    let stored = toml
+        .get(&toml.canonical_name(name.to_string()))
+        .and_then(|entry| entry.variant.clone());
+    if stored.is_some_and(|stored| variants.contains(&stored)) {
+        return Ok(None);
+    }
+
+    Select::new(format!(
+        "`{}` bundles {} variants, which should be installed?",
+        file.file_name().unwrap_or_default().to_string_lossy(),
+        variants.len()
+    ))
+    .options(variants.into_iter().map(DemandOption::new).collect())
+    .run()
+    .map(Some)
+    .into_diagnostic()
 }
 
-fn main() -> Result<()> {
+/// Prompt for `what`, or fail pointing at `--flag` when there is no terminal to ask on.
+fn ask(what: &str, flag: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        return Err(miette!(
+            code = ErrorClass::Usage.code(),
+            help = format!("Pass it with `--{flag}`"),
+            "Could not read the {} from the archive",
+            what.to_lowercase()
+        ));
+    }
+    let answer = Input::new(what).run().into_diagnostic()?;
+    match answer.trim() {
+        "" => Err(miette!(
+            code = ErrorClass::Usage.code(),
+            "No {} given",
+            what.to_lowercase()
+        )),
+        answer => Ok(answer.to_string()),
+    }
+}
+
+/// Ask before doing something hard to take back, unless `yes` is set.
+fn confirm(message: &str, yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Err(miette!(
+            code = ErrorClass::Usage.code(),
+            help = tr("confirm-pass-yes"),
+            "{}",
+            tr_with("confirm-needed", &[("message", &message)])
+        ));
+    }
+
+    if Confirm::new(message).run().into_diagnostic()? {
+        Ok(())
+    } else {
+        Err(miette!(
+            code = ErrorClass::Cancelled.code(),
+            "{}",
+            tr("aborted")
+        ))
+    }
+}
+
+/// Fail for commands that only make sense for `Cyberpunk 2077`.
+fn cyberpunk_only(game: Option<&str>, command: &str) -> Result<()> {
+    match game {
+        Some(game) if game != DEFAULT_GAME => Err(miette!(
+            code = ErrorClass::Usage.code(),
+            help = "Drop `--game` to use it with `Cyberpunk 2077`",
+            "`vapor {command}` is only supported for `Cyberpunk 2077`"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Resolve `name` to the registry key spelling it, or a close match when `fuzzy` is set.
+///
+/// Unresolvable names are passed through so the handler can report them.
+fn resolve_name(handler: &ModHandler, name: String, fuzzy: bool) -> Result<String> {
+    let toml = handler.load_toml()?;
+    match toml.resolve_name(&name, fuzzy) {
+        Some(resolved) if !ModName::from(resolved).matches(&name) => {
+            eprintln!("Using `{resolved}` for `{name}`");
+            Ok(resolved.to_string())
+        }
+        Some(resolved) => Ok(resolved.to_string()),
+        None => Ok(name),
+    }
+}
+
+/// Enable or disable each of `names` in order.
+fn move_all(handler: &ModHandler, names: &[String], which: Move) -> Result<()> {
+    for name in names {
+        match handler.move_mod(name, which)?.operation {
+            Operation::Move(moved) => println!(
+                "{} `{name}`",
+                match moved {
+                    Move::Enable => "Disabled",
+                    Move::Disable => "Enabled",
+                }
+            ),
+            _ => unreachable!("Others not possible in disable or enable"),
+        }
+    }
+    Ok(())
+}
+
+fn is_glob(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+}
+
+/// Resolve `names` to mods in the registry, each a name like [`resolve_name`] takes or a glob
+/// pattern, keeping their order and dropping duplicates.
+fn resolve_names(handler: &ModHandler, names: &[String], fuzzy: bool) -> Result<Vec<String>> {
+    let toml = handler.load_toml()?;
+    let mut resolved = Vec::<String>::new();
+
+    for name in names {
+        let matched = if is_glob(name) && !toml.contains(name) {
+            let pattern = Pattern::new(name).into_diagnostic()?;
+            let matched = toml
+                .mods
+                .keys()
+                .filter(|mod_name| pattern.matches(mod_name.as_str()))
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            if matched.is_empty() {
+                return Err(miette!(
+                    code = ErrorClass::MissingMod.code(),
+                    help = "Run `vapor list --all` to see every mod",
+                    "No mods match `{name}`"
+                ));
+            }
+            matched
+        } else {
+            vec![resolve_name(handler, name.clone(), fuzzy)?]
+        };
+
+        for name in matched {
+            if !resolved.contains(&name) {
+                resolved.push(name);
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// `names` quoted for messages, like `` `a`, `b` ``.
+fn quoted(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Print what happened to each mod: a line for one, a table for more.
+fn print_summary(summary: &[(String, String)]) {
+    if let [(name, result)] = summary {
+        println!("{result} `{name}`");
+        return;
+    }
+
+    let width = summary
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .chain(["Mod".len()])
+        .max()
+        .unwrap_or_default();
+    println!("{:<width$}  Result", "Mod");
+    for (name, result) in summary {
+        println!("{name:<width$}  {result}");
+    }
+}
+
+/// Build a query from CLI filters, falling back to `default_enabled` without `--enabled` or
+/// `--disabled`.
+fn mod_query(
+    filter: FilterArgs,
+    pattern: Option<&str>,
+    default_enabled: Option<bool>,
+) -> Result<ModQuery> {
+    Ok(ModQuery {
+        enabled: match (filter.enabled, filter.disabled) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => default_enabled,
+        },
+        missing_deps: filter.missing_deps,
+        tag: filter.tag,
+        kind: filter.kind.map(Into::into),
+        pattern: pattern.map(Pattern::new).transpose().into_diagnostic()?,
+        sort: match filter.sort {
+            Sort::Name => SortKey::Name,
+            Sort::InstalledAt => SortKey::InstalledAt,
+            Sort::Version => SortKey::Version,
+            Sort::Size => SortKey::Size,
+        },
+    })
+}
+
+/// `code` as a process exit code, which only keeps the low byte.
+fn exit_code(code: i32) -> ExitCode {
+    ExitCode::from(code as u8)
+}
+
+fn main() -> ExitCode {
+    match start() {
+        Ok(code) => code,
+        Err(report) => errors::exit(report),
+    }
+}
+
+/// Run the command, returning rather than exiting with its code so destructors like the log
+/// guard still run.
+fn start() -> Result<ExitCode> {
     let cli = CyberArgs::parse();
+    let _guard = logging::init(cli.verbose);
+    i18n::init(cli.lang.as_deref());
+
+    let mut color = cli.color.map(ColorChoice::from);
+    let mut offline = cli.offline;
+    // Errors here are reported by the command, once the miette hook is set up.
+    if let Ok(config) = CyberToml::load() {
+        name::set_folding(config.main.name_folding);
+        color = color.or(Some(config.main.color));
+        offline |= !config.network.enabled;
+        download::configure(&config.network);
+    }
+    download::set_offline(offline);
+
+    let color = color.unwrap_or_default();
+    render::init(color);
+    if color != ColorChoice::Auto || !render::links() {
+        let (color, links) = (render::color(), render::links());
+        miette::set_hook(Box::new(move |_| {
+            let mut opts = miette::MietteHandlerOpts::new().terminal_links(links);
+            if !color {
+                opts = opts.color(false);
+            }
+            Box::new(opts.build())
+        }))?;
+    }
+
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .into_diagnostic()?;
+    }
+
+    run(cli)
+}
+
+fn run(cli: CyberArgs) -> Result<ExitCode> {
+    let game = cli.game.as_deref();
+    let instance = cli.instance.as_deref();
+    let root = cli.root.as_deref();
+    let fuzzy = cli.fuzzy;
+    let dry_run = cli.dry_run;
+    let no_trash = cli.no_trash;
+    let mutates = cli.cmds.mutates();
 
     match cli.cmds {
-        Command::Init => {
-            Init::new()?.setup_cyber().into_diagnostic()?;
-        }
-        Command::Status { json } => {
-            let config = load_config()?;
-            let toml = ModHandler::new(config.main.path).load_toml()?;
-            let (out, code) = toml.status(json);
+        Command::Init { root_dirs, sdcard } => match game {
+            _ if let Some(root) = root => init_root(root)?,
+            Some(id) if id != DEFAULT_GAME => {
+                let init = Init::new_game(id)?;
+                let init = if sdcard { init.sdcard()? } else { init };
+                init.setup_game(id, root_dirs)?;
+            }
+            _ => {
+                let init = Init::new()?;
+                let init = if sdcard { init.sdcard()? } else { init };
+                init.setup_cyber().into_diagnostic()?;
+            }
+        },
+        Command::Status {
+            pattern,
+            json,
+            summary,
+            updates,
+            problems_only,
+            deep,
+            filter,
+        } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let toml = handler.load_toml()?;
+            let names = toml.query(
+                &mod_query(filter, pattern.as_deref(), None)?,
+                |name, entry| handler.mod_size(name, entry),
+            );
+            let game_version = match game {
+                Some(game) if game != DEFAULT_GAME => None,
+                _ => config.game_version(),
+            };
+
+            if summary {
+                let mut summary = toml.summary(game_version, &names);
+                summary.modified = names
+                    .iter()
+                    .map(|name| {
+                        handler
+                            .modified_files(name, &toml.mods[&ModName::from(*name)], deep)
+                            .len()
+                    })
+                    .sum();
+                if updates {
+                    summary.updates = Some(0);
+                    for framework in frameworks::installed(&toml) {
+                        if !names.contains(&framework.name) {
+                            continue;
+                        }
+                        match framework.latest_release() {
+                            Ok(release)
+                                if toml.mods[&ModName::from(framework.name)]
+                                    .version
+                                    .cmp_version(&ModVersion::from(release.version()))
+                                    .is_lt() =>
+                            {
+                                summary.updates = summary.updates.map(|count| count + 1);
+                            }
+                            Ok(_) => {}
+                            Err(err) => {
+                                tracing::warn!(%err, "could not check for updates");
+                                summary.updates = None;
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                println!("{}", summary.render(json));
+                return Ok(exit_code(summary.exit_code()));
+            }
+
+            let mods = names
+                .iter()
+                .map(|&name| (name, &toml.mods[&ModName::from(name)]))
+                .collect::<Vec<_>>();
+            let files = if deep {
+                let mut deep = handler.deep_file_health(&mods);
+                mods.iter()
+                    .map(|&(name, _)| (name, deep.remove(name).unwrap_or_default()))
+                    .collect::<BTreeMap<_, _>>()
+            } else {
+                mods.iter()
+                    .map(|&(name, entry)| (name, handler.file_health(name, entry)))
+                    .collect()
+            };
+            let names = if problems_only {
+                names
+                    .into_iter()
+                    .filter(|name| toml.health(name, game_version, &files[name]).is_problem())
+                    .collect()
+            } else {
+                names
+            };
+            let (out, code) = toml.status(json, game_version, &names, &files);
 
             print!("{out}");
 
-            std::process::exit(code);
+            return Ok(exit_code(code));
         }
         Command::Add {
             file,
             name,
             version,
             dependencies,
+            optional_deps,
+            game_versions,
+            provides,
+            conflicts,
+            force,
+            allow_downgrade,
+            trust,
+            inner,
+            variant,
+            password,
+            ignore_crc,
+            info,
         } => {
-            let config = load_config()?;
-            let handler = ModHandler::new(config.main.path);
-            let change = handler.add_mod(&file, name.clone(), version, &dependencies)?;
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?
+                .with_ignore_crc(ignore_crc);
+            let downloads = config.download_dir().into_diagnostic()?;
+            // What a dry run reads, joins or unpacks goes to scratch instead.
+            let scratch = dry_run.then(Scratch::new).transpose()?;
+            let downloads = scratch
+                .as_ref()
+                .map_or(downloads, |scratch| scratch.0.clone());
+            let piped = file == Path::new("-");
+            let file = if piped {
+                read_stdin(&downloads)?
+            } else {
+                split::join(&file, &downloads)?
+            };
+            mod_file_formats::check_format(&file)?;
+            let handler = unlock(handler, &file, password)?;
+            let unpacked = unpack_nested(
+                file.clone(),
+                &inner,
+                &handler.root_dirs,
+                &downloads,
+                fs::ExtractOptions {
+                    password: handler.fs.password.as_deref(),
+                    ignore_crc: handler.fs.ignore_crc,
+                },
+            )?;
+            let handler = unlock(handler, &unpacked, None)?;
+            // Variants are often only named after what sets them apart.
+            let outer = if unpacked == file || piped {
+                Metadata::default()
+            } else {
+                metadata::detect(&file)
+            };
+            if piped && unpacked != file {
+                let _ = std::fs::remove_file(&file);
+            }
+            let file = unpacked;
+            if !trust && let Err(err) = inspection::check_archive(&file, &handler.root_dirs) {
+                if !std::io::stdin().is_terminal() {
+                    return Err(err.into());
+                }
+                eprintln!("{:?}", miette::Report::new(err));
+                confirm(&tr("confirm-suspicious"), false)?;
+            }
+            let mut detected = if piped {
+                metadata::detect_contents(&file)
+            } else {
+                metadata::detect(&file)
+            }
+            .or(outer);
+            if name.is_none() || version.is_none() {
+                detected = identify(&config, game, &file).or(detected);
+            }
+            let name = match name.or(detected.name) {
+                Some(name) => name,
+                None => ask("Mod name", "name")?,
+            };
+            let version = match version.or(detected.version) {
+                Some(version) => version,
+                None => ask("Mod version", "version")?,
+            };
+            let file = if piped {
+                keep_piped(file, &name, &version, dry_run)?
+            } else {
+                file
+            };
+            let variant = match variant {
+                Some(variant) => Some(variant),
+                None => pick_variant(&handler, &file, &name)?,
+            };
+            let options = AddOptions {
+                dependencies,
+                optional_dependencies: optional_deps,
+                game_versions,
+                provides,
+                conflicts,
+                force,
+                author: info.author.or(detected.author),
+                description: info.description.or(detected.description),
+                url: info.url.or(detected.url),
+                variant,
+                allow_downgrade,
+                // Checked above, where there's a terminal to ask on.
+                trust: true,
+            };
+            let change = handler.transaction(format!("add `{name}` {version}"), || {
+                handler.add_mod(&file, name.clone(), version.clone(), &options)
+            })?;
 
-            match change {
+            match &change.operation {
                 Operation::Added(_) => println!("`{name}` is now active!"),
                 Operation::Updated { old, new } => {
                     println!("Updated `{name}` from `{old}` ~> `{new}`")
                 }
-                Operation::Move(_) => unreachable!("Moving doesn't happen in `Add`"),
+                Operation::Downgraded { old, new } => {
+                    println!("Downgraded `{name}` from `{old}` ~> `{new}`")
+                }
+                _ => unreachable!("Only installs happen in `Add`"),
+            }
+            if !dry_run {
+                println!("{}", change.summary());
+            } else if piped && file.is_dir() {
+                let _ = std::fs::remove_dir_all(&file);
+            } else if piped {
+                let _ = std::fs::remove_file(&file);
+            }
+            print_warnings(&handler);
+            dry_run_report(&handler);
+        }
+        Command::Downgrade {
+            name,
+            version,
+            force,
+            password,
+        } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let downloads = config.download_dir().into_diagnostic()?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            let (record, archive) =
+                handler.downgrade_source(&name, version.as_deref(), &downloads)?;
+            let handler = unlock(handler, &archive, password)?;
+            let change = handler
+                .transaction(format!("downgrade `{name}` to {}", record.version), || {
+                    handler.downgrade_mod(&name, &archive, &record, force)
+                })?;
+
+            match &change.operation {
+                Operation::Downgraded { old, new } => {
+                    println!("Downgraded `{name}` from `{old}` ~> `{new}`")
+                }
+                Operation::Updated { old, new } => {
+                    println!("Updated `{name}` from `{old}` ~> `{new}`")
+                }
+                Operation::Added(version) => println!("Reinstalled `{name}` `{version}`"),
+                _ => unreachable!("Only installs happen in `Downgrade`"),
+            }
+            if !dry_run {
+                println!("{}", change.summary());
+            }
+            print_warnings(&handler);
+            dry_run_report(&handler);
+        }
+        Command::Inspect { file, json } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let report = inspection::report(&file, &handler.load_toml()?, &handler.root_dirs)?;
+            println!("{}", report.render(json).trim_end());
+        }
+        Command::Adopt {
+            name,
+            version,
+            patterns,
+        } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let report = handler.adopt_mod(name.clone(), version, &patterns)?;
+
+            println!("Adopted {} files into `{name}`", report.files);
+            dry_run_report(&handler);
+        }
+        Command::Chown { pattern, new_owner } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let Operation::Chowned(files) = handler.chown(&pattern, new_owner.clone())?.operation
+            else {
+                unreachable!("Others not possible in chown")
+            };
+
+            for file in &files {
+                println!(
+                    "`{}`: `{}` -> `{new_owner}`",
+                    file.path.display(),
+                    file.owner
+                );
             }
+            println!("Handed {} files over to `{new_owner}`", files.len());
+            dry_run_report(&handler);
+        }
+        ref at @ (Command::Disable {
+            ref names, preview, ..
         }
-        ref at @ (Command::Disable { ref name } | Command::Enable { ref name }) => {
-            let config = load_config()?;
-            let handler = ModHandler::new(config.main.path);
+        | Command::Enable { ref names, preview }) => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
 
             let which = match at {
                 Command::Disable { .. } => Move::Disable,
                 Command::Enable { .. } => Move::Enable,
                 _ => unreachable!("How"),
             };
-            let change = handler.move_mod(name, which)?;
-            match change {
-                Operation::Move(moved) => println!(
-                    "{} `{name}`",
-                    match moved {
-                        Move::Enable => "Disabled",
-                        Move::Disable => "Enabled",
+            let names = resolve_names(&handler, names, fuzzy)?;
+            let mut planned = Vec::<String>::new();
+            for name in &names {
+                let request = match which {
+                    Move::Enable => Request::Enable(name.clone()),
+                    Move::Disable => Request::Disable(name.clone()),
+                };
+                let plan = handler.plan(&request)?;
+                let moved = match which {
+                    Move::Enable => plan.enable,
+                    Move::Disable => plan.disable,
+                };
+                for name in moved {
+                    if !planned.contains(&name) {
+                        planned.push(name);
                     }
-                ),
-                _ => unreachable!("Others not possible in disable or enable"),
+                }
             }
+            let extra = planned
+                .iter()
+                .filter(|planned| !names.contains(planned))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let moves = match at {
+                Command::Disable { impact, .. } => {
+                    let dependents = handler.dependents_to_disable(&names, extra, impact.into())?;
+                    planned
+                        .into_iter()
+                        .filter(|planned| names.contains(planned) || dependents.contains(planned))
+                        .collect::<Vec<_>>()
+                }
+                _ => {
+                    if !extra.is_empty() && !preview {
+                        confirm(
+                            &tr_with(
+                                "confirm-enable-dependencies",
+                                &[("mods", &extra.join(", "))],
+                            ),
+                            cli.yes,
+                        )?;
+                    }
+                    planned
+                }
+            };
+
+            let toml = handler.load_toml()?;
+            let state = match which {
+                Move::Enable => true,
+                Move::Disable => false,
+            };
+            // Mods already where they should be, unless that's all there is, so the handler explains.
+            let moves = match moves
+                .iter()
+                .filter(|name| toml.get(name).is_none_or(|entry| entry.installed != state))
+                .cloned()
+                .collect::<Vec<_>>()
+            {
+                filtered if filtered.is_empty() && names.len() == 1 => names.clone(),
+                filtered => filtered,
+            };
+
+            let done = match which {
+                Move::Enable => "Enabled",
+                Move::Disable => "Disabled",
+            };
+            let mut summary = moves
+                .iter()
+                .map(|name| {
+                    let why = match (names.contains(name), which) {
+                        (true, _) => "",
+                        (false, Move::Enable) => " (dependency)",
+                        (false, Move::Disable) => " (dependent)",
+                    };
+                    (name.clone(), format!("{done}{why}"))
+                })
+                .collect::<Vec<_>>();
+            summary.extend(
+                names
+                    .iter()
+                    .filter(|name| !moves.contains(name))
+                    .map(|name| (name.clone(), format!("Already {}", done.to_lowercase()))),
+            );
+
+            if preview {
+                let previews = moves
+                    .iter()
+                    .map(|name| handler.preview_move(name, which))
+                    .collect::<Result<Vec<_>, _>>()?;
+                print!("{}", preview::report(&previews));
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let verb = match which {
+                Move::Enable => "enable",
+                Move::Disable => "disable",
+            };
+            handler.transaction(format!("{verb} {}", quoted(&names)), || {
+                for name in &moves {
+                    handler.move_mod(name, which)?;
+                }
+                Ok(())
+            })?;
+            print_summary(&summary);
+            print_warnings(&handler);
+            dry_run_report(&handler);
+        }
+        Command::FixMoves => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let fixed = handler.fix_moves()?;
+
+            if fixed.is_empty() {
+                println!("No mods are split");
+            }
+            for fixed in &fixed {
+                println!("{}", fixed_message(fixed));
+            }
+            dry_run_report(&handler);
         }
-        Command::List { name } => {
-            let config = load_config()?;
-            let toml = ModHandler::new(config.main.path).load_toml()?;
+        Command::Remove { names, impact } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let names = resolve_names(&handler, &names, fuzzy)?;
+            let mut planned = Vec::<String>::new();
+            for name in &names {
+                for dependent in handler.plan(&Request::Remove(name.clone()))?.disable {
+                    if !planned.contains(&dependent) && !names.contains(&dependent) {
+                        planned.push(dependent);
+                    }
+                }
+            }
+            let dependents = handler.dependents_to_disable(&names, planned, (&impact).into())?;
+            backup_saves(&config, game, dry_run, "remove");
+
+            handler.transaction(format!("remove {}", quoted(&names)), || {
+                for dependent in &dependents {
+                    handler.move_mod(dependent, Move::Disable)?;
+                }
+                for name in &names {
+                    handler.remove_mod(name)?;
+                }
+                Ok(())
+            })?;
+
+            let mut summary = dependents
+                .into_iter()
+                .map(|dependent| (dependent, "Disabled (dependent)".to_string()))
+                .collect::<Vec<_>>();
+            summary.extend(names.into_iter().map(|name| (name, "Removed".to_string())));
+            print_summary(&summary);
+            dry_run_report(&handler);
+        }
+        Command::Purge => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            if !dry_run {
+                confirm(&tr("confirm-remove-all"), cli.yes)?;
+            }
+            backup_saves(&config, game, dry_run, "purge");
+
+            let removed = handler.purge()?;
+            if removed.is_empty() {
+                println!("No mods to remove");
+            }
+            for report in removed {
+                println!("Removed `{}`", report.name);
+            }
+            dry_run_report(&handler);
+        }
+        Command::Reset { keep_config } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            if !dry_run {
+                confirm(&tr("confirm-reset"), cli.yes)?;
+            }
+            backup_saves(&config, game, dry_run, "reset");
+
+            // Only archives this game's mods came from, other games and instances may use the rest.
+            let downloads = config.download_dir().into_diagnostic()?;
+            let archives = (handler.load_toml()?.mods.values())
+                .flat_map(|entry| {
+                    iter::once(&entry.file).chain(entry.history.iter().map(|record| &record.source))
+                })
+                .map(PathBuf::from)
+                .filter(|archive| archive.starts_with(&downloads) && archive.is_file())
+                .collect::<BTreeSet<_>>();
+
+            for path in handler.reset()? {
+                println!("Deleted `{}`", path.display());
+            }
+            // Downloads and the config belong to the configured setup, not to a `--root` game.
+            if root.is_none() {
+                for archive in archives {
+                    handler.fs.remove_file(&archive).into_diagnostic()?;
+                    println!("Deleted `{}`", archive.display());
+                }
+                if !keep_config {
+                    let path = Init::get_config()?;
+                    handler.fs.remove_file(&path).into_diagnostic()?;
+                    println!("Deleted `{}`", path.display());
+                }
+            }
+            dry_run_report(&handler);
+        }
+        Command::Gc => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let removed = handler.gc()?;
+
+            let size = removed.iter().map(|(_, size)| size).sum::<u64>();
+            println!(
+                "{} {} unused files from the store ({})",
+                if dry_run { "Would remove" } else { "Removed" },
+                removed.len(),
+                download_cache::format_size(size)
+            );
+            dry_run_report(&handler);
+        }
+        Command::Freeze => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let disabled = handler.freeze()?;
+
+            if disabled.is_empty() {
+                println!("No enabled mods hook into the game's code");
+            }
+            for name in &disabled {
+                println!("Disabled `{name}`");
+            }
+            println!("Run `vapor thaw` once frameworks are updated for the new game version");
+            dry_run_report(&handler);
+        }
+        Command::Thaw => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            for name in handler.thaw()? {
+                println!("Enabled `{name}`");
+            }
+            dry_run_report(&handler);
+        }
+        Command::Pack { name, out } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            let out = match out {
+                Some(out) => out,
+                None => {
+                    let toml = handler.load_toml()?;
+                    let version = toml.get(&name).map(|entry| entry.version.as_str());
+                    PathBuf::from(format!("{name}-{}.zip", version.unwrap_or_default()))
+                }
+            };
+            if out.exists() {
+                confirm(
+                    &tr_with("confirm-overwrite", &[("path", &out.display())]),
+                    cli.yes,
+                )?;
+            }
+
+            let files = handler.pack(&name, &out)?;
+            println!(
+                "{} {} files of `{name}` into `{}`",
+                if dry_run { "Would pack" } else { "Packed" },
+                files.len(),
+                out.display()
+            );
+        }
+        Command::Rename { old, new } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let old = resolve_name(&handler, old, fuzzy)?;
+            let Operation::Renamed { dependents, .. } =
+                handler.rename_mod(old.clone(), new.clone())?.operation
+            else {
+                unreachable!("Others not possible in rename")
+            };
+
+            println!("Renamed `{old}` to `{new}`");
+            for dependent in dependents {
+                println!("  - Updated dependency in `{dependent}`");
+            }
+            dry_run_report(&handler);
+        }
+        Command::Exclude { name, pattern } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            let excluded = handler.exclude_mod(name.clone(), &pattern)?;
+
+            println!("Excluded {} files from `{name}`:", excluded.len());
+            for file in excluded {
+                println!("{file}");
+            }
+            dry_run_report(&handler);
+        }
+        Command::Tag { name, action } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+
+            match action {
+                TagAction::Add { tag } => {
+                    if handler.add_tag(name.clone(), &tag)? {
+                        println!("Tagged `{name}` with `{tag}`");
+                    } else {
+                        println!("`{name}` is already tagged with `{tag}`");
+                    }
+                }
+                TagAction::Remove { tag } => {
+                    if handler.remove_tag(name.clone(), &tag)? {
+                        println!("Removed `{tag}` from `{name}`");
+                    } else {
+                        println!("`{name}` is not tagged with `{tag}`");
+                    }
+                }
+            }
+            dry_run_report(&handler);
+        }
+        Command::Describe { name, info } => {
+            if info.author.is_none() && info.description.is_none() && info.url.is_none() {
+                return Err(miette!(
+                    code = ErrorClass::Usage.code(),
+                    help = "Pass `--author`, `--description` or `--url`",
+                    "Nothing to set"
+                ));
+            }
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            handler.describe(
+                name.clone(),
+                info.author.as_deref(),
+                info.description.as_deref(),
+                info.url.as_deref(),
+            )?;
+
+            println!("Updated `{name}`");
+            dry_run_report(&handler);
+        }
+        Command::Open { name } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            let toml = handler.load_toml()?;
+            let Some(url) = toml.get(&name).and_then(|entry| entry.url.clone()) else {
+                return Err(miette!(
+                    code = ErrorClass::Usage.code(),
+                    help = format!("Set one with `vapor describe {name} --url <URL>`"),
+                    "`{name}` has no homepage"
+                ));
+            };
+
+            if dry_run {
+                println!("Would open {url}");
+            } else {
+                platform::open_url(&url).into_diagnostic()?;
+                println!("Opened {url}");
+            }
+        }
+        Command::Edit { name, files } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+
+            let targets = if files.is_empty() {
+                handler.editable_files(&name)?
+            } else {
+                let toml = handler.load_toml()?;
+                let entry = toml
+                    .get(&name)
+                    .ok_or_else(|| ModError::missing_mod(&toml, name.clone()))?;
+                let (home, _) = handler.file_roots(&name, entry.installed);
+                files
+                    .iter()
+                    .map(|file| {
+                        let file = file.replace('\\', "/");
+                        match entry
+                            .deployed_files()
+                            .find(|owned| owned.eq_ignore_ascii_case(&file))
+                        {
+                            Some(owned) => Ok((owned.clone(), home.join(owned))),
+                            None => Err(miette!(
+                                code = ErrorClass::Usage.code(),
+                                help = format!("See its files with `vapor list {name} --tree`"),
+                                "`{name}` has no file `{file}`"
+                            )),
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            };
+            if targets.is_empty() {
+                return Err(miette!(
+                    code = ErrorClass::Usage.code(),
+                    help = format!("Open any of its files with `vapor edit {name} <FILE>`"),
+                    "`{name}` has no config files"
+                ));
+            }
+
+            let paths = targets
+                .iter()
+                .map(|(_, path)| path.clone())
+                .collect::<Vec<_>>();
+            if dry_run {
+                for path in &paths {
+                    println!("Would open {}", path.display());
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            handler.unshare(&paths)?;
+            let status = platform::edit(&paths).into_diagnostic()?;
+            if !status.success() {
+                return Err(miette!(
+                    code = ErrorClass::Cancelled.code(),
+                    help = "Nothing was recorded, run `vapor edit` again to keep your changes",
+                    "The editor exited with {status}"
+                ));
+            }
+
+            let files = targets
+                .into_iter()
+                .map(|(file, _)| file)
+                .collect::<Vec<_>>();
+            let edited = handler.record_edits(&name, &files)?;
+            if edited.is_empty() {
+                println!("No changes to `{name}`");
+            } else {
+                println!("Kept edits to {} of `{name}`", quoted(&edited));
+            }
+        }
+        ref at @ (Command::Pin { ref names } | Command::Unpin { ref names }) => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let names = resolve_names(&handler, names, fuzzy)?;
+            let pinned = matches!(at, Command::Pin { .. });
+            let done = if pinned { "Pinned" } else { "Unpinned" };
+
+            let verb = if pinned { "pin" } else { "unpin" };
+            let summary = handler.transaction(format!("{verb} {}", quoted(&names)), || {
+                names
+                    .iter()
+                    .map(|name| {
+                        let result = match (handler.pin(name.clone(), pinned)?, pinned) {
+                            (true, _) => done,
+                            (false, true) => "Already pinned",
+                            (false, false) => "Not pinned",
+                        };
+                        Ok((name.clone(), result.to_string()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })?;
+            print_summary(&summary);
+            dry_run_report(&handler);
+        }
+        Command::Note { name, text } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            handler.set_note(name.clone(), &text)?;
+
+            if text.is_empty() {
+                println!("Cleared note on `{name}`");
+            } else {
+                println!("Set note on `{name}`");
+            }
+            dry_run_report(&handler);
+        }
+        Command::Info {
+            name,
+            history,
+            json,
+        } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = resolve_name(&handler, name, fuzzy)?;
+            let toml = handler.load_toml()?;
+            let Some(entry) = toml.get(&name) else {
+                return Err(ModError::missing_mod(&toml, name).into());
+            };
+
+            if history && json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&entry.history).into_diagnostic()?
+                );
+            } else if history {
+                print!("{}", entry.history(&name));
+            } else {
+                let game_version = match game {
+                    Some(game) if game != DEFAULT_GAME => None,
+                    _ => config.game_version(),
+                };
+                let files = BTreeMap::from([(name.as_str(), handler.file_health(&name, entry))]);
+                let (out, code) = toml.status(json, game_version, &[&name], &files);
+                print!("{out}");
+                return Ok(exit_code(code));
+            }
+        }
+        Command::List {
+            name,
+            all,
+            tree,
+            json,
+            filter,
+        } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let toml = handler.load_toml()?;
 
             match name {
+                Some(name) if !name.is_empty() && !toml.contains(&name) && is_glob(&name) => {
+                    let query = mod_query(filter, Some(&name), (!all).then_some(true))?;
+                    for mod_name in toml.query(&query, |name, entry| handler.mod_size(name, entry))
+                    {
+                        println!("{mod_name}");
+                    }
+                }
                 Some(name) if !name.is_empty() => {
-                    if let Some(mod_name) = toml.mods.get(&name) {
-                        for file in &mod_name.files {
-                            println!("{file}");
+                    if let Some((mod_name, entry)) = toml
+                        .resolve_name(&name, fuzzy)
+                        .and_then(|name| Some((name, toml.get(name)?)))
+                    {
+                        let kind = filter.kind.map(FileKind::from);
+                        if tree || json {
+                            let mut files = handler.file_info(mod_name, entry);
+                            files.retain(|file| kind.is_none_or(|kind| file.kind == kind));
+                            if json {
+                                println!(
+                                    "{}",
+                                    serde_json::to_string_pretty(&files).into_diagnostic()?
+                                );
+                            } else {
+                                print!("{}", file_tree::render(mod_name, &files));
+                            }
+                        } else {
+                            for file in entry
+                                .files
+                                .iter()
+                                .filter(|file| kind.is_none_or(|kind| FileKind::of(file) == kind))
+                            {
+                                println!("{file}");
+                            }
                         }
                     } else {
                         let source = format!("vapor list {name}");
                         let report = miette!(
+                            code = ErrorClass::MissingMod.code(),
                             labels = vec![LabeledSpan::at(
                                 source.len() - name.len()..source.len(),
                                 "invalid mod name"
                             )],
-                            help = "Specify a valid mod found in `vapor list`!",
+                            help = match toml.similar_names(&name).first() {
+                                Some(similar) => format!("Did you mean `{similar}`?"),
+                                None => "Specify a valid mod found in `vapor list`!".to_string(),
+                            },
                             "No mod named `{name}` found!"
                         )
                         .with_source_code(source);
                         eprintln!("{report:?}");
-                        std::process::exit(1);
+                        return Ok(ExitCode::from(ErrorClass::MissingMod.exit_code()));
                     }
                 }
                 _ => {
-                    for (mod_name, entry) in toml.mods {
-                        if entry.installed {
-                            println!("{mod_name}");
-                        }
+                    let query = mod_query(filter, None, (!all).then_some(true))?;
+                    for mod_name in toml.query(&query, |name, entry| handler.mod_size(name, entry))
+                    {
+                        println!("{mod_name}");
                     }
                 }
             }
         }
         Command::Graph => {
-            let config = load_config()?;
-            let toml = ModHandler::new(config.main.path).load_toml()?;
+            let config = load_config(instance, root)?;
+            let toml = load_handler(&config, game, dry_run, no_trash, mutates)?.load_toml()?;
             print!("{}", toml.graph());
         }
+        Command::Search {
+            query,
+            limit,
+            json,
+            remote: true,
+            install,
+        } => {
+            cyberpunk_only(game, "search --remote")?;
+            let config = load_config(instance, root)?;
+            let found = nexus::search(nexus::DEFAULT_DOMAIN, &query.join(" "), limit)?;
+
+            let Some(number) = install else {
+                println!("{}", nexus::search_report(&found, json).trim_end());
+                return Ok(ExitCode::SUCCESS);
+            };
+            let Some(chosen) = number.checked_sub(1).and_then(|index| found.get(index)) else {
+                return Err(miette!(
+                    code = ErrorClass::Usage.code(),
+                    help = "Run the search without `--install` to see the results",
+                    "There is no result {number}, only {}",
+                    found.len()
+                ));
+            };
+
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let api_key = secrets::resolve(config.main.nexus_api_key.as_deref(), NEXUS)?
+                .ok_or(nexus::NexusError::MissingKey)?;
+            let client = nexus::Client::new(api_key);
+            let file = client.main_file(nexus::DEFAULT_DOMAIN, chosen.mod_id)?;
+            // A dry run downloads to scratch, so it can still look inside.
+            let scratch = dry_run.then(Scratch::new).transpose()?;
+            let downloads = match &scratch {
+                Some(scratch) => scratch.0.clone(),
+                None => config.download_dir().into_diagnostic()?,
+            };
+            let archive = client.download(
+                nexus::DEFAULT_DOMAIN,
+                chosen.mod_id,
+                file.file_id,
+                &downloads,
+            )?;
+
+            let name = chosen.name.clone();
+            let version = file.version.unwrap_or_else(|| chosen.version.clone());
+            let options = AddOptions {
+                description: chosen.summary.clone(),
+                url: Some(format!(
+                    "https://www.nexusmods.com/{}/mods/{}",
+                    nexus::DEFAULT_DOMAIN,
+                    chosen.mod_id
+                )),
+                ..Default::default()
+            };
+            let change = handler.transaction(format!("add `{name}` {version}"), || {
+                handler.add_mod(&archive, name.clone(), version.clone(), &options)
+            })?;
+
+            match &change.operation {
+                Operation::Added(_) => println!("`{name}` is now active!"),
+                Operation::Updated { old, new } => {
+                    println!("Updated `{name}` from `{old}` ~> `{new}`")
+                }
+                Operation::Downgraded { old, new } => {
+                    println!("Downgraded `{name}` from `{old}` ~> `{new}`")
+                }
+                _ => unreachable!("Only installs happen in `Add`"),
+            }
+            if !dry_run {
+                println!("{}", change.summary());
+            }
+            print_warnings(&handler);
+            dry_run_report(&handler);
+        }
+        Command::Search {
+            query, limit, json, ..
+        } => {
+            let config = load_config(instance, root)?;
+            let toml = load_handler(&config, game, dry_run, no_trash, mutates)?.load_toml()?;
+            let hits = search::search(&toml, &query.join(" "), limit);
+            println!("{}", search::render(&hits, json).trim_end());
+        }
+        Command::Stats { top, json } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let toml = handler.load_toml()?;
+            let stats = toml.stats(top, |name, entry| handler.mod_size(name, entry));
+            println!("{}", stats.render(json).trim_end());
+        }
+        Command::Conflicts { name } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let name = name
+                .map(|name| resolve_name(&handler, name, fuzzy))
+                .transpose()?;
+            let toml = handler.load_toml()?;
+
+            if let Some(name) = &name
+                && !toml.contains(name)
+            {
+                return Err(ModError::missing_mod(&toml, name.clone()).into());
+            }
+            print!("{}", toml.conflicts_report(name.as_deref()));
+        }
+        Command::Modlist { write } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+
+            if write {
+                let order = handler.write_modlist()?;
+                if !dry_run {
+                    println!("Wrote {} archives to `{MODLIST}`", order.len());
+                }
+                dry_run_report(&handler);
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let order = handler.load_order()?;
+            let current = handler.modlist_entries();
+            print!(
+                "{}",
+                load_order::report(&order, current.as_deref(), &handler.stale_modlist_entries())
+            );
+        }
+        Command::Undo { n } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+
+            if n > 1 && !dry_run {
+                confirm(&tr_with("confirm-undo", &[("count", &n)]), cli.yes)?;
+            }
+
+            for report in handler.undo(n)? {
+                if let Operation::Undone { operation, .. } = report.operation {
+                    println!("Undid {operation}");
+                }
+            }
+            dry_run_report(&handler);
+        }
+        Command::History { json } => {
+            if json {
+                let events = EventLog::open_default()?.events()?;
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&events).into_diagnostic()?
+                );
+                return Ok(ExitCode::SUCCESS);
+            }
+            let config = load_config(instance, root)?;
+            print!(
+                "{}",
+                journal::report(
+                    &load_handler(&config, game, dry_run, no_trash, mutates)?.history()?
+                )
+            );
+        }
+        Command::Diff { snapshot } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let old = match snapshot.parse() {
+                Ok(id) => handler.registry_before(id)?,
+                Err(_) => ModHandler::read_registry(Path::new(&snapshot))?,
+            };
+
+            let diff = diff::diff(&old, &handler.load_toml()?);
+            if diff.is_empty() {
+                println!("No changes");
+            }
+            print!("{}", diff::report(&diff));
+        }
+        Command::GameVersion { detect } => {
+            cyberpunk_only(game, "game-version")?;
+            let mut config = load_config(instance, root)?;
+
+            if detect {
+                config.set_game_version(game::detect_version(Path::new(config.game_path())));
+                config.save()?;
+            }
+
+            match config.game_version() {
+                Some(version) => println!("{version}"),
+                None => {
+                    return Err(miette!(
+                        code = ErrorClass::Config.code(),
+                        help = "Detect it with `vapor game-version --detect`",
+                        "Could not detect the game version"
+                    ));
+                }
+            }
+        }
+        Command::Run {
+            proton,
+            modded,
+            force,
+        } => {
+            cyberpunk_only(game, "run")?;
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+            let toml = handler.load_toml()?;
+
+            if let Err(err) = launch::check(&toml) {
+                if !force {
+                    return Err(err.into());
+                }
+                eprintln!("{:?}", miette::Report::new(err));
+            }
+
+            handler.run_launch_hooks()?;
+            print_warnings(&handler);
+
+            let via = if proton { Via::Proton } else { Via::Steam };
+            launch::command(&config, via, modded)?
+                .spawn()
+                .into_diagnostic()?;
+        }
+        Command::Doctor { cmd } => match cmd {
+            None => {
+                let issues = doctor::health::check(game);
+                if issues.is_empty() {
+                    println!("No problems found");
+                } else {
+                    for issue in issues {
+                        eprintln!("{:?}", miette::Report::new(issue));
+                    }
+                    return Ok(ExitCode::from(ErrorClass::Problems.exit_code()));
+                }
+            }
+            Some(DoctorCommand::Logs) => {
+                cyberpunk_only(game, "doctor logs")?;
+                let config = load_config(instance, root)?;
+                let toml = ModHandler::new(config.game_path()).load_toml()?;
+                let findings = doctor::logs::scan(Path::new(config.game_path()), &toml);
+                if findings.is_empty() {
+                    println!("No errors found in logs");
+                } else {
+                    print!("{}", doctor::logs::report(&findings));
+                    return Ok(ExitCode::from(ErrorClass::Problems.exit_code()));
+                }
+            }
+        },
+        Command::Frameworks { cmd } => {
+            cyberpunk_only(game, "frameworks")?;
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, None, dry_run, no_trash, mutates)?;
+            let downloads = config.download_dir().into_diagnostic()?;
+
+            match cmd {
+                FrameworksCommand::Install { names } => {
+                    for framework in frameworks::resolve(&names)? {
+                        let release = framework.latest_release()?;
+                        match framework.install(&handler, &release, &downloads)?.operation {
+                            Operation::Added(version) => {
+                                println!("Installed `{}` `{version}`", framework.name)
+                            }
+                            Operation::Updated { old, new } => {
+                                println!("Updated `{}` from `{old}` ~> `{new}`", framework.name)
+                            }
+                            Operation::Downgraded { old, new } => {
+                                println!("Downgraded `{}` from `{old}` ~> `{new}`", framework.name)
+                            }
+                            _ => unreachable!("Only installs happen in `Add`"),
+                        }
+                    }
+                }
+                FrameworksCommand::Status => {
+                    print!("{}", frameworks::status(&handler.load_toml()?));
+                }
+                FrameworksCommand::Update => {
+                    let toml = handler.load_toml()?;
+                    for framework in frameworks::installed(&toml) {
+                        let entry = &toml.mods[&ModName::from(framework.name)];
+                        if entry.pinned {
+                            println!("`{}` is pinned, skipping", framework.name);
+                            continue;
+                        }
+                        let release = framework.latest_release()?;
+                        if entry
+                            .version
+                            .cmp_version(&ModVersion::from(release.version()))
+                            .is_ge()
+                        {
+                            println!("`{}` is up to date", framework.name);
+                            continue;
+                        }
+                        if let Operation::Updated { old, new } =
+                            framework.install(&handler, &release, &downloads)?.operation
+                        {
+                            println!("Updated `{}` from `{old}` ~> `{new}`", framework.name);
+                        }
+                    }
+                }
+            }
+            print_warnings(&handler);
+            dry_run_report(&handler);
+        }
+        #[cfg(feature = "daemon")]
+        Command::Daemon => {
+            service::set_root(root);
+            daemon::serve().into_diagnostic()?;
+        }
+        #[cfg(unix)]
+        Command::Serve { socket } => {
+            let socket = match socket {
+                Some(socket) => socket,
+                None => rpc::default_socket().into_diagnostic()?,
+            };
+            service::set_root(root);
+            rpc::serve(&socket).into_diagnostic()?;
+        }
+        Command::Config { cmd } => {
+            let mut config = Config::load()?;
+            match cmd {
+                ConfigCommand::Get { key: Some(key) } => println!("{}", config.get(&key)?),
+                ConfigCommand::Get { key: None } => {
+                    for key in config::KEYS {
+                        println!("{key} = {:?}", config.get(key)?);
+                    }
+                }
+                ConfigCommand::Set { key, value } => {
+                    config.set(&key, &value)?;
+                    config.save()?;
+                }
+                ConfigCommand::Path => println!("{}", config.path().display()),
+            }
+        }
+        Command::Collection { cmd } => {
+            let config = load_config(instance, root)?;
+            let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+
+            match cmd {
+                CollectionCommand::Create { name, mods } => {
+                    let mods = mods
+                        .into_iter()
+                        .map(|member| resolve_name(&handler, member, fuzzy))
+                        .collect::<Result<Vec<_>>>()?;
+                    handler.create_collection(name.clone(), &mods)?;
+                    println!("Created collection `{name}` with {} mods", mods.len());
+                }
+                CollectionCommand::Delete { name } => {
+                    handler.delete_collection(name.clone())?;
+                    println!("Deleted collection `{name}`");
+                }
+                CollectionCommand::List => {
+                    let toml = handler.load_toml()?;
+                    if toml.collections.is_empty() {
+                        println!("No collections");
+                    }
+                    for (name, collection) in &toml.collections {
+                        println!(
+                            "{name}: {}",
+                            collection
+                                .mods
+                                .iter()
+                                .map(ModName::as_str)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                    }
+                }
+                CollectionCommand::Enable { name } => {
+                    let collection = handler.collection(&name)?;
+
+                    let mut names = Vec::<String>::new();
+                    for member in &collection.mods {
+                        for planned in handler.plan(&Request::Enable(member.to_string()))?.enable {
+                            if !names.contains(&planned) {
+                                names.push(planned);
+                            }
+                        }
+                    }
+
+                    let extra = names
+                        .iter()
+                        .filter(|planned| !collection.mods.iter().any(|member| member == *planned))
+                        .map(String::as_str)
+                        .collect::<Vec<_>>();
+                    if !extra.is_empty() {
+                        confirm(
+                            &tr_with(
+                                "confirm-collection-dependencies",
+                                &[("name", &name), ("mods", &extra.join(", "))],
+                            ),
+                            cli.yes,
+                        )?;
+                    }
+
+                    if names.is_empty() {
+                        println!("Every mod in `{name}` is already enabled");
+                    }
+                    move_all(&handler, &names, Move::Enable)?;
+                }
+                CollectionCommand::Disable { name, impact } => {
+                    let collection = handler.collection(&name)?;
+                    let toml = handler.load_toml()?;
+
+                    let mut planned = Vec::<String>::new();
+                    for member in &collection.mods {
+                        if !toml.get(member).is_some_and(|entry| entry.installed) {
+                            continue;
+                        }
+                        for dependent in
+                            handler.plan(&Request::Disable(member.to_string()))?.disable
+                        {
+                            if !planned.contains(&dependent) {
+                                planned.push(dependent);
+                            }
+                        }
+                    }
+
+                    let (members, outside) =
+                        planned.into_iter().partition::<Vec<_>, _>(|planned| {
+                            collection.mods.iter().any(|member| member == planned)
+                        });
+                    let mut names =
+                        handler.dependents_to_disable(&[&name], outside, (&impact).into())?;
+                    names.extend(members);
+
+                    if names.is_empty() {
+                        println!("Every mod in `{name}` is already disabled");
+                    }
+                    move_all(&handler, &names, Move::Disable)?;
+                }
+                CollectionCommand::Export { name, file } => {
+                    handler.export_collection(&name, &file)?;
+                    println!("Exported `{name}` to `{}`", file.display());
+                }
+                CollectionCommand::Import { file } => {
+                    let name = handler.import_collection(&file)?;
+                    println!("Imported collection `{name}`");
+                }
+                CollectionCommand::ImportNexus {
+                    manifest,
+                    optional,
+                    allow_mismatch,
+                } => {
+                    let manifest = nexus::Manifest::load(&manifest)?;
+                    let name = manifest.info.name.clone();
+                    if handler.collection(&name).is_ok() {
+                        return Err(ModError::CollectionExists(name).into());
+                    }
+
+                    let plan = manifest.plan(optional)?;
+                    let api_key = secrets::resolve(config.main.nexus_api_key.as_deref(), NEXUS)?
+                        .ok_or(nexus::NexusError::MissingKey)?;
+                    let client = nexus::Client::new(api_key);
+                    let downloads = config.download_dir().into_diagnostic()?;
+
+                    confirm(
+                        &tr_with(
+                            "confirm-install-modlist",
+                            &[("count", &plan.len()), ("name", &name)],
+                        ),
+                        cli.yes,
+                    )?;
+                    for planned in &plan {
+                        let archive = planned.download(&client, &downloads)?;
+                        if let Err(err) = planned.verify(&archive) {
+                            if !allow_mismatch {
+                                return Err(err.into());
+                            }
+                            eprintln!("{:?}", miette::Report::new(err));
+                            eprintln!("Installing `{}` anyway", planned.name);
+                        }
+                        match planned.install(&handler, &archive)?.operation {
+                            Operation::Added(version) => {
+                                println!("Installed `{}` {version}", planned.name)
+                            }
+                            Operation::Updated { old, new } => {
+                                println!("Updated `{}` from `{old}` ~> `{new}`", planned.name)
+                            }
+                            Operation::Downgraded { old, new } => {
+                                println!("Downgraded `{}` from `{old}` ~> `{new}`", planned.name)
+                            }
+                            _ => unreachable!("Only installs happen in `Add`"),
+                        }
+                        print_warnings(&handler);
+                    }
+
+                    let mods = plan
+                        .into_iter()
+                        .map(|planned| planned.name)
+                        .collect::<Vec<_>>();
+                    handler.create_collection(name.clone(), &mods)?;
+                    println!("Created collection `{name}` with {} mods", mods.len());
+                }
+            }
+            print_warnings(&handler);
+            dry_run_report(&handler);
+        }
+        Command::Instance { cmd } => match cmd {
+            InstanceCommand::Add { name, path } => {
+                let mut config = load_config(instance, root)?;
+                let path = path.canonicalize().into_diagnostic()?;
+                config.add_instance(&name, &path)?;
+                config.save()?;
+                println!("Added instance `{name}` at `{}`", path.display());
+            }
+            InstanceCommand::List => {
+                let config = load_config(instance, root)?;
+                let current = config.instance();
+                let marker = |name: &str| if name == current { "*" } else { " " };
+
+                println!(
+                    "{} {DEFAULT_INSTANCE}: {}",
+                    marker(DEFAULT_INSTANCE),
+                    config.main.path
+                );
+                for (name, entry) in &config.instances {
+                    println!("{} {name}: {}", marker(name), entry.path);
+                }
+            }
+            InstanceCommand::Use { name } => {
+                let mut config = load_config(instance, root)?;
+                config.select_instance(Some(&name))?;
+                config.main.instance = (name != DEFAULT_INSTANCE).then_some(name.clone());
+                config.save()?;
+                println!("Using instance `{name}`");
+            }
+        },
+        Command::Saves { cmd } => {
+            cyberpunk_only(game, "saves")?;
+            let config = load_config(instance, root)?;
+            match cmd {
+                SavesCommand::Backup => {
+                    let backup = saves::backup(&saves::find(&config)?, None)?;
+                    println!(
+                        "Backed up saves as `{}` ({})",
+                        backup.name,
+                        download_cache::format_size(backup.size)
+                    );
+                }
+                SavesCommand::Restore { backup } => {
+                    let dir = saves::find(&config)?;
+                    let backup = saves::get(backup.as_deref())?;
+                    confirm(
+                        &tr_with(
+                            "confirm-restore-saves",
+                            &[("dir", &dir.display()), ("backup", &backup.name)],
+                        ),
+                        cli.yes,
+                    )?;
+                    if dir.is_dir() {
+                        let current = saves::backup(&dir, Some("before-restore"))?;
+                        println!("Backed up current saves as `{}`", current.name);
+                    }
+                    saves::restore(&dir, &backup)?;
+                    println!("Restored saves from `{}`", backup.name);
+                }
+                SavesCommand::List => {
+                    let backups = saves::list()?;
+                    if backups.is_empty() {
+                        println!("No save backups");
+                    }
+                    for backup in backups {
+                        println!(
+                            "{} ({})",
+                            backup.name,
+                            download_cache::format_size(backup.size)
+                        );
+                    }
+                }
+            }
+        }
+        Command::Cache { cmd } => match cmd {
+            CacheCommand::Clear {
+                scripts: _,
+                tweaks,
+                all,
+            } => {
+                cyberpunk_only(game, "cache clear")?;
+                let config = load_config(instance, root)?;
+                let handler = load_handler(&config, game, dry_run, no_trash, mutates)?;
+
+                let caches = if all {
+                    GameCache::ALL.to_vec()
+                } else if tweaks {
+                    vec![GameCache::Tweaks]
+                } else {
+                    vec![GameCache::Scripts]
+                };
+                let cleared = handler.clear_cache(&caches)?;
+
+                if cleared.is_empty() {
+                    println!("Nothing to clear");
+                }
+                for file in cleared {
+                    println!("Cleared `{}`", file.display());
+                }
+                dry_run_report(&handler);
+            }
+            CacheCommand::Stats => {
+                let config = load_config(instance, root)?;
+                let stats = download_cache::stats(&config.download_dir().into_diagnostic()?)
+                    .into_diagnostic()?;
+
+                println!(
+                    "Downloads: {} archives, {}",
+                    stats.archives,
+                    download_cache::format_size(stats.archive_bytes)
+                );
+                if stats.partial > 0 {
+                    println!(
+                        "Unfinished downloads: {}, {}",
+                        stats.partial,
+                        download_cache::format_size(stats.partial_bytes)
+                    );
+                }
+                println!(
+                    "Listings: {}, {}",
+                    stats.listings,
+                    download_cache::format_size(stats.listing_bytes)
+                );
+            }
+            CacheCommand::Gc { keep, max_size } => {
+                let config = load_config(instance, root)?;
+                let toml = load_handler(&config, game, dry_run, no_trash, mutates)?.load_toml()?;
+                let referenced = toml
+                    .mods
+                    .values()
+                    .map(|entry| PathBuf::from(&entry.file))
+                    .collect::<Vec<_>>();
+
+                let removed = download_cache::gc(
+                    &config.download_dir().into_diagnostic()?,
+                    download_cache::Retention { keep, max_size },
+                    &referenced,
+                    dry_run,
+                )
+                .into_diagnostic()?;
+
+                if removed.is_empty() {
+                    println!("Nothing to remove");
+                }
+                for (path, size) in &removed {
+                    println!(
+                        "{} `{}` ({})",
+                        if dry_run { "Would remove" } else { "Removed" },
+                        path.display(),
+                        download_cache::format_size(*size)
+                    );
+                }
+                if !removed.is_empty() {
+                    let freed = removed.iter().map(|(_, size)| size).sum::<u64>();
+                    println!(
+                        "{} {}",
+                        if dry_run { "Would free" } else { "Freed" },
+                        download_cache::format_size(freed)
+                    );
+                }
+            }
+        },
+        Command::Hook { cmd } => {
+            let mut config = Config::load()?;
+            match cmd {
+                HookCommand::List => {
+                    let toml = load_handler(
+                        &load_config(instance, root)?,
+                        game,
+                        dry_run,
+                        no_trash,
+                        false,
+                    )?
+                    .load_toml()?;
+                    let hooks = std::iter::once(("every mod", &config.hooks().hooks)).chain(
+                        toml.mods
+                            .iter()
+                            .map(|(name, entry)| (name.as_str(), &entry.hooks)),
+                    );
+
+                    let mut any = false;
+                    for (owner, hooks) in hooks {
+                        for event in Event::ALL {
+                            for command in hooks.get(event) {
+                                any = true;
+                                let allowed = if config.hooks().is_allowed(command) {
+                                    ""
+                                } else {
+                                    " (not allowed)"
+                                };
+                                println!("{owner}: {event}: `{command}`{allowed}");
+                            }
+                        }
+                    }
+                    if !any {
+                        println!("No hooks set up");
+                    }
+                }
+                HookCommand::Add {
+                    event,
+                    command,
+                    mod_name,
+                } => {
+                    let event = Event::from(event);
+                    let owner = match mod_name {
+                        Some(name) => {
+                            let handler = load_handler(
+                                &load_config(instance, root)?,
+                                game,
+                                dry_run,
+                                no_trash,
+                                mutates,
+                            )?;
+                            let name = resolve_name(&handler, name, fuzzy)?;
+                            if !handler.add_hook(name.clone(), event, &command)? {
+                                println!("`{name}` already has this `{event}` hook");
+                            }
+                            format!("`{name}`")
+                        }
+                        None => {
+                            let commands = config.hooks_mut().hooks.get_mut(event);
+                            if commands.contains(&command) {
+                                println!("Every mod already has this `{event}` hook");
+                            } else {
+                                commands.push(command.clone());
+                            }
+                            "every mod".to_string()
+                        }
+                    };
+
+                    config.hooks_mut().allow(&command);
+                    if !dry_run {
+                        config.save()?;
+                    }
+                    println!("`{command}` now runs on `{event}` for {owner}");
+                }
+                HookCommand::Remove {
+                    event,
+                    command,
+                    mod_name,
+                } => {
+                    let event = Event::from(event);
+                    let removed = match mod_name {
+                        Some(name) => {
+                            let handler = load_handler(
+                                &load_config(instance, root)?,
+                                game,
+                                dry_run,
+                                no_trash,
+                                mutates,
+                            )?;
+                            let name = resolve_name(&handler, name, fuzzy)?;
+                            handler.remove_hook(name, event, &command)?
+                        }
+                        None => {
+                            let commands = config.hooks_mut().hooks.get_mut(event);
+                            let before = commands.len();
+                            commands.retain(|c| *c != command);
+                            commands.len() != before
+                        }
+                    };
+
+                    if !removed {
+                        return Err(miette!(
+                            code = ErrorClass::Usage.code(),
+                            "No `{event}` hook runs `{command}`"
+                        ));
+                    }
+                    if !dry_run {
+                        config.save()?;
+                    }
+                    println!("Removed `{event}` hook `{command}`");
+                }
+                HookCommand::Allow { command } => {
+                    if config.hooks_mut().allow(&command) {
+                        config.save()?;
+                        println!("Allowed `{command}` to run as a hook");
+                    } else {
+                        println!("`{command}` is already allowed");
+                    }
+                }
+                HookCommand::Deny { command } => {
+                    let allow = &mut config.hooks_mut().allow;
+                    let before = allow.len();
+                    allow.retain(|c| *c != command);
+                    if allow.len() == before {
+                        println!("`{command}` wasn't allowed");
+                    } else {
+                        config.save()?;
+                        println!("`{command}` will no longer run as a hook");
+                    }
+                }
+            }
+        }
+        Command::Auth { cmd } => match cmd {
+            AuthCommand::Login => {
+                let key = if std::io::stdin().is_terminal() {
+                    Input::new("Nexus API key")
+                        .description("Find it at https://www.nexusmods.com/settings/api-keys")
+                        .password(true)
+                        .run()
+                        .into_diagnostic()?
+                } else {
+                    let mut key = String::new();
+                    std::io::stdin().read_line(&mut key).into_diagnostic()?;
+                    key
+                };
+                let key = key.trim();
+                if key.is_empty() {
+                    return Err(miette!(code = ErrorClass::Auth.code(), "No API key given"));
+                }
+
+                println!(
+                    "Stored the Nexus API key in {}",
+                    secrets::store(NEXUS, key)?
+                );
+            }
+            AuthCommand::Logout => {
+                let removed = secrets::remove(NEXUS)?;
+                if removed.is_empty() {
+                    println!("No Nexus API key was stored");
+                }
+                for store in removed {
+                    println!("Removed the Nexus API key from {store}");
+                }
+            }
+            AuthCommand::Status => {
+                let config = load_config(instance, root)?;
+                match config.main.nexus_api_key.as_deref() {
+                    Some(reference) => {
+                        secrets::resolve(Some(reference), NEXUS)?;
+                        println!("Using the Nexus API key from `{reference}`");
+                    }
+                    None => match secrets::load(NEXUS)? {
+                        Some((_, store)) => println!("Using the Nexus API key in {store}"),
+                        None => println!("Not logged in, run `vapor auth login`"),
+                    },
+                }
+            }
+        },
+        Command::Completions { shell } => {
+            completions::generate(shell, &mut std::io::stdout()).into_diagnostic()?;
+        }
+        Command::Complete => {
+            // Completion must stay quiet, so any error just means no candidates.
+            if let Ok(toml) = load_config(instance, root)
+                .and_then(|config| load_handler(&config, game, dry_run, no_trash, mutates))
+                .and_then(|handler| Ok(handler.load_toml()?))
+            {
+                for name in toml.mods.keys() {
+                    println!("{name}");
+                }
+            }
+        }
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
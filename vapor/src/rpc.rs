@@ -0,0 +1,301 @@
+//! JSON-RPC 2.0 over a Unix socket, one message per line.
+//!
+//! Methods take named parameters, `game` being optional everywhere:
+//!
+//! - `list {}`: names of every mod.
+//! - `status {}`: same as `vapor status --json`.
+//! - `add {path, name, version, dependencies?}` and `add_url {url, ...}`: install an archive.
+//! - `enable {name}` and `disable {name, cascade?, force?}`, disabling refusing mods other enabled
+//!   mods depend on unless `cascade` takes them down too or `force` leaves them.
+//! - `undo {n?}`: undo the last operations.
+//! - `jobs {}`, `job {id}` and `cancel {id}`: list, look up and cancel the operations above.
+//! - `subscribe {}`: receive `progress {job, operation, stage}` and `registry_changed {game}`
+//!   notifications on this connection.
+//!
+//! Operations that change mods are jobs on one [`JobQueue`], run one at a time. A request for one
+//! is answered once the job finishes, while other connections can look it up or cancel it.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use libvapor::jobs::{JobContext, JobId, JobQueue, JobStatus};
+use libvapor::mod_manager::handler::{Impact, Move};
+use libvapor::platform;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::service;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any error from Vapor itself.
+const VAPOR_ERROR: i64 = -32000;
+
+/// Socket used when none is given, `$XDG_RUNTIME_DIR/vapor/vapor.sock`.
+pub fn default_socket() -> io::Result<PathBuf> {
+    platform::place_runtime_file("vapor.sock")
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Params,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Params {
+    game: Option<String>,
+    name: Option<String>,
+    path: Option<PathBuf>,
+    url: Option<String>,
+    version: Option<String>,
+    dependencies: Vec<String>,
+    n: Option<usize>,
+    id: Option<JobId>,
+    cascade: bool,
+    force: bool,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<miette::Report> for RpcError {
+    fn from(err: miette::Report) -> Self {
+        Self {
+            code: VAPOR_ERROR,
+            message: err.to_string(),
+        }
+    }
+}
+
+fn required<T>(value: Option<T>, name: &str) -> Result<T, RpcError> {
+    value.ok_or_else(|| RpcError {
+        code: INVALID_PARAMS,
+        message: format!("missing `{name}`"),
+    })
+}
+
+type Writer = Arc<Mutex<UnixStream>>;
+type Subscribers = Arc<Mutex<Vec<Writer>>>;
+
+struct Server {
+    jobs: JobQueue,
+    subscribers: Subscribers,
+}
+
+/// Send a notification to every subscriber, dropping those that hung up.
+fn notify(subscribers: &Subscribers, method: &str, params: Value) {
+    let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+    subscribers
+        .lock()
+        .unwrap()
+        .retain(|writer| send(writer, &message).is_ok());
+}
+
+impl Server {
+    fn new() -> Self {
+        let subscribers = Subscribers::default();
+        let jobs = JobQueue::new().with_listener({
+            let subscribers = Arc::clone(&subscribers);
+            move |job| {
+                notify(
+                    &subscribers,
+                    "progress",
+                    json!({ "job": job.id, "operation": job.operation, "stage": job.status.to_string() }),
+                );
+            }
+        });
+
+        Self { jobs, subscribers }
+    }
+
+    /// Queue `work` and wait for it, returning what it returned.
+    fn run(
+        &self,
+        operation: String,
+        work: impl FnOnce(&JobContext) -> miette::Result<String> + Send + 'static,
+    ) -> Result<String, RpcError> {
+        let id = self.jobs.submit(operation, work);
+
+        let message = match self.jobs.wait(id) {
+            Some(JobStatus::Done { result }) => return Ok(result),
+            Some(JobStatus::Failed { error }) => error,
+            Some(status) => status.to_string(),
+            None => format!("job {id} was forgotten"),
+        };
+        Err(RpcError {
+            code: VAPOR_ERROR,
+            message,
+        })
+    }
+
+    fn call(&self, method: &str, params: Params) -> Result<Value, RpcError> {
+        let game = params.game.clone();
+
+        let changed = |result: Value| {
+            notify(
+                &self.subscribers,
+                "registry_changed",
+                json!({ "game": game.as_deref().unwrap_or_default() }),
+            );
+            Ok(result)
+        };
+
+        match method {
+            "list" => Ok(json!(service::list(game.as_deref())?)),
+            "status" => Ok(serde_json::from_str(&service::status(game.as_deref())?)
+                .expect("Status is always valid JSON")),
+            "add" | "add_url" => {
+                let name = required(params.name, "name")?;
+                let version = required(params.version, "version")?;
+                let operation = format!("add `{name}`");
+                let dependencies = params.dependencies;
+                let game = game.clone();
+
+                let report = if method == "add" {
+                    let path = required(params.path, "path")?;
+                    self.run(operation, move |job| {
+                        service::add_mod(job, game.as_deref(), &path, name, version, &dependencies)
+                    })?
+                } else {
+                    let url = required(params.url, "url")?;
+                    self.run(operation, move |job| {
+                        service::add_url(job, game.as_deref(), &url, name, version, &dependencies)
+                    })?
+                };
+
+                changed(serde_json::from_str(&report).expect("Reports are always valid JSON"))
+            }
+            "enable" | "disable" => {
+                let which = if method == "enable" {
+                    Move::Enable
+                } else {
+                    Move::Disable
+                };
+                let name = required(params.name, "name")?;
+                let impact = if params.cascade {
+                    Impact::Cascade
+                } else if params.force {
+                    Impact::Force
+                } else {
+                    Impact::Refuse
+                };
+                let game = game.clone();
+                let reports = self.run(format!("{method} `{name}`"), move |_| {
+                    service::move_mod(game.as_deref(), name, which, impact)
+                })?;
+                changed(serde_json::from_str(&reports).expect("Reports are always valid JSON"))
+            }
+            "undo" => {
+                let n = params.n.unwrap_or(1);
+                let game = game.clone();
+                let undone = self.run(format!("undo {n}"), move |_| {
+                    Ok(service::undo(game.as_deref(), n)?.join("\n"))
+                })?;
+                changed(json!(undone.lines().collect::<Vec<_>>()))
+            }
+            "jobs" => Ok(json!(self.jobs.jobs())),
+            "job" => {
+                let id = required(params.id, "id")?;
+                self.jobs
+                    .status(id)
+                    .map(|status| json!(status))
+                    .ok_or_else(|| RpcError {
+                        code: INVALID_PARAMS,
+                        message: format!("no job {id}"),
+                    })
+            }
+            "cancel" => Ok(json!(self.jobs.cancel(required(params.id, "id")?))),
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method `{method}`"),
+            }),
+        }
+    }
+
+    fn handle(&self, stream: UnixStream) -> io::Result<()> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (id, result) = match serde_json::from_str::<Request>(&line) {
+                Ok(request) if request.method == "subscribe" => {
+                    self.subscribers.lock().unwrap().push(Arc::clone(&writer));
+                    (request.id, Ok(json!(true)))
+                }
+                Ok(request) => (request.id, self.call(&request.method, request.params)),
+                Err(err) => (
+                    Value::Null,
+                    Err(RpcError {
+                        code: PARSE_ERROR,
+                        message: err.to_string(),
+                    }),
+                ),
+            };
+
+            let response = match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(err) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": err.code, "message": err.message },
+                }),
+            };
+            send(&writer, &response)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn send(writer: &Writer, message: &Value) -> io::Result<()> {
+    let mut stream = writer.lock().unwrap();
+    writeln!(stream, "{message}")
+}
+
+/// Listen on `socket` until killed, replacing a stale socket left by a previous run.
+pub fn serve(socket: &Path) -> io::Result<()> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("`{}` is already being served", socket.display()),
+            ));
+        }
+        fs::remove_file(socket)?;
+    }
+
+    let listener = UnixListener::bind(socket)?;
+    let server = Arc::new(Server::new());
+
+    tracing::info!(socket = %socket.display(), "serving");
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            if let Err(err) = server.handle(stream) {
+                tracing::warn!(%err, "connection closed");
+            }
+        });
+    }
+
+    Ok(())
+}
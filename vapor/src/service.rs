@@ -0,0 +1,174 @@
+//! Operations shared by `vapor daemon` and `vapor serve`.
+//!
+//! An empty or missing game means `Cyberpunk 2077`.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use libvapor::download::{self, DownloadError};
+use libvapor::init::DEFAULT_GAME;
+use libvapor::jobs::JobContext;
+use libvapor::mod_manager::handler::{AddOptions, Impact, Move, Operation};
+use libvapor::mod_manager::name::ModName;
+use libvapor::resolver::Request;
+use miette::{IntoDiagnostic, Result, miette};
+
+use crate::{load_config, load_handler};
+
+/// Game directory every request manages, from `--root`.
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Manage the game at `root` rather than the configured one for the rest of the run.
+pub fn set_root(root: Option<&Path>) {
+    if let Some(root) = root {
+        let _ = ROOT.set(root.to_path_buf());
+    }
+}
+
+fn root() -> Option<&'static Path> {
+    ROOT.get().map(PathBuf::as_path)
+}
+
+fn game(game: Option<&str>) -> Option<&str> {
+    game.filter(|game| !game.is_empty())
+}
+
+/// Install the archive at `path`, returning its
+/// [`OperationReport`](libvapor::mod_manager::handler::OperationReport) as JSON.
+pub fn add_mod(
+    job: &JobContext,
+    game_id: Option<&str>,
+    path: &Path,
+    name: String,
+    version: String,
+    dependencies: &[String],
+) -> Result<String> {
+    let config = load_config(None, root())?;
+    let handler = load_handler(&config, game(game_id), false, false, true)?;
+
+    let options = AddOptions {
+        dependencies: dependencies.iter().map(ModName::from).collect(),
+        ..Default::default()
+    };
+
+    if job.is_cancelled() {
+        return Err(miette!("Cancelled"));
+    }
+    job.extracting();
+
+    serde_json::to_string(&handler.add_mod(path, name, version, &options)?).into_diagnostic()
+}
+
+/// Download the archive at `url` into the download cache, then install it.
+pub fn add_url(
+    job: &JobContext,
+    game_id: Option<&str>,
+    url: &str,
+    name: String,
+    version: String,
+    dependencies: &[String],
+) -> Result<String> {
+    let file_name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|url| url.rsplit('/').next())
+        .filter(|file_name| !file_name.is_empty())
+        .ok_or_else(|| miette!("`{url}` does not end in a file name"))?;
+
+    let archive = download::download_with_progress(
+        url,
+        &load_config(None, root())?
+            .download_dir()
+            .map_err(DownloadError::from)?,
+        file_name,
+        |downloaded, total| job.downloading(downloaded, total),
+        || job.is_cancelled(),
+    )?;
+
+    add_mod(job, game_id, &archive, name, version, dependencies)
+}
+
+/// Enable or disable `name`, along with whatever the resolver plans for it, returning the
+/// [`OperationReport`](libvapor::mod_manager::handler::OperationReport) of each as a JSON array.
+///
+/// Enabled mods depending on a disabled one are handled as `impact` says, see
+/// [`ModHandler::dependents_to_disable`](libvapor::mod_manager::handler::ModHandler::dependents_to_disable).
+/// Every move is one operation, undone together and rolled back if one fails.
+pub fn move_mod(
+    game_id: Option<&str>,
+    name: String,
+    which: Move,
+    impact: Impact,
+) -> Result<String> {
+    let config = load_config(None, root())?;
+    let handler = load_handler(&config, game(game_id), false, false, true)?;
+    let plan = handler.plan(&match which {
+        Move::Enable => Request::Enable(name.clone()),
+        Move::Disable => Request::Disable(name.clone()),
+    })?;
+
+    let mut names = match which {
+        Move::Enable => plan.enable,
+        Move::Disable => {
+            let mut names = handler.dependents_to_disable(&[&name], plan.disable, impact)?;
+            names.push(name.clone());
+            names
+        }
+    };
+    if names.is_empty() {
+        names.push(name.clone());
+    }
+    let verb = match which {
+        Move::Enable => "enable",
+        Move::Disable => "disable",
+    };
+    let reports = handler.transaction(format!("{verb} `{name}`"), || {
+        names
+            .into_iter()
+            .map(|name| handler.move_mod(name, which))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    serde_json::to_string(&reports).into_diagnostic()
+}
+
+/// Undo the last `n` operations, returning their summaries.
+pub fn undo(game_id: Option<&str>, n: usize) -> Result<Vec<String>> {
+    let config = load_config(None, root())?;
+    let reports = load_handler(&config, game(game_id), false, false, true)?.undo(n)?;
+    Ok(reports
+        .into_iter()
+        .filter_map(|report| match report.operation {
+            Operation::Undone { operation, .. } => Some(operation),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Names of every mod.
+pub fn list(game_id: Option<&str>) -> Result<Vec<String>> {
+    let config = load_config(None, root())?;
+    let toml = load_handler(&config, game(game_id), false, false, false)?.load_toml()?;
+
+    Ok(toml.mods.into_keys().map(ModName::into_string).collect())
+}
+
+/// Status of every mod as JSON, like `vapor status --json`.
+pub fn status(game_id: Option<&str>) -> Result<String> {
+    let game_id = game(game_id);
+    let config = load_config(None, root())?;
+    let handler = load_handler(&config, game_id, false, false, false)?;
+    let toml = handler.load_toml()?;
+    let names = toml.mods.keys().map(ModName::as_str).collect::<Vec<_>>();
+    let game_version = match game_id {
+        Some(game_id) if game_id != DEFAULT_GAME => None,
+        _ => config.game_version(),
+    };
+    let files = toml
+        .mods
+        .iter()
+        .map(|(name, entry)| (name.as_str(), handler.file_health(name.as_str(), entry)))
+        .collect();
+
+    Ok(toml.status(true, game_version, &names, &files).0)
+}